@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+
+use crate::models::price_bar::PriceBar;
+
+/// Simple close-to-close returns keyed by the date of the later bar in
+/// `zone`, the exchange's time zone.
+pub fn returns_by_date(bars: &[PriceBar], zone: Tz) -> HashMap<NaiveDate, f64> {
+    bars.windows(2)
+        .filter(|pair| pair[0].close > 0.0)
+        .map(|pair| (pair[1].timestamp.with_timezone(&zone).date_naive(), pair[1].close / pair[0].close - 1.0))
+        .collect()
+}
+
+/// Beta of `asset` against `benchmark` over the exchange dates in `zone`
+/// both series have returns for. None when there are fewer than two common
+/// dates or the benchmark did not move.
+pub fn beta(asset: &[PriceBar], benchmark: &[PriceBar], zone: Tz) -> Option<f64> {
+    let asset_returns = returns_by_date(asset, zone);
+    let benchmark_returns = returns_by_date(benchmark, zone);
+
+    let pairs: Vec<(f64, f64)> = asset_returns.iter()
+        .filter_map(|(date, r)| benchmark_returns.get(date).map(|m| (*r, *m)))
+        .collect();
+    if pairs.len() < 2 {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_asset = pairs.iter().map(|(r, _)| r).sum::<f64>() / n;
+    let mean_benchmark = pairs.iter().map(|(_, m)| m).sum::<f64>() / n;
+    let covariance = pairs.iter().map(|(r, m)| (r - mean_asset) * (m - mean_benchmark)).sum::<f64>();
+    let variance = pairs.iter().map(|(_, m)| (m - mean_benchmark).powi(2)).sum::<f64>();
+
+    if variance > 0.0 {
+        Some(covariance / variance)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use crate::models::price_bar::Timeframe;
+
+    fn bars(symbol: &str, first: DateTime<Utc>, closes: &[f64]) -> Vec<PriceBar> {
+        closes.iter().enumerate().map(|(i, close)| PriceBar {
+            symbol: symbol.to_string(),
+            timeframe: Timeframe::Daily,
+            timestamp: first + Duration::days(i as i64),
+            open: *close,
+            high: *close,
+            low: *close,
+            close: *close,
+            volume: 1000.0,
+        }).collect()
+    }
+
+    #[test]
+    fn asset_moving_twice_the_benchmark_has_beta_two() {
+        let first = Utc.with_ymd_and_hms(2024, 6, 3, 20, 0, 0).unwrap();
+        let benchmark = bars("SPY", first, &[100.0, 101.0, 99.0, 102.0]);
+        let asset = bars("AAPL", first, &[100.0, 102.0, 97.96, 103.90]);
+
+        let value = beta(&asset, &benchmark, Tz::America__New_York).unwrap();
+        assert!((value - 2.0).abs() < 0.05, "{}", value);
+    }
+
+    #[test]
+    fn returns_are_dated_in_the_exchange_zone() {
+        // 01:00 UTC on the 5th is still the evening of the 4th in New York
+        let first = Utc.with_ymd_and_hms(2024, 6, 4, 1, 0, 0).unwrap();
+        let returns = returns_by_date(&bars("SPY", first, &[100.0, 101.0]), Tz::America__New_York);
+
+        assert_eq!(returns.keys().copied().collect::<Vec<_>>(), vec![NaiveDate::from_ymd_opt(2024, 6, 4).unwrap()]);
+    }
+
+    #[test]
+    fn flat_benchmark_has_no_beta() {
+        let first = Utc.with_ymd_and_hms(2024, 6, 3, 20, 0, 0).unwrap();
+        let benchmark = bars("SPY", first, &[100.0, 100.0, 100.0]);
+        let asset = bars("AAPL", first, &[100.0, 101.0, 102.0]);
+
+        assert_eq!(beta(&asset, &benchmark, Tz::America__New_York), None);
+    }
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+const DAYS_PER_YEAR: f64 = 365.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum OptionType {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PricingInputs {
+    pub option_type: OptionType,
+    pub underlying_price: f64,
+    pub strike: f64,
+    pub time_to_expiry: f64,   // Years, ACT/365
+    pub rate: f64,             // Continuously compounded, 0.05 = 5%
+    pub dividend_yield: f64,   // Continuous, 0.01 = 1%
+    pub volatility: f64,       // Annualised, 0.25 = 25%
+}
+
+/// Theoretical value and Greeks for one option contract (per share).
+/// Theta is per calendar day, vega and rho per one percentage point.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OptionValuation {
+    pub price: f64,
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub rho: f64,
+}
+
+/// Year fraction from `now` until `expiry_close`, when the option stops
+/// trading.
+pub fn years_to_expiry(now: DateTime<Utc>, expiry_close: DateTime<Utc>) -> f64 {
+    let seconds = (expiry_close - now).num_seconds().max(0) as f64;
+    seconds / (DAYS_PER_YEAR * 24.0 * 3600.0)
+}
+
+/// Standard normal probability density.
+pub fn norm_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Standard normal cumulative distribution, using Hart's double precision
+/// rational approximation (absolute error below 1e-14).
+pub fn norm_cdf(x: f64) -> f64 {
+    let z = x.abs();
+    let tail = if z > 37.0 {
+        0.0
+    } else {
+        let e = (-z * z / 2.0).exp();
+        if z < 7.071_067_811_865_47 {
+            let mut n = 3.526_249_659_989_11e-2 * z + 0.700_383_064_443_688;
+            n = n * z + 6.373_962_203_531_65;
+            n = n * z + 33.912_866_078_383;
+            n = n * z + 112.079_291_497_871;
+            n = n * z + 221.213_596_169_931;
+            n = n * z + 220.206_867_912_376;
+            let mut d = 8.838_834_764_831_84e-2 * z + 1.755_667_163_182_64;
+            d = d * z + 16.064_177_579_207;
+            d = d * z + 86.780_732_202_946_1;
+            d = d * z + 296.564_248_779_674;
+            d = d * z + 637.333_633_378_831;
+            d = d * z + 793.826_512_519_948;
+            d = d * z + 440.413_735_824_752;
+            e * n / d
+        } else {
+            let mut f = z + 0.65;
+            f = z + 4.0 / f;
+            f = z + 3.0 / f;
+            f = z + 2.0 / f;
+            f = z + 1.0 / f;
+            e / f / 2.506_628_274_631
+        }
+    };
+
+    if x > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+fn intrinsic_value(inputs: &PricingInputs) -> f64 {
+    match inputs.option_type {
+        OptionType::Call => (inputs.underlying_price - inputs.strike).max(0.0),
+        OptionType::Put => (inputs.strike - inputs.underlying_price).max(0.0),
+    }
+}
+
+/// Black-Scholes-Merton theoretical price.
+pub fn price(inputs: &PricingInputs) -> f64 {
+    valuation(inputs).price
+}
+
+/// Black-Scholes-Merton value and Greeks for a European option with a
+/// continuous dividend yield. At or past expiry, or with no volatility, the
+/// option is worth its intrinsic value and only delta is non-zero.
+pub fn valuation(inputs: &PricingInputs) -> OptionValuation {
+    let s = inputs.underlying_price;
+    let k = inputs.strike;
+    let t = inputs.time_to_expiry;
+    let r = inputs.rate;
+    let q = inputs.dividend_yield;
+    let sigma = inputs.volatility;
+
+    if t <= 0.0 || sigma <= 0.0 || s <= 0.0 || k <= 0.0 {
+        let in_the_money = match inputs.option_type {
+            OptionType::Call => s > k,
+            OptionType::Put => s < k,
+        };
+        let delta = match (inputs.option_type, in_the_money) {
+            (OptionType::Call, true) => 1.0,
+            (OptionType::Put, true) => -1.0,
+            _ => 0.0,
+        };
+        return OptionValuation {
+            price: intrinsic_value(inputs),
+            delta,
+            gamma: 0.0,
+            theta: 0.0,
+            vega: 0.0,
+            rho: 0.0,
+        };
+    }
+
+    let sqrt_t = t.sqrt();
+    let d1 = ((s / k).ln() + (r - q + 0.5 * sigma * sigma) * t) / (sigma * sqrt_t);
+    let d2 = d1 - sigma * sqrt_t;
+    let dividend_discount = (-q * t).exp();
+    let rate_discount = (-r * t).exp();
+    let pdf_d1 = norm_pdf(d1);
+
+    let gamma = dividend_discount * pdf_d1 / (s * sigma * sqrt_t);
+    let vega = s * dividend_discount * pdf_d1 * sqrt_t;
+    let time_decay = -s * dividend_discount * pdf_d1 * sigma / (2.0 * sqrt_t);
+
+    let (price, delta, theta, rho) = match inputs.option_type {
+        OptionType::Call => {
+            let nd1 = norm_cdf(d1);
+            let nd2 = norm_cdf(d2);
+            (
+                s * dividend_discount * nd1 - k * rate_discount * nd2,
+                dividend_discount * nd1,
+                time_decay - r * k * rate_discount * nd2 + q * s * dividend_discount * nd1,
+                k * t * rate_discount * nd2,
+            )
+        }
+        OptionType::Put => {
+            let n_neg_d1 = norm_cdf(-d1);
+            let n_neg_d2 = norm_cdf(-d2);
+            (
+                k * rate_discount * n_neg_d2 - s * dividend_discount * n_neg_d1,
+                -dividend_discount * n_neg_d1,
+                time_decay + r * k * rate_discount * n_neg_d2 - q * s * dividend_discount * n_neg_d1,
+                -k * t * rate_discount * n_neg_d2,
+            )
+        }
+    };
+
+    OptionValuation {
+        price,
+        delta,
+        gamma,
+        theta: theta / DAYS_PER_YEAR,
+        vega: vega / 100.0,
+        rho: rho / 100.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(option_type: OptionType, underlying_price: f64, strike: f64, time_to_expiry: f64, rate: f64, dividend_yield: f64, volatility: f64) -> PricingInputs {
+        PricingInputs { option_type, underlying_price, strike, time_to_expiry, rate, dividend_yield, volatility }
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() < tolerance, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn prices_match_reference_values() {
+        // Hull, Options, Futures and Other Derivatives, example 15.6
+        assert_close(price(&inputs(OptionType::Call, 42.0, 40.0, 0.5, 0.1, 0.0, 0.2)), 4.7594, 1e-4);
+        assert_close(price(&inputs(OptionType::Put, 42.0, 40.0, 0.5, 0.1, 0.0, 0.2)), 0.8086, 1e-4);
+        // Haug, The Complete Guide to Option Pricing Formulas, 1.1.1 and 1.1.6
+        assert_close(price(&inputs(OptionType::Call, 60.0, 65.0, 0.25, 0.08, 0.0, 0.3)), 2.1334, 1e-4);
+        assert_close(price(&inputs(OptionType::Put, 100.0, 95.0, 0.5, 0.1, 0.05, 0.2)), 2.4648, 1e-4);
+    }
+
+    #[test]
+    fn call_greeks_match_reference_values() {
+        // Hull, tables 19.1 to 19.6: delta 0.522, gamma 0.066, vega 12.1,
+        // theta -4.31 per year and rho 8.91, vega and rho per unit
+        let call = valuation(&inputs(OptionType::Call, 49.0, 50.0, 0.3846, 0.05, 0.0, 0.2));
+
+        assert_close(call.price, 2.4005, 1e-4);
+        assert_close(call.delta, 0.5216, 1e-4);
+        assert_close(call.gamma, 0.0655, 1e-4);
+        assert_close(call.vega, 0.1211, 1e-4);
+        assert_close(call.theta, -4.31 / 365.0, 1e-4);
+        assert_close(call.rho, 0.0891, 1e-4);
+    }
+
+    #[test]
+    fn put_greeks_match_reference_values() {
+        let put = valuation(&inputs(OptionType::Put, 49.0, 50.0, 0.3846, 0.05, 0.0, 0.2));
+
+        assert_close(put.price, 2.4481, 1e-4);
+        assert_close(put.delta, -0.4784, 1e-4);
+        assert_close(put.gamma, 0.0655, 1e-4);
+        assert_close(put.vega, 0.1211, 1e-4);
+        assert_close(put.theta, -0.005077, 1e-6);
+        assert_close(put.rho, -0.0996, 1e-4);
+    }
+
+    #[test]
+    fn calls_and_puts_keep_parity() {
+        let (s, k, t, r, q) = (100.0, 95.0, 0.5, 0.1, 0.05);
+        let call = valuation(&inputs(OptionType::Call, s, k, t, r, q, 0.2));
+        let put = valuation(&inputs(OptionType::Put, s, k, t, r, q, 0.2));
+
+        assert_close(call.price - put.price, s * (-q * t).exp() - k * (-r * t).exp(), 1e-10);
+        assert_close(call.delta - put.delta, (-q * t).exp(), 1e-10);
+        assert_close(call.gamma, put.gamma, 1e-12);
+        assert_close(call.vega, put.vega, 1e-12);
+    }
+
+    #[test]
+    fn expired_options_are_worth_intrinsic_value() {
+        let call = valuation(&inputs(OptionType::Call, 110.0, 100.0, 0.0, 0.05, 0.0, 0.2));
+        let put = valuation(&inputs(OptionType::Put, 110.0, 100.0, 0.0, 0.05, 0.0, 0.2));
+
+        assert_eq!(call, OptionValuation { price: 10.0, delta: 1.0, gamma: 0.0, theta: 0.0, vega: 0.0, rho: 0.0 });
+        assert_eq!(put.price, 0.0);
+        assert_eq!(put.delta, 0.0);
+    }
+
+    #[test]
+    fn normal_distribution_is_accurate() {
+        assert_close(norm_cdf(1.96), 0.975_002_104_851_779_5, 1e-14);
+        assert_close(norm_cdf(-1.96) + norm_cdf(1.96), 1.0, 1e-15);
+        assert_close(norm_cdf(0.0), 0.5, 1e-15);
+        assert_close(norm_pdf(0.0), 0.398_942_280_401_432_7, 1e-15);
+    }
+}
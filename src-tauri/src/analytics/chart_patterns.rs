@@ -0,0 +1,607 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use crate::models::price_bar::PriceBar;
+use crate::models::stock_rating::ChartPattern;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PivotKind {
+    High,
+    Low,
+}
+
+/// A swing high or low. `index` is the position of the bar in the series
+/// the pattern was detected in.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Pivot {
+    pub index: usize,
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+    pub kind: PivotKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternDetection {
+    pub pattern: ChartPattern,
+    pub confidence: f64,           // 0 to 1
+    pub pivots: Vec<Pivot>,        // In time order
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternDetectorConfig {
+    pub pivot_window: usize,       // Bars either side a swing must dominate
+    pub tolerance: f64,            // Relative difference at which two levels count as equal
+    pub min_depth: f64,            // Smallest retracement between the peaks of a double top/bottom
+    pub base_bars: usize,          // Length of a base or consolidation
+    pub max_base_range: f64,       // Widest high-low range of a base, relative to its average close
+    pub min_prior_move: f64,       // Move into a high or low base
+    pub min_cup_depth: f64,
+    pub max_cup_depth: f64,
+    pub breakout_bars: usize,      // How recent a breakout must be
+}
+
+impl Default for PatternDetectorConfig {
+    fn default() -> Self {
+        Self {
+            pivot_window: 3,
+            tolerance: 0.03,
+            min_depth: 0.05,
+            base_bars: 20,
+            max_base_range: 0.08,
+            min_prior_move: 0.15,
+            min_cup_depth: 0.12,
+            max_cup_depth: 0.5,
+            breakout_bars: 10,
+        }
+    }
+}
+
+/// Runs every detector over `bars` (oldest first) and returns what was found,
+/// most confident first. Only patterns still in progress at the last bar are
+/// reported.
+pub fn detect_patterns(bars: &[PriceBar], config: &PatternDetectorConfig) -> Vec<PatternDetection> {
+    let pivots = find_pivots(bars, config.pivot_window);
+
+    let mut detections: Vec<PatternDetection> = [
+        detect_double_top(bars, &pivots, config),
+        detect_double_bottom(bars, &pivots, config),
+        detect_head_and_shoulders(bars, &pivots, config),
+        detect_inverse_head_and_shoulders(bars, &pivots, config),
+        detect_ascending_triangle(bars, &pivots, config),
+        detect_descending_triangle(bars, &pivots, config),
+        detect_cup(bars, &pivots, config),
+        detect_consolidation(bars, config),
+        detect_base(bars, config),
+        detect_breakout_pullback(bars, config),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|detection| detection.confidence > 0.0)
+    .collect();
+
+    detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    detections
+}
+
+/// Alternating swing highs and lows. A bar is a swing high when its high is
+/// above the `window` bars before it and not below the `window` bars after
+/// it, so ties resolve to the earliest bar. Consecutive swings of the same
+/// kind collapse to the more extreme one.
+pub fn find_pivots(bars: &[PriceBar], window: usize) -> Vec<Pivot> {
+    let window = window.max(1);
+    let mut pivots: Vec<Pivot> = Vec::new();
+    if bars.len() < 2 * window + 1 {
+        return pivots;
+    }
+
+    for i in window..bars.len() - window {
+        let before = &bars[i - window..i];
+        let after = &bars[i + 1..=i + window];
+
+        if before.iter().all(|b| b.high < bars[i].high) && after.iter().all(|b| b.high <= bars[i].high) {
+            push_pivot(&mut pivots, pivot(bars, i, PivotKind::High));
+        }
+        if before.iter().all(|b| b.low > bars[i].low) && after.iter().all(|b| b.low >= bars[i].low) {
+            push_pivot(&mut pivots, pivot(bars, i, PivotKind::Low));
+        }
+    }
+
+    pivots
+}
+
+fn pivot(bars: &[PriceBar], index: usize, kind: PivotKind) -> Pivot {
+    let price = match kind {
+        PivotKind::High => bars[index].high,
+        PivotKind::Low => bars[index].low,
+    };
+
+    Pivot { index, timestamp: bars[index].timestamp, price, kind }
+}
+
+fn push_pivot(pivots: &mut Vec<Pivot>, next: Pivot) {
+    match pivots.last_mut() {
+        Some(last) if last.kind == next.kind => {
+            let more_extreme = match next.kind {
+                PivotKind::High => next.price > last.price,
+                PivotKind::Low => next.price < last.price,
+            };
+            if more_extreme {
+                *last = next;
+            }
+        }
+        _ => pivots.push(next),
+    }
+}
+
+/// The latest run of pivots with the given kinds, ending at the last or
+/// second-to-last pivot so a pattern may be followed by one more swing.
+fn last_sequence<'a>(pivots: &'a [Pivot], kinds: &[PivotKind]) -> Option<&'a [Pivot]> {
+    (0..2).find_map(|skip| {
+        let end = pivots.len().checked_sub(skip)?;
+        let start = end.checked_sub(kinds.len())?;
+        let run = &pivots[start..end];
+        run.iter().zip(kinds).all(|(p, kind)| p.kind == *kind).then_some(run)
+    })
+}
+
+fn relative_difference(a: f64, b: f64) -> f64 {
+    let mid = (a + b) / 2.0;
+    if mid > 0.0 { (a - b).abs() / mid } else { f64::INFINITY }
+}
+
+/// 1 when `a` and `b` are equal, falling to 0 at `tolerance` apart.
+fn closeness(a: f64, b: f64, tolerance: f64) -> f64 {
+    (1.0 - relative_difference(a, b) / tolerance).max(0.0)
+}
+
+fn detection(bars: &[PriceBar], pattern: ChartPattern, confidence: f64, pivots: Vec<Pivot>) -> PatternDetection {
+    let start = pivots.first().map(|p| p.timestamp).unwrap_or(bars[0].timestamp);
+    let end = bars[bars.len() - 1].timestamp;
+
+    PatternDetection { pattern, confidence: confidence.clamp(0.0, 1.0), pivots, start, end }
+}
+
+fn last_close(bars: &[PriceBar]) -> f64 {
+    bars.last().map(|b| b.close).unwrap_or(0.0)
+}
+
+fn detect_double_top(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let run = last_sequence(pivots, &[PivotKind::High, PivotKind::Low, PivotKind::High])?;
+    let (first, trough, second) = (&run[0], &run[1], &run[2]);
+
+    let peak = first.price.min(second.price);
+    let depth = (peak - trough.price) / peak;
+    if relative_difference(first.price, second.price) > config.tolerance || depth < config.min_depth {
+        return None;
+    }
+
+    let confirmed = if last_close(bars) < trough.price { 1.0 } else { 0.0 };
+    let confidence = 0.5 * closeness(first.price, second.price, config.tolerance)
+        + 0.25 * (depth / (2.0 * config.min_depth)).min(1.0)
+        + 0.25 * confirmed;
+
+    Some(detection(bars, ChartPattern::DoubleTop, confidence, run.to_vec()))
+}
+
+fn detect_double_bottom(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let run = last_sequence(pivots, &[PivotKind::Low, PivotKind::High, PivotKind::Low])?;
+    let (first, peak, second) = (&run[0], &run[1], &run[2]);
+
+    let trough = first.price.max(second.price);
+    let depth = (peak.price - trough) / peak.price;
+    if relative_difference(first.price, second.price) > config.tolerance || depth < config.min_depth {
+        return None;
+    }
+
+    let confirmed = if last_close(bars) > peak.price { 1.0 } else { 0.0 };
+    let confidence = 0.5 * closeness(first.price, second.price, config.tolerance)
+        + 0.25 * (depth / (2.0 * config.min_depth)).min(1.0)
+        + 0.25 * confirmed;
+
+    Some(detection(bars, ChartPattern::DoubleBottom, confidence, run.to_vec()))
+}
+
+fn detect_head_and_shoulders(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    use PivotKind::{High, Low};
+    let run = last_sequence(pivots, &[High, Low, High, Low, High])?;
+    let (left, neck_left, head, neck_right, right) = (&run[0], &run[1], &run[2], &run[3], &run[4]);
+
+    let shoulders = left.price.max(right.price);
+    if head.price < shoulders * (1.0 + config.tolerance)
+        || relative_difference(left.price, right.price) > config.tolerance
+        || relative_difference(neck_left.price, neck_right.price) > 2.0 * config.tolerance
+    {
+        return None;
+    }
+
+    let neckline = neck_left.price.min(neck_right.price);
+    let confirmed = if last_close(bars) < neckline { 1.0 } else { 0.0 };
+    let confidence = 0.4 * closeness(left.price, right.price, config.tolerance)
+        + 0.3 * closeness(neck_left.price, neck_right.price, 2.0 * config.tolerance)
+        + 0.3 * confirmed;
+
+    Some(detection(bars, ChartPattern::HeadAndShoulders, confidence, run.to_vec()))
+}
+
+fn detect_inverse_head_and_shoulders(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    use PivotKind::{High, Low};
+    let run = last_sequence(pivots, &[Low, High, Low, High, Low])?;
+    let (left, neck_left, head, neck_right, right) = (&run[0], &run[1], &run[2], &run[3], &run[4]);
+
+    let shoulders = left.price.min(right.price);
+    if head.price > shoulders * (1.0 - config.tolerance)
+        || relative_difference(left.price, right.price) > config.tolerance
+        || relative_difference(neck_left.price, neck_right.price) > 2.0 * config.tolerance
+    {
+        return None;
+    }
+
+    let neckline = neck_left.price.max(neck_right.price);
+    let confirmed = if last_close(bars) > neckline { 1.0 } else { 0.0 };
+    let confidence = 0.4 * closeness(left.price, right.price, config.tolerance)
+        + 0.3 * closeness(neck_left.price, neck_right.price, 2.0 * config.tolerance)
+        + 0.3 * confirmed;
+
+    Some(detection(bars, ChartPattern::InverseHeadAndShoulders, confidence, run.to_vec()))
+}
+
+/// The last two highs and last two lows, whichever kind came last.
+fn last_two_swings(pivots: &[Pivot]) -> Option<(&[Pivot], [&Pivot; 2], [&Pivot; 2])> {
+    use PivotKind::{High, Low};
+    let run = last_sequence(pivots, &[High, Low, High, Low])
+        .or_else(|| last_sequence(pivots, &[Low, High, Low, High]))?;
+
+    let highs: Vec<&Pivot> = run.iter().filter(|p| p.kind == High).collect();
+    let lows: Vec<&Pivot> = run.iter().filter(|p| p.kind == Low).collect();
+
+    Some((run, [highs[0], highs[1]], [lows[0], lows[1]]))
+}
+
+fn detect_ascending_triangle(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let (run, highs, lows) = last_two_swings(pivots)?;
+
+    let rise = lows[1].price / lows[0].price - 1.0;
+    if relative_difference(highs[0].price, highs[1].price) > config.tolerance || rise <= config.tolerance {
+        return None;
+    }
+
+    let confidence = 0.6 * closeness(highs[0].price, highs[1].price, config.tolerance)
+        + 0.4 * (rise / (2.0 * config.tolerance)).min(1.0);
+
+    Some(detection(bars, ChartPattern::AscendingTriangle, confidence, run.to_vec()))
+}
+
+fn detect_descending_triangle(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let (run, highs, lows) = last_two_swings(pivots)?;
+
+    let fall = 1.0 - highs[1].price / highs[0].price;
+    if relative_difference(lows[0].price, lows[1].price) > config.tolerance || fall <= config.tolerance {
+        return None;
+    }
+
+    let confidence = 0.6 * closeness(lows[0].price, lows[1].price, config.tolerance)
+        + 0.4 * (fall / (2.0 * config.tolerance)).min(1.0);
+
+    Some(detection(bars, ChartPattern::DescendingTriangle, confidence, run.to_vec()))
+}
+
+/// A rounded decline and recovery from a swing high back to about the same
+/// level, with the low near the middle.
+fn detect_cup(bars: &[PriceBar], pivots: &[Pivot], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let last = bars.len().checked_sub(1)?;
+    let close = last_close(bars);
+
+    pivots.iter().rev().filter(|p| p.kind == PivotKind::High).find_map(|rim| {
+        let span = last - rim.index;
+        if span < 6 * config.pivot_window {
+            return None;
+        }
+
+        let bottom = (rim.index + 1..=last).min_by(|&a, &b| bars[a].low.total_cmp(&bars[b].low))?;
+        let depth = 1.0 - bars[bottom].low / rim.price;
+        let position = (bottom - rim.index) as f64 / span as f64;
+        if depth < config.min_cup_depth
+            || depth > config.max_cup_depth
+            || !(0.25..=0.75).contains(&position)
+            || relative_difference(close, rim.price) > 2.0 * config.tolerance
+        {
+            return None;
+        }
+
+        let right_rim = (bottom..=last).max_by(|&a, &b| bars[a].high.total_cmp(&bars[b].high).then(b.cmp(&a)))?;
+        let confidence = 0.5 * closeness(close, rim.price, 2.0 * config.tolerance)
+            + 0.5 * (1.0 - (position - 0.5).abs() * 2.0);
+        let pivots = vec![
+            rim.clone(),
+            pivot(bars, bottom, PivotKind::Low),
+            pivot(bars, right_rim, PivotKind::High),
+        ];
+
+        Some(detection(bars, ChartPattern::Cup, confidence, pivots))
+    })
+}
+
+/// High-low range of `bars` relative to their average close, with the
+/// positions of the highest and lowest bar.
+fn range_of(bars: &[PriceBar]) -> Option<(f64, usize, usize)> {
+    let high = (0..bars.len()).max_by(|&a, &b| bars[a].high.total_cmp(&bars[b].high).then(b.cmp(&a)))?;
+    let low = (0..bars.len()).min_by(|&a, &b| bars[a].low.total_cmp(&bars[b].low))?;
+    let average_close = bars.iter().map(|b| b.close).sum::<f64>() / bars.len() as f64;
+    if average_close <= 0.0 {
+        return None;
+    }
+
+    Some(((bars[high].high - bars[low].low) / average_close, high, low))
+}
+
+fn range_pivots(bars: &[PriceBar], offset: usize, high: usize, low: usize) -> Vec<Pivot> {
+    let mut pivots = vec![
+        pivot(bars, offset + high, PivotKind::High),
+        pivot(bars, offset + low, PivotKind::Low),
+    ];
+    pivots.sort_by_key(|p| p.index);
+    pivots
+}
+
+fn detect_consolidation(bars: &[PriceBar], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let offset = bars.len().checked_sub(config.base_bars)?;
+    let (range, high, low) = range_of(&bars[offset..])?;
+    if range > config.max_base_range {
+        return None;
+    }
+
+    let confidence = 1.0 - range / config.max_base_range;
+    Some(detection(bars, ChartPattern::Consolidation, confidence, range_pivots(bars, offset, high, low)))
+}
+
+/// A consolidation sitting at the top (high base) or bottom (low base) of
+/// the move that preceded it.
+fn detect_base(bars: &[PriceBar], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let offset = bars.len().checked_sub(config.base_bars)?;
+    let prior_start = offset.checked_sub(config.base_bars)?.saturating_sub(config.base_bars);
+    let base = &bars[offset..];
+    let prior = &bars[prior_start..offset];
+
+    let (range, high, low) = range_of(base)?;
+    if range > config.max_base_range {
+        return None;
+    }
+
+    let prior_high = prior.iter().map(|b| b.high).fold(f64::MIN, f64::max);
+    let prior_low = prior.iter().map(|b| b.low).fold(f64::MAX, f64::min);
+    let prior_range = prior_high - prior_low;
+    let base_low = base[low].low;
+    let base_high = base[high].high;
+
+    let (pattern, prior_move) = if prior_low > 0.0 && base_low >= prior_low + 0.75 * prior_range {
+        (ChartPattern::HighBase, base_low / prior_low - 1.0)
+    } else if prior_high > 0.0 && base_high <= prior_low + 0.25 * prior_range {
+        (ChartPattern::LowBase, 1.0 - base_high / prior_high)
+    } else {
+        return None;
+    };
+    if prior_move < config.min_prior_move {
+        return None;
+    }
+
+    let confidence = 0.5 * (1.0 - range / config.max_base_range)
+        + 0.5 * (prior_move / (2.0 * config.min_prior_move)).min(1.0);
+
+    Some(detection(bars, pattern, confidence, range_pivots(bars, offset, high, low)))
+}
+
+/// A close above the prior resistance within the last `breakout_bars`,
+/// followed by a dip back to that level that held.
+fn detect_breakout_pullback(bars: &[PriceBar], config: &PatternDetectorConfig) -> Option<PatternDetection> {
+    let recent_start = bars.len().checked_sub(config.breakout_bars)?;
+    let resistance_start = recent_start.checked_sub(config.base_bars)?;
+
+    let resistance_bar = (resistance_start..recent_start)
+        .max_by(|&a, &b| bars[a].high.total_cmp(&bars[b].high).then(b.cmp(&a)))?;
+    let resistance = bars[resistance_bar].high;
+
+    let breakout = (recent_start..bars.len())
+        .find(|&i| bars[i].close > resistance * (1.0 + config.tolerance / 2.0))?;
+    let pullback = (breakout + 1..bars.len())
+        .min_by(|&a, &b| bars[a].low.total_cmp(&bars[b].low))?;
+
+    let pullback_low = bars[pullback].low;
+    let peak = bars[breakout..pullback].iter().map(|b| b.high).fold(f64::MIN, f64::max);
+    if peak < pullback_low * (1.0 + config.tolerance)
+        || pullback_low > resistance * (1.0 + config.tolerance)
+        || pullback_low < resistance * (1.0 - config.tolerance)
+        || last_close(bars) <= resistance
+    {
+        return None;
+    }
+
+    let confidence = 0.5 + 0.5 * closeness(pullback_low, resistance, config.tolerance);
+    let pivots = vec![
+        pivot(bars, resistance_bar, PivotKind::High),
+        Pivot { index: breakout, timestamp: bars[breakout].timestamp, price: bars[breakout].close, kind: PivotKind::High },
+        pivot(bars, pullback, PivotKind::Low),
+    ];
+
+    Some(detection(bars, ChartPattern::BreakoutPullback, confidence, pivots))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::models::price_bar::Timeframe;
+
+    /// Daily bars whose close moves in straight lines between the given
+    /// prices, taking the given number of bars for each leg.
+    fn series(start: f64, legs: &[(usize, f64)]) -> Vec<PriceBar> {
+        let mut closes = vec![start];
+        for &(bars, to) in legs {
+            let from = *closes.last().unwrap();
+            closes.extend((1..=bars).map(|i| from + (to - from) * i as f64 / bars as f64));
+        }
+
+        let first_day = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        closes.iter().enumerate().map(|(i, &close)| PriceBar {
+            symbol: "TEST".to_string(),
+            timeframe: Timeframe::Daily,
+            timestamp: first_day + Duration::days(i as i64),
+            open: close,
+            high: close * 1.005,
+            low: close * 0.995,
+            close,
+            volume: 1.0,
+        }).collect()
+    }
+
+    /// Swings between `low` and `high`, three bars apart.
+    fn range(low: f64, high: f64, swings: usize) -> Vec<(usize, f64)> {
+        (0..swings).map(|i| (3, if i % 2 == 0 { high } else { low })).collect()
+    }
+
+    fn with_range(start: f64, legs: &[(usize, f64)], low: f64, high: f64, swings: usize) -> Vec<PriceBar> {
+        let mut legs = legs.to_vec();
+        legs.extend(range(low, high, swings));
+        series(start, &legs)
+    }
+
+    fn detect(bars: &[PriceBar], detector: fn(&[PriceBar], &[Pivot], &PatternDetectorConfig) -> Option<PatternDetection>) -> Option<PatternDetection> {
+        let config = PatternDetectorConfig::default();
+        detector(bars, &find_pivots(bars, config.pivot_window), &config)
+    }
+
+    #[test]
+    fn pivots_alternate() {
+        let bars = series(100.0, &range(95.0, 105.0, 6));
+        let pivots = find_pivots(&bars, 2);
+
+        assert!(pivots.len() >= 4);
+        assert!(pivots.windows(2).all(|pair| pair[0].kind != pair[1].kind && pair[0].index < pair[1].index));
+    }
+
+    #[test]
+    fn double_top() {
+        let bars = series(100.0, &[(10, 120.0), (8, 108.0), (8, 120.5), (6, 106.0)]);
+        let found = detect(&bars, detect_double_top).unwrap();
+        assert_eq!(found.pivots.len(), 3);
+        assert!(found.confidence > 0.75, "{}", found.confidence);
+
+        let uneven = series(100.0, &[(10, 120.0), (8, 108.0), (8, 130.0), (6, 106.0)]);
+        assert!(detect(&uneven, detect_double_top).is_none());
+    }
+
+    #[test]
+    fn double_bottom() {
+        let bars = series(120.0, &[(10, 100.0), (8, 112.0), (8, 99.5), (6, 114.0)]);
+        assert!(detect(&bars, detect_double_bottom).unwrap().confidence > 0.75);
+
+        let shallow = series(120.0, &[(10, 100.0), (8, 103.0), (8, 99.5), (6, 104.0)]);
+        assert!(detect(&shallow, detect_double_bottom).is_none());
+    }
+
+    #[test]
+    fn head_and_shoulders() {
+        let bars = series(100.0, &[(6, 115.0), (5, 105.0), (6, 125.0), (6, 105.5), (5, 115.5), (6, 100.0)]);
+        let found = detect(&bars, detect_head_and_shoulders).unwrap();
+        assert_eq!(found.pivots.len(), 5);
+        assert_eq!(found.pivots[2].kind, PivotKind::High);
+
+        let lopsided = series(100.0, &[(6, 115.0), (5, 105.0), (6, 125.0), (6, 105.5), (5, 122.0), (6, 100.0)]);
+        assert!(detect(&lopsided, detect_head_and_shoulders).is_none());
+    }
+
+    #[test]
+    fn inverse_head_and_shoulders() {
+        let bars = series(125.0, &[(6, 110.0), (5, 120.0), (6, 100.0), (6, 119.5), (5, 110.5), (6, 125.0)]);
+        assert!(detect(&bars, detect_inverse_head_and_shoulders).is_some());
+
+        let no_head = series(125.0, &[(6, 110.0), (5, 120.0), (6, 109.0), (6, 119.5), (5, 110.5), (6, 125.0)]);
+        assert!(detect(&no_head, detect_inverse_head_and_shoulders).is_none());
+    }
+
+    #[test]
+    fn ascending_triangle() {
+        let bars = series(90.0, &[(6, 110.0), (6, 95.0), (6, 110.2), (6, 101.0), (4, 108.0)]);
+        assert!(detect(&bars, detect_ascending_triangle).is_some());
+
+        let flat_lows = series(90.0, &[(6, 110.0), (6, 95.0), (6, 110.2), (6, 95.5), (4, 108.0)]);
+        assert!(detect(&flat_lows, detect_ascending_triangle).is_none());
+    }
+
+    #[test]
+    fn descending_triangle() {
+        let bars = series(120.0, &[(6, 100.0), (6, 115.0), (6, 100.2), (6, 109.0), (4, 102.0)]);
+        assert!(detect(&bars, detect_descending_triangle).is_some());
+
+        let flat_highs = series(120.0, &[(6, 100.0), (6, 115.0), (6, 100.2), (6, 114.5), (4, 102.0)]);
+        assert!(detect(&flat_highs, detect_descending_triangle).is_none());
+    }
+
+    #[test]
+    fn cup() {
+        let bars = series(90.0, &[(5, 100.0), (15, 80.0), (15, 99.0)]);
+        let found = detect(&bars, detect_cup).unwrap();
+        assert_eq!(found.pivots[1].kind, PivotKind::Low);
+
+        let too_deep = series(90.0, &[(5, 100.0), (15, 40.0), (15, 99.0)]);
+        assert!(detect(&too_deep, detect_cup).is_none());
+    }
+
+    #[test]
+    fn consolidation() {
+        let config = PatternDetectorConfig::default();
+        let bars = with_range(100.0, &[], 98.0, 101.0, 20);
+        assert!(detect_consolidation(&bars, &config).unwrap().confidence > 0.0);
+
+        let wide = with_range(100.0, &[], 90.0, 110.0, 20);
+        assert!(detect_consolidation(&wide, &config).is_none());
+    }
+
+    #[test]
+    fn high_and_low_bases() {
+        let config = PatternDetectorConfig::default();
+        let high = with_range(80.0, &[(40, 110.0)], 108.0, 111.0, 7);
+        assert_eq!(detect_base(&high, &config).unwrap().pattern, ChartPattern::HighBase);
+        let low = with_range(130.0, &[(40, 100.0)], 98.0, 101.0, 7);
+        assert_eq!(detect_base(&low, &config).unwrap().pattern, ChartPattern::LowBase);
+
+        let no_prior_move = with_range(100.0, &[], 98.0, 101.0, 20);
+        assert!(detect_base(&no_prior_move, &config).is_none());
+    }
+
+    #[test]
+    fn breakout_pullback() {
+        let config = PatternDetectorConfig::default();
+        let mut legs = range(95.0, 100.0, 10);
+        legs.extend([(3, 106.0), (3, 101.0), (4, 104.0)]);
+        let bars = series(100.0, &legs);
+        assert!(detect_breakout_pullback(&bars, &config).unwrap().confidence > 0.5);
+
+        let mut failed = range(95.0, 100.0, 10);
+        failed.extend([(3, 106.0), (3, 94.0), (4, 101.0)]);
+        assert!(detect_breakout_pullback(&series(100.0, &failed), &config).is_none());
+    }
+
+    #[test]
+    fn a_steady_trend_has_no_reversal_patterns() {
+        let bars = series(50.0, &[(60, 120.0)]);
+        let found: Vec<ChartPattern> = detect_patterns(&bars, &PatternDetectorConfig::default())
+            .into_iter()
+            .map(|detection| detection.pattern)
+            .collect();
+
+        assert!(!found.contains(&ChartPattern::DoubleTop));
+        assert!(!found.contains(&ChartPattern::HeadAndShoulders));
+        assert!(!found.contains(&ChartPattern::Consolidation));
+    }
+
+    #[test]
+    fn detections_are_sorted_by_confidence() {
+        let bars = series(100.0, &[(6, 115.0), (5, 105.0), (6, 125.0), (6, 105.5), (5, 115.5), (6, 100.0)]);
+        let found = detect_patterns(&bars, &PatternDetectorConfig::default());
+
+        assert_eq!(found[0].pattern, ChartPattern::HeadAndShoulders);
+        assert!(found.windows(2).all(|pair| pair[0].confidence >= pair[1].confidence));
+    }
+}
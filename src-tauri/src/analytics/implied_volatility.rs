@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::black_scholes::{self, OptionType, PricingInputs};
+
+const MIN_VOLATILITY: f64 = 1e-4;
+const MAX_VOLATILITY: f64 = 5.0;
+const PRICE_TOLERANCE: f64 = 1e-8;
+const MAX_NEWTON_ITERATIONS: usize = 50;
+const MAX_BISECTION_ITERATIONS: usize = 200;
+// Below this vega (per vol point) a Newton step is too unstable to trust
+const MIN_NEWTON_VEGA: f64 = 1e-8;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ImpliedVolError {
+    #[error("option has expired, implied volatility is undefined")]
+    Expired,
+    #[error("market price {price} is below the no-arbitrage lower bound {bound}")]
+    BelowIntrinsic { price: f64, bound: f64 },
+    #[error("market price {price} is above the no-arbitrage upper bound {bound}")]
+    AboveUpperBound { price: f64, bound: f64 },
+    #[error("implied volatility solver did not converge")]
+    NoConvergence,
+}
+
+/// Solves for the volatility at which the Black-Scholes-Merton price of
+/// `inputs` equals `market_price`. The `volatility` of `inputs` is used as the
+/// Newton starting point when positive; bisection takes over whenever a Newton
+/// step leaves the bracket or vega vanishes (deep ITM/OTM, near expiry).
+pub fn implied_volatility(inputs: &PricingInputs, market_price: f64) -> Result<f64, ImpliedVolError> {
+    if inputs.time_to_expiry <= 0.0 {
+        return Err(ImpliedVolError::Expired);
+    }
+
+    let t = inputs.time_to_expiry;
+    let discounted_spot = inputs.underlying_price * (-inputs.dividend_yield * t).exp();
+    let discounted_strike = inputs.strike * (-inputs.rate * t).exp();
+    let (lower_bound, upper_bound) = match inputs.option_type {
+        OptionType::Call => ((discounted_spot - discounted_strike).max(0.0), discounted_spot),
+        OptionType::Put => ((discounted_strike - discounted_spot).max(0.0), discounted_strike),
+    };
+
+    if market_price < lower_bound - PRICE_TOLERANCE {
+        return Err(ImpliedVolError::BelowIntrinsic { price: market_price, bound: lower_bound });
+    }
+    if market_price >= upper_bound {
+        return Err(ImpliedVolError::AboveUpperBound { price: market_price, bound: upper_bound });
+    }
+    if market_price - lower_bound <= PRICE_TOLERANCE {
+        // No time value left to explain
+        return Ok(MIN_VOLATILITY);
+    }
+
+    let mut trial = inputs.clone();
+    let mut low = MIN_VOLATILITY;
+    let mut high = MAX_VOLATILITY;
+    let mut sigma = if inputs.volatility > 0.0 {
+        inputs.volatility.clamp(low, high)
+    } else {
+        initial_guess(inputs, market_price)
+    };
+
+    for _ in 0..MAX_NEWTON_ITERATIONS {
+        trial.volatility = sigma;
+        let valuation = black_scholes::valuation(&trial);
+        let diff = valuation.price - market_price;
+
+        if diff.abs() < PRICE_TOLERANCE {
+            return Ok(sigma);
+        }
+
+        // Price is increasing in volatility, so each evaluation tightens the bracket
+        if diff > 0.0 {
+            high = sigma;
+        } else {
+            low = sigma;
+        }
+
+        let vega = valuation.vega * 100.0;
+        if vega < MIN_NEWTON_VEGA {
+            break;
+        }
+
+        let next = sigma - diff / vega;
+        if next <= low || next >= high {
+            break;
+        }
+        sigma = next;
+    }
+
+    bisect(&mut trial, market_price, low, high)
+}
+
+fn bisect(trial: &mut PricingInputs, market_price: f64, mut low: f64, mut high: f64) -> Result<f64, ImpliedVolError> {
+    trial.volatility = high;
+    if black_scholes::price(trial) < market_price {
+        return Err(ImpliedVolError::NoConvergence);
+    }
+
+    for _ in 0..MAX_BISECTION_ITERATIONS {
+        let mid = 0.5 * (low + high);
+        trial.volatility = mid;
+        let diff = black_scholes::price(trial) - market_price;
+
+        if diff.abs() < PRICE_TOLERANCE || high - low < 1e-12 {
+            return Ok(mid);
+        }
+        if diff > 0.0 {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    Err(ImpliedVolError::NoConvergence)
+}
+
+/// Brenner-Subrahmanyam approximation, good near the money.
+fn initial_guess(inputs: &PricingInputs, market_price: f64) -> f64 {
+    let guess = (2.0 * std::f64::consts::PI / inputs.time_to_expiry).sqrt() * market_price
+        / inputs.underlying_price;
+    if guess.is_finite() {
+        guess.clamp(0.05, 2.0)
+    } else {
+        0.3
+    }
+}
+
+/// Splits the change in an option's theoretical value between entry and exit
+/// into the part due to the underlying moving, to time passing and to implied
+/// volatility changing. The components always sum to `total`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PnlAttribution {
+    pub total: f64,
+    pub direction: f64,
+    pub time_decay: f64,
+    pub volatility: f64,
+}
+
+pub fn attribute_pnl(entry: &PricingInputs, exit: &PricingInputs) -> PnlAttribution {
+    let entry_value = black_scholes::price(entry);
+
+    let mut moved = entry.clone();
+    moved.underlying_price = exit.underlying_price;
+    let after_move = black_scholes::price(&moved);
+
+    moved.time_to_expiry = exit.time_to_expiry;
+    moved.rate = exit.rate;
+    moved.dividend_yield = exit.dividend_yield;
+    let after_time = black_scholes::price(&moved);
+
+    let exit_value = black_scholes::price(exit);
+
+    PnlAttribution {
+        total: exit_value - entry_value,
+        direction: after_move - entry_value,
+        time_decay: after_time - after_move,
+        volatility: exit_value - after_time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(option_type: OptionType, underlying_price: f64, strike: f64, time_to_expiry: f64, volatility: f64) -> PricingInputs {
+        PricingInputs { option_type, underlying_price, strike, time_to_expiry, rate: 0.05, dividend_yield: 0.01, volatility }
+    }
+
+    fn assert_recovers(option_type: OptionType, underlying_price: f64, strike: f64, time_to_expiry: f64, volatility: f64) {
+        let market_price = black_scholes::price(&inputs(option_type, underlying_price, strike, time_to_expiry, volatility));
+        let solved = implied_volatility(&inputs(option_type, underlying_price, strike, time_to_expiry, 0.0), market_price).unwrap();
+        assert!((solved - volatility).abs() < 1e-6, "solved {} for {}", solved, volatility);
+    }
+
+    #[test]
+    fn recovers_the_volatility_of_a_price() {
+        assert_recovers(OptionType::Call, 100.0, 100.0, 0.5, 0.25);
+        assert_recovers(OptionType::Put, 100.0, 110.0, 1.0, 0.4);
+        assert_recovers(OptionType::Call, 100.0, 100.0, 2.0, 3.0);
+    }
+
+    #[test]
+    fn recovers_deep_out_of_the_money_volatility() {
+        assert_recovers(OptionType::Call, 100.0, 160.0, 0.25, 0.5);
+        assert_recovers(OptionType::Put, 100.0, 60.0, 0.25, 0.5);
+    }
+
+    #[test]
+    fn recovers_volatility_near_expiry() {
+        assert_recovers(OptionType::Call, 100.0, 102.0, 1.0 / 365.0, 0.3);
+    }
+
+    #[test]
+    fn rejects_prices_below_intrinsic_value() {
+        let result = implied_volatility(&inputs(OptionType::Call, 110.0, 100.0, 0.5, 0.0), 5.0);
+        assert!(matches!(result, Err(ImpliedVolError::BelowIntrinsic { price, .. }) if price == 5.0));
+    }
+
+    #[test]
+    fn rejects_prices_above_the_upper_bound() {
+        let result = implied_volatility(&inputs(OptionType::Call, 100.0, 100.0, 0.5, 0.0), 100.0);
+        assert!(matches!(result, Err(ImpliedVolError::AboveUpperBound { .. })));
+    }
+
+    #[test]
+    fn rejects_expired_options() {
+        assert_eq!(implied_volatility(&inputs(OptionType::Put, 100.0, 100.0, 0.0, 0.2), 1.0), Err(ImpliedVolError::Expired));
+    }
+
+    #[test]
+    fn prices_with_no_time_value_have_minimum_volatility() {
+        let option = inputs(OptionType::Call, 100.0, 100.0, 0.5, 0.0);
+        let lower_bound = 100.0 * (-0.01f64 * 0.5).exp() - 100.0 * (-0.05f64 * 0.5).exp();
+        assert_eq!(implied_volatility(&option, lower_bound), Ok(MIN_VOLATILITY));
+    }
+
+    #[test]
+    fn reports_prices_no_volatility_reaches() {
+        // Worth about 97.8 at the 500% volatility cap, below the 99.0 bound
+        let option = inputs(OptionType::Call, 100.0, 100.0, 1.0, 0.0);
+        assert_eq!(implied_volatility(&option, 99.0), Err(ImpliedVolError::NoConvergence));
+    }
+
+    #[test]
+    fn attribution_adds_up() {
+        let entry = inputs(OptionType::Call, 100.0, 100.0, 0.5, 0.3);
+        let exit = inputs(OptionType::Call, 105.0, 100.0, 0.4, 0.2);
+        let attribution = attribute_pnl(&entry, &exit);
+
+        assert!(attribution.direction > 0.0);
+        assert!(attribution.time_decay < 0.0);
+        assert!(attribution.volatility < 0.0);
+        let sum = attribution.direction + attribution.time_decay + attribution.volatility;
+        assert!((sum - attribution.total).abs() < 1e-12);
+    }
+}
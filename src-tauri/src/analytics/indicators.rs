@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::models::indicator::{Indicator, IndicatorPoint};
+use crate::models::price_bar::PriceBar;
+
+// Every function returns one value per input bar, oldest first, with None
+// until enough bars have been seen.
+
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+
+    let mut sum = 0.0;
+    for (i, value) in values.iter().enumerate() {
+        sum += value;
+        if i >= period {
+            sum -= values[i - period];
+        }
+        if i + 1 >= period {
+            out[i] = Some(sum / period as f64);
+        }
+    }
+
+    out
+}
+
+/// Exponential moving average seeded with the simple average of the first
+/// `period` values.
+pub fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return out;
+    }
+
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut current = values[..period].iter().sum::<f64>() / period as f64;
+    out[period - 1] = Some(current);
+    for i in period..values.len() {
+        current += alpha * (values[i] - current);
+        out[i] = Some(current);
+    }
+
+    out
+}
+
+/// Wilder's running average, as used by ATR, RSI and ADX.
+fn wilder(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return out;
+    }
+
+    let mut current = values[..period].iter().sum::<f64>() / period as f64;
+    out[period - 1] = Some(current);
+    for i in period..values.len() {
+        current += (values[i] - current) / period as f64;
+        out[i] = Some(current);
+    }
+
+    out
+}
+
+/// Applies `f` to the values from the first Some onwards, keeping alignment.
+fn on_defined(values: &[Option<f64>], f: impl Fn(&[f64]) -> Vec<Option<f64>>) -> Vec<Option<f64>> {
+    let start = values.iter().position(Option::is_some).unwrap_or(values.len());
+    let defined: Vec<f64> = values[start..].iter().map(|v| v.unwrap_or_default()).collect();
+
+    let mut out = vec![None; start];
+    out.extend(f(&defined));
+    out
+}
+
+fn true_ranges(bars: &[PriceBar]) -> Vec<f64> {
+    bars.iter().enumerate().map(|(i, bar)| {
+        let range = bar.high - bar.low;
+        match i.checked_sub(1).map(|prev| bars[prev].close) {
+            Some(prev_close) => range.max((bar.high - prev_close).abs()).max((bar.low - prev_close).abs()),
+            None => range,
+        }
+    }).collect()
+}
+
+pub fn atr(bars: &[PriceBar], period: usize) -> Vec<Option<f64>> {
+    wilder(&true_ranges(bars), period)
+}
+
+pub fn rsi(closes: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; closes.len()];
+    if closes.len() < 2 {
+        return out;
+    }
+
+    let changes: Vec<f64> = closes.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let gains = wilder(&changes.iter().map(|c| c.max(0.0)).collect::<Vec<_>>(), period);
+    let losses = wilder(&changes.iter().map(|c| (-c).max(0.0)).collect::<Vec<_>>(), period);
+
+    for (i, (gain, loss)) in gains.iter().zip(&losses).enumerate() {
+        if let (Some(gain), Some(loss)) = (gain, loss) {
+            out[i + 1] = Some(if *loss == 0.0 {
+                if *gain == 0.0 { 50.0 } else { 100.0 }
+            } else {
+                100.0 - 100.0 / (1.0 + gain / loss)
+            });
+        }
+    }
+
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdPoint {
+    pub macd: f64,
+    pub signal: Option<f64>,
+    pub histogram: Option<f64>,
+}
+
+pub fn macd(closes: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<Option<MacdPoint>> {
+    let fast_ema = ema(closes, fast);
+    let slow_ema = ema(closes, slow);
+    let line: Vec<Option<f64>> = fast_ema.iter().zip(&slow_ema)
+        .map(|(f, s)| Some((*f)? - (*s)?))
+        .collect();
+    let signal_line = on_defined(&line, |values| ema(values, signal));
+
+    line.iter().zip(&signal_line).map(|(macd, signal)| {
+        macd.map(|macd| MacdPoint {
+            macd,
+            signal: *signal,
+            histogram: signal.map(|signal| macd - signal),
+        })
+    }).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+pub fn bollinger_bands(closes: &[f64], period: usize, std_devs: f64) -> Vec<Option<Band>> {
+    sma(closes, period).iter().enumerate().map(|(i, middle)| {
+        let middle = (*middle)?;
+        let window = &closes[i + 1 - period..=i];
+        let variance = window.iter().map(|c| (c - middle).powi(2)).sum::<f64>() / period as f64;
+        let width = std_devs * variance.sqrt();
+
+        Some(Band { middle, upper: middle + width, lower: middle - width })
+    }).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdxPoint {
+    pub adx: Option<f64>,
+    pub plus_di: f64,
+    pub minus_di: f64,
+}
+
+/// Wilder's average directional index with the +DI and -DI lines.
+pub fn adx(bars: &[PriceBar], period: usize) -> Vec<Option<AdxPoint>> {
+    let mut out = vec![None; bars.len()];
+    if bars.len() < 2 {
+        return out;
+    }
+
+    let mut plus_dm = Vec::with_capacity(bars.len() - 1);
+    let mut minus_dm = Vec::with_capacity(bars.len() - 1);
+    for pair in bars.windows(2) {
+        let up = pair[1].high - pair[0].high;
+        let down = pair[0].low - pair[1].low;
+        plus_dm.push(if up > down && up > 0.0 { up } else { 0.0 });
+        minus_dm.push(if down > up && down > 0.0 { down } else { 0.0 });
+    }
+
+    let tr = wilder(&true_ranges(bars)[1..], period);
+    let plus = wilder(&plus_dm, period);
+    let minus = wilder(&minus_dm, period);
+
+    let lines: Vec<Option<(f64, f64)>> = (0..tr.len()).map(|i| {
+        let tr = tr[i].filter(|tr| *tr > 0.0)?;
+        Some((100.0 * plus[i]? / tr, 100.0 * minus[i]? / tr))
+    }).collect();
+    let dx: Vec<Option<f64>> = lines.iter().map(|line| {
+        line.map(|(plus_di, minus_di)| {
+            let total = plus_di + minus_di;
+            if total > 0.0 { 100.0 * (plus_di - minus_di).abs() / total } else { 0.0 }
+        })
+    }).collect();
+    let adx_line = on_defined(&dx, |values| wilder(values, period));
+
+    for (i, line) in lines.iter().enumerate() {
+        if let Some((plus_di, minus_di)) = line {
+            out[i + 1] = Some(AdxPoint { adx: adx_line[i], plus_di: *plus_di, minus_di: *minus_di });
+        }
+    }
+
+    out
+}
+
+/// Volume-weighted average of the typical price. With a `session_zone` the
+/// average restarts on each calendar day in that zone, as for intraday bars;
+/// otherwise it is anchored at the first bar.
+pub fn vwap(bars: &[PriceBar], session_zone: Option<Tz>) -> Vec<Option<f64>> {
+    let mut out = Vec::with_capacity(bars.len());
+    let mut price_volume = 0.0;
+    let mut volume = 0.0;
+
+    for (i, bar) in bars.iter().enumerate() {
+        let new_day = session_zone.is_some_and(|zone| {
+            i > 0 && bar.timestamp.with_timezone(&zone).date_naive() != bars[i - 1].timestamp.with_timezone(&zone).date_naive()
+        });
+        if new_day {
+            price_volume = 0.0;
+            volume = 0.0;
+        }
+
+        price_volume += (bar.high + bar.low + bar.close) / 3.0 * bar.volume;
+        volume += bar.volume;
+        out.push(if volume > 0.0 { Some(price_volume / volume) } else { None });
+    }
+
+    out
+}
+
+/// Performance of `asset` relative to `benchmark` since the first bar both
+/// have, 1.0 meaning they moved alike. Bars are matched by timestamp.
+pub fn relative_strength(asset: &[PriceBar], benchmark: &[PriceBar]) -> Vec<Option<f64>> {
+    let benchmark_closes: HashMap<DateTime<Utc>, f64> = benchmark.iter()
+        .map(|bar| (bar.timestamp, bar.close))
+        .collect();
+
+    let mut base: Option<(f64, f64)> = None;
+    asset.iter().map(|bar| {
+        let benchmark_close = *benchmark_closes.get(&bar.timestamp)?;
+        if bar.close <= 0.0 || benchmark_close <= 0.0 {
+            return None;
+        }
+
+        let (asset_base, benchmark_base) = *base.get_or_insert((bar.close, benchmark_close));
+        Some((bar.close / asset_base) / (benchmark_close / benchmark_base))
+    }).collect()
+}
+
+/// Computes `indicator` over `bars`. `benchmark` holds the benchmark's bars
+/// for relative strength and is ignored otherwise; `session_zone` is passed
+/// to VWAP.
+pub fn compute(indicator: &Indicator, bars: &[PriceBar], benchmark: &[PriceBar], session_zone: Option<Tz>) -> Vec<IndicatorPoint> {
+    let closes: Vec<f64> = bars.iter().map(|bar| bar.close).collect();
+
+    let values: Vec<Vec<Option<f64>>> = match indicator {
+        Indicator::Sma { period } => single(sma(&closes, *period)),
+        Indicator::Ema { period } => single(ema(&closes, *period)),
+        Indicator::Atr { period } => single(atr(bars, *period)),
+        Indicator::Rsi { period } => single(rsi(&closes, *period)),
+        Indicator::Macd { fast, slow, signal } => macd(&closes, *fast, *slow, *signal).iter()
+            .map(|point| match point {
+                Some(p) => vec![Some(p.macd), p.signal, p.histogram],
+                None => vec![None; 3],
+            })
+            .collect(),
+        Indicator::BollingerBands { period, std_devs } => bollinger_bands(&closes, *period, *std_devs).iter()
+            .map(|band| match band {
+                Some(b) => vec![Some(b.middle), Some(b.upper), Some(b.lower)],
+                None => vec![None; 3],
+            })
+            .collect(),
+        Indicator::Adx { period } => adx(bars, *period).iter()
+            .map(|point| match point {
+                Some(p) => vec![p.adx, Some(p.plus_di), Some(p.minus_di)],
+                None => vec![None; 3],
+            })
+            .collect(),
+        Indicator::Vwap => single(vwap(bars, session_zone)),
+        Indicator::RelativeStrength { .. } => single(relative_strength(bars, benchmark)),
+    };
+
+    bars.iter().zip(values)
+        .map(|(bar, values)| IndicatorPoint { timestamp: bar.timestamp, values })
+        .collect()
+}
+
+fn single(values: Vec<Option<f64>>) -> Vec<Vec<Option<f64>>> {
+    values.into_iter().map(|value| vec![value]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::models::price_bar::Timeframe;
+
+    fn bar(timestamp: DateTime<Utc>, high: f64, low: f64, close: f64, volume: f64) -> PriceBar {
+        PriceBar {
+            symbol: "AAPL".to_string(),
+            timeframe: Timeframe::Daily,
+            timestamp,
+            open: close,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+
+    fn daily(ranges: &[(f64, f64, f64)]) -> Vec<PriceBar> {
+        let first = Utc.with_ymd_and_hms(2024, 6, 3, 20, 0, 0).unwrap();
+        ranges.iter().enumerate()
+            .map(|(i, (high, low, close))| bar(first + Duration::days(i as i64), *high, *low, *close, 1000.0))
+            .collect()
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn moving_averages_start_after_a_full_period() {
+        assert_eq!(sma(&[1.0, 2.0, 3.0, 4.0, 5.0], 3), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(ema(&[2.0, 4.0, 6.0, 8.0, 12.0], 3), vec![None, None, Some(4.0), Some(6.0), Some(9.0)]);
+        assert!(sma(&[1.0, 2.0], 0).iter().all(Option::is_none));
+        assert!(ema(&[1.0, 2.0], 3).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn rsi_uses_wilder_averages_of_gains_and_losses() {
+        let values = rsi(&[1.0, 2.0, 1.0, 2.0, 1.0], 2);
+
+        assert_eq!(values[..2], [None, None]);
+        assert_close(values[2], 50.0);
+        assert_close(values[3], 75.0);
+        assert_close(values[4], 37.5);
+        assert_close(rsi(&[1.0, 2.0, 3.0, 4.0], 2)[3], 100.0);
+        assert_close(rsi(&[5.0, 5.0, 5.0], 2)[2], 50.0);
+    }
+
+    #[test]
+    fn atr_counts_gaps_from_the_previous_close() {
+        let bars = daily(&[(10.0, 8.0, 9.0), (12.0, 9.0, 11.0), (11.0, 10.0, 10.5)]);
+        let values = atr(&bars, 2);
+
+        assert_eq!(values[0], None);
+        assert_close(values[1], 2.5);
+        assert_close(values[2], 1.75);
+    }
+
+    #[test]
+    fn bollinger_bands_use_the_population_deviation() {
+        let closes = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let band = bollinger_bands(&closes, 8, 2.0)[7].unwrap();
+
+        assert_eq!(band, Band { middle: 5.0, upper: 9.0, lower: 1.0 });
+    }
+
+    #[test]
+    fn macd_of_a_flat_series_is_zero() {
+        let points = macd(&[10.0; 12], 3, 6, 3);
+
+        assert_eq!(points[4], None);
+        assert_eq!(points[5], Some(MacdPoint { macd: 0.0, signal: None, histogram: None }));
+        assert_eq!(points[7], Some(MacdPoint { macd: 0.0, signal: Some(0.0), histogram: Some(0.0) }));
+    }
+
+    #[test]
+    fn adx_of_a_steady_climb_is_at_its_maximum() {
+        let ranges: Vec<(f64, f64, f64)> = (0..12).map(|i| (i as f64 + 11.0, i as f64 + 9.0, i as f64 + 10.0)).collect();
+        let points = adx(&daily(&ranges), 3);
+
+        let last = points[11].unwrap();
+        assert_close(Some(last.plus_di), 50.0);
+        assert_close(Some(last.minus_di), 0.0);
+        assert_close(last.adx, 100.0);
+        assert_eq!(points[0], None);
+    }
+
+    #[test]
+    fn vwap_restarts_on_each_exchange_day() {
+        // 23:00 and 01:00 UTC fall on the same New York evening
+        let bars = vec![
+            bar(Utc.with_ymd_and_hms(2024, 6, 4, 23, 0, 0).unwrap(), 10.0, 10.0, 10.0, 100.0),
+            bar(Utc.with_ymd_and_hms(2024, 6, 5, 1, 0, 0).unwrap(), 20.0, 20.0, 20.0, 100.0),
+            bar(Utc.with_ymd_and_hms(2024, 6, 5, 15, 0, 0).unwrap(), 30.0, 30.0, 30.0, 100.0),
+        ];
+
+        let values = vwap(&bars, Some(Tz::America__New_York));
+        assert_eq!(values, vec![Some(10.0), Some(15.0), Some(30.0)]);
+        assert_eq!(vwap(&bars, None)[2], Some(20.0));
+    }
+
+    #[test]
+    fn relative_strength_compares_growth_on_shared_bars() {
+        let asset = daily(&[(10.0, 10.0, 10.0), (15.0, 15.0, 15.0), (20.0, 20.0, 20.0)]);
+        let benchmark = daily(&[(50.0, 50.0, 50.0), (50.0, 50.0, 50.0)]);
+
+        assert_eq!(relative_strength(&asset, &benchmark), vec![Some(1.0), Some(1.5), None]);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod black_scholes;
+pub mod implied_volatility;
+pub mod payoff;
+pub mod beta;
+pub mod portfolio_greeks;
+pub mod pattern_stats;
+pub mod opportunity;
+pub mod chart_patterns;
+pub mod indicators;
+pub mod trend;
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::pattern_stats::PatternStats;
+use crate::models::stock_rating::{ChartPattern, StockRating};
+
+pub const DEFAULT_SCORE_BINS: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SentimentPoint {
+    pub timestamp: DateTime<Utc>,
+    pub overall_score: f64,
+    pub market_sentiment: i32,
+    pub sector_sentiment: i32,
+    pub security_sentiment: i32,
+    pub confidence: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoreBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoreDistribution {
+    pub bins: Vec<ScoreBin>,
+    pub total: usize,
+    pub current_score: Option<f64>,
+    pub current_percentile: Option<f64>,   // Share of scores at or below the current score, 0 to 100
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternGauge {
+    pub pattern: ChartPattern,
+    pub bonus: f64,
+    pub min: f64,
+    pub max: f64,
+    pub frequency: Option<f64>,
+    pub hit_rate: Option<f64>,
+    pub closed_trades: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpportunityChart {
+    pub symbol: String,
+    pub history: Vec<SentimentPoint>,      // Oldest first
+    pub distribution: ScoreDistribution,
+    pub pattern_gauge: Option<PatternGauge>,
+}
+
+impl From<&StockRating> for SentimentPoint {
+    fn from(rating: &StockRating) -> Self {
+        Self {
+            timestamp: rating.timestamp,
+            overall_score: rating.overall_score,
+            market_sentiment: rating.market_sentiment,
+            sector_sentiment: rating.sector_sentiment,
+            security_sentiment: rating.security_sentiment,
+            confidence: rating.confidence,
+        }
+    }
+}
+
+/// Builds the chart for one security. `history` is its ratings in any order,
+/// `all_scores` the scores it is compared against.
+pub fn build_opportunity_chart(
+    symbol: &str,
+    history: &[StockRating],
+    all_scores: &[f64],
+    pattern_stats: Option<&PatternStats>,
+    bins: usize,
+) -> OpportunityChart {
+    let mut points: Vec<SentimentPoint> = history.iter().map(SentimentPoint::from).collect();
+    points.sort_by_key(|point| point.timestamp);
+
+    let latest = history.iter().max_by_key(|rating| rating.timestamp);
+
+    OpportunityChart {
+        symbol: symbol.to_string(),
+        history: points,
+        distribution: score_distribution(all_scores, latest.map(|rating| rating.overall_score), bins),
+        pattern_gauge: latest.map(|rating| PatternGauge {
+            pattern: rating.chart_pattern.clone(),
+            bonus: rating.pattern_bonus,
+            min: -1.0,
+            max: 1.0,
+            frequency: pattern_stats.map(|stats| stats.frequency),
+            hit_rate: pattern_stats.and_then(|stats| stats.for_direction(rating.bull_bear).hit_rate),
+            closed_trades: pattern_stats.map_or(0, |stats| stats.for_direction(rating.bull_bear).closed_trades),
+        }),
+    }
+}
+
+/// Histogram of `scores` in `bins` equal-width bins spanning their range,
+/// widened to include the current score.
+pub fn score_distribution(scores: &[f64], current_score: Option<f64>, bins: usize) -> ScoreDistribution {
+    let bins = bins.max(1);
+    let values = scores.iter().copied().chain(current_score);
+    let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+
+    let mut histogram = Vec::new();
+    if low.is_finite() {
+        // Give a single repeated score a bin of width one around it
+        let (low, high) = if high - low < 1e-9 { (low - 0.5, high + 0.5) } else { (low, high) };
+        let width = (high - low) / bins as f64;
+
+        histogram = (0..bins).map(|i| ScoreBin {
+            lower: low + width * i as f64,
+            upper: low + width * (i + 1) as f64,
+            count: 0,
+        }).collect();
+
+        for score in scores {
+            let index = (((score - low) / width) as usize).min(bins - 1);
+            histogram[index].count += 1;
+        }
+    }
+
+    let current_percentile = match current_score {
+        Some(current) if !scores.is_empty() => {
+            let at_or_below = scores.iter().filter(|score| **score <= current).count();
+            Some(at_or_below as f64 / scores.len() as f64 * 100.0)
+        }
+        _ => None,
+    };
+
+    ScoreDistribution {
+        bins: histogram,
+        total: scores.len(),
+        current_score,
+        current_percentile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn scores_fill_equal_width_bins() {
+        let distribution = score_distribution(&[0.0, 1.0, 2.0, 3.0, 4.0], Some(3.0), 4);
+
+        let counts: Vec<usize> = distribution.bins.iter().map(|bin| bin.count).collect();
+        assert_eq!(counts, vec![1, 1, 1, 2]);
+        assert_eq!((distribution.bins[0].lower, distribution.bins[3].upper), (0.0, 4.0));
+        assert_eq!(distribution.current_percentile, Some(80.0));
+    }
+
+    #[test]
+    fn range_widens_to_the_current_score() {
+        let distribution = score_distribution(&[2.0, 2.0], Some(6.0), 2);
+
+        assert_eq!(distribution.bins[1].upper, 6.0);
+        assert_eq!(distribution.bins[0].count, 2);
+        assert_eq!(distribution.current_percentile, Some(100.0));
+    }
+
+    #[test]
+    fn single_score_gets_a_bin_around_it() {
+        let distribution = score_distribution(&[5.0], None, 1);
+
+        assert_eq!((distribution.bins[0].lower, distribution.bins[0].upper), (4.5, 5.5));
+        assert_eq!(distribution.current_percentile, None);
+        assert!(score_distribution(&[], None, 3).bins.is_empty());
+    }
+
+    #[test]
+    fn chart_orders_history_and_gauges_the_latest_rating() {
+        let now = Utc::now();
+        let mut older = StockRating::new("AAPL", "Information Technology");
+        older.timestamp = now - Duration::days(10);
+        older.overall_score = 2.0;
+        let mut latest = StockRating::new("AAPL", "Information Technology");
+        latest.timestamp = now;
+        latest.overall_score = 4.0;
+        latest.chart_pattern = ChartPattern::Cup;
+        latest.pattern_bonus = 0.5;
+
+        let chart = build_opportunity_chart("AAPL", &[latest, older], &[1.0, 4.0, 6.0], None, DEFAULT_SCORE_BINS);
+
+        assert_eq!(chart.history.iter().map(|point| point.overall_score).collect::<Vec<_>>(), vec![2.0, 4.0]);
+        let gauge = chart.pattern_gauge.unwrap();
+        assert_eq!((gauge.pattern, gauge.bonus, gauge.closed_trades), (ChartPattern::Cup, 0.5, 0));
+        assert_eq!(chart.distribution.current_score, Some(4.0));
+    }
+}
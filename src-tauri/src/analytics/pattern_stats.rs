@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::stock_rating::ChartPattern;
+
+/// Closed trades it takes before a pattern's own hit rate outweighs its
+/// textbook bias in the bonus.
+const PRIOR_TRADES: f64 = 10.0;
+/// Weight of the textbook bias when there is no trade history at all.
+const PRIOR_EDGE: f64 = 0.5;
+
+/// Pattern of a rating and when it was rated.
+pub type RatedPattern = (ChartPattern, DateTime<Utc>);
+
+/// A closed trade taken on a pattern.
+#[derive(Debug, Clone)]
+pub struct PatternOutcome {
+    pub pattern: ChartPattern,
+    pub bull_bear: i8,             // Direction of the analysis behind the trade
+    pub profit_loss: f64,
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Closed trades on a pattern in one direction.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DirectionStats {
+    pub closed_trades: usize,
+    pub winning_trades: usize,
+    pub hit_rate: Option<f64>,     // None without closed trades
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternStats {
+    pub pattern: ChartPattern,
+    pub occurrences: usize,
+    pub frequency: f64,            // Share of ratings in the window, 0 to 1
+    pub long: DirectionStats,      // Trades on bull analyses
+    pub short: DirectionStats,     // Trades on bear analyses
+}
+
+impl PatternStats {
+    /// Trades taken in the direction of `bull_bear`.
+    pub fn for_direction(&self, bull_bear: i8) -> &DirectionStats {
+        if bull_bear < 0 { &self.short } else { &self.long }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternWindow {
+    pub days: Option<i64>,         // None for all history
+    pub total_ratings: usize,
+    pub patterns: Vec<PatternStats>,   // Most frequent first
+}
+
+/// Counts pattern occurrences among `ratings` in the `days` before `now` and
+/// attaches the hit rates, per direction, of trades on each pattern closed
+/// before `now`.
+pub fn pattern_window(
+    ratings: &[RatedPattern],
+    outcomes: &[PatternOutcome],
+    days: Option<i64>,
+    now: DateTime<Utc>,
+) -> PatternWindow {
+    let since = days.map(|days| now - Duration::days(days));
+    let in_window: Vec<&ChartPattern> = ratings.iter()
+        .filter(|(_, timestamp)| *timestamp <= now && !matches!(since, Some(since) if *timestamp < since))
+        .map(|(pattern, _)| pattern)
+        .collect();
+
+    let mut counts: HashMap<&ChartPattern, usize> = HashMap::new();
+    for pattern in &in_window {
+        *counts.entry(*pattern).or_default() += 1;
+    }
+
+    let total_ratings = in_window.len();
+    let mut patterns: Vec<PatternStats> = counts.into_iter()
+        .map(|(pattern, occurrences)| {
+            let known: Vec<&PatternOutcome> = outcomes.iter()
+                .filter(|outcome| outcome.pattern == *pattern && outcome.closed_at < now)
+                .collect();
+
+            PatternStats {
+                pattern: pattern.clone(),
+                occurrences,
+                frequency: occurrences as f64 / total_ratings as f64,
+                long: direction_stats(known.iter().filter(|outcome| outcome.bull_bear >= 0)),
+                short: direction_stats(known.iter().filter(|outcome| outcome.bull_bear < 0)),
+            }
+        })
+        .collect();
+    patterns.sort_by(|a, b| {
+        let trades = |stats: &PatternStats| stats.long.closed_trades + stats.short.closed_trades;
+        b.occurrences.cmp(&a.occurrences).then_with(|| trades(b).cmp(&trades(a)))
+    });
+
+    PatternWindow { days, total_ratings, patterns }
+}
+
+fn direction_stats<'a>(outcomes: impl Iterator<Item = &'a &'a PatternOutcome>) -> DirectionStats {
+    let mut stats = DirectionStats::default();
+    for outcome in outcomes {
+        stats.closed_trades += 1;
+        if outcome.profit_loss > 0.0 {
+            stats.winning_trades += 1;
+        }
+    }
+    if stats.closed_trades > 0 {
+        stats.hit_rate = Some(stats.winning_trades as f64 / stats.closed_trades as f64);
+    }
+
+    stats
+}
+
+/// Bonus, from -1 to 1, for a rating with the given pattern and direction,
+/// positive when the pattern supports the direction. The edge of a pattern is
+/// the hit rate of trades taken in that direction mapped onto -1 to 1,
+/// blended with its textbook bias until it has enough closed trades. Rare
+/// patterns keep their full edge, while a pattern seen on every rating keeps
+/// half of it.
+pub fn pattern_bonus(pattern: &ChartPattern, stats: Option<&PatternStats>, bull_bear: i8) -> f64 {
+    let prior = pattern.bias() * bull_bear as f64 * PRIOR_EDGE;
+
+    let (edge, rarity) = match stats {
+        Some(stats) => {
+            let direction = stats.for_direction(bull_bear);
+            let edge = match direction.hit_rate {
+                Some(hit_rate) => {
+                    let trades = direction.closed_trades as f64;
+                    let observed = hit_rate * 2.0 - 1.0;
+                    (prior * PRIOR_TRADES + observed * trades) / (PRIOR_TRADES + trades)
+                }
+                None => prior,
+            };
+            (edge, 1.0 - stats.frequency)
+        }
+        None => (prior, 1.0),
+    };
+
+    edge * (0.5 + 0.5 * rarity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap()
+    }
+
+    fn outcome(bull_bear: i8, profit_loss: f64, day: u32) -> PatternOutcome {
+        PatternOutcome { pattern: ChartPattern::Cup, bull_bear, profit_loss, closed_at: at(day) }
+    }
+
+    #[test]
+    fn hit_rates_are_kept_per_direction() {
+        let ratings = vec![(ChartPattern::Cup, at(1))];
+        let outcomes = vec![outcome(1, 100.0, 2), outcome(1, -50.0, 3), outcome(-1, 80.0, 4)];
+
+        let window = pattern_window(&ratings, &outcomes, None, at(10));
+        let stats = &window.patterns[0];
+        assert_eq!(stats.long, DirectionStats { closed_trades: 2, winning_trades: 1, hit_rate: Some(0.5) });
+        assert_eq!(stats.short, DirectionStats { closed_trades: 1, winning_trades: 1, hit_rate: Some(1.0) });
+    }
+
+    #[test]
+    fn trades_closed_later_are_ignored() {
+        let ratings = vec![(ChartPattern::Cup, at(1))];
+        let outcomes = vec![outcome(1, 100.0, 2), outcome(1, 100.0, 20)];
+
+        let window = pattern_window(&ratings, &outcomes, None, at(10));
+        assert_eq!(window.patterns[0].long.closed_trades, 1);
+    }
+
+    #[test]
+    fn bonus_is_relative_to_the_rating_direction() {
+        // Without history the bonus follows the textbook bias
+        assert!(pattern_bonus(&ChartPattern::Cup, None, 1) > 0.0);
+        assert!(pattern_bonus(&ChartPattern::Cup, None, -1) < 0.0);
+        assert!(pattern_bonus(&ChartPattern::DoubleTop, None, -1) > 0.0);
+
+        // Winning shorts on a bullish pattern help bear ratings, not bull ones
+        let ratings = vec![(ChartPattern::Cup, at(1))];
+        let outcomes: Vec<PatternOutcome> = (2..22).map(|day| outcome(-1, 10.0, day)).collect();
+        let window = pattern_window(&ratings, &outcomes, None, at(25));
+        let stats = window.patterns.first();
+        assert!(pattern_bonus(&ChartPattern::Cup, stats, -1) > 0.0);
+        assert_eq!(pattern_bonus(&ChartPattern::Cup, stats, 1), pattern_bonus(&ChartPattern::Cup, None, 1) * 0.5);
+    }
+}
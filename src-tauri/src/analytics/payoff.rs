@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+
+use super::black_scholes::{self, OptionType};
+use crate::models::calendar::TradingCalendar;
+use crate::models::option_leg::OptionLeg;
+
+pub const CONTRACT_MULTIPLIER: f64 = 100.0;
+const DEFAULT_PRICE_STEPS: usize = 200;
+const DEFAULT_RANGE_PERCENT: f64 = 0.3;
+
+/// Legs and shares making up one position, scaled by `quantity` spread units.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoffPosition {
+    pub legs: Vec<OptionLeg>,
+    pub shares: f64,              // Signed, negative for short stock
+    pub share_cost: f64,          // Entry price of the shares
+    pub quantity: f64,
+    pub multiplier: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoffRequest {
+    pub underlying_price: f64,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    pub steps: Option<usize>,
+    pub scenario_dates: Vec<NaiveDate>,
+    pub rate: f64,
+    pub dividend_yield: f64,
+    pub volatility: f64,          // Used for legs without an entry IV
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoffCurve {
+    pub label: String,
+    pub date: NaiveDate,
+    pub pnl: Vec<f64>,            // One value per entry of `PayoffDiagram::prices`
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoffDiagram {
+    pub prices: Vec<f64>,
+    pub expiry_curve: PayoffCurve,
+    pub scenario_curves: Vec<PayoffCurve>,
+    pub breakevens: Vec<f64>,
+    pub max_profit: Option<f64>,  // None when unlimited
+    pub max_loss: Option<f64>,    // None when unlimited, otherwise negative
+}
+
+impl PayoffPosition {
+    pub fn from_legs(legs: Vec<OptionLeg>, quantity: f64) -> Self {
+        Self {
+            legs,
+            shares: 0.0,
+            share_cost: 0.0,
+            quantity,
+            multiplier: CONTRACT_MULTIPLIER,
+        }
+    }
+
+    pub fn first_expiry(&self) -> Option<NaiveDate> {
+        self.legs.iter().map(|leg| leg.expiry).min()
+    }
+
+    /// Position P&L if the underlying is at `underlying_price` at the close of
+    /// `date` on `calendar`. Legs expiring on or before `date` are worth
+    /// intrinsic value, the rest are priced with Black-Scholes at their entry IV.
+    pub fn pnl_at(&self, underlying_price: f64, date: NaiveDate, calendar: &TradingCalendar, request: &PayoffRequest) -> f64 {
+        let valuation_time = calendar.expiry_close(date);
+
+        let options: f64 = self.legs.iter().map(|leg| {
+            let volatility = leg.entry_iv.unwrap_or(request.volatility);
+            let inputs = leg.pricing_inputs(underlying_price, valuation_time, calendar, request.rate, request.dividend_yield, volatility);
+            let value = black_scholes::price(&inputs);
+            leg.direction() * leg.ratio as f64 * (value - leg.premium) * self.multiplier
+        }).sum();
+
+        let stock = self.shares * (underlying_price - self.share_cost);
+
+        (options + stock) * self.quantity
+    }
+
+    /// Rate at which expiry P&L grows per dollar of underlying far above all
+    /// strikes; positive means unlimited profit, negative unlimited loss.
+    fn upside_slope(&self) -> f64 {
+        let calls: f64 = self.legs.iter()
+            .filter(|leg| leg.option_type == OptionType::Call)
+            .map(|leg| leg.direction() * leg.ratio as f64 * self.multiplier)
+            .sum();
+        (calls + self.shares) * self.quantity
+    }
+}
+
+/// Payoff of `position` at its first expiry and at each scenario date, on
+/// the sessions of `calendar`. A position without legs is valued as of
+/// `today`, the exchange date.
+pub fn build_payoff(position: &PayoffPosition, request: &PayoffRequest, calendar: &TradingCalendar, today: NaiveDate) -> PayoffDiagram {
+    let prices = price_grid(position, request);
+    let expiry = position.first_expiry().unwrap_or(today);
+
+    let curve = |label: String, date: NaiveDate| PayoffCurve {
+        pnl: prices.iter().map(|&p| position.pnl_at(p, date, calendar, request)).collect(),
+        label,
+        date,
+    };
+
+    let expiry_curve = curve("Expiry".to_string(), expiry);
+    let scenario_curves = request.scenario_dates.iter()
+        .filter(|&&date| date < expiry)
+        .map(|&date| curve(date.to_string(), date))
+        .collect();
+
+    let breakevens = find_breakevens(&prices, &expiry_curve.pnl);
+
+    let slope = position.upside_slope();
+    let grid_max = expiry_curve.pnl.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let grid_min = expiry_curve.pnl.iter().cloned().fold(f64::INFINITY, f64::min);
+    // The underlying cannot go below zero, so only the upside can be unbounded
+    let floor = position.pnl_at(0.0, expiry, calendar, request);
+
+    PayoffDiagram {
+        prices,
+        expiry_curve,
+        scenario_curves,
+        breakevens,
+        max_profit: if slope > 1e-9 { None } else { Some(grid_max.max(floor)) },
+        max_loss: if slope < -1e-9 { None } else { Some(grid_min.min(floor)) },
+    }
+}
+
+/// Evenly spaced prices around the underlying, with every strike added so the
+/// kinks of the expiry curve fall exactly on grid points.
+fn price_grid(position: &PayoffPosition, request: &PayoffRequest) -> Vec<f64> {
+    let strikes = position.legs.iter().map(|leg| leg.strike);
+    let lowest_strike = strikes.clone().fold(request.underlying_price, f64::min);
+    let highest_strike = strikes.fold(request.underlying_price, f64::max);
+
+    let min_price = request.min_price
+        .unwrap_or(lowest_strike * (1.0 - DEFAULT_RANGE_PERCENT))
+        .max(0.0);
+    let max_price = request.max_price
+        .unwrap_or(highest_strike * (1.0 + DEFAULT_RANGE_PERCENT))
+        .max(min_price);
+    let steps = request.steps.unwrap_or(DEFAULT_PRICE_STEPS).max(1);
+
+    let step = (max_price - min_price) / steps as f64;
+    let mut prices: Vec<f64> = (0..=steps).map(|i| min_price + step * i as f64).collect();
+    prices.extend(position.legs.iter()
+        .map(|leg| leg.strike)
+        .filter(|&k| k > min_price && k < max_price));
+    prices.sort_by(f64::total_cmp);
+    prices.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+    prices
+}
+
+fn find_breakevens(prices: &[f64], pnl: &[f64]) -> Vec<f64> {
+    let mut breakevens = Vec::new();
+
+    for i in 0..prices.len() {
+        if pnl[i] == 0.0 {
+            breakevens.push(prices[i]);
+        } else if i + 1 < prices.len() && pnl[i + 1] != 0.0 && (pnl[i] < 0.0) != (pnl[i + 1] < 0.0) {
+            let fraction = pnl[i] / (pnl[i] - pnl[i + 1]);
+            breakevens.push(prices[i] + fraction * (prices[i + 1] - prices[i]));
+        }
+    }
+
+    breakevens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::option_leg::LegSide;
+    use crate::services::calendar_service;
+
+    fn request(underlying_price: f64) -> PayoffRequest {
+        PayoffRequest {
+            underlying_price,
+            min_price: None,
+            max_price: None,
+            steps: Some(100),
+            scenario_dates: Vec::new(),
+            rate: 0.05,
+            dividend_yield: 0.0,
+            volatility: 0.3,
+        }
+    }
+
+    #[test]
+    fn long_call_risks_the_premium_for_unlimited_profit() {
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let mut leg = OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Buy);
+        leg.premium = 5.0;
+        let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        let diagram = build_payoff(&PayoffPosition::from_legs(vec![leg], 1.0), &request(100.0), &calendar_service::built_in_calendar().unwrap(), today);
+
+        assert_eq!(diagram.expiry_curve.date, expiry);
+        assert_eq!(diagram.max_profit, None);
+        assert!((diagram.max_loss.unwrap() + 500.0).abs() < 1e-6);
+        assert_eq!(diagram.breakevens.len(), 1);
+        assert!((diagram.breakevens[0] - 105.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stock_is_valued_on_the_given_exchange_date() {
+        let mut position = PayoffPosition::from_legs(Vec::new(), 1.0);
+        position.shares = 10.0;
+        position.share_cost = 50.0;
+        let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        let diagram = build_payoff(&position, &request(50.0), &calendar_service::built_in_calendar().unwrap(), today);
+
+        assert_eq!(diagram.expiry_curve.date, today);
+        assert!((diagram.breakevens[0] - 50.0).abs() < 1e-6);
+        assert!((diagram.max_loss.unwrap() + 500.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unbounded_price_range_does_not_panic() {
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let leg = OptionLeg::new("AAPL", expiry, 100.0, OptionType::Put, LegSide::Sell);
+        let mut request = request(100.0);
+        request.max_price = Some(f64::INFINITY);
+
+        let diagram = build_payoff(&PayoffPosition::from_legs(vec![leg], 1.0), &request, &calendar_service::built_in_calendar().unwrap(), expiry);
+        assert_eq!(diagram.prices.len(), diagram.expiry_curve.pnl.len());
+    }
+}
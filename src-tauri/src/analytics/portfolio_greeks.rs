@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+
+use super::payoff::CONTRACT_MULTIPLIER;
+use crate::models::calendar::TradingCalendar;
+use crate::models::portfolio_risk::PortfolioRiskSettings;
+use crate::models::trade::Trade;
+
+/// Greeks of one position in share-equivalent terms: delta in shares, theta
+/// in dollars per day, vega in dollars per vol point.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionGreeks {
+    pub trade_id: Option<i64>,
+    pub underlying: String,
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnderlyingGreeks {
+    pub underlying: String,
+    pub underlying_price: f64,
+    pub beta: Option<f64>,
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub beta_weighted_delta: Option<f64>,   // In benchmark shares
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum GreekBreach {
+    ThetaBelow { value: f64, limit: f64 },
+    ThetaAbove { value: f64, limit: f64 },
+    VegaBelow { value: f64, limit: f64 },
+    VegaAbove { value: f64, limit: f64 },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PortfolioGreeks {
+    pub benchmark: String,
+    pub benchmark_price: Option<f64>,
+    pub underlyings: Vec<UnderlyingGreeks>,
+    pub net_delta: f64,
+    pub net_gamma: f64,
+    pub net_theta: f64,
+    pub net_vega: f64,
+    pub beta_weighted_delta: f64,
+    pub missing_prices: Vec<String>,   // Underlyings left out for lack of a price
+    pub breaches: Vec<GreekBreach>,
+}
+
+pub fn position_greeks(
+    trade: &Trade,
+    underlying_price: f64,
+    now: DateTime<Utc>,
+    calendar: &TradingCalendar,
+    settings: &PortfolioRiskSettings,
+) -> PositionGreeks {
+    let mut greeks = PositionGreeks {
+        trade_id: trade.id,
+        underlying: trade.symbol.to_ascii_uppercase(),
+        delta: 0.0,
+        gamma: 0.0,
+        theta: 0.0,
+        vega: 0.0,
+    };
+
+    if trade.legs.is_empty() {
+        greeks.delta = trade.quantity as f64 * trade.side.sign();
+        return greeks;
+    }
+
+    let scale = CONTRACT_MULTIPLIER * trade.quantity as f64;
+    for leg in &trade.legs {
+        let volatility = leg.exit_iv.or(leg.entry_iv).unwrap_or(settings.default_volatility);
+        let valuation = leg.position_valuation(underlying_price, now, calendar, settings.rate, settings.dividend_yield, volatility);
+        greeks.delta += valuation.delta * scale;
+        greeks.gamma += valuation.gamma * scale;
+        greeks.theta += valuation.theta * scale;
+        greeks.vega += valuation.vega * scale;
+    }
+
+    greeks
+}
+
+/// Rolls position Greeks up per underlying and for the whole portfolio.
+/// Beta-weighted delta expresses each underlying's delta in benchmark shares.
+pub fn aggregate(
+    positions: &[PositionGreeks],
+    prices: &HashMap<String, f64>,
+    betas: &HashMap<String, f64>,
+    benchmark_price: Option<f64>,
+    settings: &PortfolioRiskSettings,
+) -> PortfolioGreeks {
+    let mut underlyings: Vec<UnderlyingGreeks> = Vec::new();
+
+    for position in positions {
+        let index = match underlyings.iter().position(|u| u.underlying == position.underlying) {
+            Some(index) => index,
+            None => {
+                underlyings.push(UnderlyingGreeks {
+                    underlying: position.underlying.clone(),
+                    underlying_price: prices.get(&position.underlying).copied().unwrap_or(0.0),
+                    beta: betas.get(&position.underlying).copied(),
+                    delta: 0.0,
+                    gamma: 0.0,
+                    theta: 0.0,
+                    vega: 0.0,
+                    beta_weighted_delta: None,
+                });
+                underlyings.len() - 1
+            }
+        };
+
+        let entry = &mut underlyings[index];
+        entry.delta += position.delta;
+        entry.gamma += position.gamma;
+        entry.theta += position.theta;
+        entry.vega += position.vega;
+    }
+
+    for entry in underlyings.iter_mut() {
+        entry.beta_weighted_delta = match (entry.beta, benchmark_price) {
+            (Some(beta), Some(benchmark_price)) if benchmark_price > 0.0 => {
+                Some(entry.delta * beta * entry.underlying_price / benchmark_price)
+            }
+            _ => None,
+        };
+    }
+    underlyings.sort_by(|a, b| a.underlying.cmp(&b.underlying));
+
+    let net_theta = underlyings.iter().map(|u| u.theta).sum();
+    let net_vega = underlyings.iter().map(|u| u.vega).sum();
+
+    PortfolioGreeks {
+        benchmark: settings.benchmark.clone(),
+        benchmark_price,
+        net_delta: underlyings.iter().map(|u| u.delta).sum(),
+        net_gamma: underlyings.iter().map(|u| u.gamma).sum(),
+        net_theta,
+        net_vega,
+        beta_weighted_delta: underlyings.iter().filter_map(|u| u.beta_weighted_delta).sum(),
+        underlyings,
+        missing_prices: Vec::new(),
+        breaches: check_limits(net_theta, net_vega, settings),
+    }
+}
+
+pub fn check_limits(net_theta: f64, net_vega: f64, settings: &PortfolioRiskSettings) -> Vec<GreekBreach> {
+    let mut breaches = Vec::new();
+
+    if let Some(limit) = settings.min_net_theta {
+        if net_theta < limit {
+            breaches.push(GreekBreach::ThetaBelow { value: net_theta, limit });
+        }
+    }
+    if let Some(limit) = settings.max_net_theta {
+        if net_theta > limit {
+            breaches.push(GreekBreach::ThetaAbove { value: net_theta, limit });
+        }
+    }
+    if let Some(limit) = settings.min_net_vega {
+        if net_vega < limit {
+            breaches.push(GreekBreach::VegaBelow { value: net_vega, limit });
+        }
+    }
+    if let Some(limit) = settings.max_net_vega {
+        if net_vega > limit {
+            breaches.push(GreekBreach::VegaAbove { value: net_vega, limit });
+        }
+    }
+
+    breaches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::trade::PositionSide;
+    use crate::services::calendar_service;
+
+    #[test]
+    fn short_stock_has_negative_delta() {
+        let mut trade = Trade::new("AAPL", 1);
+        trade.quantity = 100;
+        trade.side = PositionSide::Short;
+
+        let calendar = calendar_service::built_in_calendar().unwrap();
+        let greeks = position_greeks(&trade, 150.0, Utc::now(), &calendar, &PortfolioRiskSettings::default());
+        assert_eq!(greeks.delta, -100.0);
+        assert_eq!(greeks.gamma, 0.0);
+    }
+
+    #[test]
+    fn limits_report_each_breach() {
+        let settings = PortfolioRiskSettings {
+            min_net_theta: Some(-50.0),
+            max_net_vega: Some(200.0),
+            ..Default::default()
+        };
+
+        assert!(check_limits(-10.0, 100.0, &settings).is_empty());
+        assert_eq!(check_limits(-60.0, 250.0, &settings), vec![
+            GreekBreach::ThetaBelow { value: -60.0, limit: -50.0 },
+            GreekBreach::VegaAbove { value: 250.0, limit: 200.0 },
+        ]);
+    }
+}
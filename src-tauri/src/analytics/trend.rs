@@ -0,0 +1,177 @@
+use crate::analytics::chart_patterns::{self, PivotKind};
+use crate::analytics::indicators;
+use crate::models::price_bar::PriceBar;
+use crate::models::stock_rating::MarketTrend;
+use crate::models::trend::TrendSignal;
+
+const SHORT_PERIOD: usize = 20;
+const LONG_PERIOD: usize = 50;
+const SLOPE_BARS: usize = 5;
+/// Relative change of the short average over SLOPE_BARS below which it counts as flat.
+const FLAT_SLOPE: f64 = 0.005;
+const ADX_PERIOD: usize = 14;
+/// ADX below this means no trend, whatever the averages say.
+const TRENDLESS_ADX: f64 = 20.0;
+const PIVOT_WINDOW: usize = 3;
+
+/// Bars needed before a chart can be classified.
+pub const MIN_BARS: usize = LONG_PERIOD + SLOPE_BARS;
+
+/// Score at which a weighted blend of charts counts as trending.
+pub const TREND_THRESHOLD: f64 = 1.0 / 3.0;
+
+/// Classifies one chart from its moving averages, swing structure and ADX.
+/// None when there are fewer than MIN_BARS bars.
+pub fn classify(bars: &[PriceBar]) -> Option<TrendSignal> {
+    if bars.len() < MIN_BARS {
+        return None;
+    }
+
+    let closes: Vec<f64> = bars.iter().map(|bar| bar.close).collect();
+    let last = closes.len() - 1;
+    let short = indicators::sma(&closes, SHORT_PERIOD);
+    let long = indicators::sma(&closes, LONG_PERIOD);
+    let (short_now, short_before, long_now) = (short[last]?, short[last - SLOPE_BARS]?, long[last]?);
+
+    let slope = short_now / short_before - 1.0;
+    let slope_vote = if slope > FLAT_SLOPE { 1 } else if slope < -FLAT_SLOPE { -1 } else { 0 };
+
+    let close = closes[last];
+    let stacking_vote = if close > short_now && short_now > long_now {
+        1
+    } else if close < short_now && short_now < long_now {
+        -1
+    } else {
+        0
+    };
+
+    let structure_vote = swing_structure(bars);
+    let adx = indicators::adx(bars, ADX_PERIOD)[last].and_then(|point| point.adx);
+
+    let votes = slope_vote + stacking_vote + structure_vote;
+    let score = votes as f64 / 3.0;
+    let trendless = adx.is_some_and(|adx| adx < TRENDLESS_ADX);
+    let trend = if trendless && votes.abs() < 3 {
+        MarketTrend::Sideways
+    } else if votes >= 2 {
+        MarketTrend::Uptrend
+    } else if votes <= -2 {
+        MarketTrend::Downtrend
+    } else if slope_vote == 0 && stacking_vote == 0 {
+        MarketTrend::Sideways
+    } else {
+        MarketTrend::Uncertain
+    };
+
+    Some(TrendSignal { trend, score, slope_vote, stacking_vote, structure_vote, adx })
+}
+
+/// +1 when the last two swing highs and lows both rose, -1 when both fell.
+fn swing_structure(bars: &[PriceBar]) -> i32 {
+    let pivots = chart_patterns::find_pivots(bars, PIVOT_WINDOW);
+    let last_two = |kind: PivotKind| -> Option<(f64, f64)> {
+        let mut prices = pivots.iter().rev().filter(|p| p.kind == kind).map(|p| p.price);
+        let latest = prices.next()?;
+        Some((prices.next()?, latest))
+    };
+
+    match (last_two(PivotKind::High), last_two(PivotKind::Low)) {
+        (Some((h1, h2)), Some((l1, l2))) if h2 > h1 && l2 > l1 => 1,
+        (Some((h1, h2)), Some((l1, l2))) if h2 < h1 && l2 < l1 => -1,
+        _ => 0,
+    }
+}
+
+/// Blends per-chart signals by weight. A weak blend is Sideways only when
+/// every chart is; otherwise the charts disagree and it is Uncertain.
+pub fn blend(signals: &[(f64, &TrendSignal)]) -> (MarketTrend, f64) {
+    let total_weight: f64 = signals.iter().map(|(weight, _)| weight).sum();
+    if total_weight <= 0.0 {
+        return (MarketTrend::Uncertain, 0.0);
+    }
+
+    let score = signals.iter().map(|(weight, signal)| weight * signal.score).sum::<f64>() / total_weight;
+    let trend = if score >= TREND_THRESHOLD {
+        MarketTrend::Uptrend
+    } else if score <= -TREND_THRESHOLD {
+        MarketTrend::Downtrend
+    } else if signals.iter().all(|(_, signal)| signal.trend == MarketTrend::Sideways) {
+        MarketTrend::Sideways
+    } else {
+        MarketTrend::Uncertain
+    };
+
+    (trend, score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+    use crate::models::price_bar::Timeframe;
+
+    /// Swings of ten bars around a line rising by `drift` a bar.
+    fn swinging(drift: f64, count: usize) -> Vec<PriceBar> {
+        const SWING: [f64; 10] = [100.0, 101.0, 102.0, 103.0, 102.0, 101.0, 100.0, 99.0, 98.0, 99.0];
+        let first = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+
+        (0..count).map(|i| {
+            let close = SWING[i % SWING.len()] + drift * i as f64;
+            PriceBar {
+                symbol: "AAPL".to_string(),
+                timeframe: Timeframe::Daily,
+                timestamp: first + Duration::days(i as i64),
+                open: close,
+                high: close + 0.5,
+                low: close - 0.5,
+                close,
+                volume: 1000.0,
+            }
+        }).collect()
+    }
+
+    fn signal(trend: MarketTrend, score: f64) -> TrendSignal {
+        TrendSignal { trend, score, slope_vote: 0, stacking_vote: 0, structure_vote: 0, adx: None }
+    }
+
+    #[test]
+    fn rising_swings_are_an_uptrend() {
+        let signal = classify(&swinging(0.5, 80)).unwrap();
+
+        assert_eq!(signal.trend, MarketTrend::Uptrend, "{:?}", signal);
+        assert_eq!((signal.slope_vote, signal.stacking_vote, signal.structure_vote), (1, 1, 1));
+    }
+
+    #[test]
+    fn falling_swings_are_a_downtrend() {
+        let signal = classify(&swinging(-0.5, 80)).unwrap();
+
+        assert_eq!(signal.trend, MarketTrend::Downtrend, "{:?}", signal);
+        assert_eq!(signal.score, -1.0);
+    }
+
+    #[test]
+    fn repeating_swings_are_sideways() {
+        let signal = classify(&swinging(0.0, 80)).unwrap();
+
+        assert_eq!(signal.trend, MarketTrend::Sideways, "{:?}", signal);
+        assert_eq!(signal.score, 0.0);
+    }
+
+    #[test]
+    fn short_history_is_not_classified() {
+        assert!(classify(&swinging(0.5, MIN_BARS - 1)).is_none());
+    }
+
+    #[test]
+    fn blend_weighs_charts_and_calls_disagreement_uncertain() {
+        let up = signal(MarketTrend::Uptrend, 1.0);
+        let down = signal(MarketTrend::Downtrend, -1.0);
+        let flat = signal(MarketTrend::Sideways, 0.0);
+
+        assert_eq!(blend(&[(3.0, &up), (1.0, &down)]), (MarketTrend::Uptrend, 0.5));
+        assert_eq!(blend(&[(1.0, &up), (1.0, &down)]), (MarketTrend::Uncertain, 0.0));
+        assert_eq!(blend(&[(1.0, &flat), (2.0, &flat)]), (MarketTrend::Sideways, 0.0));
+        assert_eq!(blend(&[]), (MarketTrend::Uncertain, 0.0));
+    }
+}
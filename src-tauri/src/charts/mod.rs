@@ -0,0 +1,62 @@
+pub mod payoff;
+pub mod opportunity;
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Resolves a PNG export path that was not picked in a save dialog. Those
+/// may only point inside `data_dir`; relative paths are taken from there.
+pub fn data_dir_export_path(path: &Path, data_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+        return Err(format!("{} is not a .png file", path.display()).into());
+    }
+    let file_name = path.file_name().ok_or("Export path has no file name")?;
+
+    let data_dir = data_dir.canonicalize()?;
+    let directory = data_dir.join(path.parent().unwrap_or(Path::new("")));
+    let directory = directory.canonicalize().map_err(|e| format!("{}: {}", directory.display(), e))?;
+    let resolved = directory.join(file_name);
+
+    // A link already at the path could point anywhere
+    let target = if resolved.exists() { resolved.canonicalize()? } else { resolved.clone() };
+    if !target.starts_with(&data_dir) {
+        return Err(format!(
+            "{} is outside the data directory; choose the file in the save dialog instead",
+            path.display(),
+        ).into());
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("exports")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn exports_resolve_inside_the_data_directory() {
+        let dir = data_dir("export-inside");
+        let root = dir.canonicalize().unwrap();
+
+        assert_eq!(data_dir_export_path(Path::new("payoff.png"), &dir).unwrap(), root.join("payoff.png"));
+        assert_eq!(data_dir_export_path(&dir.join("exports/payoff.png"), &dir).unwrap(), root.join("exports/payoff.png"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn exports_cannot_leave_the_data_directory() {
+        let dir = data_dir("export-outside");
+
+        assert!(data_dir_export_path(Path::new("../payoff.png"), &dir).is_err());
+        assert!(data_dir_export_path(Path::new("exports/../../payoff.png"), &dir).is_err());
+        assert!(data_dir_export_path(&std::env::temp_dir().join("payoff.png"), &dir).is_err());
+        assert!(data_dir_export_path(Path::new("settings.toml"), &dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
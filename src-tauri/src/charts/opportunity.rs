@@ -0,0 +1,158 @@
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+use crate::analytics::opportunity::OpportunityChart;
+
+const CHART_SIZE: (u32, u32) = (960, 720);
+
+pub fn render_opportunity_svg(chart: &OpportunityChart) -> Result<String, Box<dyn Error>> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, CHART_SIZE).into_drawing_area();
+        draw_opportunity(&root, chart)?;
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+pub fn render_opportunity_png(chart: &OpportunityChart, path: &Path) -> Result<(), Box<dyn Error>> {
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    draw_opportunity(&root, chart)?;
+    root.present()?;
+    Ok(())
+}
+
+fn draw_opportunity<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, chart: &OpportunityChart) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let root = root.titled(&format!("{} opportunity", chart.symbol), ("sans-serif", 24))?;
+
+    let (top, bottom) = root.split_vertically(400);
+    let (left, right) = bottom.split_horizontally(600);
+
+    draw_sentiment_trend(&top, chart)?;
+    draw_score_distribution(&left, chart)?;
+    draw_pattern_gauge(&right, chart)?;
+
+    Ok(())
+}
+
+fn draw_sentiment_trend<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, chart: &OpportunityChart) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let count = chart.history.len().max(2);
+    let (low, high) = chart.history.iter()
+        .fold((-3.0_f64, 3.0_f64), |(lo, hi), point| (lo.min(point.overall_score), hi.max(point.overall_score)));
+
+    let mut plot = ChartBuilder::on(area)
+        .caption("Sentiment trend", ("sans-serif", 18))
+        .margin(12)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0..count - 1, (low - 0.5)..(high + 0.5))?;
+
+    let history = &chart.history;
+    plot.configure_mesh()
+        .x_desc("Rating")
+        .y_desc("Score")
+        .x_label_formatter(&|i| history.get(*i).map(|p| p.timestamp.format("%Y-%m-%d").to_string()).unwrap_or_default())
+        .draw()?;
+
+    let series = [
+        ("Overall", RED, history.iter().map(|p| p.overall_score).collect::<Vec<f64>>()),
+        ("Market", BLUE, history.iter().map(|p| p.market_sentiment as f64).collect()),
+        ("Sector", GREEN, history.iter().map(|p| p.sector_sentiment as f64).collect()),
+        ("Security", MAGENTA, history.iter().map(|p| p.security_sentiment as f64).collect()),
+    ];
+
+    for (label, color, values) in series {
+        plot.draw_series(LineSeries::new(
+            values.into_iter().enumerate(),
+            color.stroke_width(if label == "Overall" { 2 } else { 1 }),
+        ))?
+        .label(label)
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    plot.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    Ok(())
+}
+
+fn draw_score_distribution<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, chart: &OpportunityChart) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let bins = &chart.distribution.bins;
+    let low = bins.first().map_or(-1.0, |bin| bin.lower);
+    let high = bins.last().map_or(1.0, |bin| bin.upper);
+    let tallest = bins.iter().map(|bin| bin.count).max().unwrap_or(0).max(1);
+
+    let mut plot = ChartBuilder::on(area)
+        .caption("Score distribution", ("sans-serif", 18))
+        .margin(12)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(low..high, 0..tallest + 1)?;
+
+    plot.configure_mesh()
+        .x_desc("Score")
+        .y_desc("Securities")
+        .draw()?;
+
+    plot.draw_series(bins.iter().map(|bin| {
+        Rectangle::new([(bin.lower, 0), (bin.upper, bin.count)], BLUE.mix(0.4).filled())
+    }))?;
+
+    if let Some(current) = chart.distribution.current_score {
+        plot.draw_series(LineSeries::new([(current, 0), (current, tallest + 1)], RED.stroke_width(2)))?;
+    }
+
+    Ok(())
+}
+
+fn draw_pattern_gauge<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, chart: &OpportunityChart) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let gauge = match &chart.pattern_gauge {
+        Some(gauge) => gauge,
+        None => return Ok(()),
+    };
+
+    let mut plot = ChartBuilder::on(area)
+        .caption(format!("Pattern bonus: {:?}", gauge.pattern), ("sans-serif", 18))
+        .margin(12)
+        .x_label_area_size(40)
+        .build_cartesian_2d(gauge.min..gauge.max, 0.0..1.0)?;
+
+    plot.configure_mesh()
+        .disable_y_mesh()
+        .disable_y_axis()
+        .x_desc("Bonus")
+        .draw()?;
+
+    plot.draw_series([
+        Rectangle::new([(gauge.min, 0.35), (0.0, 0.65)], RED.mix(0.2).filled()),
+        Rectangle::new([(0.0, 0.35), (gauge.max, 0.65)], GREEN.mix(0.2).filled()),
+    ])?;
+    plot.draw_series(LineSeries::new([(gauge.bonus, 0.25), (gauge.bonus, 0.75)], BLACK.stroke_width(3)))?;
+
+    if let Some(hit_rate) = gauge.hit_rate {
+        plot.draw_series(std::iter::once(Text::new(
+            format!("Hit rate {:.0}% over {} trades", hit_rate * 100.0, gauge.closed_trades),
+            (gauge.min, 0.9),
+            ("sans-serif", 14),
+        )))?;
+    }
+
+    Ok(())
+}
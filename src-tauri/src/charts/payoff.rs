@@ -0,0 +1,89 @@
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+use crate::analytics::payoff::PayoffDiagram;
+
+const CHART_SIZE: (u32, u32) = (960, 540);
+const SCENARIO_COLORS: [RGBColor; 4] = [BLUE, MAGENTA, CYAN, RGBColor(255, 140, 0)];
+
+pub fn render_payoff_svg(diagram: &PayoffDiagram) -> Result<String, Box<dyn Error>> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, CHART_SIZE).into_drawing_area();
+        draw_payoff(&root, diagram)?;
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+pub fn render_payoff_png(diagram: &PayoffDiagram, path: &Path) -> Result<(), Box<dyn Error>> {
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    draw_payoff(&root, diagram)?;
+    root.present()?;
+    Ok(())
+}
+
+fn draw_payoff<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, diagram: &PayoffDiagram) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let first_price = diagram.prices.first().copied().unwrap_or(0.0);
+    let last_price = diagram.prices.last().copied().unwrap_or(1.0).max(first_price + 1.0);
+
+    let all_pnl = diagram.scenario_curves.iter()
+        .chain(std::iter::once(&diagram.expiry_curve))
+        .flat_map(|curve| curve.pnl.iter().copied());
+    let (mut low, mut high) = all_pnl.fold((0.0_f64, 0.0_f64), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let padding = ((high - low) * 0.1).max(1.0);
+    low -= padding;
+    high += padding;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption("Payoff", ("sans-serif", 24))
+        .margin(16)
+        .x_label_area_size(40)
+        .y_label_area_size(70)
+        .build_cartesian_2d(first_price..last_price, low..high)?;
+
+    chart.configure_mesh()
+        .x_desc("Underlying price")
+        .y_desc("P&L")
+        .draw()?;
+
+    chart.draw_series(LineSeries::new(
+        [(first_price, 0.0), (last_price, 0.0)],
+        BLACK.mix(0.4),
+    ))?;
+
+    for (i, curve) in diagram.scenario_curves.iter().enumerate() {
+        let color = SCENARIO_COLORS[i % SCENARIO_COLORS.len()];
+        chart.draw_series(LineSeries::new(
+            diagram.prices.iter().copied().zip(curve.pnl.iter().copied()),
+            color.stroke_width(1),
+        ))?
+        .label(curve.label.clone())
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    chart.draw_series(LineSeries::new(
+        diagram.prices.iter().copied().zip(diagram.expiry_curve.pnl.iter().copied()),
+        RED.stroke_width(2),
+    ))?
+    .label(diagram.expiry_curve.label.clone())
+    .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+
+    chart.draw_series(diagram.breakevens.iter().map(|&price| {
+        Circle::new((price, 0.0), 4, BLACK.filled())
+    }))?;
+
+    chart.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    Ok(())
+}
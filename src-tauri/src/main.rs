@@ -3,82 +3,1433 @@
     windows_subsystem = "windows"
 )]
 
+mod analytics;
+mod charts;
 mod models;
 mod services;
 
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
 use tauri::State;
 use tauri::Manager;
 
-use crate::models::{PsychologicalState, StockRating, Trade};
-use crate::services::{psychological_service, stock_rating_service, trade_service};
+use crate::analytics::black_scholes::{self, OptionValuation, PricingInputs};
+use crate::analytics::chart_patterns::PatternDetection;
+use crate::analytics::implied_volatility::{self, PnlAttribution};
+use crate::analytics::opportunity::OpportunityChart;
+use crate::analytics::pattern_stats::PatternWindow;
+use crate::analytics::payoff::{PayoffDiagram, PayoffRequest};
+use crate::analytics::portfolio_greeks::PortfolioGreeks;
+use crate::models::account::{Account, AccountSummary};
+use crate::models::alert::{Alert, AlertCondition, AlertStatus, AlertTrigger};
+use crate::models::app_settings::{AppSettings, SettingsChange};
+use crate::models::calendar::{HoldingPeriod, TradingCalendar, TradingSession};
+use crate::models::detailed_analysis::{AnalysisUpdate, SpreadLeg, TradePhase};
+use crate::models::indicator::{Indicator, IndicatorLine, IndicatorSeries};
+use crate::models::job::{JobOutcome, JobRun, JobRunStatus, ScheduledJob};
+use crate::models::occ_symbol::{OccFormat, OccSymbol};
+use crate::models::option_event::{OptionEvent, OptionEventType};
+use crate::models::portfolio_risk::PortfolioRiskSettings;
+use crate::models::price_bar::{PriceBar, Timeframe};
+use crate::models::promotion::{RatingChain, TradeLineage};
+use crate::models::rating_freshness::{RatingDecaySettings, RatingFreshness};
+use crate::models::scoring_model::ScoringModel;
+use crate::models::sector::{Sector, SectorMatch, SectorReview, SectorSelection};
+use crate::models::security::{Security, SecurityImportSummary};
+use crate::models::time_zone::{LocalTimestamp, TimeZoneSettings};
+use crate::models::trade::TradeUpdate;
+use crate::models::trend::{TrendClassification, TrendSettings};
+use crate::models::watchlist::{Watchlist, WatchlistItem};
+use crate::models::option_leg::{LegPreset, LegSide};
+use crate::models::{ChecklistTemplate, CompletedChecklist, DetailedAnalysis, OptionLeg, PsychologicalState, StockRating, Trade};
+use crate::services::{account_service, alert_service, app_settings_service, calendar_service, checklist_service, detailed_analysis_service, indicator_service, option_leg_service, opportunity_service, option_lifecycle_service, pattern_detection_service, pattern_stats_service, payoff_service, portfolio_risk_service, price_service, promotion_service, psychological_service, rating_freshness_service, scheduler_service, scoring_service, sector_service, security_service, stock_rating_service, time_zone_service, trade_service, trend_service, watchlist_service};
+use crate::services::option_lifecycle_service::{ExpiringLeg, ExpiryRun};
+use crate::services::scoring_service::ScoreComparison;
+
+/// How often the background scheduler looks for due jobs.
+const SCHEDULER_TICK_SECONDS: u64 = 30;
 
 struct AppState {
     db: Mutex<Option<rusqlite::Connection>>,
+    data_dir: Mutex<Option<PathBuf>>,
+}
+
+/// Where the database, backups and reports live.
+fn data_dir(app_state: &AppState) -> Result<PathBuf, String> {
+    app_state.data_dir.lock().unwrap_or_else(PoisonError::into_inner).clone().ok_or_else(|| "Data directory not initialized".to_string())
+}
+
+/// Where a chart PNG may be written: a file the user picked in the save
+/// dialog, or a path inside the data directory.
+fn export_path(app_handle: &tauri::AppHandle, app_state: &AppState, path: &str) -> Result<PathBuf, String> {
+    // Save dialog results are added to the file system scope
+    if app_handle.fs_scope().is_allowed(path) {
+        return Ok(PathBuf::from(path));
+    }
+
+    charts::data_dir_export_path(std::path::Path::new(path), &data_dir(app_state)?)
+        .map_err(|e| e.to_string())
+}
+
+/// The data directory used when the settings do not name one.
+fn default_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle.path_resolver().app_data_dir().ok_or_else(|| "No app data directory".to_string())
+}
+
+#[tauri::command]
+fn save_psychological_state(app_state: State<AppState>, state: PsychologicalState) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut state_copy = state;
+    psychological_service::save_psychological_state(conn, &mut state_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_psychological_state(app_state: State<AppState>, id: i64) -> Result<PsychologicalState, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    psychological_service::get_psychological_state(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_recent_psychological_states(app_state: State<AppState>, limit: i64) -> Result<Vec<PsychologicalState>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    psychological_service::get_recent_psychological_states(conn, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_stock_rating(app_state: State<AppState>, rating: StockRating) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut rating_copy = rating;
+    stock_rating_service::save_stock_rating(conn, &mut rating_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_stock_rating(app_state: State<AppState>, id: i64) -> Result<StockRating, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    stock_rating_service::get_stock_rating(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_stock_ratings_by_symbol(app_state: State<AppState>, symbol: String) -> Result<Vec<StockRating>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    stock_rating_service::get_stock_ratings_by_symbol(conn, &symbol)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_recent_stock_ratings(app_state: State<AppState>, limit: i64) -> Result<Vec<StockRating>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    stock_rating_service::get_recent_stock_ratings(conn, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_scoring_model(app_state: State<AppState>, model: ScoringModel) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut model_copy = model;
+    scoring_service::save_scoring_model(conn, &mut model_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_scoring_models(app_state: State<AppState>) -> Result<Vec<ScoringModel>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scoring_service::get_scoring_models(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_active_scoring_model(app_state: State<AppState>) -> Result<ScoringModel, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scoring_service::get_active_scoring_model(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_active_scoring_model(app_state: State<AppState>, version: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scoring_service::set_active_scoring_model(conn, version)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn rescore_ratings(app_state: State<AppState>, version: i64) -> Result<usize, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scoring_service::rescore_ratings(conn, version)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn compare_scoring_models(app_state: State<AppState>, baseline_version: i64, candidate_version: i64, limit: i64) -> Result<Vec<ScoreComparison>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scoring_service::compare_scoring_models(conn, baseline_version, candidate_version, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_pattern_histogram(app_state: State<AppState>, window_days: Option<Vec<i64>>, account_id: Option<i64>) -> Result<Vec<PatternWindow>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let windows = window_days.unwrap_or_else(|| pattern_stats_service::DEFAULT_WINDOWS.to_vec());
+    pattern_stats_service::get_pattern_histogram(conn, &windows, chrono::Utc::now(), account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_opportunity_chart(app_state: State<AppState>, symbol: String, bins: Option<usize>) -> Result<OpportunityChart, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    opportunity_service::get_opportunity_chart(conn, &symbol, bins)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn render_opportunity_svg(chart: OpportunityChart) -> Result<String, String> {
+    charts::opportunity::render_opportunity_svg(&chart)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_opportunity_png(app_handle: tauri::AppHandle, app_state: State<AppState>, chart: OpportunityChart, path: String) -> Result<(), String> {
+    let path = export_path(&app_handle, &app_state, &path)?;
+    charts::opportunity::render_opportunity_png(&chart, &path)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_rating_decay_settings(app_state: State<AppState>) -> Result<RatingDecaySettings, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    rating_freshness_service::get_rating_decay_settings(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_rating_decay_settings(app_state: State<AppState>, settings: RatingDecaySettings) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    rating_freshness_service::save_rating_decay_settings(conn, &settings)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_rating_freshness(app_state: State<AppState>) -> Result<Vec<RatingFreshness>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    rating_freshness_service::get_rating_freshness(conn, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_rerating_queue(app_state: State<AppState>) -> Result<Vec<RatingFreshness>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    rating_freshness_service::get_rerating_queue(conn, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn start_rerating(app_state: State<AppState>, symbol: String) -> Result<StockRating, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    rating_freshness_service::start_rerating(conn, &symbol)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_sectors(app_state: State<AppState>) -> Result<Vec<Sector>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::get_sectors(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_custom_sector(app_state: State<AppState>, sector: Sector) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut sector_copy = sector;
+    sector_service::save_custom_sector(conn, &mut sector_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_custom_sector(app_state: State<AppState>, sector: Sector) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::update_custom_sector(conn, &sector)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_custom_sector(app_state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::delete_custom_sector(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn match_sector(app_state: State<AppState>, value: String) -> Result<SectorMatch, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::match_sector(conn, &value)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_sector_reviews(app_state: State<AppState>, pending_only: bool) -> Result<Vec<SectorReview>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::get_sector_reviews(conn, pending_only)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn resolve_sector_review(app_state: State<AppState>, raw_value: String, sector_id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::resolve_sector_review(conn, &raw_value, sector_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_sector_selection(app_state: State<AppState>, selection: SectorSelection) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut selection_copy = selection;
+    sector_service::add_sector_selection(conn, &mut selection_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_sector_selections(app_state: State<AppState>) -> Result<Vec<SectorSelection>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::get_sector_selections(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_sector_selection(app_state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    sector_service::remove_sector_selection(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn import_securities_csv(app_state: State<AppState>, path: String) -> Result<SecurityImportSummary, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    security_service::import_securities_csv(conn, std::path::Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_security(app_state: State<AppState>, symbol: String) -> Result<Option<Security>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    security_service::get_security(conn, &symbol)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn search_securities(app_state: State<AppState>, query: String, limit: i64) -> Result<Vec<Security>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    security_service::search_securities(conn, &query, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn validate_symbol(app_state: State<AppState>, symbol: String) -> Result<Option<Security>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    security_service::validate_symbol(conn, &symbol)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_watchlist(app_state: State<AppState>, watchlist: Watchlist) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut watchlist_copy = watchlist;
+    watchlist_service::save_watchlist(conn, &mut watchlist_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_watchlist(app_state: State<AppState>, watchlist: Watchlist) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::update_watchlist(conn, &watchlist)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_watchlist(app_state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::delete_watchlist(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_watchlist(app_state: State<AppState>, id: i64) -> Result<Watchlist, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::get_watchlist(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_watchlists(app_state: State<AppState>) -> Result<Vec<Watchlist>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::get_watchlists(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_watchlist_item(app_state: State<AppState>, item: WatchlistItem) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut item_copy = item;
+    watchlist_service::add_watchlist_item(conn, &mut item_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_watchlist_item(app_state: State<AppState>, item: WatchlistItem) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::update_watchlist_item(conn, &item)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_watchlist_item(app_state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::remove_watchlist_item(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn refresh_watchlist(app_state: State<AppState>, id: i64) -> Result<Watchlist, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::refresh_watchlist(conn, id, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn promote_watchlist_item(app_state: State<AppState>, item_id: i64) -> Result<DetailedAnalysis, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    watchlist_service::promote_watchlist_item(conn, item_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn promote_rating(app_state: State<AppState>, rating_id: i64) -> Result<DetailedAnalysis, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    promotion_service::promote_rating(conn, rating_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn plan_trade_from_analysis(app_state: State<AppState>, analysis_id: i64) -> Result<Trade, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    promotion_service::plan_trade(conn, analysis_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_rating_chain(app_state: State<AppState>, rating_id: i64) -> Result<RatingChain, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    promotion_service::get_rating_chain(conn, rating_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_trade_lineage(app_state: State<AppState>, trade_id: i64) -> Result<TradeLineage, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    promotion_service::get_trade_lineage(conn, trade_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_analyses_by_rating(app_state: State<AppState>, rating_id: i64) -> Result<Vec<DetailedAnalysis>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    detailed_analysis_service::get_analyses_by_rating(conn, rating_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn detect_chart_patterns(app_state: State<AppState>, symbol: String, timeframe: Timeframe) -> Result<Vec<PatternDetection>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    pattern_detection_service::detect_chart_patterns(conn, &symbol, timeframe)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn suggest_chart_pattern(app_state: State<AppState>, symbol: String) -> Result<Option<PatternDetection>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    pattern_detection_service::suggest_chart_pattern(conn, &symbol)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_indicator(app_state: State<AppState>, symbol: String, timeframe: Timeframe, indicator: Indicator) -> Result<IndicatorSeries, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    indicator_service::get_indicator(conn, &symbol, timeframe, &indicator)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_indicator_value(app_state: State<AppState>, symbol: String, line: IndicatorLine) -> Result<Option<f64>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    indicator_service::get_indicator_value(conn, &symbol, &line)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clear_indicator_cache(app_state: State<AppState>) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    indicator_service::clear_indicator_cache(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_trend_settings(app_state: State<AppState>) -> Result<TrendSettings, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    trend_service::get_trend_settings(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_trend_settings(app_state: State<AppState>, settings: TrendSettings) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    trend_service::save_trend_settings(conn, &settings)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn classify_market_trend(app_state: State<AppState>, symbol: String) -> Result<TrendClassification, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    trend_service::classify_market_trend(conn, &symbol)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_alert(app_state: State<AppState>, alert: Alert) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut alert_copy = alert;
+    alert_service::save_alert(conn, &mut alert_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_alert(app_state: State<AppState>, alert: Alert) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    alert_service::update_alert(conn, &alert)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_alert(app_state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    alert_service::delete_alert(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_alert(app_state: State<AppState>, id: i64) -> Result<Alert, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    alert_service::get_alert(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_alerts(app_state: State<AppState>, status: Option<AlertStatus>, account_id: Option<i64>) -> Result<Vec<Alert>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    alert_service::get_alerts(conn, status, account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_alerts_by_analysis(app_state: State<AppState>, analysis_id: i64) -> Result<Vec<Alert>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    alert_service::get_alerts_by_analysis(conn, analysis_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_alert_triggers(app_state: State<AppState>, limit: i64, account_id: Option<i64>) -> Result<Vec<AlertTrigger>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    alert_service::get_alert_triggers(conn, limit, account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn parse_alert_text(text: String, reference_price: Option<f64>) -> Option<AlertCondition> {
+    AlertCondition::parse(&text, reference_price)
+}
+
+#[tauri::command]
+fn evaluate_alerts(app_handle: tauri::AppHandle, app_state: State<AppState>, symbol: Option<String>) -> Result<Vec<AlertTrigger>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let triggers = alert_service::evaluate_alerts(conn, symbol.as_deref(), chrono::Utc::now())
+        .map_err(|e| e.to_string())?;
+    
+    notify_alert_triggers(&app_handle, &triggers)?;
+    
+    Ok(triggers)
+}
+
+/// Emits each triggered alert to the frontend and shows it as a desktop
+/// notification. A trigger that cannot be delivered is logged and the rest
+/// still go out; the triggers are already recorded.
+fn notify_alert_triggers(app_handle: &tauri::AppHandle, triggers: &[AlertTrigger]) -> Result<(), String> {
+    for trigger in triggers {
+        if let Err(e) = app_handle.emit_all("alert-triggered", trigger.clone()) {
+            eprintln!("Could not emit alert {}: {}", trigger.alert_id, e);
+        }
+        
+        let body = match (&trigger.message, trigger.price) {
+            (Some(message), _) => message.clone(),
+            (None, Some(price)) => format!("Last price {:.2}", price),
+            (None, None) => String::new(),
+        };
+        let shown = tauri::api::notification::Notification::new(app_handle.config().tauri.bundle.identifier.clone())
+            .title(&trigger.title)
+            .body(body)
+            .show();
+        if let Err(e) = shown {
+            eprintln!("Could not show notification for alert {}: {}", trigger.alert_id, e);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Tells the UI about a job run and surfaces anything it found.
+/// Failures to deliver are logged, so the remaining runs still go out.
+fn publish_job_run(app_handle: &tauri::AppHandle, run: &JobRun, outcome: &JobOutcome) -> Result<(), String> {
+    if let Err(e) = app_handle.emit_all("job-run", run.clone()) {
+        eprintln!("Could not emit run of job {}: {}", run.job_name, e);
+    }
+    
+    let body = match run.status {
+        JobRunStatus::Failed => {
+            if let Err(e) = app_handle.emit_all("job-failed", run.clone()) {
+                eprintln!("Could not emit failure of job {}: {}", run.job_name, e);
+            }
+            Some(format!("Failed: {}", run.summary))
+        }
+        JobRunStatus::Succeeded => outcome.notification.clone(),
+    };
+    if let Some(body) = body {
+        let shown = tauri::api::notification::Notification::new(app_handle.config().tauri.bundle.identifier.clone())
+            .title(&run.job_name)
+            .body(body)
+            .show();
+        if let Err(e) = shown {
+            eprintln!("Could not show notification for job {}: {}", run.job_name, e);
+        }
+    }
+    
+    notify_alert_triggers(app_handle, &outcome.alert_triggers)
+}
+
+/// Runs the jobs that are due, holding the database only while they run.
+/// A command that panicked while holding the database does not stop the
+/// scheduler; the connection itself is still sound.
+fn run_scheduled_jobs(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let app_state: State<AppState> = app_handle.state();
+    let data_dir = data_dir(&app_state)?;
+    
+    let runs = {
+        let db_guard = app_state.db.lock().unwrap_or_else(PoisonError::into_inner);
+        let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+        
+        scheduler_service::run_due_jobs(conn, &data_dir, chrono::Utc::now())
+            .map_err(|e| e.to_string())?
+    };
+    
+    for (run, outcome) in &runs {
+        publish_job_run(app_handle, run, outcome)?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn get_scheduled_jobs(app_state: State<AppState>) -> Result<Vec<ScheduledJob>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scheduler_service::get_scheduled_jobs(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_scheduled_job(app_state: State<AppState>, job: ScheduledJob) -> Result<ScheduledJob, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut job_copy = job;
+    scheduler_service::save_scheduled_job(conn, &mut job_copy)
+        .map_err(|e| e.to_string())?;
+    
+    Ok(job_copy)
+}
+
+#[tauri::command]
+fn update_scheduled_job(app_state: State<AppState>, job: ScheduledJob) -> Result<ScheduledJob, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut job_copy = job;
+    scheduler_service::update_scheduled_job(conn, &mut job_copy)
+        .map_err(|e| e.to_string())?;
+    
+    Ok(job_copy)
+}
+
+#[tauri::command]
+fn delete_scheduled_job(app_state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scheduler_service::delete_scheduled_job(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_job_enabled(app_state: State<AppState>, id: i64, enabled: bool) -> Result<ScheduledJob, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scheduler_service::set_job_enabled(conn, id, enabled)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn run_job_now(app_handle: tauri::AppHandle, app_state: State<AppState>, id: i64) -> Result<JobRun, String> {
+    let data_dir = data_dir(&app_state)?;
+    
+    let (run, outcome) = {
+        let db_guard = app_state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+        
+        scheduler_service::run_job_now(conn, id, &data_dir, chrono::Utc::now())
+            .map_err(|e| e.to_string())?
+    };
+    
+    publish_job_run(&app_handle, &run, &outcome)?;
+    
+    Ok(run)
+}
+
+#[tauri::command]
+fn get_job_runs(app_state: State<AppState>, job_id: Option<i64>, limit: i64) -> Result<Vec<JobRun>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scheduler_service::get_job_runs(conn, job_id, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn validate_cron(app_state: State<AppState>, expression: String) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    scheduler_service::validate_cron(conn, &expression)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_trading_calendars(app_state: State<AppState>) -> Result<Vec<TradingCalendar>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::get_calendars(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn import_trading_calendar(app_state: State<AppState>, path: String) -> Result<TradingCalendar, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::import_calendar_file(conn, std::path::Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_trading_calendar(app_state: State<AppState>, exchange: String) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::delete_calendar(conn, &exchange)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_trading_session(app_state: State<AppState>, exchange: String, date: chrono::NaiveDate) -> Result<Option<TradingSession>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::get_session(conn, &exchange, date)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn is_market_open(app_state: State<AppState>, symbol: String) -> Result<bool, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::is_market_open(conn, &symbol, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_next_trading_session(app_state: State<AppState>, symbol: String) -> Result<Option<TradingSession>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::next_session_for(conn, &symbol, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_holding_period(app_state: State<AppState>, trade_id: i64) -> Result<Option<HoldingPeriod>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    calendar_service::get_holding_period(conn, trade_id, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_time_zone_settings(app_state: State<AppState>) -> Result<TimeZoneSettings, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    time_zone_service::get_time_zone_settings(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_time_zone_settings(app_state: State<AppState>, settings: TimeZoneSettings) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    time_zone_service::save_time_zone_settings(conn, &settings)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn localize_timestamps(app_state: State<AppState>, timestamps: Vec<chrono::DateTime<chrono::Utc>>) -> Result<Vec<LocalTimestamp>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    time_zone_service::localize_timestamps(conn, &timestamps)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_app_settings(app_state: State<AppState>) -> Result<AppSettings, String> {
+    let settings_file = app_settings_service::settings_file_path().map_err(|e| e.to_string())?;
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    app_settings_service::get_app_settings(conn, &settings_file)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_app_settings(app_handle: tauri::AppHandle, app_state: State<AppState>, settings: AppSettings) -> Result<SettingsChange, String> {
+    let settings_file = app_settings_service::settings_file_path().map_err(|e| e.to_string())?;
+    let default_data_dir = default_data_dir(&app_handle)?;
+    let change = {
+        let db_guard = app_state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+        
+        app_settings_service::save_app_settings(conn, &settings_file, &default_data_dir, &settings)
+            .map_err(|e| e.to_string())?
+    };
+    
+    publish_settings_change(&app_handle, &change)?;
+    
+    Ok(change)
+}
+
+#[tauri::command]
+fn get_setting_value(app_state: State<AppState>, key: String) -> Result<serde_json::Value, String> {
+    let settings_file = app_settings_service::settings_file_path().map_err(|e| e.to_string())?;
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    app_settings_service::get_setting_value(conn, &settings_file, &key)
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn save_psychological_state(app_state: State<AppState>, state: PsychologicalState) -> Result<i64, String> {
+fn set_setting_value(app_handle: tauri::AppHandle, app_state: State<AppState>, key: String, value: serde_json::Value) -> Result<SettingsChange, String> {
+    let settings_file = app_settings_service::settings_file_path().map_err(|e| e.to_string())?;
+    let default_data_dir = default_data_dir(&app_handle)?;
+    let change = {
+        let db_guard = app_state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+        
+        app_settings_service::set_setting_value(conn, &settings_file, &default_data_dir, &key, value)
+            .map_err(|e| e.to_string())?
+    };
+    
+    publish_settings_change(&app_handle, &change)?;
+    
+    Ok(change)
+}
+
+/// Tells the UI which settings changed, if any.
+fn publish_settings_change(app_handle: &tauri::AppHandle, change: &SettingsChange) -> Result<(), String> {
+    if change.sections.is_empty() {
+        return Ok(());
+    }
+    
+    app_handle.emit_all("settings-changed", change.clone())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_account(app_state: State<AppState>, account: Account) -> Result<Account, String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    let mut state_copy = state;
-    psychological_service::save_psychological_state(conn, &mut state_copy)
+    let mut account_copy = account;
+    account_service::save_account(conn, &mut account_copy)
+        .map_err(|e| e.to_string())?;
+    
+    Ok(account_copy)
+}
+
+#[tauri::command]
+fn update_account(app_state: State<AppState>, account: Account) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    account_service::update_account(conn, &account)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_psychological_state(app_state: State<AppState>, id: i64) -> Result<PsychologicalState, String> {
+fn delete_account(app_state: State<AppState>, id: i64) -> Result<(), String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    psychological_service::get_psychological_state(conn, id)
+    account_service::delete_account(conn, id)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_recent_psychological_states(app_state: State<AppState>, limit: i64) -> Result<Vec<PsychologicalState>, String> {
+fn get_account(app_state: State<AppState>, id: i64) -> Result<Account, String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    psychological_service::get_recent_psychological_states(conn, limit)
+    account_service::get_account(conn, id)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn save_stock_rating(app_state: State<AppState>, rating: StockRating) -> Result<i64, String> {
+fn get_accounts(app_state: State<AppState>) -> Result<Vec<Account>, String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    let mut rating_copy = rating;
-    stock_rating_service::save_stock_rating(conn, &mut rating_copy)
+    account_service::get_accounts(conn)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_stock_rating(app_state: State<AppState>, id: i64) -> Result<StockRating, String> {
+fn get_account_summary(app_handle: tauri::AppHandle, app_state: State<AppState>, account_id: Option<i64>) -> Result<AccountSummary, String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    stock_rating_service::get_stock_rating(conn, id)
+    let summary = account_service::get_account_summary(conn, account_id, chrono::Utc::now())
+        .map_err(|e| e.to_string())?;
+    
+    if !summary.breaches.is_empty() {
+        if let Err(e) = app_handle.emit_all("risk-limit-breach", summary.clone()) {
+            eprintln!("Could not emit risk limit breach: {}", e);
+        }
+    }
+    
+    Ok(summary)
+}
+
+#[tauri::command]
+fn save_detailed_analysis(app_state: State<AppState>, analysis: DetailedAnalysis) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut analysis_copy = analysis;
+    detailed_analysis_service::save_detailed_analysis(conn, &mut analysis_copy)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_stock_ratings_by_symbol(app_state: State<AppState>, symbol: String) -> Result<Vec<StockRating>, String> {
+fn update_detailed_analysis(app_state: State<AppState>, analysis: AnalysisUpdate) -> Result<(), String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    stock_rating_service::get_stock_ratings_by_symbol(conn, &symbol)
+    detailed_analysis_service::apply_analysis_update(conn, analysis)
+        .map(|_| ())
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_recent_stock_ratings(app_state: State<AppState>, limit: i64) -> Result<Vec<StockRating>, String> {
+fn unlink_analysis_rating(app_state: State<AppState>, id: i64) -> Result<(), String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    stock_rating_service::get_recent_stock_ratings(conn, limit)
+    detailed_analysis_service::unlink_rating(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_detailed_analysis(app_state: State<AppState>, id: i64) -> Result<DetailedAnalysis, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    detailed_analysis_service::get_detailed_analysis(conn, id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_recent_detailed_analyses(app_state: State<AppState>, limit: i64, account_id: Option<i64>) -> Result<Vec<DetailedAnalysis>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    detailed_analysis_service::get_recent_detailed_analyses(conn, limit, account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn price_option(inputs: PricingInputs) -> OptionValuation {
+    black_scholes::valuation(&inputs)
+}
+
+#[tauri::command]
+fn update_analysis_greeks(app_state: State<AppState>, analysis_id: i64, inputs: PricingInputs) -> Result<DetailedAnalysis, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    detailed_analysis_service::update_analysis_greeks(conn, analysis_id, &inputs)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn solve_implied_volatility(inputs: PricingInputs, market_price: f64) -> Result<f64, String> {
+    implied_volatility::implied_volatility(&inputs, market_price)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn attribute_option_pnl(entry: PricingInputs, exit: PricingInputs) -> PnlAttribution {
+    implied_volatility::attribute_pnl(&entry, &exit)
+}
+
+#[tauri::command]
+fn record_leg_implied_volatility(
+    app_state: State<AppState>,
+    analysis_id: i64,
+    leg: SpreadLeg,
+    phase: TradePhase,
+    inputs: PricingInputs,
+    market_price: f64,
+) -> Result<DetailedAnalysis, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    detailed_analysis_service::record_leg_implied_volatility(conn, analysis_id, leg, phase, &inputs, market_price)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_leg_iv_change(app_state: State<AppState>, analysis_id: i64, leg: SpreadLeg) -> Result<Option<f64>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    detailed_analysis_service::get_detailed_analysis(conn, analysis_id)
+        .map(|analysis| analysis.leg_iv_change(leg))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn build_preset_legs(preset: LegPreset, underlying: String, expiry: chrono::NaiveDate) -> Vec<OptionLeg> {
+    preset.build(&underlying, expiry)
+}
+
+#[tauri::command]
+fn parse_option_symbol(symbol: String, side: LegSide) -> Result<OptionLeg, String> {
+    OccSymbol::parse(&symbol)
+        .map(|occ| occ.to_leg(side))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn format_option_symbol(leg: OptionLeg, format: OccFormat) -> Result<String, String> {
+    let occ = OccSymbol::from_leg(&leg);
+    occ.validate().map_err(|e| e.to_string())?;
+    Ok(occ.format(format))
+}
+
+#[tauri::command]
+fn get_legs_by_analysis(app_state: State<AppState>, analysis_id: i64) -> Result<Vec<OptionLeg>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_leg_service::get_legs_by_analysis(conn, analysis_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_legs_by_trade(app_state: State<AppState>, trade_id: i64) -> Result<Vec<OptionLeg>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_leg_service::get_legs_by_trade(conn, trade_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn record_option_leg_iv(
+    app_state: State<AppState>,
+    leg_id: i64,
+    phase: TradePhase,
+    underlying_price: f64,
+    market_price: f64,
+) -> Result<OptionLeg, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_leg_service::record_option_leg_iv(conn, leg_id, phase, underlying_price, market_price, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_analysis_payoff(app_state: State<AppState>, analysis_id: i64, request: PayoffRequest) -> Result<PayoffDiagram, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    payoff_service::get_analysis_payoff(conn, analysis_id, &request, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_trade_payoff(app_state: State<AppState>, trade_id: i64, request: PayoffRequest) -> Result<PayoffDiagram, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    payoff_service::get_trade_payoff(conn, trade_id, &request, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn render_payoff_svg(diagram: PayoffDiagram) -> Result<String, String> {
+    charts::payoff::render_payoff_svg(&diagram)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_payoff_png(app_handle: tauri::AppHandle, app_state: State<AppState>, diagram: PayoffDiagram, path: String) -> Result<(), String> {
+    let path = export_path(&app_handle, &app_state, &path)?;
+    charts::payoff::render_payoff_png(&diagram, &path)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_expiring_legs(app_state: State<AppState>, from: chrono::NaiveDate, to: chrono::NaiveDate, account_id: Option<i64>) -> Result<Vec<ExpiringLeg>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_lifecycle_service::get_expiring_legs(conn, from, to, chrono::Utc::now(), account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_legs_expiring_this_week(app_state: State<AppState>, account_id: Option<i64>) -> Result<Vec<ExpiringLeg>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_lifecycle_service::get_legs_expiring_this_week(conn, chrono::Utc::now(), account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn record_option_event(
+    app_state: State<AppState>,
+    trade_id: i64,
+    leg_id: i64,
+    event_type: OptionEventType,
+    underlying_price: Option<f64>,
+) -> Result<OptionEvent, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_lifecycle_service::record_option_event(conn, trade_id, leg_id, event_type, underlying_price, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn expire_worthless_legs(app_state: State<AppState>, as_of: chrono::NaiveDate, settlement_prices: HashMap<String, f64>) -> Result<ExpiryRun, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_lifecycle_service::expire_worthless_legs(conn, as_of, &settlement_prices)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_option_events_by_trade(app_state: State<AppState>, trade_id: i64) -> Result<Vec<OptionEvent>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    option_lifecycle_service::get_option_events_by_trade(conn, trade_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_child_trades(app_state: State<AppState>, trade_id: i64) -> Result<Vec<Trade>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    trade_service::get_child_trades(conn, trade_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn import_price_bars(app_handle: tauri::AppHandle, app_state: State<AppState>, bars: Vec<PriceBar>) -> Result<usize, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let imported = price_service::import_bars(conn, &bars)
+        .map_err(|e| e.to_string())?;
+    
+    let symbols: std::collections::BTreeSet<String> = bars.iter()
+        .map(|bar| bar.symbol.to_ascii_uppercase())
+        .collect();
+    for symbol in symbols {
+        let triggers = alert_service::evaluate_alerts(conn, Some(&symbol), chrono::Utc::now())
+            .map_err(|e| e.to_string())?;
+        notify_alert_triggers(&app_handle, &triggers)?;
+    }
+    
+    Ok(imported)
+}
+
+#[tauri::command]
+fn get_price_bars(app_state: State<AppState>, symbol: String, timeframe: Timeframe, limit: i64) -> Result<Vec<PriceBar>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    price_service::get_bars(conn, &symbol, timeframe, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_portfolio_risk_settings(app_state: State<AppState>) -> Result<PortfolioRiskSettings, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    portfolio_risk_service::get_portfolio_risk_settings(conn)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_portfolio_risk_settings(app_state: State<AppState>, settings: PortfolioRiskSettings) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    portfolio_risk_service::save_portfolio_risk_settings(conn, &settings)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_portfolio_greeks(app_handle: tauri::AppHandle, app_state: State<AppState>, account_id: Option<i64>) -> Result<PortfolioGreeks, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let greeks = portfolio_risk_service::get_portfolio_greeks(conn, chrono::Utc::now(), account_id)
+        .map_err(|e| e.to_string())?;
+    
+    if !greeks.breaches.is_empty() {
+        app_handle.emit_all("portfolio-risk-breach", greeks.breaches.clone())
+            .map_err(|e| e.to_string())?;
+    }
+    
+    Ok(greeks)
+}
+
+#[tauri::command]
+fn save_checklist_template(app_state: State<AppState>, template: ChecklistTemplate) -> Result<i64, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    let mut template_copy = template;
+    checklist_service::save_checklist_template(conn, &mut template_copy)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_checklist_template(app_state: State<AppState>, template: ChecklistTemplate) -> Result<(), String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    checklist_service::update_checklist_template(conn, &template)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_checklist_templates(app_state: State<AppState>, strategy: Option<String>) -> Result<Vec<ChecklistTemplate>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    match strategy {
+        Some(strategy) => checklist_service::get_checklist_templates_for_strategy(conn, &strategy),
+        None => checklist_service::get_checklist_templates(conn),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn complete_checklist(
+    app_state: State<AppState>,
+    analysis_id: i64,
+    template_id: i64,
+    manual_answers: HashMap<String, bool>,
+) -> Result<CompletedChecklist, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    checklist_service::complete_checklist(conn, analysis_id, template_id, &manual_answers)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_checklists_by_analysis(app_state: State<AppState>, analysis_id: i64) -> Result<Vec<CompletedChecklist>, String> {
+    let db_guard = app_state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Database not initialized")?;
+    
+    checklist_service::get_checklists_by_analysis(conn, analysis_id)
         .map_err(|e| e.to_string())
 }
 
@@ -93,11 +1444,12 @@ fn save_trade(app_state: State<AppState>, trade: Trade) -> Result<i64, String> {
 }
 
 #[tauri::command]
-fn update_trade(app_state: State<AppState>, trade: Trade) -> Result<(), String> {
+fn update_trade(app_state: State<AppState>, trade: TradeUpdate) -> Result<(), String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    trade_service::update_trade(conn, &trade)
+    trade_service::apply_trade_update(conn, trade)
+        .map(|_| ())
         .map_err(|e| e.to_string())
 }
 
@@ -120,11 +1472,11 @@ fn get_trades_by_analysis(app_state: State<AppState>, analysis_id: i64) -> Resul
 }
 
 #[tauri::command]
-fn get_recent_trades(app_state: State<AppState>, limit: i64) -> Result<Vec<Trade>, String> {
+fn get_recent_trades(app_state: State<AppState>, limit: i64, account_id: Option<i64>) -> Result<Vec<Trade>, String> {
     let db_guard = app_state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("Database not initialized")?;
     
-    trade_service::get_recent_trades(conn, limit)
+    trade_service::get_recent_trades(conn, limit, account_id)
         .map_err(|e| e.to_string())
 }
 
@@ -132,6 +1484,7 @@ fn main() {
     tauri::Builder::default()
         .manage(AppState {
             db: Mutex::new(None),
+            data_dir: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             save_psychological_state,
@@ -141,6 +1494,132 @@ fn main() {
             get_stock_rating,
             get_stock_ratings_by_symbol,
             get_recent_stock_ratings,
+            save_scoring_model,
+            get_scoring_models,
+            get_active_scoring_model,
+            set_active_scoring_model,
+            rescore_ratings,
+            compare_scoring_models,
+            get_pattern_histogram,
+            get_opportunity_chart,
+            render_opportunity_svg,
+            export_opportunity_png,
+            get_rating_decay_settings,
+            save_rating_decay_settings,
+            get_rating_freshness,
+            get_rerating_queue,
+            start_rerating,
+            get_sectors,
+            save_custom_sector,
+            update_custom_sector,
+            delete_custom_sector,
+            match_sector,
+            get_sector_reviews,
+            resolve_sector_review,
+            add_sector_selection,
+            get_sector_selections,
+            remove_sector_selection,
+            import_securities_csv,
+            get_security,
+            search_securities,
+            validate_symbol,
+            save_watchlist,
+            update_watchlist,
+            delete_watchlist,
+            get_watchlist,
+            get_watchlists,
+            add_watchlist_item,
+            update_watchlist_item,
+            remove_watchlist_item,
+            refresh_watchlist,
+            promote_watchlist_item,
+            promote_rating,
+            plan_trade_from_analysis,
+            get_rating_chain,
+            get_trade_lineage,
+            get_analyses_by_rating,
+            detect_chart_patterns,
+            suggest_chart_pattern,
+            get_indicator,
+            get_indicator_value,
+            clear_indicator_cache,
+            get_trend_settings,
+            save_trend_settings,
+            classify_market_trend,
+            save_alert,
+            update_alert,
+            delete_alert,
+            get_alert,
+            get_alerts,
+            get_alerts_by_analysis,
+            get_alert_triggers,
+            parse_alert_text,
+            evaluate_alerts,
+            get_scheduled_jobs,
+            save_scheduled_job,
+            update_scheduled_job,
+            delete_scheduled_job,
+            set_job_enabled,
+            run_job_now,
+            get_job_runs,
+            validate_cron,
+            get_trading_calendars,
+            import_trading_calendar,
+            delete_trading_calendar,
+            get_trading_session,
+            is_market_open,
+            get_next_trading_session,
+            get_holding_period,
+            get_time_zone_settings,
+            save_time_zone_settings,
+            localize_timestamps,
+            get_app_settings,
+            save_app_settings,
+            get_setting_value,
+            set_setting_value,
+            save_account,
+            update_account,
+            delete_account,
+            get_account,
+            get_accounts,
+            get_account_summary,
+            save_detailed_analysis,
+            update_detailed_analysis,
+            unlink_analysis_rating,
+            get_detailed_analysis,
+            get_recent_detailed_analyses,
+            price_option,
+            update_analysis_greeks,
+            solve_implied_volatility,
+            attribute_option_pnl,
+            record_leg_implied_volatility,
+            get_leg_iv_change,
+            build_preset_legs,
+            parse_option_symbol,
+            format_option_symbol,
+            get_legs_by_analysis,
+            get_legs_by_trade,
+            record_option_leg_iv,
+            get_analysis_payoff,
+            get_trade_payoff,
+            render_payoff_svg,
+            export_payoff_png,
+            get_expiring_legs,
+            get_legs_expiring_this_week,
+            record_option_event,
+            expire_worthless_legs,
+            get_option_events_by_trade,
+            get_child_trades,
+            import_price_bars,
+            get_price_bars,
+            get_portfolio_risk_settings,
+            save_portfolio_risk_settings,
+            get_portfolio_greeks,
+            save_checklist_template,
+            update_checklist_template,
+            get_checklist_templates,
+            complete_checklist,
+            get_checklists_by_analysis,
             save_trade,
             update_trade,
             get_trade,
@@ -151,23 +1630,50 @@ fn main() {
             // Initialize database connection
             let app_state: State<AppState> = app.state();
             let mut db_guard = app_state.db.lock().unwrap();
-            let db_path = app.path_resolver()
-                .app_data_dir()
-                .unwrap()
-                .join("stock_dashboard.db");
+            
+            // The data directory comes from the settings file, since it
+            // cannot be kept in the database it locates
+            // A settings file that cannot be read falls back to the defaults
+            let storage = app_settings_service::load_storage_settings(&app_settings_service::settings_file_path()?)
+                .unwrap_or_else(|e| {
+                    eprintln!("Using default storage settings: {}", e);
+                    Default::default()
+                });
+            let data_dir = match storage.data_directory {
+                Some(dir) => dir,
+                None => default_data_dir(&app.handle())?,
+            };
+            let db_path = data_dir.join(app_settings_service::DATABASE_FILE);
             
             // Ensure directory exists
-            if let Some(parent) = db_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
+            std::fs::create_dir_all(&data_dir)?;
             
-            *db_guard = Some(rusqlite::Connection::open(db_path)?);
+            *db_guard = Some(rusqlite::Connection::open(&db_path)?);
+            *app_state.data_dir.lock().unwrap() = Some(data_dir.clone());
             
             // Initialize database schema
             if let Some(conn) = &*db_guard {
                 services::db::initialize_database(conn)?;
+                
+                // Exchange calendars dropped next to the database
+                let calendars = calendar_service::load_calendar_directory(conn, &data_dir.join("calendars"))?;
+                for failure in &calendars.failed {
+                    eprintln!("Skipped calendar {}", failure);
+                }
+                
+                // Trading days depend on the calendars just loaded
+                trade_service::assign_trading_days(conn)?;
             }
             
+            // Run scheduled jobs in the background
+            let app_handle = app.handle();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_secs(SCHEDULER_TICK_SECONDS));
+                if let Err(e) = run_scheduled_jobs(&app_handle) {
+                    let _ = app_handle.emit_all("scheduler-error", e);
+                }
+            });
+            
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::portfolio_risk::RiskLimits;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AccountType {
+    Cash,
+    Margin,
+    Ira,
+    Paper,
+}
+
+/// A brokerage account trades and analyses are booked to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Account {
+    pub id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub name: String,
+    pub broker: Option<String>,
+    pub base_currency: String,     // ISO 4217 code
+    pub starting_balance: f64,
+    pub account_type: AccountType,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum RiskLimitBreach {
+    OpenPositions { count: usize, limit: u32 },
+    PositionSize { trade_id: i64, symbol: String, percent: f64, limit: f64 },
+    TradeRisk { trade_id: i64, symbol: String, percent: f64, limit: f64 },
+    DailyLoss { loss: f64, limit: f64 },
+}
+
+/// An open trade as the risk limits see it.
+#[derive(Debug, Clone)]
+pub struct OpenPosition {
+    pub trade_id: i64,
+    pub symbol: String,
+    pub entry_value: f64,
+    pub risk_max: f64,             // Maximum loss planned in its analysis
+}
+
+/// Balances and risk limit checks for one account, or for all accounts
+/// together when `account_id` is None.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountSummary {
+    pub account_id: Option<i64>,
+    pub name: String,
+    pub currency: String,          // Accounts are only consolidated in one currency
+    pub starting_balance: f64,
+    pub realized_pnl: f64,
+    pub equity: f64,               // Starting balance plus realized profit/loss
+    pub daily_pnl: f64,            // Realized on the current exchange date
+    pub open_positions: usize,
+    pub planned_trades: usize,
+    pub breaches: Vec<RiskLimitBreach>,
+}
+
+impl Account {
+    pub fn new(name: &str, account_type: AccountType) -> Self {
+        Self {
+            id: None,
+            created_at: Utc::now(),
+            name: name.to_string(),
+            broker: None,
+            base_currency: "USD".to_string(),
+            starting_balance: 0.0,
+            account_type,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Account has no name".to_string());
+        }
+        let currency = &self.base_currency;
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!("Currency '{}' is not a three-letter code", currency));
+        }
+        if !self.starting_balance.is_finite() || self.starting_balance < 0.0 {
+            return Err("Starting balance cannot be negative".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl AccountSummary {
+    /// Checks the summary against `limits`, given its open positions.
+    pub fn check_limits(&mut self, positions: &[OpenPosition], limits: &RiskLimits) {
+        let mut breaches = Vec::new();
+
+        if self.open_positions > limits.max_open_positions as usize {
+            breaches.push(RiskLimitBreach::OpenPositions { count: self.open_positions, limit: limits.max_open_positions });
+        }
+
+        if self.equity > 0.0 {
+            for position in positions {
+                let percent = position.entry_value.abs() / self.equity * 100.0;
+                if percent > limits.max_position_percent {
+                    breaches.push(RiskLimitBreach::PositionSize {
+                        trade_id: position.trade_id,
+                        symbol: position.symbol.clone(),
+                        percent,
+                        limit: limits.max_position_percent,
+                    });
+                }
+                if let Some(percent) = limits.trade_risk_breach(position.risk_max, self.equity) {
+                    breaches.push(RiskLimitBreach::TradeRisk {
+                        trade_id: position.trade_id,
+                        symbol: position.symbol.clone(),
+                        percent,
+                        limit: limits.max_risk_per_trade_percent,
+                    });
+                }
+            }
+        }
+
+        let max_loss = self.equity.max(0.0) * limits.max_daily_loss_percent / 100.0;
+        if -self.daily_pnl > max_loss {
+            breaches.push(RiskLimitBreach::DailyLoss { loss: -self.daily_pnl, limit: max_loss });
+        }
+
+        self.breaches = breaches;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(equity: f64, daily_pnl: f64, open_positions: usize) -> AccountSummary {
+        AccountSummary {
+            account_id: Some(1),
+            name: "Brokerage".to_string(),
+            currency: "USD".to_string(),
+            starting_balance: equity,
+            realized_pnl: 0.0,
+            equity,
+            daily_pnl,
+            open_positions,
+            planned_trades: 0,
+            breaches: Vec::new(),
+        }
+    }
+
+    fn position(trade_id: i64, symbol: &str, entry_value: f64, risk_max: f64) -> OpenPosition {
+        OpenPosition { trade_id, symbol: symbol.to_string(), entry_value, risk_max }
+    }
+
+    #[test]
+    fn within_limits_has_no_breaches() {
+        let mut summary = summary(100_000.0, -2_000.0, 2);
+        summary.check_limits(&[position(1, "AAPL", 20_000.0, 800.0), position(2, "MSFT", -15_000.0, 1_000.0)], &RiskLimits::default());
+
+        assert!(summary.breaches.is_empty(), "{:?}", summary.breaches);
+    }
+
+    #[test]
+    fn each_limit_is_reported() {
+        let limits = RiskLimits { max_open_positions: 1, ..RiskLimits::default() };
+        let mut summary = summary(100_000.0, -3_500.0, 2);
+        summary.check_limits(&[position(1, "AAPL", 10_000.0, 1_500.0), position(2, "TSLA", -25_000.0, 500.0)], &limits);
+
+        assert_eq!(summary.breaches, vec![
+            RiskLimitBreach::OpenPositions { count: 2, limit: 1 },
+            RiskLimitBreach::TradeRisk { trade_id: 1, symbol: "AAPL".to_string(), percent: 1.5, limit: 1.0 },
+            RiskLimitBreach::PositionSize { trade_id: 2, symbol: "TSLA".to_string(), percent: 25.0, limit: 20.0 },
+            RiskLimitBreach::DailyLoss { loss: 3_500.0, limit: 3_000.0 },
+        ]);
+    }
+
+    #[test]
+    fn any_loss_breaches_without_equity() {
+        let mut summary = summary(0.0, -1.0, 1);
+        summary.check_limits(&[position(1, "AAPL", 5_000.0, 500.0)], &RiskLimits::default());
+
+        assert_eq!(summary.breaches, vec![RiskLimitBreach::DailyLoss { loss: 1.0, limit: 0.0 }]);
+    }
+
+    #[test]
+    fn accounts_need_a_name_and_an_iso_currency() {
+        let mut account = Account::new("Brokerage", AccountType::Margin);
+        assert!(account.validate().is_ok());
+
+        account.base_currency = "usd".to_string();
+        assert!(account.validate().is_err());
+        account.base_currency = "USD".to_string();
+        account.starting_balance = -1.0;
+        assert!(account.validate().is_err());
+        account.starting_balance = 0.0;
+        account.name = " ".to_string();
+        assert!(account.validate().is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::checklist::Comparison;
+use super::detailed_analysis::DetailedAnalysis;
+use super::indicator::{Indicator, IndicatorLine};
+use super::price_bar::Timeframe;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AlertCondition {
+    CrossesAbove { level: f64 },
+    CrossesBelow { level: f64 },
+    PercentMove { percent: f64, reference_price: f64 },   // Negative for a drop
+    Indicator { line: IndicatorLine, comparison: Comparison, value: f64 },
+    StopTouched,                   // Stop loss of the linked analysis
+    TargetTouched,                 // Target price of the linked analysis
+    DaysToExpiry { days: i64 },    // Nearest leg expiry of the linked analysis
+    At { time: DateTime<Utc> },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AlertStatus {
+    Active,
+    Triggered,
+    Dismissed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Alert {
+    pub id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub symbol: String,
+    pub analysis_id: Option<i64>,
+    pub condition: AlertCondition,
+    pub message: Option<String>,
+    pub source_text: Option<String>,   // Free-text alert this was parsed from
+    pub status: AlertStatus,
+    pub last_price: Option<f64>,       // Price at the previous evaluation, to detect crossings
+    pub triggered_at: Option<DateTime<Utc>>,
+}
+
+impl Alert {
+    pub fn new(symbol: &str, condition: AlertCondition) -> Self {
+        Self {
+            id: None,
+            created_at: Utc::now(),
+            symbol: symbol.to_string(),
+            analysis_id: None,
+            condition,
+            message: None,
+            source_text: None,
+            status: AlertStatus::Active,
+            last_price: None,
+            triggered_at: None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        format!("{}: {}", self.symbol, self.condition.describe())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertTrigger {
+    pub id: Option<i64>,
+    pub alert_id: i64,
+    pub symbol: String,
+    pub triggered_at: DateTime<Utc>,
+    pub price: Option<f64>,
+    pub title: String,
+    pub message: Option<String>,
+}
+
+/// Values an alert condition is checked against.
+pub struct AlertContext<'a> {
+    pub now: DateTime<Utc>,
+    pub today: NaiveDate,          // Exchange date at `now`
+    pub price: Option<f64>,
+    pub previous_price: Option<f64>,
+    pub low: Option<f64>,          // Range of the latest bar, for touches between closes
+    pub high: Option<f64>,
+    pub analysis: Option<&'a DetailedAnalysis>,
+    pub indicator_value: Option<f64>,
+}
+
+impl AlertCondition {
+    /// Whether the condition holds. Conditions missing what they need, such
+    /// as a price or a linked analysis, never do; crossings need the price of
+    /// the previous evaluation.
+    pub fn is_met(&self, context: &AlertContext) -> bool {
+        let price = context.price;
+        let low = context.low.or(price);
+        let high = context.high.or(price);
+        match self {
+            AlertCondition::CrossesAbove { level } => price.is_some_and(|price| {
+                price >= *level && context.previous_price.is_some_and(|previous| previous < *level)
+            }),
+            AlertCondition::CrossesBelow { level } => price.is_some_and(|price| {
+                price <= *level && context.previous_price.is_some_and(|previous| previous > *level)
+            }),
+            AlertCondition::PercentMove { percent, reference_price } => price.is_some_and(|price| {
+                if *reference_price <= 0.0 {
+                    return false;
+                }
+                let change = (price / reference_price - 1.0) * 100.0;
+                if *percent >= 0.0 { change >= *percent } else { change <= *percent }
+            }),
+            AlertCondition::Indicator { comparison, value, .. } => {
+                context.indicator_value.is_some_and(|actual| comparison.holds(actual, *value))
+            }
+            AlertCondition::StopTouched => match (low, high, context.analysis) {
+                (Some(low), Some(high), Some(analysis)) if analysis.stop_loss > 0.0 => {
+                    if analysis.bull_bear >= 0 { low <= analysis.stop_loss } else { high >= analysis.stop_loss }
+                }
+                _ => false,
+            },
+            AlertCondition::TargetTouched => match (low, high, context.analysis) {
+                (Some(low), Some(high), Some(analysis)) if analysis.target_price > 0.0 => {
+                    if analysis.bull_bear >= 0 { high >= analysis.target_price } else { low <= analysis.target_price }
+                }
+                _ => false,
+            },
+            AlertCondition::DaysToExpiry { days } => context.analysis
+                .and_then(|analysis| analysis.legs.iter().map(|leg| leg.expiry).min())
+                .is_some_and(|expiry| (expiry - context.today).num_days() <= *days),
+            AlertCondition::At { time } => context.now >= *time,
+        }
+    }
+
+    /// Whether the condition watches prices, and so only means something
+    /// while the market is open.
+    pub fn needs_session(&self) -> bool {
+        !matches!(self, AlertCondition::DaysToExpiry { .. } | AlertCondition::At { .. })
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            AlertCondition::CrossesAbove { level } => format!("price crossed above {:.2}", level),
+            AlertCondition::CrossesBelow { level } => format!("price crossed below {:.2}", level),
+            AlertCondition::PercentMove { percent, reference_price } => {
+                format!("price moved {:+.1}% from {:.2}", percent, reference_price)
+            }
+            AlertCondition::Indicator { line, comparison, value } => {
+                format!("{:?} {:?} {}", line.indicator, comparison, value)
+            }
+            AlertCondition::StopTouched => "stop loss touched".to_string(),
+            AlertCondition::TargetTouched => "target price reached".to_string(),
+            AlertCondition::DaysToExpiry { days } => format!("{} days or less to expiry", days),
+            AlertCondition::At { time } => format!("reminder for {}", time.format("%Y-%m-%d %H:%M")),
+        }
+    }
+
+    /// Reads a free-text alert such as "above 150", "-5%", "RSI(14) > 70",
+    /// "stop", "7 DTE" or "on 2024-06-01". Percent moves need
+    /// `reference_price`. None when the text is not understood.
+    pub fn parse(text: &str, reference_price: Option<f64>) -> Option<AlertCondition> {
+        let normalized = text.to_ascii_lowercase().replace(['$', ','], " ");
+        let tokens = split_operators(&normalized);
+        let words: Vec<&str> = tokens.iter().map(String::as_str).collect();
+
+        if words.iter().any(|w| w.starts_with("stop")) {
+            return Some(AlertCondition::StopTouched);
+        }
+        if words.iter().any(|w| w.starts_with("target")) {
+            return Some(AlertCondition::TargetTouched);
+        }
+        if let Some(days) = parse_days_to_expiry(&words) {
+            return Some(AlertCondition::DaysToExpiry { days });
+        }
+        if let Some(date) = words.iter().find_map(|w| NaiveDate::parse_from_str(w, "%Y-%m-%d").ok()) {
+            return Some(AlertCondition::At { time: date.and_hms_opt(0, 0, 0)?.and_utc() });
+        }
+        if let Some(percent) = parse_percent(&words) {
+            return Some(AlertCondition::PercentMove { percent, reference_price: reference_price? });
+        }
+        if words.iter().any(|w| indicator_name(w).is_some()) {
+            return parse_indicator(&words);
+        }
+
+        let (direction, level) = parse_comparison(&words)?;
+        match direction {
+            Some(true) => Some(AlertCondition::CrossesAbove { level }),
+            Some(false) => Some(AlertCondition::CrossesBelow { level }),
+            None => match reference_price {
+                Some(reference) if level >= reference => Some(AlertCondition::CrossesAbove { level }),
+                Some(_) => Some(AlertCondition::CrossesBelow { level }),
+                None => None,
+            },
+        }
+    }
+}
+
+/// Splits on whitespace, brackets and comparison operators, keeping the
+/// operators as tokens.
+fn split_operators(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() || c == '(' || c == ')' || c == ':' {
+            tokens.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+        } else if c == '>' || c == '<' {
+            tokens.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            let mut op = c.to_string();
+            if chars.peek() == Some(&'=') {
+                op.push('=');
+                chars.next();
+            }
+            tokens.push(op);
+        } else {
+            current.push(c);
+        }
+    }
+    tokens.extend((!current.is_empty()).then_some(current));
+
+    tokens
+}
+
+fn number(token: &str) -> Option<f64> {
+    token.trim_end_matches('%').parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+fn parse_days_to_expiry(words: &[&str]) -> Option<i64> {
+    if let Some(days) = words.iter().find_map(|w| w.strip_suffix("dte").and_then(|n| n.parse().ok())) {
+        return Some(days);
+    }
+
+    let dte_at = words.iter().position(|w| *w == "dte" || w.starts_with("expir"))?;
+    words[..dte_at].iter().rev().chain(&words[dte_at + 1..]).find_map(|w| w.parse().ok())
+}
+
+fn parse_percent(words: &[&str]) -> Option<f64> {
+    let at = words.iter().position(|w| w.ends_with('%'))?;
+    let value = number(words[at])?;
+    let falling = words[..at].iter().any(|w| ["down", "drop", "drops", "fall", "falls", "below", "under"].contains(w));
+
+    Some(if falling { -value.abs() } else { value })
+}
+
+/// Comparison keyword followed by a number. The direction is None when the
+/// text only names a level, as in "at 150".
+fn parse_comparison(words: &[&str]) -> Option<(Option<bool>, f64)> {
+    let mut direction = None;
+    for word in words {
+        match *word {
+            ">" | ">=" | "above" | "over" | "breaks" | "crosses" => direction = direction.or(Some(true)),
+            "<" | "<=" | "below" | "under" => direction = Some(false),
+            _ => {}
+        }
+        if let Some(level) = number(word) {
+            return Some((direction, level));
+        }
+    }
+
+    None
+}
+
+/// Indicator named by a word such as "rsi" or "sma50".
+fn indicator_name(word: &str) -> Option<&'static str> {
+    ["rsi", "sma", "ema", "atr", "adx", "vwap"].into_iter().find(|name| {
+        word.strip_prefix(name).is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+fn parse_indicator(words: &[&str]) -> Option<AlertCondition> {
+    let (name, period) = words.iter().enumerate().find_map(|(i, word)| {
+        let name = indicator_name(word)?;
+        let attached = word[name.len()..].parse::<usize>().ok();
+        let following = words.get(i + 1).and_then(|w| w.parse::<usize>().ok())
+            .filter(|_| words.get(i + 2).is_some_and(|w| number(w).is_none()));
+        Some((name, attached.or(following)))
+    })?;
+
+    let indicator = match name {
+        "rsi" => Indicator::Rsi { period: period.unwrap_or(14) },
+        "sma" => Indicator::Sma { period: period? },
+        "ema" => Indicator::Ema { period: period? },
+        "atr" => Indicator::Atr { period: period.unwrap_or(14) },
+        "adx" => Indicator::Adx { period: period.unwrap_or(14) },
+        _ => Indicator::Vwap,
+    };
+
+    let at = words.iter().position(|w| ["<", "<=", ">", ">=", "above", "below", "over", "under"].contains(w))?;
+    let comparison = match words[at] {
+        ">" | "above" | "over" => Comparison::GreaterThan,
+        ">=" => Comparison::GreaterOrEqual,
+        "<" | "below" | "under" => Comparison::LessThan,
+        _ => Comparison::LessOrEqual,
+    };
+    let value = words[at + 1..].iter().find_map(|w| number(w))?;
+
+    Some(AlertCondition::Indicator {
+        line: IndicatorLine { indicator, output: None, timeframe: Timeframe::Daily },
+        comparison,
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(price: f64, previous_price: Option<f64>, analysis: Option<&DetailedAnalysis>) -> AlertContext<'_> {
+        let now = Utc::now();
+        AlertContext {
+            now,
+            today: now.date_naive(),
+            price: Some(price),
+            previous_price,
+            low: None,
+            high: None,
+            analysis,
+            indicator_value: None,
+        }
+    }
+
+    #[test]
+    fn crossing_needs_a_previous_price() {
+        let condition = AlertCondition::CrossesAbove { level: 150.0 };
+
+        assert!(!condition.is_met(&context(155.0, None, None)));
+        assert!(!condition.is_met(&context(155.0, Some(151.0), None)));
+        assert!(condition.is_met(&context(155.0, Some(149.0), None)));
+    }
+
+    #[test]
+    fn stop_and_target_use_the_bar_range() {
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.stop_loss = 95.0;
+        analysis.target_price = 120.0;
+
+        let mut touched = context(100.0, Some(100.0), Some(&analysis));
+        touched.low = Some(94.5);
+        touched.high = Some(101.0);
+        assert!(AlertCondition::StopTouched.is_met(&touched));
+        assert!(!AlertCondition::TargetTouched.is_met(&touched));
+
+        analysis.bull_bear = -1;
+        analysis.stop_loss = 105.0;
+        analysis.target_price = 96.0;
+        let mut short = context(100.0, Some(100.0), Some(&analysis));
+        short.low = Some(95.5);
+        short.high = Some(104.0);
+        assert!(!AlertCondition::StopTouched.is_met(&short));
+        assert!(AlertCondition::TargetTouched.is_met(&short));
+    }
+
+    #[test]
+    fn parses_free_text_alerts() {
+        assert_eq!(AlertCondition::parse("above 150", None), Some(AlertCondition::CrossesAbove { level: 150.0 }));
+        assert_eq!(AlertCondition::parse("at 90", Some(100.0)), Some(AlertCondition::CrossesBelow { level: 90.0 }));
+        assert_eq!(AlertCondition::parse("drops 5%", Some(100.0)), Some(AlertCondition::PercentMove { percent: -5.0, reference_price: 100.0 }));
+        assert_eq!(AlertCondition::parse("7 DTE", None), Some(AlertCondition::DaysToExpiry { days: 7 }));
+        assert_eq!(AlertCondition::parse("hit stop", None), Some(AlertCondition::StopTouched));
+        assert!(matches!(
+            AlertCondition::parse("RSI(14) > 70", None),
+            Some(AlertCondition::Indicator { comparison: Comparison::GreaterThan, value, .. }) if value == 70.0
+        ));
+        assert_eq!(AlertCondition::parse("check earnings", None), None);
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::portfolio_risk::PortfolioRiskSettings;
+use super::time_zone::TimeZoneSettings;
+use super::trade::Trade;
+
+/// Where the app keeps its data. Read before the database is opened, so it
+/// is kept in a TOML file in the user's config directory.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct StorageSettings {
+    #[serde(default)]
+    pub data_directory: Option<PathBuf>,   // None for the platform's app data directory
+}
+
+/// Broker commissions, charged on each order. None by default, so profits
+/// only change once the user enters their broker's rates.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FeeSettings {
+    pub per_trade: f64,
+    pub per_share: f64,
+    pub per_contract: f64,
+}
+
+impl FeeSettings {
+    /// Commission on one order for the whole of `trade`: shares for stock,
+    /// contracts of every leg for options.
+    pub fn order_fee(&self, trade: &Trade) -> f64 {
+        let quantity = trade.quantity as f64;
+        if trade.legs.is_empty() {
+            self.per_trade + self.per_share * quantity
+        } else {
+            let contracts: u32 = trade.legs.iter().map(|leg| leg.ratio).sum();
+            self.per_trade + self.per_contract * quantity * contracts as f64
+        }
+    }
+}
+
+/// Balances and currencies belong to each account; only the fee schedule is
+/// shared by all of them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AccountSettings {
+    pub fees: FeeSettings,
+}
+
+/// Every user setting in one typed document. Sections are stored where the
+/// rest of the app reads them: storage in the settings file, risk with the
+/// portfolio risk settings and the rest in the database. Scoring weights are
+/// kept only in the scoring models; settings choose which version is active.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AppSettings {
+    pub storage: StorageSettings,
+    pub account: AccountSettings,
+    pub risk: PortfolioRiskSettings,
+    pub time_zones: TimeZoneSettings,
+    pub scoring_model_version: Option<i64>,
+}
+
+/// Sent to the UI whenever settings are saved.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettingsChange {
+    pub sections: Vec<String>,
+    pub settings: AppSettings,
+    pub restart_required: bool,    // The data directory only changes on restart
+}
+
+impl AppSettings {
+    /// Checks values the types alone cannot, reporting every problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if let Some(directory) = &self.storage.data_directory {
+            if !directory.is_absolute() {
+                problems.push(format!("Data directory {} must be an absolute path", directory.display()));
+            }
+        }
+
+        let fees = &self.account.fees;
+        if [fees.per_trade, fees.per_share, fees.per_contract].iter().any(|fee| !fee.is_finite() || *fee < 0.0) {
+            problems.push("Fees cannot be negative".to_string());
+        }
+
+        let risk = &self.risk.limits;
+        let percents = [
+            ("Risk per trade", risk.max_risk_per_trade_percent),
+            ("Position size", risk.max_position_percent),
+            ("Daily loss", risk.max_daily_loss_percent),
+        ];
+        for (name, percent) in percents {
+            if !(percent > 0.0 && percent <= 100.0) {
+                problems.push(format!("{} limit must be between 0 and 100%", name));
+            }
+        }
+        if risk.max_open_positions == 0 {
+            problems.push("At least one open position must be allowed".to_string());
+        }
+        let portfolio = &self.risk;
+        if portfolio.benchmark.trim().is_empty() {
+            problems.push("Beta needs a benchmark symbol".to_string());
+        }
+        if !(portfolio.default_volatility.is_finite() && portfolio.default_volatility > 0.0) {
+            problems.push("Default volatility must be positive".to_string());
+        }
+        let ranges = [
+            ("theta", portfolio.min_net_theta, portfolio.max_net_theta),
+            ("vega", portfolio.min_net_vega, portfolio.max_net_vega),
+        ];
+        for (name, min, max) in ranges {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    problems.push(format!("Minimum net {} is above the maximum", name));
+                }
+            }
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems.join("; ")) }
+    }
+
+    /// Names of the sections that differ from `other`.
+    pub fn changed_sections(&self, other: &AppSettings) -> Vec<String> {
+        let sections = [
+            ("storage", self.storage != other.storage),
+            ("account", self.account != other.account),
+            ("risk", self.risk != other.risk),
+            ("time_zones", self.time_zones != other.time_zones),
+            ("scoring_model_version", self.scoring_model_version != other.scoring_model_version),
+        ];
+
+        sections.iter().filter(|(_, changed)| *changed).map(|(name, _)| name.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::analytics::black_scholes::OptionType;
+    use crate::models::option_leg::{LegSide, OptionLeg};
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(AppSettings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let mut settings = AppSettings::default();
+        settings.storage.data_directory = Some(PathBuf::from("relative/data"));
+        settings.account.fees.per_trade = -1.0;
+        settings.risk.limits.max_daily_loss_percent = 0.0;
+        settings.risk.min_net_vega = Some(10.0);
+        settings.risk.max_net_vega = Some(-10.0);
+
+        let problems = settings.validate().unwrap_err();
+        assert_eq!(problems.split("; ").count(), 4, "{}", problems);
+    }
+
+    #[test]
+    fn order_fees_count_shares_or_contracts() {
+        let fees = FeeSettings { per_trade: 1.0, per_share: 0.01, per_contract: 0.65 };
+        let mut trade = Trade::new("AAPL", 1);
+        trade.quantity = 200;
+        assert!((fees.order_fee(&trade) - 3.0).abs() < 1e-9);
+
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let mut short_leg = OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Sell);
+        short_leg.ratio = 2;
+        trade.legs = vec![OptionLeg::new("AAPL", expiry, 95.0, OptionType::Call, LegSide::Buy), short_leg];
+        trade.quantity = 5;
+        assert!((fees.order_fee(&trade) - (1.0 + 0.65 * 15.0)).abs() < 1e-9);
+    }
+}
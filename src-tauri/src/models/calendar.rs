@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// Trading days searched before giving up on finding a session.
+const SEARCH_LIMIT_DAYS: i64 = 366;
+
+/// Local time options are taken to expire on days without a session.
+const DEFAULT_EXPIRY_CLOSE_HOUR: u32 = 16;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EarlyClose {
+    pub date: NaiveDate,
+    pub close: NaiveTime,          // Exchange local time
+}
+
+/// Dates a calendar's holidays and early closes are known for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct CalendarCoverage {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// Sessions and closures of an exchange, as read from a calendar file.
+/// Times are local to `timezone`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TradingCalendar {
+    pub exchange: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,      // Other exchange codes sharing these sessions
+    #[serde(default)]
+    pub coverage: Option<CalendarCoverage>,   // None when the closures are complete for all dates
+    pub timezone: Tz,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+    #[serde(default = "default_weekend")]
+    pub weekend: Vec<Weekday>,
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+    #[serde(default)]
+    pub early_closes: Vec<EarlyClose>,
+}
+
+fn default_weekend() -> Vec<Weekday> {
+    vec![Weekday::Sat, Weekday::Sun]
+}
+
+/// One trading day's session in UTC.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TradingSession {
+    pub exchange: String,
+    pub date: NaiveDate,
+    pub open: DateTime<Utc>,
+    pub close: DateTime<Utc>,
+    pub early_close: bool,
+    #[serde(default)]
+    pub outside_coverage: bool,    // Holidays unknown, so the session may not happen
+}
+
+/// Outcome of loading a directory of calendar files.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CalendarLoad {
+    pub loaded: usize,
+    pub failed: Vec<String>,       // Each file left out, with why
+}
+
+/// How long a trade was held, to its exit or to now when still open.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HoldingPeriod {
+    pub trade_id: i64,
+    pub exchange: String,
+    pub entry_day: NaiveDate,
+    pub exit_day: NaiveDate,
+    pub calendar_days: i64,
+    pub trading_days: i64,         // Sessions after the entry day up to the exit day
+    pub open: bool,
+}
+
+impl TradingCalendar {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.exchange.trim().is_empty() {
+            return Err("Calendar has no exchange".to_string());
+        }
+        if self.open >= self.close {
+            return Err(format!("{} opens at {} but closes at {}", self.exchange, self.open, self.close));
+        }
+        if let Some(early) = self.early_closes.iter().find(|early| early.close <= self.open || early.close > self.close) {
+            return Err(format!("{} early close on {} is outside the session", self.exchange, early.date));
+        }
+        if let Some(coverage) = self.coverage {
+            if coverage.from > coverage.to {
+                return Err(format!("{} coverage ends before it starts", self.exchange));
+            }
+            let dates = self.holidays.iter().map(|holiday| holiday.date)
+                .chain(self.early_closes.iter().map(|early| early.date));
+            if let Some(date) = dates.filter(|date| !self.is_covered(*date)).min() {
+                return Err(format!("{} closure on {} is outside its coverage", self.exchange, date));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the calendar covers `exchange`, by code or alias.
+    pub fn covers(&self, exchange: &str) -> bool {
+        let exchange = exchange.trim();
+        self.exchange.eq_ignore_ascii_case(exchange)
+            || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(exchange))
+    }
+
+    /// Whether the calendar knows the holidays of `date`.
+    pub fn is_covered(&self, date: NaiveDate) -> bool {
+        self.coverage.is_none_or(|coverage| coverage.from <= date && date <= coverage.to)
+    }
+
+    pub fn holiday(&self, date: NaiveDate) -> Option<&Holiday> {
+        self.holidays.iter().find(|holiday| holiday.date == date)
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.contains(&date.weekday()) && self.holiday(date).is_none()
+    }
+
+    pub fn session(&self, date: NaiveDate) -> Option<TradingSession> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+
+        let early_close = self.early_closes.iter().find(|early| early.date == date);
+        let close = early_close.map_or(self.close, |early| early.close);
+
+        Some(TradingSession {
+            exchange: self.exchange.clone(),
+            date,
+            open: self.to_utc(date, self.open)?,
+            close: self.to_utc(date, close)?,
+            early_close: early_close.is_some(),
+            outside_coverage: !self.is_covered(date),
+        })
+    }
+
+    /// When options expiring on `date` stop trading: the close of its
+    /// session, or 16:00 local time when there is none.
+    pub fn expiry_close(&self, date: NaiveDate) -> DateTime<Utc> {
+        let default_close = NaiveTime::from_hms_opt(DEFAULT_EXPIRY_CLOSE_HOUR, 0, 0).unwrap();
+        self.session(date)
+            .map(|session| session.close)
+            .or_else(|| self.to_utc(date, default_close))
+            .unwrap_or_else(|| date.and_time(default_close).and_utc())
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        self.session(self.local_date(at))
+            .is_some_and(|session| session.open <= at && at < session.close)
+    }
+
+    /// The exchange-local date of `at`.
+    pub fn local_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// The trading day `at` belongs to: its local date when that is a
+    /// trading day, otherwise the next one. Activity after the close stays
+    /// on the day it happened.
+    pub fn trading_day(&self, at: DateTime<Utc>) -> NaiveDate {
+        let date = self.local_date(at);
+        self.next_trading_day_from(date).unwrap_or(date)
+    }
+
+    /// The first session opening after `after`.
+    pub fn next_session(&self, after: DateTime<Utc>) -> Option<TradingSession> {
+        let mut date = self.local_date(after);
+        for _ in 0..SEARCH_LIMIT_DAYS {
+            if let Some(session) = self.session(date).filter(|session| session.open > after) {
+                return Some(session);
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    /// Trading days after `start` up to and including `end`; zero when both
+    /// fall on the same day and negative when `end` comes first.
+    pub fn trading_days_between(&self, start: NaiveDate, end: NaiveDate) -> i64 {
+        let (from, to, sign) = if start <= end { (start, end, 1) } else { (end, start, -1) };
+
+        let count = from.iter_days()
+            .skip(1)
+            .take_while(|date| *date <= to)
+            .filter(|date| self.is_trading_day(*date))
+            .count() as i64;
+
+        sign * count
+    }
+
+    fn next_trading_day_from(&self, date: NaiveDate) -> Option<NaiveDate> {
+        date.iter_days()
+            .take(SEARCH_LIMIT_DAYS as usize)
+            .find(|date| self.is_trading_day(*date))
+    }
+
+    /// Local wall time on `date` in UTC, taking the later instant when
+    /// daylight saving makes it ambiguous and skipping ahead an hour when it
+    /// does not exist.
+    fn to_utc(&self, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+        let local = date.and_time(time);
+        let resolved = match self.timezone.from_local_datetime(&local) {
+            LocalResult::Single(dt) => dt,
+            LocalResult::Ambiguous(_, latest) => latest,
+            LocalResult::None => self.timezone.from_local_datetime(&(local + Duration::hours(1))).single()?,
+        };
+
+        Some(resolved.with_timezone(&Utc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn nyse() -> TradingCalendar {
+        TradingCalendar {
+            exchange: "NYSE".to_string(),
+            aliases: vec!["NASDAQ".to_string()],
+            coverage: Some(CalendarCoverage { from: date(2024, 1, 1), to: date(2024, 12, 31) }),
+            timezone: Tz::America__New_York,
+            open: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            weekend: default_weekend(),
+            holidays: vec![Holiday { date: date(2024, 7, 4), name: "Independence Day".to_string() }],
+            early_closes: vec![EarlyClose { date: date(2024, 7, 3), close: NaiveTime::from_hms_opt(13, 0, 0).unwrap() }],
+        }
+    }
+
+    #[test]
+    fn sessions_follow_daylight_saving_and_early_closes() {
+        let calendar = nyse();
+
+        let winter = calendar.session(date(2024, 1, 2)).unwrap();
+        assert_eq!(winter.open, Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap());
+        let summer = calendar.session(date(2024, 7, 3)).unwrap();
+        assert_eq!(summer.open, Utc.with_ymd_and_hms(2024, 7, 3, 13, 30, 0).unwrap());
+        assert_eq!(summer.close, Utc.with_ymd_and_hms(2024, 7, 3, 17, 0, 0).unwrap());
+        assert!(summer.early_close);
+        assert!(calendar.session(date(2024, 7, 4)).is_none());
+        assert!(calendar.session(date(2024, 7, 6)).is_none());
+    }
+
+    #[test]
+    fn trading_days_skip_weekends_and_holidays() {
+        let calendar = nyse();
+
+        // Saturday evening belongs to Monday; the holiday to the day after
+        assert_eq!(calendar.trading_day(Utc.with_ymd_and_hms(2024, 7, 6, 20, 0, 0).unwrap()), date(2024, 7, 8));
+        assert_eq!(calendar.trading_day(Utc.with_ymd_and_hms(2024, 7, 4, 15, 0, 0).unwrap()), date(2024, 7, 5));
+        assert_eq!(calendar.trading_days_between(date(2024, 7, 1), date(2024, 7, 8)), 4);
+        assert_eq!(calendar.trading_days_between(date(2024, 7, 8), date(2024, 7, 1)), -4);
+        assert!(!calendar.is_open(Utc.with_ymd_and_hms(2024, 7, 3, 17, 30, 0).unwrap()));
+        assert_eq!(
+            calendar.next_session(Utc.with_ymd_and_hms(2024, 7, 3, 18, 0, 0).unwrap()).unwrap().date,
+            date(2024, 7, 5)
+        );
+    }
+
+    #[test]
+    fn sessions_outside_coverage_are_flagged() {
+        let calendar = nyse();
+
+        assert!(!calendar.session(date(2024, 12, 30)).unwrap().outside_coverage);
+        assert!(calendar.session(date(2025, 1, 2)).unwrap().outside_coverage);
+
+        let mut stray = nyse();
+        stray.holidays.push(Holiday { date: date(2025, 1, 1), name: "New Year's Day".to_string() });
+        assert!(stray.validate().is_err());
+        assert!(nyse().validate().is_ok());
+    }
+
+    #[test]
+    fn options_expire_at_the_session_close() {
+        let calendar = nyse();
+
+        assert_eq!(calendar.expiry_close(date(2024, 7, 3)), Utc.with_ymd_and_hms(2024, 7, 3, 17, 0, 0).unwrap());
+        assert_eq!(calendar.expiry_close(date(2024, 1, 19)), Utc.with_ymd_and_hms(2024, 1, 19, 21, 0, 0).unwrap());
+        // Without a session, 16:00 in New York
+        assert_eq!(calendar.expiry_close(date(2024, 7, 4)), Utc.with_ymd_and_hms(2024, 7, 4, 20, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+
+use super::detailed_analysis::DetailedAnalysis;
+use super::indicator::IndicatorLine;
+use super::psychological_state::PsychologicalState;
+use super::stock_rating::StockRating;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ChecklistEnforcement {
+    Block,   // Trades cannot be saved while a required item fails
+    Flag,    // Trades are saved but noted as having skipped the checklist
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Comparison {
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparison {
+    pub fn holds(&self, actual: f64, expected: f64) -> bool {
+        match self {
+            Comparison::LessThan => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::GreaterThan => actual > expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+            Comparison::Equal => (actual - expected).abs() < f64::EPSILON,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ChecklistField {
+    OverallScore,
+    Confidence,
+    BullBear,
+    MarketSentiment,
+    SectorSentiment,
+    SecuritySentiment,   // From the latest StockRating of the security
+    RatingScore,         // StockRating.overall_score of the latest rating
+    EntryPrice,
+    StopLoss,
+    TargetPrice,
+    RiskMax,
+    Reward,
+    RewardRiskRatio,
+    DebitCredit,
+    Quantity,
+    PsychRiskScore,      // Total risk score of the latest PsychologicalState
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum ChecklistRule {
+    Compare {
+        field: ChecklistField,
+        comparison: Comparison,
+        value: f64,
+    },
+    // Market trend agrees with the bull/bear direction of the analysis
+    TrendAligned,
+    // Latest value of an indicator on the analysed security
+    Indicator {
+        line: IndicatorLine,
+        comparison: Comparison,
+        value: f64,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum ChecklistItemKind {
+    Manual,
+    Rule(ChecklistRule),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChecklistItem {
+    pub label: String,
+    pub required: bool,
+    pub kind: ChecklistItemKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChecklistTemplate {
+    pub id: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub name: String,
+    pub strategy: String,          // Empty applies to every strategy
+    pub enforcement: ChecklistEnforcement,
+    pub items: Vec<ChecklistItem>,
+}
+
+impl ChecklistTemplate {
+    #[cfg(test)]
+    pub fn new(name: &str, strategy: &str) -> Self {
+        Self {
+            id: None,
+            timestamp: Utc::now(),
+            name: name.to_string(),
+            strategy: strategy.to_string(),
+            enforcement: ChecklistEnforcement::Flag,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn applies_to(&self, strategy: &str) -> bool {
+        self.strategy.is_empty() || self.strategy.eq_ignore_ascii_case(strategy)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ChecklistOutcome {
+    Passed,
+    Failed,
+    Unavailable,   // Rule could not be evaluated, e.g. no rating for the security
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChecklistItemResult {
+    pub label: String,
+    pub required: bool,
+    pub kind: ChecklistItemKind,
+    pub outcome: ChecklistOutcome,
+    pub actual_value: Option<f64>,
+}
+
+impl ChecklistItemResult {
+    pub fn is_blocking(&self) -> bool {
+        self.required && self.outcome != ChecklistOutcome::Passed
+    }
+}
+
+/// Values a checklist is evaluated against.
+pub struct ChecklistContext<'a> {
+    pub analysis: &'a DetailedAnalysis,
+    pub rating: Option<&'a StockRating>,
+    pub psychological_state: Option<&'a PsychologicalState>,
+    pub indicator_values: HashMap<String, f64>,   // Keyed by IndicatorLine::key
+}
+
+impl<'a> ChecklistContext<'a> {
+    pub fn field_value(&self, field: &ChecklistField) -> Option<f64> {
+        let analysis = self.analysis;
+        match field {
+            ChecklistField::OverallScore => Some(analysis.overall_score as f64),
+            ChecklistField::Confidence => Some(analysis.confidence as f64),
+            ChecklistField::BullBear => Some(analysis.bull_bear as f64),
+            ChecklistField::MarketSentiment => Some(analysis.market_sentiment as f64),
+            ChecklistField::SectorSentiment => Some(analysis.sector_sentiment as f64),
+            ChecklistField::SecuritySentiment => self.rating.map(|r| r.security_sentiment as f64),
+            ChecklistField::RatingScore => self.rating.map(|r| r.overall_score),
+            ChecklistField::EntryPrice => Some(analysis.entry_price),
+            ChecklistField::StopLoss => Some(analysis.stop_loss),
+            ChecklistField::TargetPrice => Some(analysis.target_price),
+            ChecklistField::RiskMax => Some(analysis.risk_max),
+            ChecklistField::Reward => Some(analysis.reward),
+            ChecklistField::RewardRiskRatio => {
+                if analysis.risk_max > 0.0 {
+                    Some(analysis.reward / analysis.risk_max)
+                } else {
+                    None
+                }
+            }
+            ChecklistField::DebitCredit => Some(analysis.debit_credit),
+            ChecklistField::Quantity => Some(analysis.quantity as f64),
+            ChecklistField::PsychRiskScore => self.psychological_state.map(|s| s.total_risk_score),
+        }
+    }
+
+    pub fn evaluate_rule(&self, rule: &ChecklistRule) -> (ChecklistOutcome, Option<f64>) {
+        match rule {
+            ChecklistRule::Compare { field, comparison, value } => match self.field_value(field) {
+                Some(actual) if comparison.holds(actual, *value) => (ChecklistOutcome::Passed, Some(actual)),
+                Some(actual) => (ChecklistOutcome::Failed, Some(actual)),
+                None => (ChecklistOutcome::Unavailable, None),
+            },
+            ChecklistRule::TrendAligned => {
+                let aligned = self.analysis.market_trend.direction() * self.analysis.bull_bear as i32 > 0;
+                if aligned {
+                    (ChecklistOutcome::Passed, None)
+                } else {
+                    (ChecklistOutcome::Failed, None)
+                }
+            }
+            ChecklistRule::Indicator { line, comparison, value } => match self.indicator_values.get(&line.key()) {
+                Some(actual) if comparison.holds(*actual, *value) => (ChecklistOutcome::Passed, Some(*actual)),
+                Some(actual) => (ChecklistOutcome::Failed, Some(*actual)),
+                None => (ChecklistOutcome::Unavailable, None),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompletedChecklist {
+    pub id: Option<i64>,
+    pub analysis_id: i64,
+    pub template_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub enforcement: ChecklistEnforcement,
+    pub results: Vec<ChecklistItemResult>,
+    pub passed: bool,
+}
+
+impl CompletedChecklist {
+    /// Evaluates every item of `template`. Manual items are answered through
+    /// `manual_answers`, keyed by item label; unanswered ones count as failed.
+    pub fn evaluate(
+        analysis_id: i64,
+        template: &ChecklistTemplate,
+        context: &ChecklistContext,
+        manual_answers: &HashMap<String, bool>,
+    ) -> Self {
+        let results: Vec<ChecklistItemResult> = template.items.iter().map(|item| {
+            let (outcome, actual_value) = match &item.kind {
+                ChecklistItemKind::Manual => {
+                    if manual_answers.get(&item.label).copied().unwrap_or(false) {
+                        (ChecklistOutcome::Passed, None)
+                    } else {
+                        (ChecklistOutcome::Failed, None)
+                    }
+                }
+                ChecklistItemKind::Rule(rule) => context.evaluate_rule(rule),
+            };
+
+            ChecklistItemResult {
+                label: item.label.clone(),
+                required: item.required,
+                kind: item.kind.clone(),
+                outcome,
+                actual_value,
+            }
+        }).collect();
+
+        let passed = !results.iter().any(|r| r.is_blocking());
+
+        Self {
+            id: None,
+            analysis_id,
+            template_id: template.id.unwrap_or_default(),
+            timestamp: Utc::now(),
+            enforcement: template.enforcement.clone(),
+            results,
+            passed,
+        }
+    }
+
+    pub fn failed_required_items(&self) -> Vec<String> {
+        self.results.iter()
+            .filter(|r| r.is_blocking())
+            .map(|r| r.label.clone())
+            .collect()
+    }
+}
+
+/// Whether a trade may be opened against an analysis given its checklists.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum TradeGate {
+    Clear,
+    Flagged(Vec<String>),
+    Blocked(Vec<String>),
+}
@@ -0,0 +1,187 @@
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// Minutes searched for the next run before giving up, a little over four
+/// years so that schedules such as "0 0 29 2 *" still resolve.
+const SEARCH_LIMIT_MINUTES: i64 = 4 * 366 * 24 * 60 + 1;
+
+/// A five-field cron expression: minute, hour, day of month, month and day
+/// of week (0 or 7 is Sunday). Fields accept `*`, lists, ranges and steps,
+/// and the shorthands @hourly, @daily, @weekly and @monthly are understood.
+/// As in cron, when both day fields are restricted either may match. The
+/// fields are read as wall-clock time in the zone the schedule is given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpression {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Cron expression '{}' needs five fields", expression));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, "day of week")?;
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days_of_month: parse_field(fields[2], 1, 31, "day of month")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            days_of_week,
+            day_of_month_restricted: fields[2] != "*",
+            day_of_week_restricted: fields[4] != "*",
+        })
+    }
+
+    pub fn matches<Z: TimeZone>(&self, time: &DateTime<Z>) -> bool {
+        self.minutes[time.minute() as usize]
+            && self.hours[time.hour() as usize]
+            && self.months[time.month() as usize]
+            && self.day_matches(time)
+    }
+
+    fn day_matches<Z: TimeZone>(&self, time: &DateTime<Z>) -> bool {
+        let day_of_month = self.days_of_month[time.day() as usize];
+        let day_of_week = self.days_of_week[time.weekday().num_days_from_sunday() as usize];
+
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+
+    /// The first whole minute strictly after `after` that matches in `zone`,
+    /// skipping whole local hours and days that cannot. Wall-clock times a
+    /// daylight saving change skips never match.
+    pub fn next_after(&self, after: DateTime<Utc>, zone: Tz) -> Option<DateTime<Utc>> {
+        let mut time = after.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        let limit = time + Duration::minutes(SEARCH_LIMIT_MINUTES);
+
+        while time < limit {
+            let local = time.with_timezone(&zone);
+            let skip_to = if self.matches(&local) {
+                return Some(time);
+            } else if !self.months[local.month() as usize] || !self.day_matches(&local) {
+                local.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?
+            } else if !self.hours[local.hour() as usize] {
+                local.naive_local().duration_trunc(Duration::hours(1)).ok()? + Duration::hours(1)
+            } else {
+                time += Duration::minutes(1);
+                continue;
+            };
+
+            // A local time that does not exist falls back to stepping by the minute
+            time = match zone.from_local_datetime(&skip_to).earliest() {
+                Some(next) if next > time => next.with_timezone(&Utc),
+                _ => time + Duration::minutes(1),
+            };
+        }
+
+        None
+    }
+}
+
+/// Flags for the values `min..=max` a field selects, indexed by value.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<Vec<bool>, String> {
+    let mut selected = vec![false; max as usize + 1];
+    let invalid = || format!("Invalid {} field '{}'", name, field);
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?)
+        } else {
+            let value: u32 = range.parse().map_err(|_| invalid())?;
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            selected[value as usize] = true;
+        }
+    }
+
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_fields_and_shorthands() {
+        assert_eq!(CronExpression::parse("@daily"), CronExpression::parse("0 0 * * *"));
+        assert!(CronExpression::parse("*/15 9-16 * * 1-5").is_ok());
+        assert!(CronExpression::parse("0 0 * * 7").is_ok());
+        assert!(CronExpression::parse("0 0 * *").is_err());
+        assert!(CronExpression::parse("60 0 * * *").is_err());
+        assert!(CronExpression::parse("*/0 * * * *").is_err());
+        assert!(CronExpression::parse("0 5-2 * * *").is_err());
+    }
+
+    #[test]
+    fn finds_the_next_run_in_utc() {
+        let weekdays = CronExpression::parse("30 13 * * 1-5").unwrap();
+        // Friday 2024-06-07 after the run, so the next is Monday
+        assert_eq!(weekdays.next_after(utc(2024, 6, 7, 14, 0), Tz::UTC), Some(utc(2024, 6, 10, 13, 30)));
+
+        let leap_day = CronExpression::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap_day.next_after(utc(2024, 3, 1, 0, 0), Tz::UTC), Some(utc(2028, 2, 29, 0, 0)));
+    }
+
+    #[test]
+    fn either_restricted_day_field_matches() {
+        let cron = CronExpression::parse("0 12 1 * 1").unwrap();
+        // Monday 2024-06-03 comes before the 1st of July
+        assert_eq!(cron.next_after(utc(2024, 6, 1, 13, 0), Tz::UTC), Some(utc(2024, 6, 3, 12, 0)));
+    }
+
+    #[test]
+    fn runs_at_local_wall_clock_time_across_daylight_saving() {
+        let cron = CronExpression::parse("0 22 * * *").unwrap();
+        let zone = Tz::America__New_York;
+
+        // 22:00 EDT is 02:00 UTC, 22:00 EST is 03:00 UTC
+        assert_eq!(cron.next_after(utc(2024, 11, 2, 12, 0), zone), Some(utc(2024, 11, 3, 2, 0)));
+        assert_eq!(cron.next_after(utc(2024, 11, 3, 12, 0), zone), Some(utc(2024, 11, 4, 3, 0)));
+
+        // 02:30 does not exist on 2024-03-10 in New York
+        let skipped = CronExpression::parse("30 2 * * *").unwrap();
+        assert_eq!(skipped.next_after(utc(2024, 3, 10, 0, 0), zone), Some(utc(2024, 3, 11, 6, 30)));
+    }
+}
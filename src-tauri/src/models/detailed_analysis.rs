@@ -1,21 +1,52 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
-use super::stock_rating::{MarketTrend, ChartPattern};
+use super::option_leg::{self, LegSide, OptionLeg};
+use super::stock_rating::{MarketTrend, ChartPattern, StockRating};
+use crate::analytics::black_scholes::OptionValuation;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SpreadLeg {
+    Short,
+    Long,
+}
+
+impl SpreadLeg {
+    pub fn side(&self) -> LegSide {
+        match self {
+            SpreadLeg::Short => LegSide::Sell,
+            SpreadLeg::Long => LegSide::Buy,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TradePhase {
+    Entry,
+    Exit,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DetailedAnalysis {
     pub id: Option<i64>,
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub rating_id: Option<i64>,    // Rating this analysis was promoted from
+    #[serde(default)]
+    pub account_id: Option<i64>,   // Default account when not chosen
     pub bull_bear: i8,             // Bull = +1, Bear = -1
     pub confidence: u8,            // 0 to 100%
     pub market_trend: MarketTrend,
+    #[serde(default)]
+    pub computed_market_trend: Option<MarketTrend>,   // Classifier's value, kept beside the user's
     pub chart_pattern: ChartPattern,
     pub strategy: String,
     pub overall_score: i32,
     pub market_sentiment: i32,     // -3 to +3
     pub sector_sentiment: i32,     // -3 to +3
-    pub sector: String,
+    pub sector: String,            // Name of the sector below
+    #[serde(default)]
+    pub sector_id: Option<i64>,
     pub security: String,
     pub bought: bool,
     pub entry_reason: String,
@@ -23,8 +54,10 @@ pub struct DetailedAnalysis {
     pub entry_price: f64,
     pub stop_loss: f64,
     pub target_price: f64,
-    pub short_leg: Option<String>,
+    pub short_leg: Option<String>,     // Free-text legs from before structured legs
     pub long_leg: Option<String>,
+    #[serde(default)]
+    pub legs: Vec<OptionLeg>,
     pub debit_credit: f64,
     pub quantity: u32,
     pub risk_max: f64,
@@ -35,25 +68,42 @@ pub struct DetailedAnalysis {
     pub theta: Option<f64>,
     pub gamma: Option<f64>,
     pub vega: Option<f64>,
+    #[serde(default)]
+    pub rho: Option<f64>,
     pub alerts: Vec<String>,
     pub exit_reason: Option<String>,
     pub skip_reason: Option<String>,
 }
 
+/// An edit of a stored analysis as the UI sends it. Legs left out keep
+/// their stored value; an empty list removes them. Without a `rating_id`
+/// the rating link is kept; it is removed on its own.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnalysisUpdate {
+    #[serde(flatten)]
+    pub analysis: DetailedAnalysis,
+    #[serde(default)]
+    pub legs: Option<Vec<OptionLeg>>,
+}
+
 impl DetailedAnalysis {
     pub fn new(security: &str, sector: &str) -> Self {
         Self {
             id: None,
             timestamp: Utc::now(),
+            rating_id: None,
+            account_id: None,
             bull_bear: 1,
             confidence: 50,
             market_trend: MarketTrend::Uncertain,
+            computed_market_trend: None,
             chart_pattern: ChartPattern::Other("None".to_string()),
             strategy: String::new(),
             overall_score: 0,
             market_sentiment: 0,
             sector_sentiment: 0,
             sector: sector.to_string(),
+            sector_id: None,
             security: security.to_string(),
             bought: false,
             entry_reason: String::new(),
@@ -63,6 +113,7 @@ impl DetailedAnalysis {
             target_price: 0.0,
             short_leg: None,
             long_leg: None,
+            legs: Vec::new(),
             debit_credit: 0.0,
             quantity: 0,
             risk_max: 0.0,
@@ -73,13 +124,41 @@ impl DetailedAnalysis {
             theta: None,
             gamma: None,
             vega: None,
+            rho: None,
             alerts: Vec::new(),
             exit_reason: None,
             skip_reason: None,
         }
     }
 
+    /// A new analysis starting from the opinions recorded in a rating.
+    pub fn from_rating(rating: &StockRating) -> Self {
+        Self {
+            rating_id: rating.id,
+            bull_bear: rating.bull_bear,
+            confidence: rating.confidence,
+            market_trend: rating.market_trend.clone(),
+            computed_market_trend: rating.computed_market_trend.clone(),
+            chart_pattern: rating.chart_pattern.clone(),
+            strategy: rating.strategy.clone(),
+            overall_score: rating.overall_score.round() as i32,
+            market_sentiment: rating.market_sentiment,
+            sector_sentiment: rating.sector_sentiment,
+            sector_id: rating.sector_id,
+            ..Self::new(&rating.symbol, &rating.sector)
+        }
+    }
+
+    /// With structured legs the net debit/credit is derived from their premiums.
+    pub fn update_debit_credit(&mut self) {
+        if !self.legs.is_empty() {
+            self.debit_credit = option_leg::net_debit_credit(&self.legs);
+        }
+    }
+
     pub fn calculate_risk_reward(&mut self) {
+        self.update_debit_credit();
+
         if self.bought {
             if self.entry_price > 0.0 && self.stop_loss > 0.0 {
                 self.risk_max = (self.entry_price - self.stop_loss).abs() * self.quantity as f64;
@@ -94,18 +173,81 @@ impl DetailedAnalysis {
         }
     }
 
-    pub fn update_profit(&mut self, exit_price: f64, _exit_time: DateTime<Utc>) {
-        if self.bought {
-            self.max_gain = Some((exit_price - self.entry_price) * self.quantity as f64);
-            if self.entry_price > 0.0 {
-                self.percent_profit = Some((exit_price - self.entry_price) / self.entry_price * 100.0);
-            }
-        } else {
-            // For options or other instruments
-            self.max_gain = Some(exit_price * self.quantity as f64);
-            if self.debit_credit != 0.0 {
-                self.percent_profit = Some((exit_price - self.debit_credit) / self.debit_credit.abs() * 100.0);
-            }
+    pub fn apply_greeks(&mut self, valuation: &OptionValuation) {
+        self.delta = Some(valuation.delta);
+        self.theta = Some(valuation.theta);
+        self.gamma = Some(valuation.gamma);
+        self.vega = Some(valuation.vega);
+        self.rho = Some(valuation.rho);
+    }
+
+    /// Records an entry or exit IV on the only structured leg on that side of
+    /// the spread. Returns the leg it was recorded on.
+    pub fn set_leg_iv(&mut self, leg: SpreadLeg, phase: TradePhase, iv: f64) -> Result<&OptionLeg, String> {
+        let side = leg.side();
+        let mut on_side = self.legs.iter_mut().filter(|option_leg| option_leg.side == side);
+        let option_leg = match (on_side.next(), on_side.next()) {
+            (Some(option_leg), None) => option_leg,
+            (None, _) => return Err(format!("Analysis has no {:?} leg", leg)),
+            (Some(_), Some(_)) => return Err(format!("Analysis has more than one {:?} leg; record the IV on the leg instead", leg)),
+        };
+        match phase {
+            TradePhase::Entry => option_leg.entry_iv = Some(iv),
+            TradePhase::Exit => option_leg.exit_iv = Some(iv),
+        }
+
+        Ok(option_leg)
+    }
+
+    /// Change in implied volatility of a leg between entry and exit; a negative
+    /// value on a closed trade is the vol crush. Read from the only structured
+    /// leg on that side.
+    pub fn leg_iv_change(&self, leg: SpreadLeg) -> Option<f64> {
+        let side = leg.side();
+        let mut on_side = self.legs.iter().filter(|option_leg| option_leg.side == side);
+        let option_leg = on_side.next()?;
+        if on_side.next().is_some() {
+            return None;
         }
+
+        Some(option_leg.exit_iv? - option_leg.entry_iv?)
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::analytics::black_scholes::OptionType;
+
+    #[test]
+    fn structured_legs_hold_the_iv() {
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let mut short_put = OptionLeg::new("AAPL", expiry, 100.0, OptionType::Put, LegSide::Sell);
+        short_put.entry_iv = Some(0.4);
+        short_put.exit_iv = Some(0.25);
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.legs = vec![short_put];
+
+        assert!((analysis.leg_iv_change(SpreadLeg::Short).unwrap() + 0.15).abs() < 1e-12);
+        assert_eq!(analysis.leg_iv_change(SpreadLeg::Long), None);
+    }
+
+    #[test]
+    fn iv_is_recorded_on_the_only_leg_on_that_side() {
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        assert!(analysis.set_leg_iv(SpreadLeg::Long, TradePhase::Entry, 0.3).is_err());
+
+        analysis.legs = vec![
+            OptionLeg::new("AAPL", expiry, 95.0, OptionType::Call, LegSide::Buy),
+            OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Sell),
+        ];
+        analysis.set_leg_iv(SpreadLeg::Long, TradePhase::Entry, 0.3).unwrap();
+        analysis.set_leg_iv(SpreadLeg::Long, TradePhase::Exit, 0.2).unwrap();
+        assert!((analysis.leg_iv_change(SpreadLeg::Long).unwrap() + 0.1).abs() < 1e-12);
+
+        analysis.legs.push(OptionLeg::new("AAPL", expiry, 105.0, OptionType::Call, LegSide::Sell));
+        assert!(analysis.set_leg_iv(SpreadLeg::Short, TradePhase::Entry, 0.3).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::price_bar::Timeframe;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Indicator {
+    Sma { period: usize },
+    Ema { period: usize },
+    Atr { period: usize },
+    Rsi { period: usize },
+    Macd { fast: usize, slow: usize, signal: usize },
+    BollingerBands { period: usize, std_devs: f64 },
+    Adx { period: usize },
+    Vwap,
+    RelativeStrength { benchmark: String },
+}
+
+impl Indicator {
+    /// Names of the values each point carries, in order.
+    pub fn outputs(&self) -> &'static [&'static str] {
+        match self {
+            Indicator::Macd { .. } => &["macd", "signal", "histogram"],
+            Indicator::BollingerBands { .. } => &["middle", "upper", "lower"],
+            Indicator::Adx { .. } => &["adx", "plus_di", "minus_di"],
+            _ => &["value"],
+        }
+    }
+
+    /// Symbol whose bars the indicator needs besides the security's own.
+    pub fn benchmark(&self) -> Option<&str> {
+        match self {
+            Indicator::RelativeStrength { benchmark } => Some(benchmark),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndicatorPoint {
+    pub timestamp: DateTime<Utc>,
+    pub values: Vec<Option<f64>>,   // One per Indicator::outputs
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndicatorSeries {
+    pub symbol: String,
+    pub timeframe: Timeframe,
+    pub indicator: Indicator,
+    pub computed_at: DateTime<Utc>,
+    pub points: Vec<IndicatorPoint>,
+}
+
+impl IndicatorSeries {
+    /// Most recent value of the named output, or of the first output when
+    /// `output` is None.
+    pub fn latest(&self, output: Option<&str>) -> Option<f64> {
+        let index = match output {
+            Some(name) => self.indicator.outputs().iter().position(|o| o.eq_ignore_ascii_case(name))?,
+            None => 0,
+        };
+
+        self.points.iter().rev().find_map(|point| point.values.get(index).copied().flatten())
+    }
+}
+
+/// One line of an indicator on a security's chart, as referenced by
+/// checklist rules and alerts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndicatorLine {
+    pub indicator: Indicator,
+    #[serde(default)]
+    pub output: Option<String>,    // None for the first output
+    pub timeframe: Timeframe,
+}
+
+impl IndicatorLine {
+    /// Identifies the line among values computed ahead of evaluation.
+    pub fn key(&self) -> String {
+        format!("{:?}/{:?}/{}", self.timeframe, self.indicator, self.output.as_deref().unwrap_or(""))
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::account::AccountSummary;
+use super::alert::AlertTrigger;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum JobKind {
+    Backup { keep: usize },                   // Copies of the database to keep
+    RerateReminder,
+    AlertEvaluation,
+    MarkToMarket,
+    StalePlanCleanup { older_than_days: i64 },
+    Report,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    pub id: Option<i64>,
+    pub name: String,
+    pub kind: JobKind,
+    pub cron: String,              // Five-field cron expression, in the user's time zone
+    pub enabled: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub next_run_at: Option<DateTime<Utc>>,   // None while disabled
+}
+
+impl ScheduledJob {
+    pub fn new(name: &str, kind: JobKind, cron: &str) -> Self {
+        Self {
+            id: None,
+            name: name.to_string(),
+            kind,
+            cron: cron.to_string(),
+            enabled: true,
+            last_run_at: None,
+            next_run_at: None,
+        }
+    }
+
+    /// Jobs created on a new database.
+    pub fn defaults() -> Vec<ScheduledJob> {
+        vec![
+            Self::new("Nightly backup", JobKind::Backup { keep: 14 }, "0 2 * * *"),
+            Self::new("Re-rating reminder", JobKind::RerateReminder, "0 13 * * 1-5"),
+            Self::new("Alert evaluation", JobKind::AlertEvaluation, "*/5 * * * *"),
+            Self::new("Mark-to-market", JobKind::MarkToMarket, "*/15 * * * 1-5"),
+            // Cancels trades, so left for the user to turn on
+            Self { enabled: false, ..Self::new("Stale plan cleanup", JobKind::StalePlanCleanup { older_than_days: 30 }, "30 2 * * *") },
+            Self::new("Daily report", JobKind::Report, "0 22 * * 1-5"),
+        ]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum JobRunStatus {
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobRun {
+    pub id: Option<i64>,
+    pub job_id: i64,
+    pub job_name: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: JobRunStatus,
+    pub summary: String,           // Outcome, or the error of a failed run
+}
+
+/// What a job run produced besides its summary, for the caller to surface.
+#[derive(Debug, Default, Clone)]
+pub struct JobOutcome {
+    pub summary: String,
+    pub notification: Option<String>,   // Worth a desktop notification
+    pub alert_triggers: Vec<AlertTrigger>,
+}
+
+/// A snapshot of the book written by the report job.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailyReport {
+    pub generated_at: DateTime<Utc>,
+    pub open_trades: usize,
+    pub planned_trades: usize,
+    pub net_delta: f64,
+    pub net_theta: f64,
+    pub net_vega: f64,
+    pub beta_weighted_delta: f64,
+    pub stale_ratings: Vec<String>,
+    pub active_alerts: usize,
+    #[serde(default)]
+    pub accounts: Vec<AccountSummary>,   // Each account, then all accounts together when they share a currency
+}
@@ -2,8 +2,31 @@ pub mod psychological_state;
 pub mod stock_rating;
 pub mod detailed_analysis;
 pub mod trade;
+pub mod account;
+pub mod checklist;
+pub mod option_leg;
+pub mod occ_symbol;
+pub mod option_event;
+pub mod price_bar;
+pub mod indicator;
+pub mod trend;
+pub mod time_zone;
+pub mod alert;
+pub mod app_settings;
+pub mod calendar;
+pub mod cron;
+pub mod job;
+pub mod portfolio_risk;
+pub mod scoring_model;
+pub mod rating_freshness;
+pub mod sector;
+pub mod security;
+pub mod watchlist;
+pub mod promotion;
 
 pub use psychological_state::PsychologicalState;
 pub use stock_rating::StockRating;
 pub use detailed_analysis::DetailedAnalysis;
-pub use trade::Trade; 
\ No newline at end of file
+pub use trade::Trade;
+pub use checklist::{ChecklistTemplate, CompletedChecklist};
+pub use option_leg::OptionLeg;
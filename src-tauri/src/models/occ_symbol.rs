@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use chrono::{Datelike, NaiveDate, Weekday};
+use thiserror::Error;
+
+use super::option_leg::{LegSide, OptionLeg};
+use crate::analytics::black_scholes::OptionType;
+
+const OCC_ROOT_WIDTH: usize = 6;
+const OCC_STRIKE_DIGITS: usize = 8;
+// Strikes are written in thousandths of a dollar
+const OCC_STRIKE_SCALE: f64 = 1000.0;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OccSymbolError {
+    #[error("'{0}' is not an option symbol")]
+    Malformed(String),
+    #[error("invalid option root '{0}'")]
+    InvalidRoot(String),
+    #[error("invalid expiry date '{0}'")]
+    InvalidExpiry(String),
+    #[error("strike {0} cannot be written as an OCC strike")]
+    NonStandardStrike(f64),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum OccFormat {
+    Standard,   // "AAPL  250117C00150000", root padded to six characters
+    Compact,    // "AAPL250117C00150000"
+    Dotted,     // ".AAPL250117C150", strike as a plain decimal
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OccSymbol {
+    pub root: String,
+    pub expiry: NaiveDate,
+    pub option_type: OptionType,
+    pub strike: f64,
+}
+
+impl OccSymbol {
+    /// Parses the OCC format with or without root padding, and the vendor
+    /// variant with a leading dot and a decimal strike.
+    pub fn parse(symbol: &str) -> Result<Self, OccSymbolError> {
+        let trimmed = symbol.trim();
+        let malformed = || OccSymbolError::Malformed(symbol.to_string());
+        if !trimmed.is_ascii() {
+            return Err(malformed());
+        }
+        let dotted = trimmed.starts_with('.');
+        let body = trimmed.trim_start_matches('.');
+
+        let strike_start = body
+            .rfind(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .map(|i| i + 1)
+            .ok_or_else(malformed)?;
+        let strike_text = &body[strike_start..];
+        if strike_text.is_empty() || strike_start < 7 {
+            return Err(malformed());
+        }
+
+        let option_type = match &body[strike_start - 1..strike_start] {
+            "C" | "c" => OptionType::Call,
+            "P" | "p" => OptionType::Put,
+            _ => return Err(malformed()),
+        };
+
+        let date_text = &body[strike_start - 7..strike_start - 1];
+        if !date_text.chars().all(|c| c.is_ascii_digit()) {
+            return Err(malformed());
+        }
+        let expiry = NaiveDate::parse_from_str(&format!("20{}", date_text), "%Y%m%d")
+            .map_err(|_| OccSymbolError::InvalidExpiry(date_text.to_string()))?;
+
+        let root = body[..strike_start - 7].trim().to_ascii_uppercase();
+
+        let strike = if !dotted && strike_text.len() == OCC_STRIKE_DIGITS && !strike_text.contains('.') {
+            strike_text.parse::<u64>().map_err(|_| malformed())? as f64 / OCC_STRIKE_SCALE
+        } else {
+            strike_text.parse::<f64>().map_err(|_| malformed())?
+        };
+
+        let parsed = Self { root, expiry, option_type, strike };
+        parsed.validate()?;
+
+        Ok(parsed)
+    }
+
+    pub fn validate(&self) -> Result<(), OccSymbolError> {
+        if self.root.is_empty()
+            || self.root.len() > OCC_ROOT_WIDTH
+            || !self.root.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(OccSymbolError::InvalidRoot(self.root.clone()));
+        }
+
+        // Monthly expiries were listed on Saturdays before 2015, so only Sunday is invalid
+        if self.expiry.weekday() == Weekday::Sun {
+            return Err(OccSymbolError::InvalidExpiry(self.expiry.to_string()));
+        }
+
+        // Adjusted contracts list strikes such as 33.33 after splits and
+        // special dividends, so any strike OCC can write is accepted
+        let scaled = self.strike * OCC_STRIKE_SCALE;
+        if !(scaled >= 1.0 && scaled < 10f64.powi(OCC_STRIKE_DIGITS as i32)) || (scaled - scaled.round()).abs() > 1e-6 {
+            return Err(OccSymbolError::NonStandardStrike(self.strike));
+        }
+
+        Ok(())
+    }
+
+    pub fn format(&self, format: OccFormat) -> String {
+        let date = self.expiry.format("%y%m%d");
+        let type_code = match self.option_type {
+            OptionType::Call => 'C',
+            OptionType::Put => 'P',
+        };
+        let strike_code = format!("{:0width$}", (self.strike * OCC_STRIKE_SCALE).round() as u64, width = OCC_STRIKE_DIGITS);
+
+        match format {
+            OccFormat::Standard => format!("{:<width$}{}{}{}", self.root, date, type_code, strike_code, width = OCC_ROOT_WIDTH),
+            OccFormat::Compact => format!("{}{}{}{}", self.root, date, type_code, strike_code),
+            OccFormat::Dotted => format!(".{}{}{}{}", self.root, date, type_code, self.strike),
+        }
+    }
+
+    pub fn to_leg(&self, side: LegSide) -> OptionLeg {
+        OptionLeg::new(&self.root, self.expiry, self.strike, self.option_type, side)
+    }
+
+    pub fn from_leg(leg: &OptionLeg) -> Self {
+        Self {
+            root: leg.underlying.to_ascii_uppercase(),
+            expiry: leg.expiry,
+            option_type: leg.option_type,
+            strike: leg.strike,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_variant_to_the_same_symbol() {
+        let expected = OccSymbol {
+            root: "AAPL".to_string(),
+            expiry: NaiveDate::from_ymd_opt(2025, 1, 17).unwrap(),
+            option_type: OptionType::Call,
+            strike: 150.0,
+        };
+
+        for symbol in ["AAPL  250117C00150000", "AAPL250117C00150000", ".AAPL250117C150", " aapl250117c00150000 "] {
+            assert_eq!(OccSymbol::parse(symbol).unwrap(), expected, "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let symbol = OccSymbol::parse("SPY   250117P00452500").unwrap();
+
+        assert_eq!(symbol.format(OccFormat::Standard), "SPY   250117P00452500");
+        assert_eq!(symbol.format(OccFormat::Compact), "SPY250117P00452500");
+        assert_eq!(symbol.format(OccFormat::Dotted), ".SPY250117P452.5");
+        for format in [OccFormat::Standard, OccFormat::Compact, OccFormat::Dotted] {
+            assert_eq!(OccSymbol::parse(&symbol.format(format)).unwrap(), symbol);
+        }
+    }
+
+    #[test]
+    fn accepts_adjusted_strikes() {
+        assert_eq!(OccSymbol::parse("AAPL  250117C00033330").unwrap().strike, 33.33);
+        assert_eq!(OccSymbol::parse(".F250117C1.5").unwrap().strike, 1.5);
+    }
+
+    #[test]
+    fn rejects_strikes_occ_cannot_write() {
+        assert_eq!(OccSymbol::parse("AAPL  250117C00000000"), Err(OccSymbolError::NonStandardStrike(0.0)));
+        assert_eq!(OccSymbol::parse(".AAPL250117C150.0005"), Err(OccSymbolError::NonStandardStrike(150.0005)));
+        assert_eq!(OccSymbol::parse(".AAPL250117C100000"), Err(OccSymbolError::NonStandardStrike(100000.0)));
+    }
+
+    #[test]
+    fn rejects_malformed_symbols() {
+        assert!(matches!(OccSymbol::parse("AAPL  251317C00150000"), Err(OccSymbolError::InvalidExpiry(_))));
+        assert!(matches!(OccSymbol::parse("AAPL  250119C00150000"), Err(OccSymbolError::InvalidExpiry(_))));
+        assert!(matches!(OccSymbol::parse("AAPL  250117X00150000"), Err(OccSymbolError::Malformed(_))));
+        assert!(matches!(OccSymbol::parse("250117C00150000"), Err(OccSymbolError::InvalidRoot(_))));
+        assert!(matches!(OccSymbol::parse("AAPL"), Err(OccSymbolError::Malformed(_))));
+        assert!(matches!(OccSymbol::parse("AAPLé250117C150"), Err(OccSymbolError::Malformed(_))));
+        assert!(matches!(OccSymbol::parse("ÅÅÅÅÅÅÅ9"), Err(OccSymbolError::Malformed(_))));
+    }
+}
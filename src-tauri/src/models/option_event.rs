@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum OptionEventType {
+    ExpiredWorthless,
+    Exercised,   // We exercised a bought leg
+    Assigned,    // A sold leg was assigned to us
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OptionEvent {
+    pub id: Option<i64>,
+    pub trade_id: i64,
+    pub leg_id: i64,
+    pub event_type: OptionEventType,
+    pub timestamp: DateTime<Utc>,
+    pub underlying_price: Option<f64>,
+    pub resulting_trade_id: Option<i64>,   // Stock trade created by exercise or assignment
+    pub notes: Option<String>,
+}
+
+impl OptionEvent {
+    pub fn new(trade_id: i64, leg_id: i64, event_type: OptionEventType) -> Self {
+        Self {
+            id: None,
+            trade_id,
+            leg_id,
+            event_type,
+            timestamp: Utc::now(),
+            underlying_price: None,
+            resulting_trade_id: None,
+            notes: None,
+        }
+    }
+}
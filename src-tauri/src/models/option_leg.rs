@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::calendar::TradingCalendar;
+use crate::analytics::black_scholes::{self, OptionType, OptionValuation, PricingInputs};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum LegSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OptionLeg {
+    pub id: Option<i64>,
+    pub analysis_id: Option<i64>,
+    pub trade_id: Option<i64>,
+    pub underlying: String,
+    pub expiry: NaiveDate,
+    pub strike: f64,
+    pub option_type: OptionType,
+    pub side: LegSide,
+    pub ratio: u32,                // Contracts per spread unit
+    pub premium: f64,              // Per share, always positive
+    pub entry_iv: Option<f64>,
+    pub exit_iv: Option<f64>,
+}
+
+impl OptionLeg {
+    pub fn new(underlying: &str, expiry: NaiveDate, strike: f64, option_type: OptionType, side: LegSide) -> Self {
+        Self {
+            id: None,
+            analysis_id: None,
+            trade_id: None,
+            underlying: underlying.to_string(),
+            expiry,
+            strike,
+            option_type,
+            side,
+            ratio: 1,
+            premium: 0.0,
+            entry_iv: None,
+            exit_iv: None,
+        }
+    }
+
+    /// +1 for bought legs, -1 for sold legs.
+    pub fn direction(&self) -> f64 {
+        match self.side {
+            LegSide::Buy => 1.0,
+            LegSide::Sell => -1.0,
+        }
+    }
+
+    /// Premium paid (positive) or received (negative) per spread unit.
+    pub fn signed_premium(&self) -> f64 {
+        self.direction() * self.premium * self.ratio as f64
+    }
+
+    /// Inputs for pricing the leg at `now`, expiring at the close `calendar`
+    /// gives its expiry date.
+    pub fn pricing_inputs(
+        &self,
+        underlying_price: f64,
+        now: DateTime<Utc>,
+        calendar: &TradingCalendar,
+        rate: f64,
+        dividend_yield: f64,
+        volatility: f64,
+    ) -> PricingInputs {
+        PricingInputs {
+            option_type: self.option_type,
+            underlying_price,
+            strike: self.strike,
+            time_to_expiry: black_scholes::years_to_expiry(now, calendar.expiry_close(self.expiry)),
+            rate,
+            dividend_yield,
+            volatility,
+        }
+    }
+
+    /// Valuation of the leg per spread unit, signed by side and scaled by ratio.
+    pub fn position_valuation(
+        &self,
+        underlying_price: f64,
+        now: DateTime<Utc>,
+        calendar: &TradingCalendar,
+        rate: f64,
+        dividend_yield: f64,
+        volatility: f64,
+    ) -> OptionValuation {
+        let valuation = black_scholes::valuation(&self.pricing_inputs(underlying_price, now, calendar, rate, dividend_yield, volatility));
+        let scale = self.direction() * self.ratio as f64;
+        OptionValuation {
+            price: valuation.price * scale,
+            delta: valuation.delta * scale,
+            gamma: valuation.gamma * scale,
+            theta: valuation.theta * scale,
+            vega: valuation.vega * scale,
+            rho: valuation.rho * scale,
+        }
+    }
+}
+
+/// Net premium of a position per spread unit; positive = debit, negative = credit.
+pub fn net_debit_credit(legs: &[OptionLeg]) -> f64 {
+    legs.iter().map(|leg| leg.signed_premium()).sum()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum LegPreset {
+    Vertical {
+        option_type: OptionType,
+        long_strike: f64,
+        short_strike: f64,
+    },
+    IronCondor {
+        long_put: f64,
+        short_put: f64,
+        short_call: f64,
+        long_call: f64,
+    },
+    Butterfly {
+        option_type: OptionType,
+        lower_strike: f64,
+        middle_strike: f64,
+        upper_strike: f64,
+    },
+    // Sells the near expiry and buys `far_expiry` at the same strike
+    Calendar {
+        option_type: OptionType,
+        strike: f64,
+        far_expiry: NaiveDate,
+    },
+    Straddle {
+        strike: f64,
+        side: LegSide,
+    },
+    Strangle {
+        put_strike: f64,
+        call_strike: f64,
+        side: LegSide,
+    },
+}
+
+impl LegPreset {
+    /// Builds the legs of the preset with zero premiums for the user to fill in.
+    pub fn build(&self, underlying: &str, expiry: NaiveDate) -> Vec<OptionLeg> {
+        let leg = |strike: f64, option_type: OptionType, side: LegSide| {
+            OptionLeg::new(underlying, expiry, strike, option_type, side)
+        };
+
+        match self {
+            LegPreset::Vertical { option_type, long_strike, short_strike } => vec![
+                leg(*long_strike, *option_type, LegSide::Buy),
+                leg(*short_strike, *option_type, LegSide::Sell),
+            ],
+            LegPreset::IronCondor { long_put, short_put, short_call, long_call } => vec![
+                leg(*long_put, OptionType::Put, LegSide::Buy),
+                leg(*short_put, OptionType::Put, LegSide::Sell),
+                leg(*short_call, OptionType::Call, LegSide::Sell),
+                leg(*long_call, OptionType::Call, LegSide::Buy),
+            ],
+            LegPreset::Butterfly { option_type, lower_strike, middle_strike, upper_strike } => {
+                let mut body = leg(*middle_strike, *option_type, LegSide::Sell);
+                body.ratio = 2;
+                vec![
+                    leg(*lower_strike, *option_type, LegSide::Buy),
+                    body,
+                    leg(*upper_strike, *option_type, LegSide::Buy),
+                ]
+            }
+            LegPreset::Calendar { option_type, strike, far_expiry } => vec![
+                leg(*strike, *option_type, LegSide::Sell),
+                OptionLeg::new(underlying, *far_expiry, *strike, *option_type, LegSide::Buy),
+            ],
+            LegPreset::Straddle { strike, side } => vec![
+                leg(*strike, OptionType::Put, *side),
+                leg(*strike, OptionType::Call, *side),
+            ],
+            LegPreset::Strangle { put_strike, call_strike, side } => vec![
+                leg(*put_strike, OptionType::Put, *side),
+                leg(*call_strike, OptionType::Call, *side),
+            ],
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// Limits as percentages of account equity, except the position count.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RiskLimits {
+    pub max_risk_per_trade_percent: f64,
+    pub max_position_percent: f64,
+    pub max_daily_loss_percent: f64,
+    pub max_open_positions: u32,
+}
+
+impl Default for RiskLimits {
+    fn default() -> Self {
+        Self {
+            max_risk_per_trade_percent: 1.0,
+            max_position_percent: 20.0,
+            max_daily_loss_percent: 3.0,
+            max_open_positions: 10,
+        }
+    }
+}
+
+impl RiskLimits {
+    /// The risk of a trade as a percentage of `equity` when it is over the
+    /// per-trade limit. Without equity no trade is checked.
+    pub fn trade_risk_breach(&self, risk_max: f64, equity: f64) -> Option<f64> {
+        if equity <= 0.0 {
+            return None;
+        }
+        let percent = risk_max.abs() / equity * 100.0;
+        (percent > self.max_risk_per_trade_percent).then_some(percent)
+    }
+}
+
+/// Assumptions and limits for the portfolio Greeks risk panel, and the
+/// account risk limits checked alongside them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PortfolioRiskSettings {
+    pub benchmark: String,
+    pub beta_lookback_days: usize,
+    pub rate: f64,
+    pub dividend_yield: f64,
+    pub default_volatility: f64,   // For legs without a recorded IV
+    pub min_net_theta: Option<f64>,
+    pub max_net_theta: Option<f64>,
+    pub min_net_vega: Option<f64>,
+    pub max_net_vega: Option<f64>,
+    #[serde(default)]
+    pub limits: RiskLimits,
+}
+
+impl Default for PortfolioRiskSettings {
+    fn default() -> Self {
+        Self {
+            benchmark: "SPY".to_string(),
+            beta_lookback_days: 252,
+            rate: 0.04,
+            dividend_yield: 0.0,
+            default_volatility: 0.3,
+            min_net_theta: None,
+            max_net_theta: None,
+            min_net_vega: None,
+            max_net_vega: None,
+            limits: RiskLimits::default(),
+        }
+    }
+}
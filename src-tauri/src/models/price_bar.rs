@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timeframe {
+    FiveMinute,
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+impl Timeframe {
+    pub fn is_intraday(&self) -> bool {
+        matches!(self, Timeframe::FiveMinute | Timeframe::Hourly)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceBar {
+    pub symbol: String,
+    pub timeframe: Timeframe,
+    pub timestamp: DateTime<Utc>,   // Start of the bar
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use super::detailed_analysis::DetailedAnalysis;
+use super::stock_rating::StockRating;
+use super::trade::Trade;
+
+/// An analysis together with the trades planned or taken from it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalysisChain {
+    pub analysis: DetailedAnalysis,
+    pub trades: Vec<Trade>,
+}
+
+/// Everything that grew out of one rating, newest analyses first.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RatingChain {
+    pub rating: StockRating,
+    pub analyses: Vec<AnalysisChain>,
+}
+
+/// The idea a trade was taken from. Analyses written before ratings could be
+/// promoted have no rating.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TradeLineage {
+    pub trade: Trade,
+    pub analysis: DetailedAnalysis,
+    pub rating: Option<StockRating>,
+}
@@ -14,6 +14,8 @@ pub struct PsychologicalState {
     pub headache_pain: i32,      // 0 to +3
     pub extra_factors: HashMap<String, i32>,
     pub total_risk_score: f64,
+    #[serde(default)]
+    pub recorded_at: Option<DateTime<Utc>>,   // When it was saved; `timestamp` may be backdated
 }
 
 impl PsychologicalState {
@@ -29,6 +31,7 @@ impl PsychologicalState {
             headache_pain: 0,
             extra_factors: HashMap::new(),
             total_risk_score: 0.0,
+            recorded_at: None,
         }
     }
 
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::stock_rating::StockRating;
+
+/// How quickly rating scores lose weight and when ratings need redoing.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RatingDecaySettings {
+    pub half_life_days: f64,       // Age at which a score counts for half
+    pub stale_after_days: i64,
+}
+
+impl Default for RatingDecaySettings {
+    fn default() -> Self {
+        Self {
+            half_life_days: 14.0,
+            stale_after_days: 21,
+        }
+    }
+}
+
+/// The latest rating of a symbol with its score decayed to `as_of`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RatingFreshness {
+    pub rating: StockRating,
+    pub as_of: DateTime<Utc>,
+    pub age_days: f64,
+    pub decay_factor: f64,         // 1 when fresh, 0.5 after one half-life
+    pub decayed_score: f64,
+    pub stale: bool,
+}
+
+impl RatingFreshness {
+    pub fn new(rating: StockRating, as_of: DateTime<Utc>, settings: &RatingDecaySettings) -> Self {
+        let age_days = rating.age_days(as_of);
+        let decay_factor = rating.decay_factor(as_of, settings.half_life_days);
+
+        Self {
+            decayed_score: rating.overall_score * decay_factor,
+            stale: rating.is_stale(as_of, settings.stale_after_days),
+            rating,
+            as_of,
+            age_days,
+            decay_factor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn score_halves_each_half_life() {
+        let now = Utc::now();
+        let mut rating = StockRating::new("AAPL", "Information Technology");
+        rating.overall_score = 8.0;
+        rating.timestamp = now - Duration::days(28);
+
+        let freshness = RatingFreshness::new(rating, now, &RatingDecaySettings::default());
+
+        assert!((freshness.age_days - 28.0).abs() < 1e-6);
+        assert!((freshness.decay_factor - 0.25).abs() < 1e-6);
+        assert!((freshness.decayed_score - 2.0).abs() < 1e-6);
+        assert!(freshness.stale);
+    }
+
+    #[test]
+    fn recent_rating_is_fresh() {
+        let now = Utc::now();
+        let mut rating = StockRating::new("AAPL", "Information Technology");
+        rating.overall_score = 8.0;
+        rating.timestamp = now - Duration::days(20);
+
+        assert!(!RatingFreshness::new(rating, now, &RatingDecaySettings::default()).stale);
+    }
+}
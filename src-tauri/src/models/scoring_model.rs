@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::stock_rating::StockRating;
+
+/// Weight applied to each component of a rating's overall score.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScoringWeights {
+    pub market: f64,
+    pub sector: f64,
+    pub security: f64,
+    pub confidence: f64,
+    pub trend_alignment: f64,
+    pub chart_pattern: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            market: 1.0,
+            sector: 1.0,
+            security: 1.0,
+            confidence: 1.0,
+            trend_alignment: 1.0,
+            chart_pattern: 1.0,
+        }
+    }
+}
+
+/// A versioned set of weights. Versions are never edited in place, so a score
+/// can always be traced back to the weights that produced it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoringModel {
+    pub version: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub name: String,
+    pub weights: ScoringWeights,
+    pub active: bool,
+    pub notes: Option<String>,
+}
+
+/// Weighted contribution of each component, so the components sum to `total`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScoreBreakdown {
+    pub market: f64,
+    pub sector: f64,
+    pub security: f64,
+    pub confidence: f64,
+    pub trend_alignment: f64,
+    pub chart_pattern: f64,
+    pub total: f64,
+}
+
+impl Default for ScoringModel {
+    fn default() -> Self {
+        Self {
+            version: None,
+            timestamp: Utc::now(),
+            name: "Default".to_string(),
+            weights: ScoringWeights::default(),
+            active: true,
+            notes: None,
+        }
+    }
+}
+
+impl ScoringWeights {
+    pub fn validate(&self) -> Result<(), String> {
+        let weights = [
+            self.market, self.sector, self.security,
+            self.confidence, self.trend_alignment, self.chart_pattern,
+        ];
+        if weights.iter().any(|weight| !weight.is_finite() || *weight < 0.0) {
+            return Err("Scoring weights cannot be negative".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl ScoringModel {
+    #[cfg(test)]
+    pub fn new(name: &str, weights: ScoringWeights) -> Self {
+        Self {
+            name: name.to_string(),
+            weights,
+            active: false,
+            ..Self::default()
+        }
+    }
+
+    /// Scores a rating. The sentiments and confidence (scaled to 0-3) count
+    /// toward the bull/bear direction; trend alignment and the chart pattern
+    /// bonus add conviction when they support it and take it away when not.
+    pub fn breakdown(&self, rating: &StockRating) -> ScoreBreakdown {
+        let direction = rating.bull_bear as f64;
+        let w = &self.weights;
+
+        let market = direction * w.market * rating.market_sentiment as f64;
+        let sector = direction * w.sector * rating.sector_sentiment as f64;
+        let security = direction * w.security * rating.security_sentiment as f64;
+        let confidence = direction * w.confidence * rating.confidence as f64 / 100.0 * 3.0;
+        // +1 when the trend agrees with the direction, -1 when it opposes it
+        let trend_agreement = direction * rating.market_trend.direction() as f64;
+        let trend_alignment = w.trend_alignment * trend_agreement;
+        // The pattern bonus is already relative to the direction
+        let chart_pattern = w.chart_pattern * rating.pattern_bonus;
+
+        ScoreBreakdown {
+            market,
+            sector,
+            security,
+            confidence,
+            trend_alignment,
+            chart_pattern,
+            total: market + sector + security + confidence + trend_alignment + chart_pattern,
+        }
+    }
+
+    pub fn score(&self, rating: &StockRating) -> f64 {
+        self.breakdown(rating).total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::stock_rating::MarketTrend;
+
+    fn rating(bull_bear: i8, trend: MarketTrend) -> StockRating {
+        let mut rating = StockRating::new("AAPL", "Technology");
+        rating.bull_bear = bull_bear;
+        rating.market_trend = trend;
+        rating
+    }
+
+    fn trend_alignment(bull_bear: i8, trend: MarketTrend) -> f64 {
+        ScoringModel::default().breakdown(&rating(bull_bear, trend)).trend_alignment
+    }
+
+    #[test]
+    fn trend_alignment_rewards_agreement_in_either_direction() {
+        assert_eq!(trend_alignment(1, MarketTrend::Uptrend), 1.0);
+        assert_eq!(trend_alignment(1, MarketTrend::Downtrend), -1.0);
+        assert_eq!(trend_alignment(-1, MarketTrend::Downtrend), 1.0);
+        assert_eq!(trend_alignment(-1, MarketTrend::Uptrend), -1.0);
+        assert_eq!(trend_alignment(-1, MarketTrend::Sideways), 0.0);
+    }
+
+    #[test]
+    fn pattern_bonus_is_not_signed_again() {
+        let mut bear = rating(-1, MarketTrend::Uncertain);
+        bear.pattern_bonus = 0.4;
+        assert_eq!(ScoringModel::default().breakdown(&bear).chart_pattern, 0.4);
+
+        bear.pattern_bonus = -0.4;
+        assert_eq!(ScoringModel::default().breakdown(&bear).chart_pattern, -0.4);
+    }
+
+    #[test]
+    fn components_sum_to_total() {
+        let mut bull = rating(1, MarketTrend::Uptrend);
+        bull.market_sentiment = 2;
+        bull.sector_sentiment = -1;
+        bull.security_sentiment = 3;
+        bull.confidence = 50;
+        bull.pattern_bonus = 0.25;
+
+        let breakdown = ScoringModel::default().breakdown(&bull);
+        assert_eq!(breakdown.total, 2.0 - 1.0 + 3.0 + 1.5 + 1.0 + 0.25);
+    }
+}
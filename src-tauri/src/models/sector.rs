@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SectorLevel {
+    Sector,
+    IndustryGroup,
+    Industry,
+    Custom,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sector {
+    pub id: Option<i64>,
+    pub code: Option<String>,      // GICS code, None for custom sectors
+    pub name: String,
+    pub level: SectorLevel,
+    pub parent_id: Option<i64>,
+    pub aliases: Vec<String>,      // Other names the sector is known by
+}
+
+/// One slot on the sector screening list. A sector may fill several slots as
+/// long as each repeat gives a rationale.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SectorSelection {
+    pub id: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub sector_id: i64,
+    pub rationale: Option<String>,
+    pub position: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SectorReviewStatus {
+    AutoMapped,
+    NeedsReview,
+    Resolved,
+}
+
+/// How a free-text sector value found in ratings or analyses was mapped.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SectorReview {
+    pub raw_value: String,
+    pub sector_id: Option<i64>,
+    pub status: SectorReviewStatus,
+    pub candidates: Vec<i64>,      // Possible sectors when ambiguous
+    pub occurrences: i64,          // Ratings and analyses still using the value unmapped
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum SectorMatch {
+    Matched(i64),
+    Ambiguous(Vec<i64>),
+    Unknown,
+}
+
+impl Sector {
+    #[cfg(test)]
+    pub fn custom(name: &str, parent_id: Option<i64>) -> Self {
+        Self {
+            id: None,
+            code: None,
+            name: name.to_string(),
+            level: SectorLevel::Custom,
+            parent_id,
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Whether `value` names this sector, ignoring case, punctuation and
+    /// "&" versus "and".
+    pub fn matches_exactly(&self, value: &str) -> bool {
+        let value = normalize_sector_name(value);
+        normalize_sector_name(&self.name) == value
+            || self.aliases.iter().any(|alias| normalize_sector_name(alias) == value)
+    }
+
+    /// Whether `value` and the sector name contain one another as whole
+    /// words, so "Gas" matches "Oil, Gas & Consumable Fuels" but "Tech" does
+    /// not match "Biotechnology".
+    pub fn matches_partially(&self, value: &str) -> bool {
+        let value = sector_name_words(value);
+        let name = sector_name_words(&self.name);
+        !value.is_empty() && (contains_words(&name, &value) || contains_words(&value, &name))
+    }
+}
+
+impl SectorLevel {
+    pub fn from_gics_code(code: &str) -> Self {
+        match code.len() {
+            2 => SectorLevel::Sector,
+            4 => SectorLevel::IndustryGroup,
+            _ => SectorLevel::Industry,
+        }
+    }
+}
+
+pub fn normalize_sector_name(value: &str) -> String {
+    value.to_lowercase()
+        .replace('&', " and ")
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+fn sector_name_words(value: &str) -> Vec<String> {
+    value.to_lowercase()
+        .replace('&', " and ")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn contains_words(words: &[String], part: &[String]) -> bool {
+    !part.is_empty() && words.windows(part.len()).any(|window| window == part)
+}
+
+/// GICS sectors, industry groups and industries as of the March 2023
+/// revision. The parent of each entry is the entry whose code is its prefix.
+pub const GICS_HIERARCHY: &[(&str, &str)] = &[
+    ("10", "Energy"),
+    ("1010", "Energy"),
+    ("101010", "Energy Equipment & Services"),
+    ("101020", "Oil, Gas & Consumable Fuels"),
+    ("15", "Materials"),
+    ("1510", "Materials"),
+    ("151010", "Chemicals"),
+    ("151020", "Construction Materials"),
+    ("151030", "Containers & Packaging"),
+    ("151040", "Metals & Mining"),
+    ("151050", "Paper & Forest Products"),
+    ("20", "Industrials"),
+    ("2010", "Capital Goods"),
+    ("201010", "Aerospace & Defense"),
+    ("201020", "Building Products"),
+    ("201030", "Construction & Engineering"),
+    ("201040", "Electrical Equipment"),
+    ("201050", "Industrial Conglomerates"),
+    ("201060", "Machinery"),
+    ("201070", "Trading Companies & Distributors"),
+    ("2020", "Commercial & Professional Services"),
+    ("202010", "Commercial Services & Supplies"),
+    ("202020", "Professional Services"),
+    ("2030", "Transportation"),
+    ("203010", "Air Freight & Logistics"),
+    ("203020", "Passenger Airlines"),
+    ("203030", "Marine Transportation"),
+    ("203040", "Ground Transportation"),
+    ("203050", "Transportation Infrastructure"),
+    ("25", "Consumer Discretionary"),
+    ("2510", "Automobiles & Components"),
+    ("251010", "Automobile Components"),
+    ("251020", "Automobiles"),
+    ("2520", "Consumer Durables & Apparel"),
+    ("252010", "Household Durables"),
+    ("252020", "Leisure Products"),
+    ("252030", "Textiles, Apparel & Luxury Goods"),
+    ("2530", "Consumer Services"),
+    ("253010", "Hotels, Restaurants & Leisure"),
+    ("253020", "Diversified Consumer Services"),
+    ("2550", "Consumer Discretionary Distribution & Retail"),
+    ("255010", "Distributors"),
+    ("255030", "Broadline Retail"),
+    ("255040", "Specialty Retail"),
+    ("30", "Consumer Staples"),
+    ("3010", "Consumer Staples Distribution & Retail"),
+    ("301010", "Consumer Staples Distribution & Retail"),
+    ("3020", "Food, Beverage & Tobacco"),
+    ("302010", "Beverages"),
+    ("302020", "Food Products"),
+    ("302030", "Tobacco"),
+    ("3030", "Household & Personal Products"),
+    ("303010", "Household Products"),
+    ("303020", "Personal Care Products"),
+    ("35", "Health Care"),
+    ("3510", "Health Care Equipment & Services"),
+    ("351010", "Health Care Equipment & Supplies"),
+    ("351020", "Health Care Providers & Services"),
+    ("351030", "Health Care Technology"),
+    ("3520", "Pharmaceuticals, Biotechnology & Life Sciences"),
+    ("352010", "Biotechnology"),
+    ("352020", "Pharmaceuticals"),
+    ("352030", "Life Sciences Tools & Services"),
+    ("40", "Financials"),
+    ("4010", "Banks"),
+    ("401010", "Banks"),
+    ("4020", "Financial Services"),
+    ("402010", "Financial Services"),
+    ("402020", "Consumer Finance"),
+    ("402030", "Capital Markets"),
+    ("402040", "Mortgage Real Estate Investment Trusts (REITs)"),
+    ("4030", "Insurance"),
+    ("403010", "Insurance"),
+    ("45", "Information Technology"),
+    ("4510", "Software & Services"),
+    ("451020", "IT Services"),
+    ("451030", "Software"),
+    ("4520", "Technology Hardware & Equipment"),
+    ("452010", "Communications Equipment"),
+    ("452020", "Technology Hardware, Storage & Peripherals"),
+    ("452030", "Electronic Equipment, Instruments & Components"),
+    ("4530", "Semiconductors & Semiconductor Equipment"),
+    ("453010", "Semiconductors & Semiconductor Equipment"),
+    ("50", "Communication Services"),
+    ("5010", "Telecommunication Services"),
+    ("501010", "Diversified Telecommunication Services"),
+    ("501020", "Wireless Telecommunication Services"),
+    ("5020", "Media & Entertainment"),
+    ("502010", "Media"),
+    ("502020", "Entertainment"),
+    ("502030", "Interactive Media & Services"),
+    ("55", "Utilities"),
+    ("5510", "Utilities"),
+    ("551010", "Electric Utilities"),
+    ("551020", "Gas Utilities"),
+    ("551030", "Multi-Utilities"),
+    ("551040", "Water Utilities"),
+    ("551050", "Independent Power and Renewable Electricity Producers"),
+    ("60", "Real Estate"),
+    ("6010", "Equity Real Estate Investment Trusts (REITs)"),
+    ("601010", "Diversified REITs"),
+    ("601025", "Industrial REITs"),
+    ("601030", "Hotel & Resort REITs"),
+    ("601040", "Office REITs"),
+    ("601050", "Health Care REITs"),
+    ("601060", "Residential REITs"),
+    ("601070", "Retail REITs"),
+    ("601080", "Specialized REITs"),
+    ("6020", "Real Estate Management & Development"),
+    ("602010", "Real Estate Management & Development"),
+];
+
+/// Common names for GICS sectors, including those on the dashboard sector list.
+pub const GICS_ALIASES: &[(&str, &str)] = &[
+    ("15", "Basic Materials"),
+    ("25", "Consumer Cyclical"),
+    ("30", "Consumer Defensive"),
+    ("40", "Financial"),
+    ("40", "Finance"),
+    ("35", "Healthcare"),
+    ("45", "Technology"),
+    ("45", "Tech"),
+    ("45", "IT"),
+    ("50", "Communications"),
+    ("50", "Telecom"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_matches_need_whole_words() {
+        let sector = Sector::custom("Oil, Gas & Consumable Fuels", None);
+
+        assert!(sector.matches_partially("gas"));
+        assert!(sector.matches_partially("Gas and Consumable"));
+        assert!(!sector.matches_partially("Ga"));
+        assert!(!Sector::custom("Biotechnology", None).matches_partially("Tech"));
+    }
+
+    #[test]
+    fn exact_matches_ignore_punctuation() {
+        let sector = Sector::custom("Hotels, Restaurants & Leisure", None);
+
+        assert!(sector.matches_exactly("hotels restaurants and leisure"));
+        assert!(!sector.matches_exactly("Hotels"));
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AssetClass {
+    Equity,
+    Etf,
+    Index,
+    Option,
+    Future,
+    Forex,
+    Crypto,
+    Other,
+}
+
+/// An entry in the symbol master.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Security {
+    pub symbol: String,
+    pub name: String,
+    pub exchange: Option<String>,
+    pub asset_class: AssetClass,
+    pub sector_id: Option<i64>,
+    pub industry_id: Option<i64>,
+    pub sector: Option<String>,        // Names of the sector and industry above
+    pub industry: Option<String>,
+    pub currency: String,
+    pub multiplier: f64,
+    pub active: bool,
+}
+
+/// One row of a symbol master CSV. Sector and industry are free text and are
+/// matched against the sector taxonomy on import.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityRecord {
+    pub symbol: String,
+    pub name: String,
+    #[serde(default)]
+    pub exchange: Option<String>,
+    #[serde(default)]
+    pub asset_class: Option<String>,
+    #[serde(default)]
+    pub sector: Option<String>,
+    #[serde(default)]
+    pub industry: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub multiplier: Option<f64>,
+    #[serde(default)]
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SecurityImportSummary {
+    pub imported: usize,
+    pub unmatched_sectors: Vec<String>,   // "SYMBOL: value" for sectors left unset
+    pub errors: Vec<String>,              // "line N: reason" for rows skipped
+}
+
+impl AssetClass {
+    /// Parses the asset class column of a symbol master CSV.
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "equity" | "stock" | "common stock" | "cs" => AssetClass::Equity,
+            "etf" | "fund" => AssetClass::Etf,
+            "index" => AssetClass::Index,
+            "option" => AssetClass::Option,
+            "future" | "futures" => AssetClass::Future,
+            "forex" | "fx" | "currency" => AssetClass::Forex,
+            "crypto" | "cryptocurrency" => AssetClass::Crypto,
+            _ => AssetClass::Other,
+        }
+    }
+}
+
+pub fn normalize_symbol(symbol: &str) -> String {
+    symbol.trim().to_ascii_uppercase()
+}
+
+/// Edit distance between two symbols, counting a swap of adjacent characters
+/// as one edit so "APPL" is one step from "AAPL".
+pub fn symbol_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use super::scoring_model::ScoringModel;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum MarketTrend {
     Uptrend,
     Downtrend,
@@ -9,7 +11,7 @@ pub enum MarketTrend {
     Uncertain,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum ChartPattern {
     HighBase,
     LowBase,
@@ -25,26 +27,68 @@ pub enum ChartPattern {
     Other(String),
 }
 
+impl MarketTrend {
+    /// +1 for an uptrend, -1 for a downtrend, 0 otherwise.
+    pub fn direction(&self) -> i32 {
+        match self {
+            MarketTrend::Uptrend => 1,
+            MarketTrend::Downtrend => -1,
+            MarketTrend::Sideways | MarketTrend::Uncertain => 0,
+        }
+    }
+}
+
+impl ChartPattern {
+    /// +1 for patterns that usually resolve upward, -1 for those that usually
+    /// resolve downward, 0 for neutral or unknown patterns.
+    pub fn bias(&self) -> f64 {
+        match self {
+            ChartPattern::HighBase
+            | ChartPattern::AscendingTriangle
+            | ChartPattern::Cup
+            | ChartPattern::InverseHeadAndShoulders
+            | ChartPattern::DoubleBottom
+            | ChartPattern::BreakoutPullback => 1.0,
+            ChartPattern::LowBase
+            | ChartPattern::DescendingTriangle
+            | ChartPattern::HeadAndShoulders
+            | ChartPattern::DoubleTop => -1.0,
+            ChartPattern::Consolidation | ChartPattern::Other(_) => 0.0,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StockRating {
     pub id: Option<i64>,
     pub timestamp: DateTime<Utc>,
     pub symbol: String,
     pub security_name: Option<String>,
-    pub sector: String,
+    pub sector: String,            // Name of the sector below
+    #[serde(default)]
+    pub sector_id: Option<i64>,
     pub market_sentiment: i32,     // -3 to +3
     pub sector_sentiment: i32,     // -3 to +3
     pub security_sentiment: i32,   // -3 to +3
     pub bull_bear: i8,             // Bull = +1, Bear = -1
     pub confidence: u8,            // 0 to 100%
     pub market_trend: MarketTrend,
+    #[serde(default)]
+    pub computed_market_trend: Option<MarketTrend>,   // Classifier's value, kept beside the user's
     pub chart_pattern: ChartPattern,
     pub strategy: String,
-    pub overall_score: i32,        // Computed
+    pub overall_score: f64,        // Computed
     pub notes: Option<String>,
+    #[serde(default)]
+    pub model_version: Option<i64>,   // ScoringModel that produced overall_score
+    #[serde(default)]
+    pub pattern_bonus: f64,           // -1 to 1, from chart pattern statistics
+    #[serde(default)]
+    pub recorded_at: Option<DateTime<Utc>>,   // When it was saved; `timestamp` may be backdated
 }
 
 impl StockRating {
+    #[cfg(test)]
     pub fn new(symbol: &str, sector: &str) -> Self {
         Self {
             id: None,
@@ -52,28 +96,60 @@ impl StockRating {
             symbol: symbol.to_string(),
             security_name: None,
             sector: sector.to_string(),
+            sector_id: None,
             market_sentiment: 0,
             sector_sentiment: 0,
             security_sentiment: 0,
             bull_bear: 1,
             confidence: 50,
             market_trend: MarketTrend::Uncertain,
+            computed_market_trend: None,
             chart_pattern: ChartPattern::Other("None".to_string()),
             strategy: String::new(),
-            overall_score: 0,
+            overall_score: 0.0,
             notes: None,
+            model_version: None,
+            pattern_bonus: 0.0,
+            recorded_at: None,
         }
     }
 
-    pub fn calculate_overall_score(&self) -> i32 {
-        let bull_bear_factor = self.bull_bear as i32;
-        let confidence_factor = (self.confidence as f32 / 100.0) * 3.0;
-        let sentiment_sum = self.market_sentiment + self.sector_sentiment + self.security_sentiment;
-        
-        bull_bear_factor * (sentiment_sum + confidence_factor as i32)
+    pub fn calculate_overall_score(&self, model: &ScoringModel) -> f64 {
+        model.score(self)
+    }
+
+    pub fn update_overall_score(&mut self, model: &ScoringModel) {
+        self.overall_score = self.calculate_overall_score(model);
+        self.model_version = model.version;
     }
 
-    pub fn update_overall_score(&mut self) {
-        self.overall_score = self.calculate_overall_score();
+    pub fn age_days(&self, as_of: DateTime<Utc>) -> f64 {
+        ((as_of - self.timestamp).num_seconds() as f64 / 86_400.0).max(0.0)
     }
-} 
\ No newline at end of file
+
+    /// Weight of the score after exponential decay with the given half-life.
+    pub fn decay_factor(&self, as_of: DateTime<Utc>, half_life_days: f64) -> f64 {
+        if half_life_days <= 0.0 {
+            return 1.0;
+        }
+        0.5_f64.powf(self.age_days(as_of) / half_life_days)
+    }
+
+    pub fn is_stale(&self, as_of: DateTime<Utc>, stale_after_days: i64) -> bool {
+        self.age_days(as_of) >= stale_after_days as f64
+    }
+
+    /// A new, unsaved rating starting from the values of this one.
+    pub fn rerate(&self) -> StockRating {
+        Self {
+            id: None,
+            timestamp: Utc::now(),
+            computed_market_trend: None,
+            overall_score: 0.0,
+            model_version: None,
+            pattern_bonus: 0.0,
+            recorded_at: None,
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+/// How far ahead of the clock an entry's timestamp may be, to allow for
+/// clock drift between the UI and the backend.
+const MAX_FUTURE_SKEW_SECONDS: i64 = 300;
+
+/// Zones used to show times and to decide which day a time falls on.
+/// Everything is stored in UTC.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TimeZoneSettings {
+    pub user_timezone: Tz,         // Where the user reads the dashboard
+    pub exchange_timezone: Tz,     // From the default exchange's calendar; for trading dates
+}
+
+impl Default for TimeZoneSettings {
+    fn default() -> Self {
+        Self {
+            user_timezone: Tz::America__New_York,
+            exchange_timezone: Tz::America__New_York,
+        }
+    }
+}
+
+/// A stored UTC time as the user and the exchange see it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalTimestamp {
+    pub utc: DateTime<Utc>,
+    pub user_time: DateTime<FixedOffset>,
+    pub user_date: NaiveDate,
+    pub exchange_time: DateTime<FixedOffset>,
+    pub exchange_date: NaiveDate,
+}
+
+impl TimeZoneSettings {
+    pub fn exchange_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.exchange_timezone).date_naive()
+    }
+
+    pub fn localize(&self, at: DateTime<Utc>) -> LocalTimestamp {
+        let user_time = at.with_timezone(&self.user_timezone);
+        let exchange_time = at.with_timezone(&self.exchange_timezone);
+
+        LocalTimestamp {
+            utc: at,
+            user_time: user_time.fixed_offset(),
+            user_date: user_time.date_naive(),
+            exchange_time: exchange_time.fixed_offset(),
+            exchange_date: exchange_time.date_naive(),
+        }
+    }
+}
+
+/// Accepts a user-supplied timestamp for an entry recorded at `recorded_at`.
+/// Entries may be backdated but not dated in the future.
+pub fn check_entry_timestamp(timestamp: DateTime<Utc>, recorded_at: DateTime<Utc>) -> Result<(), String> {
+    if timestamp > recorded_at + Duration::seconds(MAX_FUTURE_SKEW_SECONDS) {
+        return Err(format!("Timestamp {} is in the future", timestamp.to_rfc3339()));
+    }
+
+    Ok(())
+}
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::detailed_analysis::DetailedAnalysis;
+use super::option_leg::OptionLeg;
+use crate::analytics::payoff::CONTRACT_MULTIPLIER;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum TradeStatus {
@@ -9,10 +13,31 @@ pub enum TradeStatus {
     Cancelled,
 }
 
+/// Whether a stock position is bought or sold short. Option trades are Long;
+/// their legs carry the sides.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum PositionSide {
+    #[default]
+    Long,
+    Short,
+}
+
+impl PositionSide {
+    /// +1 for long positions, -1 for short ones.
+    pub fn sign(&self) -> f64 {
+        match self {
+            PositionSide::Long => 1.0,
+            PositionSide::Short => -1.0,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Trade {
     pub id: Option<i64>,
     pub analysis_id: i64,
+    #[serde(default)]
+    pub account_id: Option<i64>,   // The analysis's account when not chosen
     pub timestamp: DateTime<Utc>,
     pub symbol: String,
     pub status: TradeStatus,
@@ -21,9 +46,31 @@ pub struct Trade {
     pub entry_price: Option<f64>,
     pub exit_price: Option<f64>,
     pub quantity: u32,
-    pub profit_loss: Option<f64>,
+    #[serde(default)]
+    pub side: PositionSide,
+    pub profit_loss: Option<f64>,  // After fees
     pub percent_return: Option<f64>,
+    #[serde(default)]
+    pub fees: Option<f64>,         // Commissions taken out when the trade closed
     pub notes: Option<String>,
+    #[serde(default)]
+    pub parent_trade_id: Option<i64>,   // Option trade this trade resulted from
+    #[serde(default)]
+    pub trading_day: Option<NaiveDate>, // Session of the entry, or of the plan until entered
+    #[serde(default)]
+    pub recorded_at: Option<DateTime<Utc>>,   // When it was saved; `timestamp` may be backdated
+    #[serde(default)]
+    pub legs: Vec<OptionLeg>,
+}
+
+/// An edit of a stored trade as the UI sends it. Legs left out keep their
+/// stored value; an empty list removes them.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TradeUpdate {
+    #[serde(flatten)]
+    pub trade: Trade,
+    #[serde(default)]
+    pub legs: Option<Vec<OptionLeg>>,
 }
 
 impl Trade {
@@ -31,6 +78,7 @@ impl Trade {
         Self {
             id: None,
             analysis_id,
+            account_id: None,
             timestamp: Utc::now(),
             symbol: symbol.to_string(),
             status: TradeStatus::Planned,
@@ -39,9 +87,27 @@ impl Trade {
             entry_price: None,
             exit_price: None,
             quantity: 0,
+            side: PositionSide::Long,
             profit_loss: None,
             percent_return: None,
+            fees: None,
             notes: None,
+            parent_trade_id: None,
+            trading_day: None,
+            recorded_at: None,
+            legs: Vec::new(),
+        }
+    }
+
+    /// A Planned trade carrying over the plan recorded in an analysis.
+    pub fn from_analysis(analysis_id: i64, analysis: &DetailedAnalysis) -> Self {
+        Self {
+            entry_price: (analysis.entry_price > 0.0).then_some(analysis.entry_price),
+            quantity: analysis.quantity,
+            notes: (!analysis.entry_reason.is_empty()).then(|| analysis.entry_reason.clone()),
+            legs: analysis.legs.clone(),
+            account_id: analysis.account_id,
+            ..Self::new(&analysis.security, analysis_id)
         }
     }
 
@@ -56,15 +122,80 @@ impl Trade {
         self.exit_time = Some(exit_time);
         self.exit_price = Some(exit_price);
         self.status = TradeStatus::Closed;
-        
-        if let Some(entry_price) = self.entry_price {
-            self.profit_loss = Some((exit_price - entry_price) * self.quantity as f64);
-            self.percent_return = Some((exit_price - entry_price) / entry_price * 100.0);
+        self.compute_profit_loss();
+    }
+
+    /// Works out the profit before fees from the entry and exit prices.
+    /// Without both prices the profit is left as it is.
+    pub fn compute_profit_loss(&mut self) {
+        if let (Some(entry_price), Some(exit_price)) = (self.entry_price, self.exit_price) {
+            let sign = self.side.sign();
+            self.profit_loss = Some((exit_price - entry_price) * self.quantity as f64 * self.multiplier() * sign);
+            if entry_price != 0.0 {
+                self.percent_return = Some((exit_price - entry_price) / entry_price.abs() * 100.0 * sign);
+            }
         }
     }
 
-    pub fn cancel_trade(&mut self, reason: &str) {
-        self.status = TradeStatus::Cancelled;
-        self.notes = Some(reason.to_string());
+    /// Takes commissions out of the profit of a closed trade.
+    pub fn charge_fees(&mut self, fees: f64) {
+        self.fees = Some(fees);
+        if let Some(profit_loss) = self.profit_loss {
+            let profit_loss = profit_loss - fees;
+            self.profit_loss = Some(profit_loss);
+            if let Some(entry_value) = self.entry_value().filter(|value| *value != 0.0) {
+                self.percent_return = Some(profit_loss / entry_value.abs() * 100.0);
+            }
+        }
     }
-} 
\ No newline at end of file
+
+    /// Cost of the position at its entry price; option trades are counted in
+    /// contracts.
+    pub fn entry_value(&self) -> Option<f64> {
+        Some(self.entry_price? * self.quantity as f64 * self.multiplier())
+    }
+
+    /// Shares per unit of quantity: one for stock, a contract for options.
+    fn multiplier(&self) -> f64 {
+        if self.legs.is_empty() { 1.0 } else { CONTRACT_MULTIPLIER }
+    }
+
+    pub fn flag_checklist_failures(&mut self, failed_items: &[String]) {
+        let flag = format!("[Checklist failed: {}]", failed_items.join(", "));
+        self.notes = match self.notes.take() {
+            Some(notes) if !notes.is_empty() => Some(format!("{} {}", flag, notes)),
+            _ => Some(flag),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::analytics::black_scholes::OptionType;
+    use crate::models::option_leg::LegSide;
+
+    #[test]
+    fn short_stock_gains_when_the_price_falls() {
+        let mut trade = Trade::new("AAPL", 1);
+        trade.side = PositionSide::Short;
+        trade.enter_trade(Utc::now(), 100.0, 50);
+        trade.exit_trade(Utc::now(), 90.0);
+
+        assert_eq!(trade.profit_loss, Some(500.0));
+        assert_eq!(trade.percent_return, Some(10.0));
+    }
+
+    #[test]
+    fn option_profit_counts_whole_contracts() {
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let mut trade = Trade::new("AAPL", 1);
+        trade.legs = vec![OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Buy)];
+        trade.enter_trade(Utc::now(), 2.0, 3);
+        trade.exit_trade(Utc::now(), 3.5);
+
+        assert_eq!(trade.profit_loss, Some(450.0));
+        assert_eq!(trade.entry_value(), Some(600.0));
+    }
+}
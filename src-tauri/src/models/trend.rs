@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::price_bar::Timeframe;
+use super::stock_rating::MarketTrend;
+
+/// What the classifier saw on one chart. Votes are +1 bullish, -1 bearish
+/// and 0 neutral.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendSignal {
+    pub trend: MarketTrend,
+    pub score: f64,                // -1 to 1, the average of the votes
+    pub slope_vote: i32,           // Direction of the short moving average
+    pub stacking_vote: i32,        // Close, short and long averages in order
+    pub structure_vote: i32,       // Higher highs and lows, or lower ones
+    pub adx: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeframeTrend {
+    pub symbol: String,
+    pub timeframe: Timeframe,
+    pub weight: f64,
+    pub signal: TrendSignal,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendClassification {
+    pub symbol: String,
+    pub computed_at: DateTime<Utc>,
+    pub trend: MarketTrend,
+    pub score: f64,                // Weighted over `charts`
+    pub charts: Vec<TimeframeTrend>,   // Only charts with enough bars
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendSettings {
+    pub index_symbol: String,      // Broad market index blended into every classification
+    pub index_weight: f64,
+    pub timeframes: Vec<(Timeframe, f64)>,   // Symbol charts and their weights
+}
+
+impl Default for TrendSettings {
+    fn default() -> Self {
+        Self {
+            index_symbol: "SPY".to_string(),
+            index_weight: 1.0,
+            timeframes: vec![(Timeframe::Daily, 2.0), (Timeframe::Weekly, 1.0)],
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+
+use super::stock_rating::{ChartPattern, MarketTrend, StockRating};
+
+/// Conditions a symbol's latest rating must meet to be on a rule-built
+/// watchlist. Unset conditions match everything.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WatchlistRule {
+    pub max_age_days: Option<i64>,
+    pub min_score: Option<f64>,
+    pub max_score: Option<f64>,
+    pub min_confidence: Option<u8>,
+    pub bull_bear: Option<i8>,
+    #[serde(default)]
+    pub market_trends: Vec<MarketTrend>,       // Any of these
+    #[serde(default)]
+    pub chart_patterns: Vec<ChartPattern>,     // Any of these
+    pub sector_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum WatchlistItemSource {
+    Manual,
+    Rule,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchlistItem {
+    pub id: Option<i64>,
+    pub watchlist_id: i64,
+    pub symbol: String,
+    pub added_at: DateTime<Utc>,
+    pub source: WatchlistItemSource,
+    pub notes: Option<String>,
+    pub target_price: Option<f64>,
+    pub rating_id: Option<i64>,      // Rating that put the symbol on a rule-built list
+    pub analysis_id: Option<i64>,    // Set once promoted to a detailed analysis
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Watchlist {
+    pub id: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub name: String,
+    pub description: Option<String>,
+    pub rule: Option<WatchlistRule>,
+    #[serde(default)]
+    pub items: Vec<WatchlistItem>,
+}
+
+impl WatchlistRule {
+    pub fn matches(&self, rating: &StockRating, now: DateTime<Utc>) -> bool {
+        self.max_age_days.is_none_or(|days| rating.timestamp >= now - Duration::days(days))
+            && self.min_score.is_none_or(|min| rating.overall_score >= min)
+            && self.max_score.is_none_or(|max| rating.overall_score <= max)
+            && self.min_confidence.is_none_or(|min| rating.confidence >= min)
+            && self.bull_bear.is_none_or(|direction| rating.bull_bear == direction)
+            && (self.market_trends.is_empty() || self.market_trends.contains(&rating.market_trend))
+            && (self.chart_patterns.is_empty() || self.chart_patterns.contains(&rating.chart_pattern))
+            && self.sector_id.is_none_or(|sector_id| rating.sector_id == Some(sector_id))
+    }
+}
+
+impl Watchlist {
+    #[cfg(test)]
+    pub fn new(name: &str, rule: Option<WatchlistRule>) -> Self {
+        Self {
+            id: None,
+            timestamp: Utc::now(),
+            name: name.to_string(),
+            description: None,
+            rule,
+            items: Vec::new(),
+        }
+    }
+}
+
+impl WatchlistItem {
+    pub fn new(watchlist_id: i64, symbol: &str) -> Self {
+        Self {
+            id: None,
+            watchlist_id,
+            symbol: symbol.to_string(),
+            added_at: Utc::now(),
+            source: WatchlistItemSource::Manual,
+            notes: None,
+            target_price: None,
+            rating_id: None,
+            analysis_id: None,
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::Deserialize;
+use serde_json::to_string;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::models::account::{Account, AccountSummary, AccountType, OpenPosition};
+use crate::models::trade::TradeStatus;
+use crate::services::{detailed_analysis_service, portfolio_risk_service, settings_service, time_zone_service, trade_service};
+
+const ACCOUNT_COLUMNS: &str = "id, created_at, name, broker, base_currency, starting_balance, account_type";
+
+pub fn save_account(conn: &Connection, account: &mut Account) -> Result<i64, Box<dyn Error>> {
+    account.validate()?;
+    account.created_at = Utc::now();
+
+    conn.execute(
+        "INSERT INTO accounts (created_at, name, broker, base_currency, starting_balance, account_type)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            account.created_at.to_rfc3339(),
+            account.name,
+            account.broker,
+            account.base_currency,
+            account.starting_balance,
+            to_string(&account.account_type)?,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    account.id = Some(id);
+
+    Ok(id)
+}
+
+pub fn update_account(conn: &Connection, account: &Account) -> Result<(), Box<dyn Error>> {
+    account.validate()?;
+
+    conn.execute(
+        "UPDATE accounts
+        SET name = ?1, broker = ?2, base_currency = ?3, starting_balance = ?4, account_type = ?5
+        WHERE id = ?6",
+        params![
+            account.name,
+            account.broker,
+            account.base_currency,
+            account.starting_balance,
+            to_string(&account.account_type)?,
+            account.id,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Deletes an account nothing has been booked to yet.
+pub fn delete_account(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    let (trades, analyses): (i64, i64) = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM trades WHERE account_id = ?1),
+            (SELECT COUNT(*) FROM detailed_analyses WHERE account_id = ?1)",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if trades > 0 || analyses > 0 {
+        return Err(format!("Account has {} trades and {} analyses booked to it", trades, analyses).into());
+    }
+
+    conn.execute("DELETE FROM accounts WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+pub fn get_account(conn: &Connection, id: i64) -> Result<Account, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM accounts WHERE id = ?1", ACCOUNT_COLUMNS))?;
+
+    let account = stmt.query_row(params![id], account_from_row)?;
+
+    Ok(account)
+}
+
+pub fn get_accounts(conn: &Connection) -> Result<Vec<Account>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM accounts ORDER BY id", ACCOUNT_COLUMNS))?;
+
+    let accounts = stmt.query_map([], account_from_row)?.collect::<rusqlite::Result<_>>()?;
+
+    Ok(accounts)
+}
+
+/// Fails unless the account exists, for entries naming one.
+pub fn check_account_exists(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    let exists: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM accounts WHERE id = ?1)", params![id], |row| row.get(0))?;
+    if !exists {
+        return Err(format!("Account {} does not exist", id).into());
+    }
+
+    Ok(())
+}
+
+/// Equity and currency as the account settings held them before accounts
+/// existed.
+#[derive(Deserialize)]
+struct LegacyAccountSettings {
+    equity: Option<f64>,
+    currency: Option<String>,
+}
+
+/// The account entries go to when none is chosen: the first one created.
+/// When there are no accounts yet, one is made, carrying over the equity
+/// and currency once kept in the account settings.
+pub fn default_account_id(conn: &Connection) -> Result<i64, Box<dyn Error>> {
+    let id = conn.query_row("SELECT id FROM accounts ORDER BY id LIMIT 1", [], |row| row.get(0)).optional()?;
+
+    match id {
+        Some(id) => Ok(id),
+        None => {
+            let mut account = Account::new("Default", AccountType::Cash);
+            if let Some(legacy) = settings_service::get_setting::<LegacyAccountSettings>(conn, settings_service::ACCOUNT_KEY)? {
+                account.base_currency = legacy.currency.unwrap_or(account.base_currency);
+                account.starting_balance = legacy.equity.unwrap_or(account.starting_balance);
+            }
+            save_account(conn, &mut account)
+        }
+    }
+}
+
+/// Books trades and analyses stored before accounts existed to the default
+/// account.
+pub fn assign_default_account(conn: &Connection) -> Result<usize, Box<dyn Error>> {
+    let unassigned: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM trades WHERE account_id IS NULL)
+            + (SELECT COUNT(*) FROM detailed_analyses WHERE account_id IS NULL)",
+        [],
+        |row| row.get(0),
+    )?;
+    if unassigned == 0 {
+        return Ok(0);
+    }
+
+    let id = default_account_id(conn)?;
+    conn.execute("UPDATE detailed_analyses SET account_id = ?1 WHERE account_id IS NULL", params![id])?;
+    conn.execute("UPDATE trades SET account_id = ?1 WHERE account_id IS NULL", params![id])?;
+
+    Ok(unassigned as usize)
+}
+
+/// Balances, open positions and risk limit breaches of one account, or of
+/// all accounts together when `account_id` is None. Accounts in different
+/// currencies are not added up.
+pub fn get_account_summary(conn: &Connection, account_id: Option<i64>, now: DateTime<Utc>) -> Result<AccountSummary, Box<dyn Error>> {
+    let accounts = match account_id {
+        Some(id) => vec![get_account(conn, id)?],
+        None => get_accounts(conn)?,
+    };
+
+    let mut currencies: Vec<String> = accounts.iter().map(|account| account.base_currency.clone()).collect();
+    currencies.sort();
+    currencies.dedup();
+    let currency = match currencies.as_slice() {
+        [] => "USD".to_string(),
+        [currency] => currency.clone(),
+        _ => return Err(format!("Accounts are in {}; summarize them one at a time", currencies.join(", ")).into()),
+    };
+
+    let closed_status = to_string(&TradeStatus::Closed)?;
+    let mut stmt = conn.prepare(
+        "SELECT profit_loss, exit_time FROM trades
+        WHERE status = ?1 AND profit_loss IS NOT NULL AND (?2 IS NULL OR account_id = ?2)"
+    )?;
+    let closed = stmt.query_map(params![closed_status, account_id], |row| {
+        Ok((row.get::<_, f64>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+
+    let time_zones = time_zone_service::get_time_zone_settings(conn)?;
+    let today = time_zones.exchange_date(now);
+    let mut realized_pnl = 0.0;
+    let mut daily_pnl = 0.0;
+    for row in closed {
+        let (profit_loss, exit_time) = row?;
+        realized_pnl += profit_loss;
+        if let Some(exit_time) = exit_time {
+            let exit_time = DateTime::parse_from_rfc3339(&exit_time)?.with_timezone(&Utc);
+            if time_zones.exchange_date(exit_time) == today {
+                daily_pnl += profit_loss;
+            }
+        }
+    }
+
+    let open_trades = trade_service::get_open_trades(conn, account_id)?;
+    let mut positions = Vec::new();
+    for trade in &open_trades {
+        if let (Some(trade_id), Some(entry_value)) = (trade.id, trade.entry_value()) {
+            positions.push(OpenPosition {
+                trade_id,
+                symbol: trade.symbol.clone(),
+                entry_value,
+                risk_max: detailed_analysis_service::get_risk_max(conn, trade.analysis_id)?,
+            });
+        }
+    }
+
+    let starting_balance: f64 = accounts.iter().map(|account| account.starting_balance).sum();
+    let mut summary = AccountSummary {
+        account_id,
+        name: match account_id {
+            Some(_) => accounts[0].name.clone(),
+            None => "All accounts".to_string(),
+        },
+        currency,
+        starting_balance,
+        realized_pnl,
+        equity: starting_balance + realized_pnl,
+        daily_pnl,
+        open_positions: open_trades.len(),
+        planned_trades: trade_service::count_trades(conn, TradeStatus::Planned, account_id)?,
+        breaches: Vec::new(),
+    };
+
+    let risk = portfolio_risk_service::get_portfolio_risk_settings(conn)?;
+    summary.check_limits(&positions, &risk.limits);
+
+    Ok(summary)
+}
+
+fn account_from_row(row: &Row) -> rusqlite::Result<Account> {
+    let created_at_str: String = row.get(1)?;
+    let created_at = chrono::DateTime::parse_from_rfc3339(&created_at_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let account_type_json: String = row.get(6)?;
+    let account_type: AccountType = serde_json::from_str(&account_type_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(Account {
+        id: Some(row.get(0)?),
+        created_at,
+        name: row.get(2)?,
+        broker: row.get(3)?,
+        base_currency: row.get(4)?,
+        starting_balance: row.get(5)?,
+        account_type,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DetailedAnalysis, Trade};
+    use crate::services::{alert_service, db, detailed_analysis_service};
+
+    #[test]
+    fn mixed_currencies_are_not_consolidated() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        account_fixture(&conn, "Brokerage", "USD");
+        account_fixture(&conn, "ISA", "GBP");
+
+        assert!(get_account_summary(&conn, None, Utc::now()).is_err());
+        assert_eq!(get_account_summary(&conn, Some(2), Utc::now()).unwrap().currency, "GBP");
+    }
+
+    #[test]
+    fn summary_counts_planned_trades_of_the_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let first = account_fixture(&conn, "Brokerage", "USD");
+        let second = account_fixture(&conn, "Paper", "USD");
+
+        for account_id in [first, first, second] {
+            let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+            analysis.account_id = Some(account_id);
+            let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+            trade_service::save_trade(&conn, &mut Trade::new("AAPL", analysis_id)).unwrap();
+        }
+
+        assert_eq!(get_account_summary(&conn, Some(first), Utc::now()).unwrap().planned_trades, 2);
+        assert_eq!(get_account_summary(&conn, None, Utc::now()).unwrap().planned_trades, 3);
+    }
+
+    #[test]
+    fn alerts_are_scoped_by_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let first = account_fixture(&conn, "Brokerage", "USD");
+        let second = account_fixture(&conn, "Paper", "USD");
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.account_id = Some(second);
+        analysis.alerts = vec!["above 200".to_string()];
+        detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        assert_eq!(alert_service::get_alerts(&conn, None, Some(second)).unwrap().len(), 1);
+        assert!(alert_service::get_alerts(&conn, None, Some(first)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn analyses_need_an_existing_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.account_id = Some(42);
+        assert!(detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).is_err());
+    }
+
+    #[test]
+    fn the_default_account_carries_over_the_old_account_settings() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let legacy = serde_json::json!({ "equity": 25_000.0, "currency": "EUR", "fees": { "per_trade": 1.0, "per_share": 0.0, "per_contract": 0.65 } });
+        settings_service::set_setting(&conn, settings_service::ACCOUNT_KEY, &legacy).unwrap();
+
+        let account = get_account(&conn, default_account_id(&conn).unwrap()).unwrap();
+        assert_eq!(account.base_currency, "EUR");
+        assert_eq!(account.starting_balance, 25_000.0);
+    }
+
+    fn account_fixture(conn: &Connection, name: &str, currency: &str) -> i64 {
+        let mut account = Account::new(name, AccountType::Cash);
+        account.base_currency = currency.to_string();
+        save_account(conn, &mut account).unwrap()
+    }
+}
@@ -0,0 +1,405 @@
+use rusqlite::{Connection, Row, params};
+use serde_json::to_string;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::models::alert::{Alert, AlertCondition, AlertContext, AlertStatus, AlertTrigger};
+use crate::models::calendar::TradingCalendar;
+use crate::models::detailed_analysis::DetailedAnalysis;
+use crate::models::price_bar::PriceBar;
+use crate::services::{calendar_service, detailed_analysis_service, indicator_service, price_service, settings_service};
+
+const ALERT_COLUMNS: &str =
+    "id, created_at, symbol, analysis_id, condition, message, source_text, status, last_price, triggered_at";
+
+const TRIGGER_COLUMNS: &str = "id, alert_id, symbol, triggered_at, price, title, message";
+
+pub fn save_alert(conn: &Connection, alert: &mut Alert) -> Result<i64, Box<dyn Error>> {
+    alert.symbol = alert.symbol.trim().to_ascii_uppercase();
+    alert.created_at = Utc::now();
+
+    conn.execute(
+        "INSERT INTO alerts
+        (created_at, symbol, analysis_id, condition, message, source_text, status, last_price, triggered_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            alert.created_at.to_rfc3339(),
+            alert.symbol,
+            alert.analysis_id,
+            to_string(&alert.condition)?,
+            alert.message,
+            alert.source_text,
+            to_string(&alert.status)?,
+            alert.last_price,
+            alert.triggered_at.map(|dt| dt.to_rfc3339()),
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    alert.id = Some(id);
+
+    Ok(id)
+}
+
+/// Stores changes to an alert. Setting it back to Active re-arms it.
+pub fn update_alert(conn: &Connection, alert: &Alert) -> Result<(), Box<dyn Error>> {
+    let triggered_at = match alert.status {
+        AlertStatus::Active => None,
+        _ => alert.triggered_at,
+    };
+
+    conn.execute(
+        "UPDATE alerts
+        SET symbol = ?1, analysis_id = ?2, condition = ?3, message = ?4, status = ?5, last_price = ?6,
+        triggered_at = ?7
+        WHERE id = ?8",
+        params![
+            alert.symbol.trim().to_ascii_uppercase(),
+            alert.analysis_id,
+            to_string(&alert.condition)?,
+            alert.message,
+            to_string(&alert.status)?,
+            alert.last_price,
+            triggered_at.map(|dt| dt.to_rfc3339()),
+            alert.id,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_alert(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM alert_triggers WHERE alert_id = ?1", params![id])?;
+    conn.execute("DELETE FROM alerts WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+pub fn get_alert(conn: &Connection, id: i64) -> Result<Alert, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM alerts WHERE id = ?1", ALERT_COLUMNS))?;
+
+    Ok(stmt.query_row(params![id], alert_from_row)?)
+}
+
+/// Alerts with the given status, or all of them, newest first. With an
+/// account, only alerts on that account's analyses.
+pub fn get_alerts(conn: &Connection, status: Option<AlertStatus>, account_id: Option<i64>) -> Result<Vec<Alert>, Box<dyn Error>> {
+    let status_json = status.map(|s| to_string(&s)).transpose()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM alerts
+        WHERE (?1 IS NULL OR status = ?1)
+            AND (?2 IS NULL OR analysis_id IN (SELECT id FROM detailed_analyses WHERE account_id = ?2))
+        ORDER BY created_at DESC, id DESC",
+        ALERT_COLUMNS
+    ))?;
+
+    let alerts_iter = stmt.query_map(params![status_json, account_id], alert_from_row)?;
+
+    let mut alerts = Vec::new();
+    for alert in alerts_iter {
+        alerts.push(alert?);
+    }
+
+    Ok(alerts)
+}
+
+pub fn get_alerts_by_analysis(conn: &Connection, analysis_id: i64) -> Result<Vec<Alert>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM alerts WHERE analysis_id = ?1 ORDER BY created_at, id",
+        ALERT_COLUMNS
+    ))?;
+
+    let alerts_iter = stmt.query_map(params![analysis_id], alert_from_row)?;
+
+    let mut alerts = Vec::new();
+    for alert in alerts_iter {
+        alerts.push(alert?);
+    }
+
+    Ok(alerts)
+}
+
+/// Latest triggers, of alerts on one account's analyses or of all alerts.
+pub fn get_alert_triggers(conn: &Connection, limit: i64, account_id: Option<i64>) -> Result<Vec<AlertTrigger>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM alert_triggers
+        WHERE ?2 IS NULL OR alert_id IN (
+            SELECT alerts.id FROM alerts
+            JOIN detailed_analyses ON detailed_analyses.id = alerts.analysis_id
+            WHERE detailed_analyses.account_id = ?2
+        )
+        ORDER BY triggered_at DESC, id DESC LIMIT ?1",
+        TRIGGER_COLUMNS
+    ))?;
+
+    let triggers_iter = stmt.query_map(params![limit, account_id], trigger_from_row)?;
+
+    let mut triggers = Vec::new();
+    for trigger in triggers_iter {
+        triggers.push(trigger?);
+    }
+
+    Ok(triggers)
+}
+
+/// Checks the active alerts, of one symbol or all, against the latest stored
+/// close. Alerts that fire are marked Triggered and recorded; every alert
+/// remembers the price it saw so the next run can detect crossings. Price
+/// alerts are left alone while their exchange is closed.
+pub fn evaluate_alerts(conn: &Connection, symbol: Option<&str>, now: DateTime<Utc>) -> Result<Vec<AlertTrigger>, Box<dyn Error>> {
+    let symbol = symbol.map(|s| s.trim().to_ascii_uppercase());
+    let alerts: Vec<Alert> = get_alerts(conn, Some(AlertStatus::Active), None)?
+        .into_iter()
+        .filter(|alert| symbol.as_ref().is_none_or(|s| *s == alert.symbol))
+        .collect();
+
+    let mut bars: HashMap<String, Option<PriceBar>> = HashMap::new();
+    let mut calendars: HashMap<String, TradingCalendar> = HashMap::new();
+    let mut analyses: HashMap<i64, DetailedAnalysis> = HashMap::new();
+    let mut triggers = Vec::new();
+
+    for mut alert in alerts {
+        if !calendars.contains_key(&alert.symbol) {
+            calendars.insert(alert.symbol.clone(), calendar_service::calendar_for_symbol(conn, &alert.symbol)?);
+        }
+        let calendar = &calendars[&alert.symbol];
+        if alert.condition.needs_session() && !calendar.is_open(now) {
+            continue;
+        }
+        // Expiries are dates of the exchange the symbol trades on
+        let today = calendar.local_date(now);
+
+        if !bars.contains_key(&alert.symbol) {
+            bars.insert(alert.symbol.clone(), price_service::get_latest_bar(conn, &alert.symbol)?);
+        }
+        let bar = bars[&alert.symbol].as_ref();
+        let price = bar.map(|bar| bar.close);
+
+        if let Some(analysis_id) = alert.analysis_id {
+            if let Entry::Vacant(entry) = analyses.entry(analysis_id) {
+                entry.insert(detailed_analysis_service::get_detailed_analysis(conn, analysis_id)?);
+            }
+        }
+
+        let indicator_value = match &alert.condition {
+            AlertCondition::Indicator { line, .. } => indicator_service::get_indicator_value(conn, &alert.symbol, line)?,
+            _ => None,
+        };
+
+        let context = AlertContext {
+            now,
+            today,
+            price,
+            previous_price: alert.last_price,
+            low: bar.map(|bar| bar.low),
+            high: bar.map(|bar| bar.high),
+            analysis: alert.analysis_id.and_then(|id| analyses.get(&id)),
+            indicator_value,
+        };
+
+        if alert.condition.is_met(&context) {
+            alert.status = AlertStatus::Triggered;
+            alert.triggered_at = Some(now);
+
+            let mut trigger = AlertTrigger {
+                id: None,
+                alert_id: alert.id.unwrap_or_default(),
+                symbol: alert.symbol.clone(),
+                triggered_at: now,
+                price,
+                title: alert.title(),
+                message: alert.message.clone(),
+            };
+            save_alert_trigger(conn, &mut trigger)?;
+            triggers.push(trigger);
+        }
+
+        if price.is_some() {
+            alert.last_price = price;
+        }
+        update_alert(conn, &alert)?;
+    }
+
+    Ok(triggers)
+}
+
+/// Creates structured alerts for the free-text alerts of an analysis that can
+/// be parsed and are not in `converted`, the texts converted before. Alerts
+/// the user deleted since are not brought back. Returns the number created.
+pub fn sync_analysis_alerts(conn: &Connection, analysis: &DetailedAnalysis, converted: &[String]) -> Result<usize, Box<dyn Error>> {
+    let analysis_id = match analysis.id {
+        Some(id) => id,
+        None => return Ok(0),
+    };
+
+    let reference_price = if analysis.entry_price > 0.0 {
+        Some(analysis.entry_price)
+    } else {
+        price_service::get_latest_close(conn, &analysis.security)?
+    };
+
+    let mut created = 0;
+    for text in &analysis.alerts {
+        if converted.contains(text) {
+            continue;
+        }
+
+        if let Some(condition) = AlertCondition::parse(text, reference_price) {
+            let mut alert = Alert::new(&analysis.security, condition);
+            alert.analysis_id = Some(analysis_id);
+            alert.source_text = Some(text.clone());
+            save_alert(conn, &mut alert)?;
+            created += 1;
+        }
+    }
+
+    Ok(created)
+}
+
+/// Converts the free-text alerts of every stored analysis, once.
+pub fn parse_legacy_alerts(conn: &Connection) -> Result<usize, Box<dyn Error>> {
+    if settings_service::get_setting::<bool>(conn, settings_service::LEGACY_ALERTS_KEY)? == Some(true) {
+        return Ok(0);
+    }
+
+    let ids: Vec<i64> = {
+        let mut stmt = conn.prepare("SELECT id FROM detailed_analyses WHERE alerts <> '[]' ORDER BY id")?;
+        let ids_iter = stmt.query_map([], |row| row.get(0))?;
+        ids_iter.collect::<rusqlite::Result<_>>()?
+    };
+
+    let mut created = 0;
+    for id in ids {
+        let analysis = detailed_analysis_service::get_detailed_analysis(conn, id)?;
+        let converted: Vec<String> = get_alerts_by_analysis(conn, id)?
+            .into_iter()
+            .filter_map(|alert| alert.source_text)
+            .collect();
+        created += sync_analysis_alerts(conn, &analysis, &converted)?;
+    }
+    settings_service::set_setting(conn, settings_service::LEGACY_ALERTS_KEY, &true)?;
+
+    Ok(created)
+}
+
+fn save_alert_trigger(conn: &Connection, trigger: &mut AlertTrigger) -> Result<i64, Box<dyn Error>> {
+    conn.execute(
+        "INSERT INTO alert_triggers (alert_id, symbol, triggered_at, price, title, message)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            trigger.alert_id,
+            trigger.symbol,
+            trigger.triggered_at.to_rfc3339(),
+            trigger.price,
+            trigger.title,
+            trigger.message,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    trigger.id = Some(id);
+
+    Ok(id)
+}
+
+fn alert_from_row(row: &Row) -> rusqlite::Result<Alert> {
+    let created_at_str: String = row.get(1)?;
+    let created_at = chrono::DateTime::parse_from_rfc3339(&created_at_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let condition_json: String = row.get(4)?;
+    let condition = serde_json::from_str(&condition_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let status_json: String = row.get(7)?;
+    let status = serde_json::from_str(&status_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(7, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let triggered_at_str: Option<String> = row.get(9)?;
+    let triggered_at = triggered_at_str
+        .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(9, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(Alert {
+        id: Some(row.get(0)?),
+        created_at,
+        symbol: row.get(2)?,
+        analysis_id: row.get(3)?,
+        condition,
+        message: row.get(5)?,
+        source_text: row.get(6)?,
+        status,
+        last_price: row.get(8)?,
+        triggered_at,
+    })
+}
+
+fn trigger_from_row(row: &Row) -> rusqlite::Result<AlertTrigger> {
+    let triggered_at_str: String = row.get(3)?;
+    let triggered_at = chrono::DateTime::parse_from_rfc3339(&triggered_at_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    Ok(AlertTrigger {
+        id: Some(row.get(0)?),
+        alert_id: row.get(1)?,
+        symbol: row.get(2)?,
+        triggered_at,
+        price: row.get(4)?,
+        title: row.get(5)?,
+        message: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::db;
+
+    #[test]
+    fn deleted_alerts_stay_deleted() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.alerts = vec!["above 150".to_string()];
+        let id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        let alerts = get_alerts_by_analysis(&conn, id).unwrap();
+        assert_eq!(alerts.len(), 1);
+        delete_alert(&conn, alerts[0].id.unwrap()).unwrap();
+
+        analysis.alerts.push("below 120".to_string());
+        detailed_analysis_service::update_detailed_analysis(&conn, &mut analysis).unwrap();
+        db::initialize_database(&conn).unwrap();
+
+        let alerts = get_alerts_by_analysis(&conn, id).unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].condition, AlertCondition::CrossesBelow { level: 120.0 });
+    }
+
+    #[test]
+    fn days_to_expiry_counts_exchange_dates() {
+        use chrono::{NaiveDate, TimeZone};
+        use crate::analytics::black_scholes::OptionType;
+        use crate::models::option_leg::{LegSide, OptionLeg};
+
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        analysis.legs = vec![OptionLeg::new("AAPL", expiry, 200.0, OptionType::Call, LegSide::Buy)];
+        analysis.alerts = vec!["6 DTE".to_string()];
+        detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        // Friday evening in New York is seven days out, though six in UTC
+        let friday_evening = Utc.with_ymd_and_hms(2024, 6, 15, 2, 0, 0).unwrap();
+        assert!(evaluate_alerts(&conn, None, friday_evening).unwrap().is_empty());
+
+        let saturday = Utc.with_ymd_and_hms(2024, 6, 15, 14, 0, 0).unwrap();
+        assert_eq!(evaluate_alerts(&conn, None, saturday).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+use rusqlite::{Connection, params};
+use serde_json::Value;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::models::app_settings::{AppSettings, SettingsChange, StorageSettings};
+use crate::services::{db, portfolio_risk_service, scoring_service, settings_service, time_zone_service};
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// The database inside the data directory.
+pub const DATABASE_FILE: &str = "stock_dashboard.db";
+
+/// The settings file in the platform's config directory.
+pub fn settings_file_path() -> Result<PathBuf, Box<dyn Error>> {
+    let dirs = directories::ProjectDirs::from("com", "stocktrading", "dashboard")
+        .ok_or("No home directory to keep settings in")?;
+
+    Ok(dirs.config_dir().join(SETTINGS_FILE))
+}
+
+/// Reads the settings file, with defaults when it does not exist yet.
+pub fn load_storage_settings(settings_file: &Path) -> Result<StorageSettings, Box<dyn Error>> {
+    if !settings_file.exists() {
+        return Ok(StorageSettings::default());
+    }
+
+    let text = std::fs::read_to_string(settings_file)?;
+    Ok(toml::from_str(&text).map_err(|e| format!("{}: {}", settings_file.display(), e))?)
+}
+
+pub fn save_storage_settings(settings_file: &Path, settings: &StorageSettings) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = settings_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(settings_file, toml::to_string_pretty(settings)?)?;
+
+    Ok(())
+}
+
+pub fn get_app_settings(conn: &Connection, settings_file: &Path) -> Result<AppSettings, Box<dyn Error>> {
+    settings_with_storage(conn, load_storage_settings(settings_file)?)
+}
+
+/// Validates and stores the sections that changed. A changed scoring model
+/// version is activated; weights are only edited as scoring models. A new
+/// data directory gets a copy of the database unless it already holds one,
+/// which is then used from the next start; `default_data_dir` is the one
+/// used when none is set. A settings file that cannot be read is replaced.
+pub fn save_app_settings(
+    conn: &Connection,
+    settings_file: &Path,
+    default_data_dir: &Path,
+    settings: &AppSettings,
+) -> Result<SettingsChange, Box<dyn Error>> {
+    settings.validate()?;
+
+    let stored_storage = load_storage_settings(settings_file);
+    let storage_readable = stored_storage.is_ok();
+    let current = settings_with_storage(conn, stored_storage.unwrap_or_default())?;
+    let sections = settings.changed_sections(&current);
+
+    // The database sections are written together; the settings file and the
+    // database copy follow only once they are committed.
+    db::in_transaction(conn, || {
+        if settings.account != current.account {
+            settings_service::set_setting(conn, settings_service::ACCOUNT_KEY, &settings.account)?;
+        }
+        if settings.risk != current.risk {
+            portfolio_risk_service::save_portfolio_risk_settings(conn, &settings.risk)?;
+        }
+        if settings.time_zones != current.time_zones {
+            time_zone_service::save_time_zone_settings(conn, &settings.time_zones)?;
+        }
+        if settings.scoring_model_version != current.scoring_model_version {
+            if let Some(version) = settings.scoring_model_version {
+                scoring_service::set_active_scoring_model(conn, version)?;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    if settings.storage != current.storage {
+        let data_dir = settings.storage.data_directory.as_deref().unwrap_or(default_data_dir);
+        copy_database_to(conn, data_dir)?;
+    }
+    if settings.storage != current.storage || !storage_readable {
+        save_storage_settings(settings_file, &settings.storage)?;
+    }
+
+    Ok(SettingsChange {
+        restart_required: settings.storage != current.storage,
+        sections,
+        settings: settings.clone(),
+    })
+}
+
+fn settings_with_storage(conn: &Connection, storage: StorageSettings) -> Result<AppSettings, Box<dyn Error>> {
+    Ok(AppSettings {
+        storage,
+        account: settings_service::get_setting_or_default(conn, settings_service::ACCOUNT_KEY)?,
+        risk: portfolio_risk_service::get_portfolio_risk_settings(conn)?,
+        time_zones: time_zone_service::get_time_zone_settings(conn)?,
+        scoring_model_version: scoring_service::get_active_scoring_model(conn)?.version,
+    })
+}
+
+/// Copies the open database into `data_dir`, leaving a database already
+/// there alone.
+fn copy_database_to(conn: &Connection, data_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let path = data_dir.join(DATABASE_FILE);
+    if path.exists() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(data_dir)?;
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+
+    Ok(())
+}
+
+/// One setting by dotted key, such as "risk.max_open_positions".
+pub fn get_setting_value(conn: &Connection, settings_file: &Path, key: &str) -> Result<Value, Box<dyn Error>> {
+    let settings = serde_json::to_value(get_app_settings(conn, settings_file)?)?;
+
+    Ok(settings.pointer(&json_pointer(key)).cloned().ok_or_else(|| format!("Unknown setting {}", key))?)
+}
+
+/// Changes one setting by dotted key. The value must have the setting's type.
+pub fn set_setting_value(
+    conn: &Connection,
+    settings_file: &Path,
+    default_data_dir: &Path,
+    key: &str,
+    value: Value,
+) -> Result<SettingsChange, Box<dyn Error>> {
+    let storage = load_storage_settings(settings_file).unwrap_or_default();
+    let mut settings = serde_json::to_value(settings_with_storage(conn, storage)?)?;
+    let slot = settings.pointer_mut(&json_pointer(key)).ok_or_else(|| format!("Unknown setting {}", key))?;
+    *slot = value;
+
+    let settings: AppSettings = serde_json::from_value(settings)
+        .map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+    save_app_settings(conn, settings_file, default_data_dir, &settings)
+}
+
+fn json_pointer(key: &str) -> String {
+    key.split('.').map(|part| format!("/{}", part)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn a_broken_settings_file_can_be_replaced() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let dir = temp_dir("settings-broken");
+        let file = dir.join(SETTINGS_FILE);
+        std::fs::write(&file, "data_directory = [").unwrap();
+
+        assert!(get_app_settings(&conn, &file).is_err());
+        let mut settings = AppSettings::default();
+        settings.account.fees.per_trade = 1.0;
+        save_app_settings(&conn, &file, &dir, &settings).unwrap();
+        settings.scoring_model_version = Some(1);
+        assert_eq!(get_app_settings(&conn, &file).unwrap(), settings);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_new_data_directory_gets_the_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let dir = temp_dir("settings-move");
+        let file = dir.join(SETTINGS_FILE);
+
+        let existing = dir.join("existing");
+        std::fs::create_dir_all(&existing).unwrap();
+        std::fs::write(existing.join(DATABASE_FILE), "kept").unwrap();
+        let mut settings = AppSettings::default();
+        settings.storage.data_directory = Some(existing.clone());
+        save_app_settings(&conn, &file, &dir, &settings).unwrap();
+        assert_eq!(std::fs::read_to_string(existing.join(DATABASE_FILE)).unwrap(), "kept");
+
+        let fresh = dir.join("fresh");
+        settings.storage.data_directory = Some(fresh.clone());
+        save_app_settings(&conn, &file, &dir, &settings).unwrap();
+        let copy = Connection::open(fresh.join(DATABASE_FILE)).unwrap();
+        let jobs: i64 = copy.query_row("SELECT COUNT(*) FROM scheduled_jobs", [], |row| row.get(0)).unwrap();
+        assert!(jobs > 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_section_saves_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let dir = temp_dir("settings-failed");
+        let file = dir.join(SETTINGS_FILE);
+        let before = get_app_settings(&conn, &file).unwrap();
+
+        let mut settings = before.clone();
+        settings.risk.limits.max_open_positions = 5;
+        settings.storage.data_directory = Some(dir.join("data"));
+        settings.scoring_model_version = Some(99);
+        assert!(save_app_settings(&conn, &file, &dir, &settings).is_err());
+
+        assert_eq!(get_app_settings(&conn, &file).unwrap(), before);
+        assert!(!file.exists());
+        assert!(!dir.join("data").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
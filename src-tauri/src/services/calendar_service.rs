@@ -0,0 +1,192 @@
+use rusqlite::{Connection, params};
+use serde_json::to_string;
+use std::error::Error;
+use std::path::Path;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::models::calendar::{CalendarLoad, HoldingPeriod, TradingCalendar, TradingSession};
+use crate::services::{security_service, trade_service};
+
+/// Calendar used for symbols without a known exchange.
+pub const DEFAULT_EXCHANGE: &str = "NYSE";
+
+/// NYSE and NASDAQ sessions shipped with the app. A calendar file for the
+/// same exchange replaces it.
+const BUILT_IN_CALENDAR: &str = include_str!("../../calendars/xnys.json");
+
+pub fn built_in_calendar() -> Result<TradingCalendar, Box<dyn Error>> {
+    Ok(serde_json::from_str(BUILT_IN_CALENDAR)?)
+}
+
+/// Reads a calendar file and stores it, replacing any calendar of the same
+/// exchange.
+pub fn import_calendar_file(conn: &Connection, path: &Path) -> Result<TradingCalendar, Box<dyn Error>> {
+    let calendar: TradingCalendar = serde_json::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    save_calendar(conn, &calendar).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(calendar)
+}
+
+/// Imports every .json calendar file in `directory`. A file that cannot be
+/// read or is invalid is left out and reported, and the others still load.
+pub fn load_calendar_directory(conn: &Connection, directory: &Path) -> Result<CalendarLoad, Box<dyn Error>> {
+    let mut load = CalendarLoad::default();
+    if !directory.is_dir() {
+        return Ok(load);
+    }
+
+    let mut paths: Vec<_> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+        .collect();
+    paths.sort();
+
+    for path in &paths {
+        match import_calendar_file(conn, path) {
+            Ok(_) => load.loaded += 1,
+            Err(e) => load.failed.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    Ok(load)
+}
+
+pub fn save_calendar(conn: &Connection, calendar: &TradingCalendar) -> Result<(), Box<dyn Error>> {
+    calendar.validate()?;
+
+    conn.execute(
+        "INSERT INTO trading_calendars (exchange, definition, updated_at) VALUES (?1, ?2, ?3)
+        ON CONFLICT(exchange) DO UPDATE SET definition = excluded.definition, updated_at = excluded.updated_at",
+        params![
+            calendar.exchange.trim().to_ascii_uppercase(),
+            to_string(calendar)?,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_calendar(conn: &Connection, exchange: &str) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "DELETE FROM trading_calendars WHERE exchange = ?1",
+        params![exchange.trim().to_ascii_uppercase()],
+    )?;
+
+    Ok(())
+}
+
+/// Stored calendars, plus the built-in one unless a stored calendar covers
+/// its exchange.
+pub fn get_calendars(conn: &Connection) -> Result<Vec<TradingCalendar>, Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT definition FROM trading_calendars ORDER BY exchange")?;
+    let definitions = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+    let mut calendars = Vec::new();
+    for definition in definitions {
+        calendars.push(serde_json::from_str::<TradingCalendar>(&definition?)?);
+    }
+
+    let built_in = built_in_calendar()?;
+    if !calendars.iter().any(|calendar| calendar.covers(&built_in.exchange)) {
+        calendars.push(built_in);
+    }
+
+    Ok(calendars)
+}
+
+/// The calendar covering `exchange`, by code or alias.
+pub fn get_calendar(conn: &Connection, exchange: &str) -> Result<Option<TradingCalendar>, Box<dyn Error>> {
+    Ok(get_calendars(conn)?.into_iter().find(|calendar| calendar.covers(exchange)))
+}
+
+/// The calendar of the exchange `symbol` is listed on in the symbol master,
+/// falling back to the default exchange.
+pub fn calendar_for_symbol(conn: &Connection, symbol: &str) -> Result<TradingCalendar, Box<dyn Error>> {
+    let exchange = security_service::get_security(conn, symbol)?.and_then(|security| security.exchange);
+    if let Some(calendar) = exchange.map(|exchange| get_calendar(conn, &exchange)).transpose()?.flatten() {
+        return Ok(calendar);
+    }
+
+    Ok(get_calendar(conn, DEFAULT_EXCHANGE)?.ok_or("No calendar for the default exchange")?)
+}
+
+pub fn get_session(conn: &Connection, exchange: &str, date: NaiveDate) -> Result<Option<TradingSession>, Box<dyn Error>> {
+    let calendar = get_calendar(conn, exchange)?.ok_or_else(|| format!("No calendar for {}", exchange))?;
+
+    Ok(calendar.session(date))
+}
+
+pub fn is_market_open(conn: &Connection, symbol: &str, at: DateTime<Utc>) -> Result<bool, Box<dyn Error>> {
+    Ok(calendar_for_symbol(conn, symbol)?.is_open(at))
+}
+
+/// The next session of the exchange `symbol` trades on, for showing when a
+/// closed market reopens.
+pub fn next_session_for(conn: &Connection, symbol: &str, after: DateTime<Utc>) -> Result<Option<TradingSession>, Box<dyn Error>> {
+    Ok(calendar_for_symbol(conn, symbol)?.next_session(after))
+}
+
+pub fn trading_day_for(conn: &Connection, symbol: &str, at: DateTime<Utc>) -> Result<NaiveDate, Box<dyn Error>> {
+    Ok(calendar_for_symbol(conn, symbol)?.trading_day(at))
+}
+
+/// Holding period of an entered trade in trading days of its exchange. None
+/// for trades that were never entered.
+pub fn get_holding_period(conn: &Connection, trade_id: i64, now: DateTime<Utc>) -> Result<Option<HoldingPeriod>, Box<dyn Error>> {
+    let trade = trade_service::get_trade(conn, trade_id)?;
+    let entry_time = match trade.entry_time {
+        Some(entry_time) => entry_time,
+        None => return Ok(None),
+    };
+
+    let calendar = calendar_for_symbol(conn, &trade.symbol)?;
+    let entry_day = calendar.trading_day(entry_time);
+    let exit_day = calendar.trading_day(trade.exit_time.unwrap_or(now));
+
+    Ok(Some(HoldingPeriod {
+        trade_id,
+        exchange: calendar.exchange.clone(),
+        entry_day,
+        exit_day,
+        calendar_days: (exit_day - entry_day).num_days(),
+        trading_days: calendar.trading_days_between(entry_day, exit_day),
+        open: trade.exit_time.is_none(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::db;
+
+    #[test]
+    fn bad_calendar_files_are_skipped_and_reported() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let directory = std::env::temp_dir().join(format!("calendar-load-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("a-broken.json"), "{").unwrap();
+        std::fs::write(
+            directory.join("b-xlon.json"),
+            r#"{"exchange":"LSE","timezone":"Europe/London","open":"08:00:00","close":"16:30:00"}"#,
+        ).unwrap();
+
+        let load = load_calendar_directory(&conn, &directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(load.loaded, 1);
+        assert_eq!(load.failed.len(), 1);
+        assert!(load.failed[0].contains("a-broken.json"));
+        assert!(get_calendar(&conn, "LSE").unwrap().is_some());
+    }
+
+    #[test]
+    fn built_in_calendar_states_its_coverage() {
+        let calendar = built_in_calendar().unwrap();
+
+        assert!(calendar.validate().is_ok());
+        assert!(calendar.coverage.is_some());
+    }
+}
@@ -0,0 +1,340 @@
+use rusqlite::{Connection, Row, params};
+use serde_json::to_string;
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::models::checklist::{
+    ChecklistContext, ChecklistEnforcement, ChecklistItemKind, ChecklistRule, ChecklistTemplate,
+    CompletedChecklist, TradeGate,
+};
+use crate::services::{detailed_analysis_service, indicator_service, psychological_service, stock_rating_service};
+
+pub fn save_checklist_template(conn: &Connection, template: &mut ChecklistTemplate) -> Result<i64, Box<dyn Error>> {
+    template.timestamp = Utc::now();
+
+    let enforcement_json = to_string(&template.enforcement)?;
+    let items_json = to_string(&template.items)?;
+
+    conn.execute(
+        "INSERT INTO checklist_templates (timestamp, name, strategy, enforcement, items)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            template.timestamp.to_rfc3339(),
+            template.name,
+            template.strategy,
+            enforcement_json,
+            items_json,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    template.id = Some(id);
+
+    Ok(id)
+}
+
+pub fn update_checklist_template(conn: &Connection, template: &ChecklistTemplate) -> Result<(), Box<dyn Error>> {
+    let enforcement_json = to_string(&template.enforcement)?;
+    let items_json = to_string(&template.items)?;
+
+    conn.execute(
+        "UPDATE checklist_templates
+        SET name = ?1, strategy = ?2, enforcement = ?3, items = ?4
+        WHERE id = ?5",
+        params![
+            template.name,
+            template.strategy,
+            enforcement_json,
+            items_json,
+            template.id,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_checklist_template(conn: &Connection, id: i64) -> Result<ChecklistTemplate, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, name, strategy, enforcement, items
+        FROM checklist_templates
+        WHERE id = ?1"
+    )?;
+
+    let template = stmt.query_row(params![id], template_from_row)?;
+
+    Ok(template)
+}
+
+pub fn get_checklist_templates(conn: &Connection) -> Result<Vec<ChecklistTemplate>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, name, strategy, enforcement, items
+        FROM checklist_templates
+        ORDER BY name"
+    )?;
+
+    let templates_iter = stmt.query_map([], template_from_row)?;
+
+    let mut templates = Vec::new();
+    for template in templates_iter {
+        templates.push(template?);
+    }
+
+    Ok(templates)
+}
+
+pub fn get_checklist_templates_for_strategy(conn: &Connection, strategy: &str) -> Result<Vec<ChecklistTemplate>, Box<dyn Error>> {
+    let templates = get_checklist_templates(conn)?;
+
+    Ok(templates.into_iter().filter(|t| t.applies_to(strategy)).collect())
+}
+
+/// Evaluates a template against an analysis, the latest rating of its security
+/// the latest psychological state and the indicators the template refers to,
+/// and stores the result with the analysis.
+pub fn complete_checklist(
+    conn: &Connection,
+    analysis_id: i64,
+    template_id: i64,
+    manual_answers: &HashMap<String, bool>,
+) -> Result<CompletedChecklist, Box<dyn Error>> {
+    let analysis = detailed_analysis_service::get_detailed_analysis(conn, analysis_id)?;
+    let template = get_checklist_template(conn, template_id)?;
+    let rating = stock_rating_service::get_stock_ratings_by_symbol(conn, &analysis.security)?
+        .into_iter()
+        .next();
+    let psychological_state = psychological_service::get_recent_psychological_states(conn, 1)?
+        .into_iter()
+        .next();
+
+    let mut indicator_values = HashMap::new();
+    for item in &template.items {
+        if let ChecklistItemKind::Rule(ChecklistRule::Indicator { line, .. }) = &item.kind {
+            if let Some(value) = indicator_service::get_indicator_value(conn, &analysis.security, line)? {
+                indicator_values.insert(line.key(), value);
+            }
+        }
+    }
+
+    let context = ChecklistContext {
+        analysis: &analysis,
+        rating: rating.as_ref(),
+        psychological_state: psychological_state.as_ref(),
+        indicator_values,
+    };
+
+    let mut checklist = CompletedChecklist::evaluate(analysis_id, &template, &context, manual_answers);
+    save_completed_checklist(conn, &mut checklist)?;
+
+    Ok(checklist)
+}
+
+pub fn save_completed_checklist(conn: &Connection, checklist: &mut CompletedChecklist) -> Result<i64, Box<dyn Error>> {
+    checklist.timestamp = Utc::now();
+
+    let enforcement_json = to_string(&checklist.enforcement)?;
+    let results_json = to_string(&checklist.results)?;
+
+    conn.execute(
+        "INSERT INTO analysis_checklists (analysis_id, template_id, timestamp, enforcement, results, passed)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            checklist.analysis_id,
+            checklist.template_id,
+            checklist.timestamp.to_rfc3339(),
+            enforcement_json,
+            results_json,
+            checklist.passed,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    checklist.id = Some(id);
+
+    Ok(id)
+}
+
+pub fn get_checklists_by_analysis(conn: &Connection, analysis_id: i64) -> Result<Vec<CompletedChecklist>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, analysis_id, template_id, timestamp, enforcement, results, passed
+        FROM analysis_checklists
+        WHERE analysis_id = ?1
+        ORDER BY timestamp DESC"
+    )?;
+
+    let checklists_iter = stmt.query_map(params![analysis_id], completed_checklist_from_row)?;
+
+    let mut checklists = Vec::new();
+    for checklist in checklists_iter {
+        checklists.push(checklist?);
+    }
+
+    Ok(checklists)
+}
+
+/// Decides whether a trade may be opened for an analysis. Only the most recent
+/// completion of each template counts, so re-running a checklist can clear it.
+/// Templates for the analysis's strategy that were never completed count as
+/// failed.
+pub fn check_trade_gate(conn: &Connection, analysis_id: i64) -> Result<TradeGate, Box<dyn Error>> {
+    let analysis = detailed_analysis_service::get_detailed_analysis(conn, analysis_id)?;
+    let checklists = get_checklists_by_analysis(conn, analysis_id)?;
+
+    let mut seen_templates = Vec::new();
+    let mut blocked = Vec::new();
+    let mut flagged = Vec::new();
+
+    for checklist in checklists {
+        if seen_templates.contains(&checklist.template_id) {
+            continue;
+        }
+        seen_templates.push(checklist.template_id);
+
+        let failed = checklist.failed_required_items();
+        match checklist.enforcement {
+            ChecklistEnforcement::Block => blocked.extend(failed),
+            ChecklistEnforcement::Flag => flagged.extend(failed),
+        }
+    }
+
+    for template in get_checklist_templates_for_strategy(conn, &analysis.strategy)? {
+        if template.id.is_some_and(|id| seen_templates.contains(&id)) {
+            continue;
+        }
+
+        let not_completed = format!("{} not completed", template.name);
+        match template.enforcement {
+            ChecklistEnforcement::Block => blocked.push(not_completed),
+            ChecklistEnforcement::Flag => flagged.push(not_completed),
+        }
+    }
+
+    if !blocked.is_empty() {
+        Ok(TradeGate::Blocked(blocked))
+    } else if !flagged.is_empty() {
+        Ok(TradeGate::Flagged(flagged))
+    } else {
+        Ok(TradeGate::Clear)
+    }
+}
+
+fn template_from_row(row: &Row) -> rusqlite::Result<ChecklistTemplate> {
+    let timestamp_str: String = row.get(1)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let enforcement_json: String = row.get(4)?;
+    let enforcement = serde_json::from_str(&enforcement_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let items_json: String = row.get(5)?;
+    let items = serde_json::from_str(&items_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(ChecklistTemplate {
+        id: Some(row.get(0)?),
+        timestamp,
+        name: row.get(2)?,
+        strategy: row.get(3)?,
+        enforcement,
+        items,
+    })
+}
+
+fn completed_checklist_from_row(row: &Row) -> rusqlite::Result<CompletedChecklist> {
+    let timestamp_str: String = row.get(3)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let enforcement_json: String = row.get(4)?;
+    let enforcement = serde_json::from_str(&enforcement_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let results_json: String = row.get(5)?;
+    let results = serde_json::from_str(&results_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(CompletedChecklist {
+        id: Some(row.get(0)?),
+        analysis_id: row.get(1)?,
+        template_id: row.get(2)?,
+        timestamp,
+        enforcement,
+        results,
+        passed: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DetailedAnalysis;
+    use crate::models::checklist::ChecklistItem;
+    use crate::models::trade::{Trade, TradeStatus};
+    use crate::services::{db, trade_service};
+
+    fn manual_template(conn: &Connection, enforcement: ChecklistEnforcement) -> i64 {
+        let mut template = ChecklistTemplate::new("Entry", "Breakout");
+        template.enforcement = enforcement;
+        template.items.push(ChecklistItem { label: "Volume confirms".to_string(), required: true, kind: ChecklistItemKind::Manual });
+        save_checklist_template(conn, &mut template).unwrap()
+    }
+
+    fn breakout_analysis(conn: &Connection) -> i64 {
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.strategy = "Breakout".to_string();
+        detailed_analysis_service::save_detailed_analysis(conn, &mut analysis).unwrap()
+    }
+
+    #[test]
+    fn block_templates_never_run_block() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let template_id = manual_template(&conn, ChecklistEnforcement::Block);
+        let analysis_id = breakout_analysis(&conn);
+
+        assert_eq!(check_trade_gate(&conn, analysis_id).unwrap(), TradeGate::Blocked(vec!["Entry not completed".to_string()]));
+
+        let answers = HashMap::from([("Volume confirms".to_string(), true)]);
+        complete_checklist(&conn, analysis_id, template_id, &answers).unwrap();
+        assert_eq!(check_trade_gate(&conn, analysis_id).unwrap(), TradeGate::Clear);
+    }
+
+    #[test]
+    fn flag_templates_never_run_flag() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        manual_template(&conn, ChecklistEnforcement::Flag);
+        let analysis_id = breakout_analysis(&conn);
+
+        assert_eq!(check_trade_gate(&conn, analysis_id).unwrap(), TradeGate::Flagged(vec!["Entry not completed".to_string()]));
+    }
+
+    #[test]
+    fn templates_for_other_strategies_are_ignored() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        manual_template(&conn, ChecklistEnforcement::Block);
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.strategy = "Pullback".to_string();
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        assert_eq!(check_trade_gate(&conn, analysis_id).unwrap(), TradeGate::Clear);
+    }
+
+    #[test]
+    fn opening_a_planned_trade_goes_through_the_gate() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let analysis_id = breakout_analysis(&conn);
+        let mut trade = Trade::new("AAPL", analysis_id);
+        let id = trade_service::save_trade(&conn, &mut trade).unwrap();
+
+        manual_template(&conn, ChecklistEnforcement::Block);
+        trade.enter_trade(Utc::now(), 100.0, 10);
+        assert!(trade_service::update_trade(&conn, &mut trade).is_err());
+        assert!(matches!(trade_service::get_trade(&conn, id).unwrap().status, TradeStatus::Planned));
+    }
+}
@@ -1,6 +1,8 @@
-use rusqlite::Connection;
+use rusqlite::{Connection, params};
 use std::error::Error;
 
+use crate::services::{account_service, alert_service, scheduler_service, sector_service};
+
 pub fn initialize_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS psychological_states (
@@ -13,7 +15,68 @@ pub fn initialize_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
             hunger INTEGER NOT NULL,
             headache_pain INTEGER NOT NULL,
             extra_factors TEXT NOT NULL,
-            total_risk_score REAL NOT NULL
+            total_risk_score REAL NOT NULL,
+            recorded_at TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sectors (
+            id INTEGER PRIMARY KEY,
+            code TEXT UNIQUE,
+            name TEXT NOT NULL,
+            level TEXT NOT NULL,
+            parent_id INTEGER REFERENCES sectors (id),
+            aliases TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sector_selections (
+            id INTEGER PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            sector_id INTEGER NOT NULL REFERENCES sectors (id) ON DELETE CASCADE,
+            rationale TEXT,
+            position INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sector_reviews (
+            raw_value TEXT PRIMARY KEY,
+            sector_id INTEGER REFERENCES sectors (id),
+            status TEXT NOT NULL,
+            candidates TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS securities (
+            symbol TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            exchange TEXT,
+            asset_class TEXT NOT NULL,
+            sector_id INTEGER REFERENCES sectors (id),
+            industry_id INTEGER REFERENCES sectors (id),
+            currency TEXT NOT NULL,
+            multiplier REAL NOT NULL,
+            active INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scoring_models (
+            version INTEGER PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            name TEXT NOT NULL,
+            weights TEXT NOT NULL,
+            active INTEGER NOT NULL DEFAULT 0,
+            notes TEXT
         )",
         [],
     )?;
@@ -33,8 +96,26 @@ pub fn initialize_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
             market_trend TEXT NOT NULL,
             chart_pattern TEXT NOT NULL,
             strategy TEXT NOT NULL,
-            overall_score INTEGER NOT NULL,
-            notes TEXT
+            overall_score REAL NOT NULL,
+            notes TEXT,
+            model_version INTEGER REFERENCES scoring_models (version),
+            pattern_bonus REAL NOT NULL DEFAULT 0,
+            sector_id INTEGER REFERENCES sectors (id),
+            computed_market_trend TEXT,
+            recorded_at TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS accounts (
+            id INTEGER PRIMARY KEY,
+            created_at TEXT NOT NULL,
+            name TEXT NOT NULL UNIQUE,
+            broker TEXT,
+            base_currency TEXT NOT NULL,
+            starting_balance REAL NOT NULL,
+            account_type TEXT NOT NULL
         )",
         [],
     )?;
@@ -46,12 +127,16 @@ pub fn initialize_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
             bull_bear INTEGER NOT NULL,
             confidence INTEGER NOT NULL,
             market_trend TEXT NOT NULL,
+            computed_market_trend TEXT,
             chart_pattern TEXT NOT NULL,
             strategy TEXT NOT NULL,
             overall_score INTEGER NOT NULL,
             market_sentiment INTEGER NOT NULL,
             sector_sentiment INTEGER NOT NULL,
             sector TEXT NOT NULL,
+            sector_id INTEGER REFERENCES sectors (id),
+            rating_id INTEGER REFERENCES stock_ratings (id),
+            account_id INTEGER REFERENCES accounts (id),
             security TEXT NOT NULL,
             bought BOOLEAN NOT NULL,
             entry_reason TEXT NOT NULL,
@@ -71,6 +156,7 @@ pub fn initialize_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
             theta REAL,
             gamma REAL,
             vega REAL,
+            rho REAL,
             alerts TEXT NOT NULL,
             exit_reason TEXT,
             skip_reason TEXT
@@ -93,10 +179,385 @@ pub fn initialize_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
             profit_loss REAL,
             percent_return REAL,
             notes TEXT,
-            FOREIGN KEY (analysis_id) REFERENCES detailed_analyses (id)
+            parent_trade_id INTEGER,
+            trading_day TEXT,
+            recorded_at TEXT,
+            account_id INTEGER,
+            side TEXT NOT NULL DEFAULT '\"Long\"',
+            fees REAL,
+            FOREIGN KEY (analysis_id) REFERENCES detailed_analyses (id),
+            FOREIGN KEY (parent_trade_id) REFERENCES trades (id),
+            FOREIGN KEY (account_id) REFERENCES accounts (id)
         )",
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS checklist_templates (
+            id INTEGER PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            name TEXT NOT NULL,
+            strategy TEXT NOT NULL,
+            enforcement TEXT NOT NULL,
+            items TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS analysis_checklists (
+            id INTEGER PRIMARY KEY,
+            analysis_id INTEGER NOT NULL,
+            template_id INTEGER NOT NULL,
+            timestamp TEXT NOT NULL,
+            enforcement TEXT NOT NULL,
+            results TEXT NOT NULL,
+            passed BOOLEAN NOT NULL,
+            FOREIGN KEY (analysis_id) REFERENCES detailed_analyses (id),
+            FOREIGN KEY (template_id) REFERENCES checklist_templates (id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS option_legs (
+            id INTEGER PRIMARY KEY,
+            analysis_id INTEGER,
+            trade_id INTEGER,
+            underlying TEXT NOT NULL,
+            expiry TEXT NOT NULL,
+            strike REAL NOT NULL,
+            option_type TEXT NOT NULL,
+            side TEXT NOT NULL,
+            ratio INTEGER NOT NULL,
+            premium REAL NOT NULL,
+            entry_iv REAL,
+            exit_iv REAL,
+            FOREIGN KEY (analysis_id) REFERENCES detailed_analyses (id),
+            FOREIGN KEY (trade_id) REFERENCES trades (id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS option_events (
+            id INTEGER PRIMARY KEY,
+            trade_id INTEGER NOT NULL,
+            leg_id INTEGER NOT NULL,
+            event_type TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            underlying_price REAL,
+            resulting_trade_id INTEGER,
+            notes TEXT,
+            FOREIGN KEY (trade_id) REFERENCES trades (id),
+            FOREIGN KEY (leg_id) REFERENCES option_legs (id),
+            FOREIGN KEY (resulting_trade_id) REFERENCES trades (id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS price_bars (
+            symbol TEXT NOT NULL,
+            timeframe TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            open REAL NOT NULL,
+            high REAL NOT NULL,
+            low REAL NOT NULL,
+            close REAL NOT NULL,
+            volume REAL NOT NULL,
+            PRIMARY KEY (symbol, timeframe, timestamp)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS indicator_cache (
+            symbol TEXT NOT NULL,
+            timeframe TEXT NOT NULL,
+            indicator TEXT NOT NULL,
+            benchmark TEXT,
+            computed_at TEXT NOT NULL,
+            points TEXT NOT NULL,
+            PRIMARY KEY (symbol, timeframe, indicator)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS watchlists (
+            id INTEGER PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            rule TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS watchlist_items (
+            id INTEGER PRIMARY KEY,
+            watchlist_id INTEGER NOT NULL REFERENCES watchlists (id),
+            symbol TEXT NOT NULL,
+            added_at TEXT NOT NULL,
+            source TEXT NOT NULL,
+            notes TEXT,
+            target_price REAL,
+            rating_id INTEGER REFERENCES stock_ratings (id),
+            analysis_id INTEGER REFERENCES detailed_analyses (id),
+            UNIQUE (watchlist_id, symbol)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS alerts (
+            id INTEGER PRIMARY KEY,
+            created_at TEXT NOT NULL,
+            symbol TEXT NOT NULL,
+            analysis_id INTEGER REFERENCES detailed_analyses (id),
+            condition TEXT NOT NULL,
+            message TEXT,
+            source_text TEXT,
+            status TEXT NOT NULL,
+            last_price REAL,
+            triggered_at TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS alert_triggers (
+            id INTEGER PRIMARY KEY,
+            alert_id INTEGER NOT NULL REFERENCES alerts (id),
+            symbol TEXT NOT NULL,
+            triggered_at TEXT NOT NULL,
+            price REAL,
+            title TEXT NOT NULL,
+            message TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS trading_calendars (
+            exchange TEXT PRIMARY KEY,
+            definition TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scheduled_jobs (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            cron TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            last_run_at TEXT,
+            next_run_at TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS job_runs (
+            id INTEGER PRIMARY KEY,
+            job_id INTEGER NOT NULL REFERENCES scheduled_jobs (id),
+            job_name TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            status TEXT NOT NULL,
+            summary TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    migrate_database(conn)?;
+
+    sector_service::seed_gics_sectors(conn)?;
+    sector_service::map_legacy_sectors(conn)?;
+    alert_service::parse_legacy_alerts(conn)?;
+    scheduler_service::seed_default_jobs(conn)?;
+    account_service::assign_default_account(conn)?;
+
+    Ok(())
+}
+
+/// Runs `f` so that everything it writes is kept or none of it is. Uses a
+/// savepoint, so calls can nest.
+pub fn in_transaction<T>(conn: &Connection, f: impl FnOnce() -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+    conn.execute_batch("SAVEPOINT unit_of_work")?;
+
+    match f() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE unit_of_work")?;
+            Ok(value)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO unit_of_work; RELEASE unit_of_work")?;
+            Err(e)
+        }
+    }
+}
+
+/// Brings databases created by older versions up to the current schema.
+fn migrate_database(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    move_analysis_iv_to_legs(conn)?;
+    add_column_if_missing(conn, "trades", "parent_trade_id", "INTEGER REFERENCES trades (id)")?;
+    add_column_if_missing(conn, "stock_ratings", "model_version", "INTEGER REFERENCES scoring_models (version)")?;
+    add_column_if_missing(conn, "stock_ratings", "pattern_bonus", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "stock_ratings", "sector_id", "INTEGER REFERENCES sectors (id)")?;
+    add_column_if_missing(conn, "detailed_analyses", "sector_id", "INTEGER REFERENCES sectors (id)")?;
+    add_column_if_missing(conn, "detailed_analyses", "rating_id", "INTEGER REFERENCES stock_ratings (id)")?;
+    add_column_if_missing(conn, "stock_ratings", "computed_market_trend", "TEXT")?;
+    add_column_if_missing(conn, "detailed_analyses", "computed_market_trend", "TEXT")?;
+    add_column_if_missing(conn, "trades", "trading_day", "TEXT")?;
+    add_column_if_missing(conn, "trades", "recorded_at", "TEXT")?;
+    add_column_if_missing(conn, "stock_ratings", "recorded_at", "TEXT")?;
+    add_column_if_missing(conn, "psychological_states", "recorded_at", "TEXT")?;
+    add_column_if_missing(conn, "detailed_analyses", "account_id", "INTEGER REFERENCES accounts (id)")?;
+    add_column_if_missing(conn, "trades", "account_id", "INTEGER REFERENCES accounts (id)")?;
+    if add_column_if_missing(conn, "trades", "side", "TEXT NOT NULL DEFAULT '\"Long\"'")? {
+        // Stock from short call assignment or long put exercise was stored as long
+        conn.execute(
+            "UPDATE trades SET side = '\"Short\"' WHERE parent_trade_id IS NOT NULL AND notes LIKE 'Short %'",
+            [],
+        )?;
+    }
+    add_column_if_missing(conn, "trades", "fees", "REAL")?;
+    add_column_if_missing(conn, "detailed_analyses", "rho", "REAL")?;
+
+    Ok(())
+}
+
+/// Spread IVs were once kept on the analysis. They move to the only leg on
+/// their side, unless that leg already has its own, and the columns go.
+fn move_analysis_iv_to_legs(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    let columns = [
+        ("short_leg_entry_iv", "entry_iv", "\"Sell\""),
+        ("short_leg_exit_iv", "exit_iv", "\"Sell\""),
+        ("long_leg_entry_iv", "entry_iv", "\"Buy\""),
+        ("long_leg_exit_iv", "exit_iv", "\"Buy\""),
+    ];
+
+    for (column, leg_column, side) in columns {
+        if !has_column(conn, "detailed_analyses", column)? {
+            continue;
+        }
+        conn.execute(
+            &format!(
+                "UPDATE option_legs
+                SET {leg_column} = (SELECT {column} FROM detailed_analyses WHERE id = option_legs.analysis_id)
+                WHERE {leg_column} IS NULL AND side = ?1 AND analysis_id IS NOT NULL
+                AND (SELECT COUNT(*) FROM option_legs others
+                    WHERE others.analysis_id = option_legs.analysis_id AND others.side = ?1) = 1",
+                leg_column = leg_column,
+                column = column,
+            ),
+            params![side],
+        )?;
+        conn.execute(&format!("ALTER TABLE detailed_analyses DROP COLUMN {}", column), [])?;
+    }
+
     Ok(())
-} 
\ No newline at end of file
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, Box<dyn Error>> {
+    let exists = conn.query_row(
+        &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table),
+        params![column],
+        |row| row.get(0),
+    )?;
+
+    Ok(exists)
+}
+
+/// Adds the column unless the table already has it. Returns whether it was added.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool, Box<dyn Error>> {
+    let exists = has_column(conn, table, column)?;
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+
+    Ok(!exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_work_is_rolled_back_even_when_nested() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t (x INTEGER)", []).unwrap();
+
+        let result: Result<(), Box<dyn Error>> = in_transaction(&conn, || {
+            conn.execute("INSERT INTO t VALUES (1)", [])?;
+            in_transaction(&conn, || {
+                conn.execute("INSERT INTO t VALUES (2)", [])?;
+                Ok(())
+            })?;
+            Err("failed".into())
+        });
+        assert!(result.is_err());
+
+        in_transaction(&conn, || {
+            conn.execute("INSERT INTO t VALUES (3)", [])?;
+            Ok(())
+        }).unwrap();
+
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn migrations_can_run_twice() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+        initialize_database(&conn).unwrap();
+    }
+
+    #[test]
+    fn analysis_iv_moves_to_its_leg() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE detailed_analyses ADD COLUMN short_leg_entry_iv REAL;
+            ALTER TABLE detailed_analyses ADD COLUMN long_leg_entry_iv REAL;
+            INSERT INTO detailed_analyses
+            (id, timestamp, bull_bear, confidence, market_trend, chart_pattern, strategy, overall_score,
+            market_sentiment, sector_sentiment, sector, security, bought, entry_reason, time, entry_price,
+            stop_loss, target_price, debit_credit, quantity, risk_max, reward, alerts,
+            short_leg_entry_iv, long_leg_entry_iv)
+            VALUES (1, '2024-06-03T14:00:00+00:00', 1, 50, '\"Uncertain\"', '{\"Other\":\"None\"}', '', 0,
+            0, 0, 'Tech', 'AAPL', 0, '', '2024-06-03T14:00:00+00:00', 0, 0, 0, 0, 1, 0, 0, '[]', 0.4, 0.3);
+            INSERT INTO option_legs (analysis_id, underlying, expiry, strike, option_type, side, ratio, premium)
+            VALUES (1, 'AAPL', '2024-06-21', 100, '\"Call\"', '\"Sell\"', 1, 1.0),
+            (1, 'AAPL', '2024-06-21', 95, '\"Call\"', '\"Buy\"', 1, 2.0),
+            (1, 'AAPL', '2024-06-21', 90, '\"Call\"', '\"Buy\"', 1, 3.0);",
+        ).unwrap();
+
+        initialize_database(&conn).unwrap();
+
+        let ivs: Vec<(String, Option<f64>)> = conn.prepare("SELECT side, entry_iv FROM option_legs ORDER BY id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(ivs[0], ("\"Sell\"".to_string(), Some(0.4)));
+        // Two long legs: there is no telling which one the IV was for
+        assert_eq!(ivs[1].1, None);
+        assert!(!has_column(&conn, "detailed_analyses", "short_leg_entry_iv").unwrap());
+    }
+}
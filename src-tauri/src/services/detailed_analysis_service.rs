@@ -0,0 +1,525 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::analytics::black_scholes::{self, OptionValuation, PricingInputs};
+use crate::analytics::implied_volatility;
+use crate::models::detailed_analysis::{AnalysisUpdate, DetailedAnalysis, SpreadLeg, TradePhase};
+use crate::models::security;
+use crate::models::stock_rating::{MarketTrend, ChartPattern};
+use crate::services::{
+    account_service, alert_service, calendar_service, db, option_leg_service, portfolio_risk_service, price_service, sector_service,
+    security_service, trend_service,
+};
+
+const ANALYSIS_COLUMNS: &str =
+    "id, timestamp, bull_bear, confidence, market_trend, chart_pattern, strategy, overall_score,
+    market_sentiment, sector_sentiment, sector, security, bought, entry_reason, time, entry_price,
+    stop_loss, target_price, short_leg, long_leg, debit_credit, quantity, risk_max, reward,
+    max_gain, percent_profit, delta, theta, gamma, vega, alerts, exit_reason, skip_reason,
+    sector_id, rating_id, computed_market_trend, account_id, rho";
+
+pub fn save_detailed_analysis(conn: &Connection, analysis: &mut DetailedAnalysis) -> Result<i64, Box<dyn Error>> {
+    db::in_transaction(conn, || {
+        if let Some(security) = security_service::validate_symbol(conn, &analysis.security)? {
+            analysis.security = security.symbol;
+        }
+        analysis.calculate_risk_reward();
+        sector_service::assign_sector(conn, &mut analysis.sector_id, &mut analysis.sector)?;
+        if analysis.computed_market_trend.is_none() {
+            analysis.computed_market_trend = trend_service::computed_market_trend(conn, &analysis.security)?;
+        }
+        match analysis.account_id {
+            Some(id) => account_service::check_account_exists(conn, id)?,
+            None => analysis.account_id = Some(account_service::default_account_id(conn)?),
+        }
+        analysis.timestamp = Utc::now();
+        fill_leg_greeks(conn, analysis, analysis.timestamp)?;
+
+        let market_trend_json = to_string(&analysis.market_trend)?;
+        let computed_market_trend_json = analysis.computed_market_trend.as_ref().map(to_string).transpose()?;
+        let chart_pattern_json = to_string(&analysis.chart_pattern)?;
+        let alerts_json = to_string(&analysis.alerts)?;
+
+        conn.execute(
+            "INSERT INTO detailed_analyses
+            (timestamp, bull_bear, confidence, market_trend, chart_pattern, strategy, overall_score,
+            market_sentiment, sector_sentiment, sector, security, bought, entry_reason, time, entry_price,
+            stop_loss, target_price, short_leg, long_leg, debit_credit, quantity, risk_max, reward,
+            max_gain, percent_profit, delta, theta, gamma, vega, alerts, exit_reason, skip_reason,
+            sector_id, rating_id, computed_market_trend, account_id, rho)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+            ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32,
+            ?33, ?34, ?35, ?36, ?37)",
+            params![
+                analysis.timestamp.to_rfc3339(),
+                analysis.bull_bear,
+                analysis.confidence,
+                market_trend_json,
+                chart_pattern_json,
+                analysis.strategy,
+                analysis.overall_score,
+                analysis.market_sentiment,
+                analysis.sector_sentiment,
+                analysis.sector,
+                analysis.security,
+                analysis.bought,
+                analysis.entry_reason,
+                analysis.time.to_rfc3339(),
+                analysis.entry_price,
+                analysis.stop_loss,
+                analysis.target_price,
+                analysis.short_leg,
+                analysis.long_leg,
+                analysis.debit_credit,
+                analysis.quantity,
+                analysis.risk_max,
+                analysis.reward,
+                analysis.max_gain,
+                analysis.percent_profit,
+                analysis.delta,
+                analysis.theta,
+                analysis.gamma,
+                analysis.vega,
+                alerts_json,
+                analysis.exit_reason,
+                analysis.skip_reason,
+                analysis.sector_id,
+                analysis.rating_id,
+                computed_market_trend_json,
+                analysis.account_id,
+                analysis.rho,
+            ],
+        )?;
+
+        let id = conn.last_insert_rowid();
+        analysis.id = Some(id);
+
+        option_leg_service::replace_analysis_legs(conn, id, &mut analysis.legs)?;
+        alert_service::sync_analysis_alerts(conn, analysis, &[])?;
+
+        Ok(id)
+    })
+}
+
+/// Applies an edit from the UI, keeping the stored legs and rating link when
+/// it leaves them out.
+pub fn apply_analysis_update(conn: &Connection, update: AnalysisUpdate) -> Result<DetailedAnalysis, Box<dyn Error>> {
+    let mut analysis = update.analysis;
+    let id = analysis.id.ok_or("Analysis has no id")?;
+    let stored = get_detailed_analysis(conn, id)?;
+    analysis.rating_id = analysis.rating_id.or(stored.rating_id);
+    analysis.legs = update.legs.unwrap_or(stored.legs);
+    update_detailed_analysis(conn, &mut analysis)?;
+
+    Ok(analysis)
+}
+
+/// Removes the link between an analysis and the rating it was promoted from.
+pub fn unlink_rating(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    let updated = conn.execute("UPDATE detailed_analyses SET rating_id = NULL WHERE id = ?1", params![id])?;
+    if updated == 0 {
+        return Err(format!("Analysis {} does not exist", id).into());
+    }
+
+    Ok(())
+}
+
+/// Updates a stored analysis to match `analysis`, legs and rating link
+/// included. The computed trend is the classifier's and an analysis always
+/// has an account, so those keep their stored values when left out.
+pub fn update_detailed_analysis(conn: &Connection, analysis: &mut DetailedAnalysis) -> Result<(), Box<dyn Error>> {
+    db::in_transaction(conn, || {
+        let id = analysis.id.ok_or("Analysis has no id")?;
+        let stored = get_detailed_analysis(conn, id)?;
+        analysis.computed_market_trend = analysis.computed_market_trend.take().or(stored.computed_market_trend);
+        analysis.account_id = analysis.account_id.or(stored.account_id);
+        // A security delisted since the analysis was made stays valid for it
+        if security::normalize_symbol(&analysis.security) == security::normalize_symbol(&stored.security) {
+            analysis.security = stored.security;
+        } else if let Some(security) = security_service::validate_symbol(conn, &analysis.security)? {
+            analysis.security = security.symbol;
+        }
+
+        analysis.calculate_risk_reward();
+        sector_service::assign_sector(conn, &mut analysis.sector_id, &mut analysis.sector)?;
+        match analysis.account_id {
+            Some(id) => account_service::check_account_exists(conn, id)?,
+            None => analysis.account_id = Some(account_service::default_account_id(conn)?),
+        }
+        fill_leg_greeks(conn, analysis, Utc::now())?;
+
+        let market_trend_json = to_string(&analysis.market_trend)?;
+        let computed_market_trend_json = analysis.computed_market_trend.as_ref().map(to_string).transpose()?;
+        let chart_pattern_json = to_string(&analysis.chart_pattern)?;
+        let alerts_json = to_string(&analysis.alerts)?;
+
+        conn.execute(
+            "UPDATE detailed_analyses
+            SET bull_bear = ?1, confidence = ?2, market_trend = ?3, chart_pattern = ?4, strategy = ?5,
+            overall_score = ?6, market_sentiment = ?7, sector_sentiment = ?8, sector = ?9, security = ?10,
+            bought = ?11, entry_reason = ?12, time = ?13, entry_price = ?14, stop_loss = ?15,
+            target_price = ?16, short_leg = ?17, long_leg = ?18, debit_credit = ?19, quantity = ?20,
+            risk_max = ?21, reward = ?22, max_gain = ?23, percent_profit = ?24, delta = ?25, theta = ?26,
+            gamma = ?27, vega = ?28, alerts = ?29, exit_reason = ?30, skip_reason = ?31,
+            sector_id = ?32, rating_id = ?33, computed_market_trend = ?34, account_id = ?35, rho = ?36
+            WHERE id = ?37",
+            params![
+                analysis.bull_bear,
+                analysis.confidence,
+                market_trend_json,
+                chart_pattern_json,
+                analysis.strategy,
+                analysis.overall_score,
+                analysis.market_sentiment,
+                analysis.sector_sentiment,
+                analysis.sector,
+                analysis.security,
+                analysis.bought,
+                analysis.entry_reason,
+                analysis.time.to_rfc3339(),
+                analysis.entry_price,
+                analysis.stop_loss,
+                analysis.target_price,
+                analysis.short_leg,
+                analysis.long_leg,
+                analysis.debit_credit,
+                analysis.quantity,
+                analysis.risk_max,
+                analysis.reward,
+                analysis.max_gain,
+                analysis.percent_profit,
+                analysis.delta,
+                analysis.theta,
+                analysis.gamma,
+                analysis.vega,
+                alerts_json,
+                analysis.exit_reason,
+                analysis.skip_reason,
+                analysis.sector_id,
+                analysis.rating_id,
+                computed_market_trend_json,
+                analysis.account_id,
+                analysis.rho,
+                id,
+            ],
+        )?;
+
+        option_leg_service::replace_analysis_legs(conn, id, &mut analysis.legs)?;
+        if analysis.account_id != stored.account_id {
+            // Trades stay in their analysis's account
+            conn.execute("UPDATE trades SET account_id = ?1 WHERE analysis_id = ?2", params![analysis.account_id, id])?;
+        }
+        alert_service::sync_analysis_alerts(conn, analysis, &stored.alerts)?;
+
+        Ok(())
+    })
+}
+
+pub fn get_detailed_analysis(conn: &Connection, id: i64) -> Result<DetailedAnalysis, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM detailed_analyses WHERE id = ?1",
+        ANALYSIS_COLUMNS
+    ))?;
+
+    let mut analysis = stmt.query_row(params![id], analysis_from_row)?;
+    analysis.legs = option_leg_service::get_legs_by_analysis(conn, id)?;
+
+    Ok(analysis)
+}
+
+/// Maximum loss planned in an analysis; nothing when the analysis is gone.
+pub fn get_risk_max(conn: &Connection, id: i64) -> Result<f64, Box<dyn Error>> {
+    let risk_max = conn.query_row(
+        "SELECT risk_max FROM detailed_analyses WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    ).optional()?;
+
+    Ok(risk_max.unwrap_or(0.0))
+}
+
+/// Latest analyses of one account, or of all accounts when `account_id` is None.
+pub fn get_recent_detailed_analyses(conn: &Connection, limit: i64, account_id: Option<i64>) -> Result<Vec<DetailedAnalysis>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM detailed_analyses WHERE (?2 IS NULL OR account_id = ?2) ORDER BY timestamp DESC LIMIT ?1",
+        ANALYSIS_COLUMNS
+    ))?;
+
+    let analyses_iter = stmt.query_map(params![limit, account_id], analysis_from_row)?;
+
+    let mut analyses = Vec::new();
+    for analysis in analyses_iter {
+        let mut analysis = analysis?;
+        if let Some(id) = analysis.id {
+            analysis.legs = option_leg_service::get_legs_by_analysis(conn, id)?;
+        }
+        analyses.push(analysis);
+    }
+
+    Ok(analyses)
+}
+
+pub fn get_analyses_by_rating(conn: &Connection, rating_id: i64) -> Result<Vec<DetailedAnalysis>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM detailed_analyses WHERE rating_id = ?1 ORDER BY timestamp DESC",
+        ANALYSIS_COLUMNS
+    ))?;
+
+    let analyses_iter = stmt.query_map(params![rating_id], analysis_from_row)?;
+
+    let mut analyses = Vec::new();
+    for analysis in analyses_iter {
+        let mut analysis = analysis?;
+        if let Some(id) = analysis.id {
+            analysis.legs = option_leg_service::get_legs_by_analysis(conn, id)?;
+        }
+        analyses.push(analysis);
+    }
+
+    Ok(analyses)
+}
+
+/// Recomputes the analysis Greeks from the pricing inputs and stores them,
+/// in place of the ones filled in from its legs.
+pub fn update_analysis_greeks(conn: &Connection, analysis_id: i64, inputs: &PricingInputs) -> Result<DetailedAnalysis, Box<dyn Error>> {
+    let mut analysis = get_detailed_analysis(conn, analysis_id)?;
+    let valuation = black_scholes::valuation(inputs);
+
+    analysis.apply_greeks(&valuation);
+    conn.execute(
+        "UPDATE detailed_analyses SET delta = ?1, theta = ?2, gamma = ?3, vega = ?4, rho = ?5 WHERE id = ?6",
+        params![analysis.delta, analysis.theta, analysis.gamma, analysis.vega, analysis.rho, analysis_id],
+    )?;
+
+    Ok(analysis)
+}
+
+/// Fills in the Greeks of an analysis with option legs, per spread unit,
+/// priced off the latest close in the local price store. Analyses without
+/// legs or without a stored price keep the Greeks they were given.
+fn fill_leg_greeks(conn: &Connection, analysis: &mut DetailedAnalysis, now: DateTime<Utc>) -> Result<(), Box<dyn Error>> {
+    if analysis.legs.is_empty() {
+        return Ok(());
+    }
+    let underlying_price = match price_service::get_latest_close(conn, &analysis.security)? {
+        Some(price) => price,
+        None => return Ok(()),
+    };
+
+    let settings = portfolio_risk_service::get_portfolio_risk_settings(conn)?;
+    let calendar = calendar_service::calendar_for_symbol(conn, &analysis.security)?;
+    let mut total = OptionValuation { price: 0.0, delta: 0.0, gamma: 0.0, theta: 0.0, vega: 0.0, rho: 0.0 };
+    for leg in &analysis.legs {
+        let volatility = leg.exit_iv.or(leg.entry_iv).unwrap_or(settings.default_volatility);
+        let valuation = leg.position_valuation(underlying_price, now, &calendar, settings.rate, settings.dividend_yield, volatility);
+        total.price += valuation.price;
+        total.delta += valuation.delta;
+        total.gamma += valuation.gamma;
+        total.theta += valuation.theta;
+        total.vega += valuation.vega;
+        total.rho += valuation.rho;
+    }
+    analysis.apply_greeks(&total);
+
+    Ok(())
+}
+
+/// Backs out the implied volatility of one side of a spread from its market
+/// price and stores it as the entry or exit IV of the only leg on that side.
+pub fn record_leg_implied_volatility(
+    conn: &Connection,
+    analysis_id: i64,
+    leg: SpreadLeg,
+    phase: TradePhase,
+    inputs: &PricingInputs,
+    market_price: f64,
+) -> Result<DetailedAnalysis, Box<dyn Error>> {
+    let mut analysis = get_detailed_analysis(conn, analysis_id)?;
+    let iv = implied_volatility::implied_volatility(inputs, market_price)?;
+
+    let option_leg = analysis.set_leg_iv(leg, phase, iv)?;
+    option_leg_service::update_option_leg(conn, option_leg)?;
+
+    Ok(analysis)
+}
+
+fn analysis_from_row(row: &Row) -> rusqlite::Result<DetailedAnalysis> {
+    let timestamp_str: String = row.get(1)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let market_trend_json: String = row.get(4)?;
+    let market_trend: MarketTrend = serde_json::from_str(&market_trend_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let computed_market_trend_json: Option<String> = row.get(35)?;
+    let computed_market_trend = computed_market_trend_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(35, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let chart_pattern_json: String = row.get(5)?;
+    let chart_pattern: ChartPattern = serde_json::from_str(&chart_pattern_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let time_str: String = row.get(14)?;
+    let time = chrono::DateTime::parse_from_rfc3339(&time_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(14, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let alerts_json: String = row.get(30)?;
+    let alerts = serde_json::from_str(&alerts_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(30, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(DetailedAnalysis {
+        id: Some(row.get(0)?),
+        timestamp,
+        rating_id: row.get(34)?,
+        account_id: row.get(36)?,
+        bull_bear: row.get(2)?,
+        confidence: row.get(3)?,
+        market_trend,
+        computed_market_trend,
+        chart_pattern,
+        strategy: row.get(6)?,
+        overall_score: row.get(7)?,
+        market_sentiment: row.get(8)?,
+        sector_sentiment: row.get(9)?,
+        sector: row.get(10)?,
+        sector_id: row.get(33)?,
+        security: row.get(11)?,
+        bought: row.get(12)?,
+        entry_reason: row.get(13)?,
+        time,
+        entry_price: row.get(15)?,
+        stop_loss: row.get(16)?,
+        target_price: row.get(17)?,
+        short_leg: row.get(18)?,
+        long_leg: row.get(19)?,
+        legs: Vec::new(),
+        debit_credit: row.get(20)?,
+        quantity: row.get(21)?,
+        risk_max: row.get(22)?,
+        reward: row.get(23)?,
+        max_gain: row.get(24)?,
+        percent_profit: row.get(25)?,
+        delta: row.get(26)?,
+        theta: row.get(27)?,
+        gamma: row.get(28)?,
+        vega: row.get(29)?,
+        rho: row.get(37)?,
+        alerts,
+        exit_reason: row.get(31)?,
+        skip_reason: row.get(32)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::analytics::black_scholes::OptionType;
+    use crate::models::StockRating;
+    use crate::models::account::{Account, AccountType};
+    use crate::models::option_leg::{LegSide, OptionLeg};
+    use crate::models::price_bar::{PriceBar, Timeframe};
+    use crate::services::stock_rating_service;
+
+    #[test]
+    fn saving_fills_in_greeks_from_the_legs() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        price_service::import_bars(&conn, &[PriceBar {
+            symbol: "AAPL".to_string(),
+            timeframe: Timeframe::Daily,
+            timestamp: Utc::now() - chrono::Duration::days(1),
+            open: 100.0,
+            high: 100.0,
+            low: 100.0,
+            close: 100.0,
+            volume: 0.0,
+        }]).unwrap();
+
+        let expiry = Utc::now().date_naive() + chrono::Duration::days(60);
+        let mut long_call = OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Buy);
+        long_call.entry_iv = Some(0.25);
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.legs = vec![long_call];
+        let id = save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        let stored = get_detailed_analysis(&conn, id).unwrap();
+        let delta = stored.delta.unwrap();
+        assert!(delta > 0.5 && delta < 0.6, "{}", delta);
+        assert!(stored.theta.unwrap() < 0.0);
+        assert!(stored.rho.unwrap() > 0.0);
+
+        let mut short_call = OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Sell);
+        short_call.entry_iv = Some(0.25);
+        let mut payload = stored.clone();
+        payload.legs = vec![short_call];
+        update_detailed_analysis(&conn, &mut payload).unwrap();
+        assert!((get_detailed_analysis(&conn, id).unwrap().delta.unwrap() + delta).abs() < 1e-3);
+    }
+
+    #[test]
+    fn update_keeps_fields_the_payload_leaves_out() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut account = Account::new("Margin", AccountType::Margin);
+        let account_id = account_service::save_account(&conn, &mut account).unwrap();
+        let mut rating = StockRating::new("AAPL", "Information Technology");
+        let rating_id = stock_rating_service::save_stock_rating(&conn, &mut rating).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.account_id = Some(account_id);
+        analysis.rating_id = Some(rating_id);
+        analysis.computed_market_trend = Some(MarketTrend::Uptrend);
+        analysis.legs = vec![OptionLeg::new("AAPL", NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 100.0, OptionType::Call, LegSide::Buy)];
+        let id = save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        let mut payload = serde_json::to_value(get_detailed_analysis(&conn, id).unwrap()).unwrap();
+        for field in ["rating_id", "computed_market_trend", "account_id", "legs"] {
+            payload.as_object_mut().unwrap().remove(field);
+        }
+        payload["entry_reason"] = "Breakout".into();
+        apply_analysis_update(&conn, serde_json::from_value(payload.clone()).unwrap()).unwrap();
+
+        let stored = get_detailed_analysis(&conn, id).unwrap();
+        assert_eq!(stored.entry_reason, "Breakout");
+        assert_eq!(stored.rating_id, Some(rating_id));
+        assert_eq!(stored.computed_market_trend, Some(MarketTrend::Uptrend));
+        assert_eq!(stored.account_id, Some(account_id));
+        assert_eq!(stored.legs.len(), 1);
+
+        // An empty list removes the legs, and the rating link goes on its own
+        payload["legs"] = serde_json::json!([]);
+        apply_analysis_update(&conn, serde_json::from_value(payload).unwrap()).unwrap();
+        unlink_rating(&conn, id).unwrap();
+        let stored = get_detailed_analysis(&conn, id).unwrap();
+        assert!(stored.legs.is_empty());
+        assert_eq!(stored.rating_id, None);
+    }
+
+    #[test]
+    fn a_failed_leg_write_leaves_the_analysis_as_it_was() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let id = save_detailed_analysis(&conn, &mut analysis).unwrap();
+        conn.execute_batch("CREATE TRIGGER no_legs BEFORE INSERT ON option_legs BEGIN SELECT RAISE(ABORT, 'no legs'); END").unwrap();
+
+        analysis.entry_reason = "Breakout".to_string();
+        analysis.legs = vec![OptionLeg::new("AAPL", NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 100.0, OptionType::Call, LegSide::Buy)];
+        assert!(update_detailed_analysis(&conn, &mut analysis).is_err());
+        assert_eq!(get_detailed_analysis(&conn, id).unwrap().entry_reason, "");
+
+        let mut second = DetailedAnalysis::new("MSFT", "Information Technology");
+        second.legs = analysis.legs.clone();
+        assert!(save_detailed_analysis(&conn, &mut second).is_err());
+        let analyses: i64 = conn.query_row("SELECT COUNT(*) FROM detailed_analyses", [], |row| row.get(0)).unwrap();
+        assert_eq!(analyses, 1);
+    }
+}
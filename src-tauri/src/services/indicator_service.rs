@@ -0,0 +1,98 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::analytics::indicators;
+use crate::models::indicator::{Indicator, IndicatorLine, IndicatorSeries};
+use crate::models::price_bar::Timeframe;
+use crate::services::{calendar_service, price_service};
+
+/// Bars indicators are computed over, enough for long averages to settle.
+pub const INDICATOR_BARS: i64 = 500;
+
+/// The indicator over the most recent stored bars, computed once and then
+/// served from the cache until bars for the symbol or benchmark are imported.
+pub fn get_indicator(conn: &Connection, symbol: &str, timeframe: Timeframe, indicator: &Indicator) -> Result<IndicatorSeries, Box<dyn Error>> {
+    let symbol = symbol.to_ascii_uppercase();
+    let timeframe_json = to_string(&timeframe)?;
+    let indicator_json = to_string(indicator)?;
+
+    let cached: Option<(String, String)> = conn.query_row(
+        "SELECT computed_at, points FROM indicator_cache
+        WHERE symbol = ?1 AND timeframe = ?2 AND indicator = ?3",
+        params![symbol, timeframe_json, indicator_json],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+
+    if let Some((computed_at, points)) = cached {
+        return Ok(IndicatorSeries {
+            symbol,
+            timeframe,
+            indicator: indicator.clone(),
+            computed_at: chrono::DateTime::parse_from_rfc3339(&computed_at)?.with_timezone(&Utc),
+            points: serde_json::from_str(&points)?,
+        });
+    }
+
+    let bars = price_service::get_bars(conn, &symbol, timeframe, INDICATOR_BARS)?;
+    let benchmark = match indicator.benchmark() {
+        Some(benchmark) => price_service::get_bars(conn, benchmark, timeframe, INDICATOR_BARS)?,
+        None => Vec::new(),
+    };
+    // Intraday VWAP restarts each session day of the symbol's exchange
+    let session_zone = if timeframe.is_intraday() {
+        Some(calendar_service::calendar_for_symbol(conn, &symbol)?.timezone)
+    } else {
+        None
+    };
+
+    let series = IndicatorSeries {
+        symbol,
+        timeframe,
+        indicator: indicator.clone(),
+        computed_at: Utc::now(),
+        points: indicators::compute(indicator, &bars, &benchmark, session_zone),
+    };
+
+    conn.execute(
+        "INSERT OR REPLACE INTO indicator_cache
+        (symbol, timeframe, indicator, benchmark, computed_at, points)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            series.symbol,
+            timeframe_json,
+            indicator_json,
+            indicator.benchmark().map(|b| b.to_ascii_uppercase()),
+            series.computed_at.to_rfc3339(),
+            to_string(&series.points)?,
+        ],
+    )?;
+
+    Ok(series)
+}
+
+/// Latest value of one indicator line for a symbol, None while there are too
+/// few bars.
+pub fn get_indicator_value(conn: &Connection, symbol: &str, line: &IndicatorLine) -> Result<Option<f64>, Box<dyn Error>> {
+    let series = get_indicator(conn, symbol, line.timeframe, &line.indicator)?;
+
+    Ok(series.latest(line.output.as_deref()))
+}
+
+/// Drops cached indicators computed from the symbol's bars, including
+/// relative strength against it as a benchmark.
+pub fn invalidate_indicators(conn: &Connection, symbol: &str, timeframe: Timeframe) -> Result<usize, Box<dyn Error>> {
+    let removed = conn.execute(
+        "DELETE FROM indicator_cache WHERE timeframe = ?2 AND (symbol = ?1 OR benchmark = ?1)",
+        params![symbol.to_ascii_uppercase(), to_string(&timeframe)?],
+    )?;
+
+    Ok(removed)
+}
+
+pub fn clear_indicator_cache(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM indicator_cache", [])?;
+
+    Ok(())
+}
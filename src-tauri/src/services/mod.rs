@@ -1,4 +1,29 @@
 pub mod db;
 pub mod psychological_service;
 pub mod stock_rating_service;
-pub mod trade_service; 
\ No newline at end of file
+pub mod scoring_service;
+pub mod pattern_stats_service;
+pub mod pattern_detection_service;
+pub mod opportunity_service;
+pub mod rating_freshness_service;
+pub mod sector_service;
+pub mod security_service;
+pub mod watchlist_service;
+pub mod promotion_service;
+pub mod detailed_analysis_service;
+pub mod checklist_service;
+pub mod option_leg_service;
+pub mod payoff_service;
+pub mod option_lifecycle_service;
+pub mod price_service;
+pub mod indicator_service;
+pub mod trend_service;
+pub mod alert_service;
+pub mod calendar_service;
+pub mod scheduler_service;
+pub mod settings_service;
+pub mod app_settings_service;
+pub mod time_zone_service;
+pub mod portfolio_risk_service;
+pub mod trade_service;
+pub mod account_service;
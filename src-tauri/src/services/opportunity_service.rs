@@ -0,0 +1,28 @@
+use rusqlite::Connection;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::analytics::opportunity::{self, OpportunityChart, DEFAULT_SCORE_BINS};
+use crate::services::{pattern_stats_service, stock_rating_service};
+
+/// Rating history of `symbol` as a sentiment trend line, its current score
+/// against the latest score of every rated symbol, and its pattern bonus.
+pub fn get_opportunity_chart(conn: &Connection, symbol: &str, bins: Option<usize>) -> Result<OpportunityChart, Box<dyn Error>> {
+    let history = stock_rating_service::get_stock_ratings_by_symbol(conn, symbol)?;
+    let all_scores: Vec<f64> = stock_rating_service::get_latest_stock_ratings(conn)?
+        .iter()
+        .map(|rating| rating.overall_score)
+        .collect();
+
+    let pattern_window = pattern_stats_service::get_all_time_pattern_stats(conn, Utc::now())?;
+    let pattern_stats = history.first()
+        .and_then(|latest| pattern_window.patterns.iter().find(|stats| stats.pattern == latest.chart_pattern));
+
+    Ok(opportunity::build_opportunity_chart(
+        symbol,
+        &history,
+        &all_scores,
+        pattern_stats,
+        bins.unwrap_or(DEFAULT_SCORE_BINS),
+    ))
+}
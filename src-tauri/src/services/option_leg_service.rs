@@ -0,0 +1,269 @@
+use rusqlite::{Connection, Row, params};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::analytics::black_scholes::OptionType;
+use crate::analytics::implied_volatility;
+use crate::models::detailed_analysis::TradePhase;
+use crate::models::option_leg::{LegSide, OptionLeg};
+use crate::services::{calendar_service, db, portfolio_risk_service};
+
+const LEG_COLUMNS: &str =
+    "id, analysis_id, trade_id, underlying, expiry, strike, option_type, side, ratio, premium,
+    entry_iv, exit_iv";
+
+pub fn save_option_leg(conn: &Connection, leg: &mut OptionLeg) -> Result<i64, Box<dyn Error>> {
+    let option_type_json = to_string(&leg.option_type)?;
+    let side_json = to_string(&leg.side)?;
+
+    conn.execute(
+        "INSERT INTO option_legs
+        (analysis_id, trade_id, underlying, expiry, strike, option_type, side, ratio, premium,
+        entry_iv, exit_iv)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            leg.analysis_id,
+            leg.trade_id,
+            leg.underlying,
+            leg.expiry.to_string(),
+            leg.strike,
+            option_type_json,
+            side_json,
+            leg.ratio,
+            leg.premium,
+            leg.entry_iv,
+            leg.exit_iv,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    leg.id = Some(id);
+
+    Ok(id)
+}
+
+pub fn update_option_leg(conn: &Connection, leg: &OptionLeg) -> Result<(), Box<dyn Error>> {
+    let option_type_json = to_string(&leg.option_type)?;
+    let side_json = to_string(&leg.side)?;
+
+    conn.execute(
+        "UPDATE option_legs
+        SET analysis_id = ?1, trade_id = ?2, underlying = ?3, expiry = ?4, strike = ?5,
+        option_type = ?6, side = ?7, ratio = ?8, premium = ?9, entry_iv = ?10, exit_iv = ?11
+        WHERE id = ?12",
+        params![
+            leg.analysis_id,
+            leg.trade_id,
+            leg.underlying,
+            leg.expiry.to_string(),
+            leg.strike,
+            option_type_json,
+            side_json,
+            leg.ratio,
+            leg.premium,
+            leg.entry_iv,
+            leg.exit_iv,
+            leg.id,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_option_leg(conn: &Connection, id: i64) -> Result<OptionLeg, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM option_legs WHERE id = ?1", LEG_COLUMNS))?;
+
+    let leg = stmt.query_row(params![id], leg_from_row)?;
+
+    Ok(leg)
+}
+
+/// Makes the legs stored for an analysis match `legs`. Legs keep their ids so
+/// anything referring to them, such as lifecycle events, stays linked.
+pub fn replace_analysis_legs(conn: &Connection, analysis_id: i64, legs: &mut [OptionLeg]) -> Result<(), Box<dyn Error>> {
+    let existing = get_legs_by_analysis(conn, analysis_id)?;
+
+    for leg in legs.iter_mut() {
+        leg.analysis_id = Some(analysis_id);
+        leg.trade_id = None;
+    }
+
+    sync_legs(conn, &existing, legs)
+}
+
+/// Makes the legs stored for a trade match `legs`, keeping leg ids.
+pub fn replace_trade_legs(conn: &Connection, trade_id: i64, analysis_id: i64, legs: &mut [OptionLeg]) -> Result<(), Box<dyn Error>> {
+    let existing = get_legs_by_trade(conn, trade_id)?;
+
+    for leg in legs.iter_mut() {
+        leg.analysis_id = Some(analysis_id);
+        leg.trade_id = Some(trade_id);
+    }
+
+    sync_legs(conn, &existing, legs)
+}
+
+fn sync_legs(conn: &Connection, existing: &[OptionLeg], legs: &mut [OptionLeg]) -> Result<(), Box<dyn Error>> {
+    db::in_transaction(conn, || {
+        for stale in existing.iter().filter(|old| !legs.iter().any(|leg| leg.id == old.id)) {
+            conn.execute("DELETE FROM option_legs WHERE id = ?1", params![stale.id])?;
+        }
+
+        for leg in legs.iter_mut() {
+            if leg.id.is_some() && existing.iter().any(|old| old.id == leg.id) {
+                update_option_leg(conn, leg)?;
+            } else {
+                leg.id = None;
+                save_option_leg(conn, leg)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+pub fn get_legs_by_analysis(conn: &Connection, analysis_id: i64) -> Result<Vec<OptionLeg>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM option_legs WHERE analysis_id = ?1 AND trade_id IS NULL ORDER BY id",
+        LEG_COLUMNS
+    ))?;
+
+    let legs_iter = stmt.query_map(params![analysis_id], leg_from_row)?;
+
+    let mut legs = Vec::new();
+    for leg in legs_iter {
+        legs.push(leg?);
+    }
+
+    Ok(legs)
+}
+
+pub fn get_legs_by_trade(conn: &Connection, trade_id: i64) -> Result<Vec<OptionLeg>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM option_legs WHERE trade_id = ?1 ORDER BY id",
+        LEG_COLUMNS
+    ))?;
+
+    let legs_iter = stmt.query_map(params![trade_id], leg_from_row)?;
+
+    let mut legs = Vec::new();
+    for leg in legs_iter {
+        legs.push(leg?);
+    }
+
+    Ok(legs)
+}
+
+/// Backs out the implied volatility of a stored leg from its market price and
+/// records it as the leg's entry or exit IV. Strike, expiry and type come from
+/// the leg; rate and dividend yield from the portfolio risk settings.
+pub fn record_option_leg_iv(
+    conn: &Connection,
+    leg_id: i64,
+    phase: TradePhase,
+    underlying_price: f64,
+    market_price: f64,
+    observed_at: DateTime<Utc>,
+) -> Result<OptionLeg, Box<dyn Error>> {
+    let mut leg = get_option_leg(conn, leg_id)?;
+    let settings = portfolio_risk_service::get_portfolio_risk_settings(conn)?;
+    let calendar = calendar_service::calendar_for_symbol(conn, &leg.underlying)?;
+    let inputs = leg.pricing_inputs(underlying_price, observed_at, &calendar, settings.rate, settings.dividend_yield, 0.0);
+    let iv = implied_volatility::implied_volatility(&inputs, market_price)?;
+
+    match phase {
+        TradePhase::Entry => leg.entry_iv = Some(iv),
+        TradePhase::Exit => leg.exit_iv = Some(iv),
+    }
+    conn.execute(
+        "UPDATE option_legs SET entry_iv = ?1, exit_iv = ?2 WHERE id = ?3",
+        params![leg.entry_iv, leg.exit_iv, leg_id],
+    )?;
+
+    Ok(leg)
+}
+
+fn leg_from_row(row: &Row) -> rusqlite::Result<OptionLeg> {
+    let expiry_str: String = row.get(4)?;
+    let expiry = NaiveDate::parse_from_str(&expiry_str, "%Y-%m-%d")
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let option_type_json: String = row.get(6)?;
+    let option_type: OptionType = serde_json::from_str(&option_type_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let side_json: String = row.get(7)?;
+    let side: LegSide = serde_json::from_str(&side_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(7, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(OptionLeg {
+        id: Some(row.get(0)?),
+        analysis_id: row.get(1)?,
+        trade_id: row.get(2)?,
+        underlying: row.get(3)?,
+        expiry,
+        strike: row.get(5)?,
+        option_type,
+        side,
+        ratio: row.get(8)?,
+        premium: row.get(9)?,
+        entry_iv: row.get(10)?,
+        exit_iv: row.get(11)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::analytics::black_scholes;
+    use crate::models::DetailedAnalysis;
+    use crate::services::detailed_analysis_service;
+
+    #[test]
+    fn leg_iv_is_priced_from_the_stored_leg() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let observed_at = Utc.with_ymd_and_hms(2024, 5, 1, 15, 0, 0).unwrap();
+        let expiry = (observed_at + Duration::days(50)).date_naive();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.legs = vec![OptionLeg::new("AAPL", expiry, 110.0, OptionType::Put, LegSide::Sell)];
+        detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let leg_id = analysis.legs[0].id.unwrap();
+
+        let settings = portfolio_risk_service::get_portfolio_risk_settings(&conn).unwrap();
+        let calendar = calendar_service::built_in_calendar().unwrap();
+        let inputs = analysis.legs[0].pricing_inputs(100.0, observed_at, &calendar, settings.rate, settings.dividend_yield, 0.35);
+        let market_price = black_scholes::price(&inputs);
+
+        let leg = record_option_leg_iv(&conn, leg_id, TradePhase::Entry, 100.0, market_price, observed_at).unwrap();
+        assert!((leg.entry_iv.unwrap() - 0.35).abs() < 1e-6);
+        assert_eq!(get_option_leg(&conn, leg_id).unwrap().entry_iv, leg.entry_iv);
+        assert_eq!(leg.strike, 110.0);
+    }
+
+    #[test]
+    fn a_failed_leg_rewrite_keeps_the_stored_legs() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let expiry = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.legs = vec![
+            OptionLeg::new("AAPL", expiry, 100.0, OptionType::Call, LegSide::Buy),
+            OptionLeg::new("AAPL", expiry, 110.0, OptionType::Call, LegSide::Sell),
+        ];
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        conn.execute_batch(
+            "CREATE TRIGGER no_puts BEFORE INSERT ON option_legs
+            WHEN NEW.option_type = '\"Put\"' BEGIN SELECT RAISE(ABORT, 'no puts'); END",
+        ).unwrap();
+        let mut legs = vec![OptionLeg::new("AAPL", expiry, 90.0, OptionType::Put, LegSide::Buy)];
+        assert!(replace_analysis_legs(&conn, analysis_id, &mut legs).is_err());
+
+        assert_eq!(get_legs_by_analysis(&conn, analysis_id).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,477 @@
+use rusqlite::{Connection, Row, params};
+use serde::{Deserialize, Serialize};
+use serde_json::to_string;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+use crate::analytics::black_scholes::OptionType;
+use crate::analytics::payoff::CONTRACT_MULTIPLIER;
+use crate::models::calendar::TradingCalendar;
+use crate::models::option_event::{OptionEvent, OptionEventType};
+use crate::models::option_leg::{LegSide, OptionLeg};
+use crate::models::trade::{PositionSide, Trade, TradeStatus};
+use crate::services::{calendar_service, db, option_leg_service, time_zone_service, trade_service};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExpiringLeg {
+    pub trade_id: i64,
+    pub symbol: String,
+    pub leg: OptionLeg,
+    pub days_to_expiry: i64,
+}
+
+/// Outcome of expiring one date's worthless legs.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExpiryRun {
+    pub expired: Vec<OptionEvent>,
+    pub failed: Vec<String>,       // Each leg left unresolved, with why
+}
+
+/// Legs of open trades expiring between `from` and `to` (inclusive) that have
+/// not yet been expired, exercised or assigned, in one account or in all
+/// accounts when `account_id` is None. Days to expiry count from the date at
+/// `now` on the calendar of each underlying's exchange.
+pub fn get_expiring_legs(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+    now: DateTime<Utc>,
+    account_id: Option<i64>,
+) -> Result<Vec<ExpiringLeg>, Box<dyn Error>> {
+    let mut calendars: HashMap<String, TradingCalendar> = HashMap::new();
+
+    let mut expiring = unresolved_legs(conn, from, to, account_id)?;
+    for expiring_leg in &mut expiring {
+        let leg = &expiring_leg.leg;
+        if !calendars.contains_key(&leg.underlying) {
+            calendars.insert(leg.underlying.clone(), calendar_service::calendar_for_symbol(conn, &leg.underlying)?);
+        }
+        let today = calendars[&leg.underlying].local_date(now);
+        expiring_leg.days_to_expiry = (leg.expiry - today).num_days();
+    }
+
+    Ok(expiring)
+}
+
+/// Open legs expiring from the exchange date at `now` through the end of the
+/// calendar week.
+pub fn get_legs_expiring_this_week(conn: &Connection, now: DateTime<Utc>, account_id: Option<i64>) -> Result<Vec<ExpiringLeg>, Box<dyn Error>> {
+    let today = time_zone_service::exchange_today(conn, now)?;
+    let days_left = 6 - today.weekday().num_days_from_monday() as i64;
+    get_expiring_legs(conn, today, today + Duration::days(days_left), now, account_id)
+}
+
+/// Unresolved legs of open trades expiring between `from` and `to`, with
+/// days to expiry left at zero.
+fn unresolved_legs(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+    account_id: Option<i64>,
+) -> Result<Vec<ExpiringLeg>, Box<dyn Error>> {
+    let open_status = to_string(&TradeStatus::Open)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT l.id, t.id, t.symbol
+        FROM option_legs l
+        JOIN trades t ON l.trade_id = t.id
+        WHERE t.status = ?1 AND l.expiry >= ?2 AND l.expiry <= ?3 AND (?4 IS NULL OR t.account_id = ?4)
+        AND NOT EXISTS (SELECT 1 FROM option_events e WHERE e.leg_id = l.id)
+        ORDER BY l.expiry, t.symbol"
+    )?;
+
+    let rows = stmt.query_map(params![open_status, from.to_string(), to.to_string(), account_id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut legs = Vec::new();
+    for row in rows {
+        let (leg_id, trade_id, symbol) = row?;
+        legs.push(ExpiringLeg {
+            trade_id,
+            symbol,
+            leg: option_leg_service::get_option_leg(conn, leg_id)?,
+            days_to_expiry: 0,
+        });
+    }
+
+    Ok(legs)
+}
+
+/// Records that a leg of an open trade expired, was exercised or was assigned.
+/// A leg only expires worthless from the close of its expiry session, and
+/// not when the underlying price given puts it in the money. Exercise and
+/// assignment open the resulting stock trade linked back to the
+/// option trade, and once every leg is resolved the option trade is closed.
+/// All of it is stored together or not at all.
+pub fn record_option_event(
+    conn: &Connection,
+    trade_id: i64,
+    leg_id: i64,
+    event_type: OptionEventType,
+    underlying_price: Option<f64>,
+    time: DateTime<Utc>,
+) -> Result<OptionEvent, Box<dyn Error>> {
+    let mut trade = trade_service::get_trade(conn, trade_id)?;
+    if !matches!(trade.status, TradeStatus::Open) {
+        return Err(format!("Trade {} is not open", trade_id).into());
+    }
+    let leg = trade.legs.iter()
+        .find(|leg| leg.id == Some(leg_id))
+        .cloned()
+        .ok_or_else(|| format!("Leg {} does not belong to trade {}", leg_id, trade_id))?;
+
+    match (event_type, leg.side) {
+        (OptionEventType::Assigned, LegSide::Buy) => return Err("Only sold legs can be assigned".into()),
+        (OptionEventType::Exercised, LegSide::Sell) => return Err("Only bought legs can be exercised".into()),
+        _ => {}
+    }
+    if event_type == OptionEventType::ExpiredWorthless {
+        let expiry_close = calendar_service::calendar_for_symbol(conn, &leg.underlying)?.expiry_close(leg.expiry);
+        if time < expiry_close {
+            return Err(format!("Leg {} does not expire until {}", leg_id, expiry_close.to_rfc3339()).into());
+        }
+        if underlying_price.is_some_and(|price| in_the_money(&leg, price)) {
+            return Err(format!("Leg {} expired in the money; record an exercise or assignment", leg_id).into());
+        }
+    }
+
+    let resolved = get_option_events_by_trade(conn, trade_id)?;
+    if resolved.iter().any(|event| event.leg_id == leg_id) {
+        return Err(format!("Leg {} has already been resolved", leg_id).into());
+    }
+
+    let mut event = OptionEvent::new(trade_id, leg_id, event_type);
+    event.timestamp = time;
+    event.underlying_price = underlying_price;
+
+    db::in_transaction(conn, || {
+        if event_type != OptionEventType::ExpiredWorthless {
+            let mut stock_trade = stock_trade_from_leg(&trade, &leg, event_type, time);
+            event.resulting_trade_id = Some(trade_service::insert_trade(conn, &mut stock_trade)?);
+            event.notes = stock_trade.notes.clone();
+        }
+
+        save_option_event(conn, &mut event)?;
+
+        let mut resolved_legs: Vec<(i64, OptionEventType)> = resolved.iter().map(|e| (e.leg_id, e.event_type)).collect();
+        resolved_legs.push((leg_id, event_type));
+        if trade.legs.iter().all(|leg| resolved_legs.iter().any(|(id, _)| Some(*id) == leg.id)) {
+            // Per share; exit_trade applies the contract multiplier
+            let exit_price = settlement_value(&trade.legs, &resolved_legs);
+            trade.exit_trade(time, exit_price);
+            trade_service::update_trade(conn, &mut trade)?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(event)
+}
+
+/// Marks legs expiring on `as_of` that settled out of the money as expired
+/// worthless, given the settlement price of each underlying on that date.
+/// Legs without a price are left alone, as are in-the-money legs, which need
+/// an exercise or assignment instead. A leg that cannot be expired is
+/// reported and the rest carry on.
+pub fn expire_worthless_legs(
+    conn: &Connection,
+    as_of: NaiveDate,
+    settlement_prices: &HashMap<String, f64>,
+) -> Result<ExpiryRun, Box<dyn Error>> {
+    let mut run = ExpiryRun::default();
+    for ExpiringLeg { trade_id, leg, .. } in unresolved_legs(conn, as_of, as_of, None)? {
+        let price = match settlement_prices.get(&leg.underlying) {
+            Some(price) => *price,
+            None => continue,
+        };
+        if in_the_money(&leg, price) {
+            continue;
+        }
+
+        let leg_id = leg.id.unwrap_or_default();
+        let expired = calendar_service::calendar_for_symbol(conn, &leg.underlying)
+            .and_then(|calendar| {
+                // Legs expire at the close of their exchange's session
+                let expiry_close = calendar.expiry_close(leg.expiry);
+                record_option_event(conn, trade_id, leg_id, OptionEventType::ExpiredWorthless, Some(price), expiry_close)
+            });
+        match expired {
+            Ok(event) => run.expired.push(event),
+            Err(e) => run.failed.push(format!("leg {} of trade {}: {}", leg_id, trade_id, e)),
+        }
+    }
+
+    Ok(run)
+}
+
+/// Whether `leg` has intrinsic value with the underlying at `price`.
+fn in_the_money(leg: &OptionLeg, price: f64) -> bool {
+    match leg.option_type {
+        OptionType::Call => price > leg.strike,
+        OptionType::Put => price < leg.strike,
+    }
+}
+
+/// The stock position created when `leg` is exercised or assigned. The premium
+/// of the leg is folded into the share price, giving the effective cost basis.
+fn stock_trade_from_leg(trade: &Trade, leg: &OptionLeg, event_type: OptionEventType, time: DateTime<Utc>) -> Trade {
+    let buys_stock = matches!(
+        (leg.option_type, leg.side),
+        (OptionType::Call, LegSide::Buy) | (OptionType::Put, LegSide::Sell)
+    );
+    let shares = leg.ratio * trade.quantity * CONTRACT_MULTIPLIER as u32;
+    let basis = if buys_stock {
+        leg.strike + leg.direction() * leg.premium
+    } else {
+        leg.strike - leg.direction() * leg.premium
+    };
+
+    let action = match event_type {
+        OptionEventType::Assigned => "Assigned",
+        _ => "Exercised",
+    };
+    let option_name = match leg.option_type {
+        OptionType::Call => "call",
+        OptionType::Put => "put",
+    };
+    let position = if buys_stock { "Long" } else { "Short" };
+
+    let mut stock_trade = Trade::new(&leg.underlying, trade.analysis_id);
+    stock_trade.enter_trade(time, basis, shares);
+    stock_trade.side = if buys_stock { PositionSide::Long } else { PositionSide::Short };
+    stock_trade.parent_trade_id = trade.id;
+    stock_trade.account_id = trade.account_id;
+    stock_trade.notes = Some(format!(
+        "{} {} shares: {} {} {} {} (trade {})",
+        position,
+        shares,
+        action,
+        leg.underlying,
+        leg.strike,
+        option_name,
+        trade.id.unwrap_or_default(),
+    ));
+
+    stock_trade
+}
+
+/// Net exit value of the option trade per share of one spread unit, like its
+/// entry price. Expired legs settle at zero; exercised and assigned legs at
+/// their premium, since that premium now sits in the cost basis of the stock
+/// trade.
+fn settlement_value(legs: &[OptionLeg], resolved: &[(i64, OptionEventType)]) -> f64 {
+    legs.iter().map(|leg| {
+        let settled_at = match resolved.iter().find(|(id, _)| Some(*id) == leg.id) {
+            Some((_, OptionEventType::ExpiredWorthless)) | None => 0.0,
+            Some(_) => leg.premium,
+        };
+        leg.direction() * leg.ratio as f64 * settled_at
+    }).sum()
+}
+
+pub fn save_option_event(conn: &Connection, event: &mut OptionEvent) -> Result<i64, Box<dyn Error>> {
+    let event_type_json = to_string(&event.event_type)?;
+
+    conn.execute(
+        "INSERT INTO option_events
+        (trade_id, leg_id, event_type, timestamp, underlying_price, resulting_trade_id, notes)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            event.trade_id,
+            event.leg_id,
+            event_type_json,
+            event.timestamp.to_rfc3339(),
+            event.underlying_price,
+            event.resulting_trade_id,
+            event.notes,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    event.id = Some(id);
+
+    Ok(id)
+}
+
+/// Ids of every leg that has been expired, exercised or assigned.
+pub fn get_resolved_leg_ids(conn: &Connection) -> Result<HashSet<i64>, Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT leg_id FROM option_events")?;
+
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+
+    Ok(ids)
+}
+
+pub fn get_option_events_by_trade(conn: &Connection, trade_id: i64) -> Result<Vec<OptionEvent>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, trade_id, leg_id, event_type, timestamp, underlying_price, resulting_trade_id, notes
+        FROM option_events
+        WHERE trade_id = ?1
+        ORDER BY timestamp"
+    )?;
+
+    let events_iter = stmt.query_map(params![trade_id], event_from_row)?;
+
+    let mut events = Vec::new();
+    for event in events_iter {
+        events.push(event?);
+    }
+
+    Ok(events)
+}
+
+fn event_from_row(row: &Row) -> rusqlite::Result<OptionEvent> {
+    let event_type_json: String = row.get(3)?;
+    let event_type = serde_json::from_str(&event_type_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let timestamp_str: String = row.get(4)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    Ok(OptionEvent {
+        id: Some(row.get(0)?),
+        trade_id: row.get(1)?,
+        leg_id: row.get(2)?,
+        event_type,
+        timestamp,
+        underlying_price: row.get(5)?,
+        resulting_trade_id: row.get(6)?,
+        notes: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DetailedAnalysis;
+    use crate::services::detailed_analysis_service;
+    use chrono::TimeZone;
+
+    fn open_trade(conn: &Connection, legs: Vec<OptionLeg>, entry_price: f64) -> Trade {
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        trade.legs = legs;
+        trade.enter_trade(Utc.with_ymd_and_hms(2024, 6, 3, 15, 0, 0).unwrap(), entry_price, 2);
+        trade_service::save_trade(conn, &mut trade).unwrap();
+        trade
+    }
+
+    fn leg(option_type: OptionType, side: LegSide, strike: f64, premium: f64) -> OptionLeg {
+        let mut leg = OptionLeg::new("AAPL", NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), strike, option_type, side);
+        leg.premium = premium;
+        leg
+    }
+
+    #[test]
+    fn assigned_short_call_opens_short_stock() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let trade = open_trade(&conn, vec![leg(OptionType::Call, LegSide::Sell, 100.0, 2.0)], -2.0);
+        let time = Utc.with_ymd_and_hms(2024, 6, 21, 20, 0, 0).unwrap();
+
+        let event = record_option_event(&conn, trade.id.unwrap(), trade.legs[0].id.unwrap(), OptionEventType::Assigned, Some(110.0), time).unwrap();
+
+        let mut stock = trade_service::get_trade(&conn, event.resulting_trade_id.unwrap()).unwrap();
+        assert_eq!(stock.side, PositionSide::Short);
+        assert_eq!(stock.quantity, 200);
+        assert_eq!(stock.entry_price, Some(102.0));
+        stock.exit_trade(time, 112.0);
+        assert_eq!(stock.profit_loss, Some(-2000.0));
+
+        let option_trade = trade_service::get_trade(&conn, trade.id.unwrap()).unwrap();
+        assert_eq!(option_trade.profit_loss, Some(0.0));
+    }
+
+    #[test]
+    fn expiry_settles_whole_contracts() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let trade = open_trade(&conn, vec![leg(OptionType::Put, LegSide::Buy, 90.0, 1.5)], 1.5);
+        let time = Utc.with_ymd_and_hms(2024, 6, 21, 20, 0, 0).unwrap();
+
+        record_option_event(&conn, trade.id.unwrap(), trade.legs[0].id.unwrap(), OptionEventType::ExpiredWorthless, Some(95.0), time).unwrap();
+
+        let closed = trade_service::get_trade(&conn, trade.id.unwrap()).unwrap();
+        assert_eq!(closed.profit_loss, Some(-300.0));
+        assert!(record_option_event(&conn, trade.id.unwrap(), trade.legs[0].id.unwrap(), OptionEventType::ExpiredWorthless, None, time).is_err());
+    }
+
+    #[test]
+    fn days_to_expiry_count_from_the_exchange_date() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        open_trade(&conn, vec![leg(OptionType::Put, LegSide::Buy, 90.0, 1.5)], 1.5);
+        // Monday evening in New York, already Tuesday in UTC
+        let now = Utc.with_ymd_and_hms(2024, 6, 18, 2, 0, 0).unwrap();
+
+        let legs = get_legs_expiring_this_week(&conn, now, None).unwrap();
+        assert_eq!(legs.len(), 1);
+        assert_eq!(legs[0].days_to_expiry, 4);
+    }
+
+    #[test]
+    fn worthless_legs_expire_at_the_session_close() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let trade = open_trade(&conn, vec![leg(OptionType::Call, LegSide::Buy, 120.0, 1.0)], 1.0);
+        let prices = HashMap::from([("AAPL".to_string(), 110.0)]);
+
+        let run = expire_worthless_legs(&conn, NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), &prices).unwrap();
+        assert_eq!(run.expired.len(), 1);
+        assert_eq!(run.expired[0].timestamp, Utc.with_ymd_and_hms(2024, 6, 21, 20, 0, 0).unwrap());
+        assert!(matches!(trade_service::get_trade(&conn, trade.id.unwrap()).unwrap().status, TradeStatus::Closed));
+    }
+
+    #[test]
+    fn legs_only_expire_worthless_after_the_close_and_out_of_the_money() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let trade = open_trade(&conn, vec![leg(OptionType::Put, LegSide::Buy, 90.0, 1.5)], 1.5);
+        let (trade_id, leg_id) = (trade.id.unwrap(), trade.legs[0].id.unwrap());
+
+        // The session closes at 16:00 in New York, 20:00 UTC
+        let before_close = Utc.with_ymd_and_hms(2024, 6, 21, 19, 0, 0).unwrap();
+        assert!(record_option_event(&conn, trade_id, leg_id, OptionEventType::ExpiredWorthless, Some(95.0), before_close).is_err());
+        let after_close = Utc.with_ymd_and_hms(2024, 6, 21, 20, 0, 0).unwrap();
+        assert!(record_option_event(&conn, trade_id, leg_id, OptionEventType::ExpiredWorthless, Some(85.0), after_close).is_err());
+        assert!(record_option_event(&conn, trade_id, leg_id, OptionEventType::ExpiredWorthless, Some(95.0), after_close).is_ok());
+    }
+
+    #[test]
+    fn only_legs_expiring_that_day_are_expired() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut earlier = leg(OptionType::Call, LegSide::Buy, 120.0, 1.0);
+        earlier.expiry = NaiveDate::from_ymd_opt(2024, 6, 14).unwrap();
+        let trade = open_trade(&conn, vec![earlier, leg(OptionType::Call, LegSide::Buy, 130.0, 0.5)], 1.5);
+        let prices = HashMap::from([("AAPL".to_string(), 110.0)]);
+
+        let run = expire_worthless_legs(&conn, NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), &prices).unwrap();
+        assert_eq!(run.expired.len(), 1);
+        assert_eq!(run.expired[0].leg_id, trade.legs[1].id.unwrap());
+        assert!(run.failed.is_empty());
+    }
+
+    #[test]
+    fn a_leg_that_cannot_expire_does_not_stop_the_rest() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let first = open_trade(&conn, vec![leg(OptionType::Call, LegSide::Buy, 120.0, 1.0)], 1.0);
+        let second = open_trade(&conn, vec![leg(OptionType::Call, LegSide::Buy, 125.0, 1.0)], 1.0);
+        conn.execute_batch(&format!(
+            "CREATE TRIGGER stuck BEFORE INSERT ON option_events WHEN NEW.trade_id = {} BEGIN SELECT RAISE(ABORT, 'stuck'); END",
+            first.id.unwrap(),
+        )).unwrap();
+        let prices = HashMap::from([("AAPL".to_string(), 110.0)]);
+
+        let run = expire_worthless_legs(&conn, NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), &prices).unwrap();
+        assert_eq!(run.expired.len(), 1);
+        assert_eq!(run.expired[0].trade_id, second.id.unwrap());
+        assert_eq!(run.failed.len(), 1);
+    }
+}
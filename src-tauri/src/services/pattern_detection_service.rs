@@ -0,0 +1,24 @@
+use rusqlite::Connection;
+use std::error::Error;
+
+use crate::analytics::chart_patterns::{self, PatternDetection, PatternDetectorConfig};
+use crate::models::price_bar::Timeframe;
+use crate::services::price_service;
+
+/// Bars handed to the detectors, enough for a multi-month base or cup.
+pub const DETECTION_BARS: i64 = 120;
+/// Least confidence at which a detection is offered when rating a symbol.
+pub const MIN_SUGGESTION_CONFIDENCE: f64 = 0.5;
+
+pub fn detect_chart_patterns(conn: &Connection, symbol: &str, timeframe: Timeframe) -> Result<Vec<PatternDetection>, Box<dyn Error>> {
+    let bars = price_service::get_bars(conn, symbol, timeframe, DETECTION_BARS)?;
+
+    Ok(chart_patterns::detect_patterns(&bars, &PatternDetectorConfig::default()))
+}
+
+/// The most confident pattern on the daily chart, to prefill a new rating.
+pub fn suggest_chart_pattern(conn: &Connection, symbol: &str) -> Result<Option<PatternDetection>, Box<dyn Error>> {
+    let detections = detect_chart_patterns(conn, symbol, Timeframe::Daily)?;
+
+    Ok(detections.into_iter().find(|d| d.confidence >= MIN_SUGGESTION_CONFIDENCE))
+}
@@ -0,0 +1,90 @@
+use rusqlite::{Connection, params};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::analytics::pattern_stats::{self, PatternOutcome, PatternWindow, RatedPattern};
+use crate::models::stock_rating::StockRating;
+use crate::models::trade::TradeStatus;
+
+/// Rolling windows, in days, used when none are requested.
+pub const DEFAULT_WINDOWS: [i64; 3] = [30, 90, 365];
+
+/// Pattern occurrence and hit rate statistics for each of `windows`, followed
+/// by all history. Hit rates always use every closed trade of the account, or
+/// of all accounts when `account_id` is None.
+pub fn get_pattern_histogram(conn: &Connection, windows: &[i64], now: DateTime<Utc>, account_id: Option<i64>) -> Result<Vec<PatternWindow>, Box<dyn Error>> {
+    let ratings = get_rated_patterns(conn)?;
+    let outcomes = get_pattern_outcomes(conn, account_id)?;
+
+    let histogram = windows.iter()
+        .map(|days| Some(*days))
+        .chain(std::iter::once(None))
+        .map(|days| pattern_stats::pattern_window(&ratings, &outcomes, days, now))
+        .collect();
+
+    Ok(histogram)
+}
+
+/// Statistics over all accounts and all history before `as_of`, which the
+/// scoring pattern bonus is based on.
+pub fn get_all_time_pattern_stats(conn: &Connection, as_of: DateTime<Utc>) -> Result<PatternWindow, Box<dyn Error>> {
+    let (ratings, outcomes) = get_pattern_history(conn)?;
+
+    Ok(pattern_stats::pattern_window(&ratings, &outcomes, None, as_of))
+}
+
+/// Every rated pattern and every closed trade outcome across accounts, for
+/// computing statistics as of several points in time.
+pub fn get_pattern_history(conn: &Connection) -> Result<(Vec<RatedPattern>, Vec<PatternOutcome>), Box<dyn Error>> {
+    Ok((get_rated_patterns(conn)?, get_pattern_outcomes(conn, None)?))
+}
+
+/// Pattern bonus for `rating` given all-history statistics.
+pub fn pattern_bonus_for(window: &PatternWindow, rating: &StockRating) -> f64 {
+    let stats = window.patterns.iter().find(|stats| stats.pattern == rating.chart_pattern);
+    pattern_stats::pattern_bonus(&rating.chart_pattern, stats, rating.bull_bear)
+}
+
+fn get_rated_patterns(conn: &Connection) -> Result<Vec<RatedPattern>, Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT chart_pattern, timestamp FROM stock_ratings")?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut patterns = Vec::new();
+    for row in rows {
+        let (pattern_json, timestamp_str) = row?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)?.with_timezone(&Utc);
+        patterns.push((serde_json::from_str(&pattern_json)?, timestamp));
+    }
+
+    Ok(patterns)
+}
+
+/// Pattern and direction of the analysis behind each closed trade, with its
+/// profit/loss and when it closed.
+fn get_pattern_outcomes(conn: &Connection, account_id: Option<i64>) -> Result<Vec<PatternOutcome>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.chart_pattern, a.bull_bear, t.profit_loss, COALESCE(t.exit_time, t.timestamp)
+        FROM trades t
+        JOIN detailed_analyses a ON t.analysis_id = a.id
+        WHERE t.status = ?1 AND t.profit_loss IS NOT NULL AND (?2 IS NULL OR t.account_id = ?2)"
+    )?;
+
+    let rows = stmt.query_map(params![to_string(&TradeStatus::Closed)?, account_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i8>(1)?, row.get::<_, f64>(2)?, row.get::<_, String>(3)?))
+    })?;
+
+    let mut outcomes = Vec::new();
+    for row in rows {
+        let (pattern_json, bull_bear, profit_loss, closed_at) = row?;
+        outcomes.push(PatternOutcome {
+            pattern: serde_json::from_str(&pattern_json)?,
+            bull_bear,
+            profit_loss,
+            closed_at: chrono::DateTime::parse_from_rfc3339(&closed_at)?.with_timezone(&Utc),
+        });
+    }
+
+    Ok(outcomes)
+}
@@ -0,0 +1,43 @@
+use rusqlite::Connection;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::analytics::payoff::{self, PayoffDiagram, PayoffPosition, PayoffRequest};
+use crate::models::{DetailedAnalysis, Trade};
+use crate::services::{calendar_service, detailed_analysis_service, trade_service};
+
+pub fn analysis_position(analysis: &DetailedAnalysis) -> PayoffPosition {
+    if analysis.legs.is_empty() {
+        let mut position = PayoffPosition::from_legs(Vec::new(), 1.0);
+        position.shares = analysis.quantity as f64 * analysis.bull_bear.signum() as f64;
+        position.share_cost = analysis.entry_price;
+        position
+    } else {
+        PayoffPosition::from_legs(analysis.legs.clone(), analysis.quantity as f64)
+    }
+}
+
+pub fn trade_position(trade: &Trade) -> PayoffPosition {
+    if trade.legs.is_empty() {
+        let mut position = PayoffPosition::from_legs(Vec::new(), 1.0);
+        position.shares = trade.quantity as f64;
+        position.share_cost = trade.entry_price.unwrap_or(0.0);
+        position
+    } else {
+        PayoffPosition::from_legs(trade.legs.clone(), trade.quantity as f64)
+    }
+}
+
+pub fn get_analysis_payoff(conn: &Connection, analysis_id: i64, request: &PayoffRequest, now: DateTime<Utc>) -> Result<PayoffDiagram, Box<dyn Error>> {
+    let analysis = detailed_analysis_service::get_detailed_analysis(conn, analysis_id)?;
+    let calendar = calendar_service::calendar_for_symbol(conn, &analysis.security)?;
+
+    Ok(payoff::build_payoff(&analysis_position(&analysis), request, &calendar, calendar.local_date(now)))
+}
+
+pub fn get_trade_payoff(conn: &Connection, trade_id: i64, request: &PayoffRequest, now: DateTime<Utc>) -> Result<PayoffDiagram, Box<dyn Error>> {
+    let trade = trade_service::get_trade(conn, trade_id)?;
+    let calendar = calendar_service::calendar_for_symbol(conn, &trade.symbol)?;
+
+    Ok(payoff::build_payoff(&trade_position(&trade), request, &calendar, calendar.local_date(now)))
+}
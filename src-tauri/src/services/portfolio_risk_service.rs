@@ -0,0 +1,123 @@
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::analytics::{beta, portfolio_greeks};
+use crate::analytics::portfolio_greeks::PortfolioGreeks;
+use crate::models::calendar::TradingCalendar;
+use crate::models::portfolio_risk::PortfolioRiskSettings;
+use crate::models::price_bar::Timeframe;
+use crate::services::{calendar_service, option_lifecycle_service, price_service, settings_service, time_zone_service, trade_service};
+
+pub fn get_portfolio_risk_settings(conn: &Connection) -> Result<PortfolioRiskSettings, Box<dyn Error>> {
+    settings_service::get_setting_or_default(conn, settings_service::PORTFOLIO_RISK_KEY)
+}
+
+pub fn save_portfolio_risk_settings(conn: &Connection, settings: &PortfolioRiskSettings) -> Result<(), Box<dyn Error>> {
+    settings_service::set_setting(conn, settings_service::PORTFOLIO_RISK_KEY, settings)
+}
+
+/// Net Greeks of the open trades in one account, or in all accounts when
+/// `account_id` is None, priced off the latest close in the local price
+/// store, with betas against the configured benchmark.
+pub fn get_portfolio_greeks(conn: &Connection, now: DateTime<Utc>, account_id: Option<i64>) -> Result<PortfolioGreeks, Box<dyn Error>> {
+    let settings = get_portfolio_risk_settings(conn)?;
+    let open_trades = trade_service::get_open_trades(conn, account_id)?;
+
+    let lookback = settings.beta_lookback_days as i64 + 1;
+    let benchmark_bars = price_service::get_bars(conn, &settings.benchmark, Timeframe::Daily, lookback)?;
+    let benchmark_price = price_service::get_latest_close(conn, &settings.benchmark)?;
+    let zone = time_zone_service::get_time_zone_settings(conn)?.exchange_timezone;
+
+    let mut prices: HashMap<String, f64> = HashMap::new();
+    let mut betas: HashMap<String, f64> = HashMap::new();
+    let mut calendars: HashMap<String, TradingCalendar> = HashMap::new();
+    let mut missing_prices = Vec::new();
+    let mut positions = Vec::new();
+
+    let resolved_legs = option_lifecycle_service::get_resolved_leg_ids(conn)?;
+
+    for trade in &open_trades {
+        // Legs already expired, exercised or assigned are gone, and any stock
+        // they turned into is an open trade of its own
+        let mut trade = trade.clone();
+        if !trade.legs.is_empty() {
+            trade.legs.retain(|leg| !leg.id.is_some_and(|id| resolved_legs.contains(&id)));
+            if trade.legs.is_empty() {
+                continue;
+            }
+        }
+
+        let underlying = trade.symbol.to_ascii_uppercase();
+
+        if !prices.contains_key(&underlying) && !missing_prices.contains(&underlying) {
+            match price_service::get_latest_close(conn, &underlying)? {
+                Some(price) => {
+                    prices.insert(underlying.clone(), price);
+                    let bars = price_service::get_bars(conn, &underlying, Timeframe::Daily, lookback)?;
+                    if let Some(value) = beta::beta(&bars, &benchmark_bars, zone) {
+                        betas.insert(underlying.clone(), value);
+                    }
+                }
+                None => missing_prices.push(underlying.clone()),
+            }
+        }
+
+        if let Some(price) = prices.get(&underlying) {
+            if !calendars.contains_key(&underlying) {
+                calendars.insert(underlying.clone(), calendar_service::calendar_for_symbol(conn, &underlying)?);
+            }
+            positions.push(portfolio_greeks::position_greeks(&trade, *price, now, &calendars[&underlying], &settings));
+        }
+    }
+
+    let mut greeks = portfolio_greeks::aggregate(&positions, &prices, &betas, benchmark_price, &settings);
+    greeks.missing_prices = missing_prices;
+
+    Ok(greeks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+    use crate::models::DetailedAnalysis;
+    use crate::models::option_event::OptionEventType;
+    use crate::analytics::black_scholes::OptionType;
+    use crate::models::option_leg::{LegSide, OptionLeg};
+    use crate::models::price_bar::PriceBar;
+    use crate::models::trade::Trade;
+    use crate::services::{db, detailed_analysis_service};
+
+    #[test]
+    fn assigned_legs_are_counted_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let time = Utc.with_ymd_and_hms(2024, 6, 21, 20, 0, 0).unwrap();
+        price_service::import_bars(&conn, &[PriceBar {
+            symbol: "AAPL".to_string(),
+            timeframe: Timeframe::Daily,
+            timestamp: time,
+            open: 110.0,
+            high: 110.0,
+            low: 110.0,
+            close: 110.0,
+            volume: 0.0,
+        }]).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        let mut leg = OptionLeg::new("AAPL", NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 100.0, OptionType::Call, LegSide::Sell);
+        leg.premium = 2.0;
+        trade.legs = vec![leg];
+        trade.enter_trade(time, -2.0, 1);
+        trade_service::save_trade(&conn, &mut trade).unwrap();
+
+        option_lifecycle_service::record_option_event(&conn, trade.id.unwrap(), trade.legs[0].id.unwrap(), OptionEventType::Assigned, Some(110.0), time).unwrap();
+
+        let greeks = get_portfolio_greeks(&conn, time, None).unwrap();
+        assert_eq!(greeks.net_delta, -100.0);
+    }
+}
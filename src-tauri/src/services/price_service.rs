@@ -0,0 +1,117 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde_json::to_string;
+use std::collections::HashSet;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::models::price_bar::{PriceBar, Timeframe};
+use crate::services::indicator_service;
+
+/// Stores bars in the local price store, replacing any bar already stored for
+/// the same symbol, timeframe and timestamp, and drops the cached indicators
+/// the new bars make stale. Returns the number of bars saved.
+pub fn import_bars(conn: &Connection, bars: &[PriceBar]) -> Result<usize, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO price_bars
+        (symbol, timeframe, timestamp, open, high, low, close, volume)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+    )?;
+
+    for bar in bars {
+        stmt.execute(params![
+            bar.symbol.to_ascii_uppercase(),
+            to_string(&bar.timeframe)?,
+            bar.timestamp.to_rfc3339(),
+            bar.open,
+            bar.high,
+            bar.low,
+            bar.close,
+            bar.volume,
+        ])?;
+    }
+
+    let series: HashSet<(String, Timeframe)> = bars.iter()
+        .map(|bar| (bar.symbol.to_ascii_uppercase(), bar.timeframe))
+        .collect();
+    for (symbol, timeframe) in series {
+        indicator_service::invalidate_indicators(conn, &symbol, timeframe)?;
+    }
+
+    Ok(bars.len())
+}
+
+/// The most recent `limit` bars of a symbol, oldest first.
+pub fn get_bars(conn: &Connection, symbol: &str, timeframe: Timeframe, limit: i64) -> Result<Vec<PriceBar>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT symbol, timeframe, timestamp, open, high, low, close, volume
+        FROM (
+            SELECT * FROM price_bars
+            WHERE symbol = ?1 AND timeframe = ?2
+            ORDER BY timestamp DESC
+            LIMIT ?3
+        )
+        ORDER BY timestamp"
+    )?;
+
+    let bars_iter = stmt.query_map(
+        params![symbol.to_ascii_uppercase(), to_string(&timeframe)?, limit],
+        bar_from_row,
+    )?;
+
+    let mut bars = Vec::new();
+    for bar in bars_iter {
+        bars.push(bar?);
+    }
+
+    Ok(bars)
+}
+
+pub fn get_latest_close(conn: &Connection, symbol: &str) -> Result<Option<f64>, Box<dyn Error>> {
+    let close = conn.query_row(
+        "SELECT close FROM price_bars
+        WHERE symbol = ?1
+        ORDER BY timestamp DESC
+        LIMIT 1",
+        params![symbol.to_ascii_uppercase()],
+        |row| row.get(0),
+    ).optional()?;
+
+    Ok(close)
+}
+
+/// The most recent bar of a symbol in any timeframe, the one
+/// `get_latest_close` reads.
+pub fn get_latest_bar(conn: &Connection, symbol: &str) -> Result<Option<PriceBar>, Box<dyn Error>> {
+    let bar = conn.query_row(
+        "SELECT symbol, timeframe, timestamp, open, high, low, close, volume FROM price_bars
+        WHERE symbol = ?1
+        ORDER BY timestamp DESC
+        LIMIT 1",
+        params![symbol.to_ascii_uppercase()],
+        bar_from_row,
+    ).optional()?;
+
+    Ok(bar)
+}
+
+fn bar_from_row(row: &Row) -> rusqlite::Result<PriceBar> {
+    let timeframe_json: String = row.get(1)?;
+    let timeframe = serde_json::from_str(&timeframe_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let timestamp_str: String = row.get(2)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    Ok(PriceBar {
+        symbol: row.get(0)?,
+        timeframe,
+        timestamp,
+        open: row.get(3)?,
+        high: row.get(4)?,
+        low: row.get(5)?,
+        close: row.get(6)?,
+        volume: row.get(7)?,
+    })
+}
@@ -0,0 +1,93 @@
+use rusqlite::Connection;
+use std::error::Error;
+
+use crate::models::detailed_analysis::DetailedAnalysis;
+use crate::models::promotion::{AnalysisChain, RatingChain, TradeLineage};
+use crate::models::trade::Trade;
+use crate::services::{detailed_analysis_service, stock_rating_service, trade_service};
+
+/// Starts a detailed analysis from a stored rating, linked back to it.
+pub fn promote_rating(conn: &Connection, rating_id: i64) -> Result<DetailedAnalysis, Box<dyn Error>> {
+    let rating = stock_rating_service::get_stock_rating(conn, rating_id)?;
+
+    let mut analysis = DetailedAnalysis::from_rating(&rating);
+    detailed_analysis_service::save_detailed_analysis(conn, &mut analysis)?;
+
+    Ok(analysis)
+}
+
+/// Creates a Planned trade from an analysis. The trade goes through the
+/// pre-trade checklist like any other.
+pub fn plan_trade(conn: &Connection, analysis_id: i64) -> Result<Trade, Box<dyn Error>> {
+    let analysis = detailed_analysis_service::get_detailed_analysis(conn, analysis_id)?;
+
+    let mut trade = Trade::from_analysis(analysis_id, &analysis);
+    trade_service::save_trade(conn, &mut trade)?;
+
+    Ok(trade)
+}
+
+pub fn get_rating_chain(conn: &Connection, rating_id: i64) -> Result<RatingChain, Box<dyn Error>> {
+    let rating = stock_rating_service::get_stock_rating(conn, rating_id)?;
+
+    let mut analyses = Vec::new();
+    for analysis in detailed_analysis_service::get_analyses_by_rating(conn, rating_id)? {
+        let trades = match analysis.id {
+            Some(id) => trade_service::get_trades_by_analysis(conn, id)?,
+            None => Vec::new(),
+        };
+        analyses.push(AnalysisChain { analysis, trades });
+    }
+
+    Ok(RatingChain { rating, analyses })
+}
+
+pub fn get_trade_lineage(conn: &Connection, trade_id: i64) -> Result<TradeLineage, Box<dyn Error>> {
+    let trade = trade_service::get_trade(conn, trade_id)?;
+    let analysis = detailed_analysis_service::get_detailed_analysis(conn, trade.analysis_id)?;
+    let rating = match analysis.rating_id {
+        Some(rating_id) => Some(stock_rating_service::get_stock_rating(conn, rating_id)?),
+        None => None,
+    };
+
+    Ok(TradeLineage { trade, analysis, rating })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::stock_rating::StockRating;
+    use crate::services::db;
+
+    #[test]
+    fn rating_analysis_and_trade_stay_linked() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut rating = StockRating::new("AAPL", "Information Technology");
+        let rating_id = stock_rating_service::save_stock_rating(&conn, &mut rating).unwrap();
+
+        let analysis = promote_rating(&conn, rating_id).unwrap();
+        assert_eq!(analysis.rating_id, Some(rating_id));
+        let trade = plan_trade(&conn, analysis.id.unwrap()).unwrap();
+
+        let chain = get_rating_chain(&conn, rating_id).unwrap();
+        assert_eq!(chain.analyses.len(), 1);
+        assert_eq!(chain.analyses[0].trades.iter().map(|trade| trade.id).collect::<Vec<_>>(), vec![trade.id]);
+
+        let lineage = get_trade_lineage(&conn, trade.id.unwrap()).unwrap();
+        assert_eq!(lineage.analysis.id, analysis.id);
+        assert_eq!(lineage.rating.and_then(|rating| rating.id), Some(rating_id));
+    }
+
+    #[test]
+    fn analysis_without_a_rating_has_no_rating_in_its_lineage() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        let trade = plan_trade(&conn, analysis_id).unwrap();
+        assert!(get_trade_lineage(&conn, trade.id.unwrap()).unwrap().rating.is_none());
+        assert!(promote_rating(&conn, 42).is_err());
+    }
+}
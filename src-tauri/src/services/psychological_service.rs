@@ -3,18 +3,22 @@ use serde_json::to_string;
 use std::error::Error;
 use chrono::Utc;
 
+use crate::models::time_zone;
 use crate::models::PsychologicalState;
 
 pub fn save_psychological_state(conn: &Connection, state: &mut PsychologicalState) -> Result<i64, Box<dyn Error>> {
     state.update_risk_score();
-    state.timestamp = Utc::now();
+    let recorded_at = Utc::now();
+    time_zone::check_entry_timestamp(state.timestamp, recorded_at)?;
+    state.recorded_at = Some(recorded_at);
     
     let extra_factors_json = to_string(&state.extra_factors)?;
     
     conn.execute(
         "INSERT INTO psychological_states 
-        (timestamp, gain_loss_yesterday, emotional_state, fomo, market_bias, hunger, headache_pain, extra_factors, total_risk_score) 
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        (timestamp, gain_loss_yesterday, emotional_state, fomo, market_bias, hunger, headache_pain, extra_factors, total_risk_score,
+        recorded_at) 
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
             state.timestamp.to_rfc3339(),
             state.gain_loss_yesterday,
@@ -24,7 +28,8 @@ pub fn save_psychological_state(conn: &Connection, state: &mut PsychologicalStat
             state.hunger,
             state.headache_pain,
             extra_factors_json,
-            state.total_risk_score
+            state.total_risk_score,
+            state.recorded_at.map(|dt| dt.to_rfc3339()),
         ],
     )?;
     
@@ -38,7 +43,7 @@ pub fn get_psychological_state(conn: &Connection, id: i64) -> Result<Psychologic
     let mut stmt = conn.prepare(
         "SELECT 
             id, timestamp, gain_loss_yesterday, emotional_state, fomo, market_bias, 
-            hunger, headache_pain, extra_factors, total_risk_score 
+            hunger, headache_pain, extra_factors, total_risk_score, recorded_at 
         FROM psychological_states 
         WHERE id = ?1"
     )?;
@@ -53,6 +58,12 @@ pub fn get_psychological_state(conn: &Connection, id: i64) -> Result<Psychologic
         let extra_factors = serde_json::from_str(&extra_factors_json)
             .map_err(|e| rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, Box::new(e)))?;
         
+        let recorded_at_str: Option<String> = row.get(10)?;
+        let recorded_at = recorded_at_str
+            .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e)))?;
+        
         Ok(PsychologicalState {
             id: Some(row.get(0)?),
             timestamp,
@@ -64,6 +75,7 @@ pub fn get_psychological_state(conn: &Connection, id: i64) -> Result<Psychologic
             headache_pain: row.get(7)?,
             extra_factors,
             total_risk_score: row.get(9)?,
+            recorded_at,
         })
     })?;
     
@@ -74,7 +86,7 @@ pub fn get_recent_psychological_states(conn: &Connection, limit: i64) -> Result<
     let mut stmt = conn.prepare(
         "SELECT 
             id, timestamp, gain_loss_yesterday, emotional_state, fomo, market_bias, 
-            hunger, headache_pain, extra_factors, total_risk_score 
+            hunger, headache_pain, extra_factors, total_risk_score, recorded_at 
         FROM psychological_states 
         ORDER BY timestamp DESC 
         LIMIT ?1"
@@ -90,6 +102,12 @@ pub fn get_recent_psychological_states(conn: &Connection, limit: i64) -> Result<
         let extra_factors = serde_json::from_str(&extra_factors_json)
             .map_err(|e| rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, Box::new(e)))?;
         
+        let recorded_at_str: Option<String> = row.get(10)?;
+        let recorded_at = recorded_at_str
+            .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e)))?;
+        
         Ok(PsychologicalState {
             id: Some(row.get(0)?),
             timestamp,
@@ -101,6 +119,7 @@ pub fn get_recent_psychological_states(conn: &Connection, limit: i64) -> Result<
             headache_pain: row.get(7)?,
             extra_factors,
             total_risk_score: row.get(9)?,
+            recorded_at,
         })
     })?;
     
@@ -0,0 +1,55 @@
+use rusqlite::Connection;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::models::rating_freshness::{RatingDecaySettings, RatingFreshness};
+use crate::models::stock_rating::StockRating;
+use crate::services::{settings_service, stock_rating_service};
+
+pub fn get_rating_decay_settings(conn: &Connection) -> Result<RatingDecaySettings, Box<dyn Error>> {
+    settings_service::get_setting_or_default(conn, settings_service::RATING_DECAY_KEY)
+}
+
+pub fn save_rating_decay_settings(conn: &Connection, settings: &RatingDecaySettings) -> Result<(), Box<dyn Error>> {
+    if settings.half_life_days <= 0.0 {
+        return Err("Half-life must be greater than zero".into());
+    }
+    if settings.stale_after_days < 0 {
+        return Err("Stale-after days cannot be negative".into());
+    }
+
+    settings_service::set_setting(conn, settings_service::RATING_DECAY_KEY, settings)
+}
+
+/// The latest rating of every symbol with its decayed score and stale flag.
+pub fn get_rating_freshness(conn: &Connection, as_of: DateTime<Utc>) -> Result<Vec<RatingFreshness>, Box<dyn Error>> {
+    let settings = get_rating_decay_settings(conn)?;
+
+    let freshness = stock_rating_service::get_latest_stock_ratings(conn)?
+        .into_iter()
+        .map(|rating| RatingFreshness::new(rating, as_of, &settings))
+        .collect();
+
+    Ok(freshness)
+}
+
+/// Symbols whose latest rating is stale, oldest first.
+pub fn get_rerating_queue(conn: &Connection, as_of: DateTime<Utc>) -> Result<Vec<RatingFreshness>, Box<dyn Error>> {
+    let mut queue: Vec<RatingFreshness> = get_rating_freshness(conn, as_of)?
+        .into_iter()
+        .filter(|freshness| freshness.stale)
+        .collect();
+    queue.sort_by_key(|freshness| freshness.rating.timestamp);
+
+    Ok(queue)
+}
+
+/// An unsaved rating for `symbol` prefilled from its latest rating.
+pub fn start_rerating(conn: &Connection, symbol: &str) -> Result<StockRating, Box<dyn Error>> {
+    let latest = stock_rating_service::get_stock_ratings_by_symbol(conn, symbol)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No rating found for {}", symbol))?;
+
+    Ok(latest.rerate())
+}
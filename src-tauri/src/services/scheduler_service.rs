@@ -0,0 +1,463 @@
+use rusqlite::{Connection, Row, params};
+use serde_json::to_string;
+use std::error::Error;
+use std::path::Path;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::account::AccountSummary;
+use crate::models::alert::AlertStatus;
+use crate::models::cron::CronExpression;
+use crate::models::job::{DailyReport, JobKind, JobOutcome, JobRun, JobRunStatus, ScheduledJob};
+use crate::models::trade::TradeStatus;
+use crate::services::{account_service, alert_service, portfolio_risk_service, rating_freshness_service, time_zone_service, trade_service};
+
+const JOB_COLUMNS: &str = "id, name, kind, cron, enabled, last_run_at, next_run_at";
+
+const RUN_COLUMNS: &str = "id, job_id, job_name, started_at, finished_at, status, summary";
+
+const BACKUP_PREFIX: &str = "stock_dashboard-";
+
+pub fn save_scheduled_job(conn: &Connection, job: &mut ScheduledJob) -> Result<i64, Box<dyn Error>> {
+    job.next_run_at = next_run(conn, job, Utc::now())?;
+
+    conn.execute(
+        "INSERT INTO scheduled_jobs (name, kind, cron, enabled, last_run_at, next_run_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            job.name,
+            to_string(&job.kind)?,
+            job.cron.trim(),
+            job.enabled,
+            job.last_run_at.map(|dt| dt.to_rfc3339()),
+            job.next_run_at.map(|dt| dt.to_rfc3339()),
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    job.id = Some(id);
+
+    Ok(id)
+}
+
+/// Stores changes to a job and reschedules it from now.
+pub fn update_scheduled_job(conn: &Connection, job: &mut ScheduledJob) -> Result<(), Box<dyn Error>> {
+    job.next_run_at = next_run(conn, job, Utc::now())?;
+
+    conn.execute(
+        "UPDATE scheduled_jobs SET name = ?1, kind = ?2, cron = ?3, enabled = ?4, next_run_at = ?5
+        WHERE id = ?6",
+        params![
+            job.name,
+            to_string(&job.kind)?,
+            job.cron.trim(),
+            job.enabled,
+            job.next_run_at.map(|dt| dt.to_rfc3339()),
+            job.id,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_scheduled_job(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM job_runs WHERE job_id = ?1", params![id])?;
+    conn.execute("DELETE FROM scheduled_jobs WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+pub fn set_job_enabled(conn: &Connection, id: i64, enabled: bool) -> Result<ScheduledJob, Box<dyn Error>> {
+    let mut job = get_scheduled_job(conn, id)?;
+    job.enabled = enabled;
+    update_scheduled_job(conn, &mut job)?;
+
+    Ok(job)
+}
+
+pub fn get_scheduled_job(conn: &Connection, id: i64) -> Result<ScheduledJob, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scheduled_jobs WHERE id = ?1", JOB_COLUMNS))?;
+
+    Ok(stmt.query_row(params![id], job_from_row)?)
+}
+
+pub fn get_scheduled_jobs(conn: &Connection) -> Result<Vec<ScheduledJob>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scheduled_jobs ORDER BY name", JOB_COLUMNS))?;
+
+    let jobs_iter = stmt.query_map([], job_from_row)?;
+
+    let mut jobs = Vec::new();
+    for job in jobs_iter {
+        jobs.push(job?);
+    }
+
+    Ok(jobs)
+}
+
+/// Run history of one job, or of all jobs, newest first.
+pub fn get_job_runs(conn: &Connection, job_id: Option<i64>, limit: i64) -> Result<Vec<JobRun>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM job_runs WHERE ?1 IS NULL OR job_id = ?1 ORDER BY started_at DESC, id DESC LIMIT ?2",
+        RUN_COLUMNS
+    ))?;
+
+    let runs_iter = stmt.query_map(params![job_id, limit], run_from_row)?;
+
+    let mut runs = Vec::new();
+    for run in runs_iter {
+        runs.push(run?);
+    }
+
+    Ok(runs)
+}
+
+/// Creates the default jobs on a database that has none.
+pub fn seed_default_jobs(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM scheduled_jobs", [], |row| row.get(0))?;
+    if count > 0 {
+        return Ok(());
+    }
+
+    for mut job in ScheduledJob::defaults() {
+        save_scheduled_job(conn, &mut job)?;
+    }
+
+    Ok(())
+}
+
+/// Runs every enabled job that is due at `now`, recording each run. Jobs
+/// missed while the app was closed run once and are rescheduled from `now`.
+pub fn run_due_jobs(conn: &Connection, data_dir: &Path, now: DateTime<Utc>) -> Result<Vec<(JobRun, JobOutcome)>, Box<dyn Error>> {
+    let due: Vec<ScheduledJob> = get_scheduled_jobs(conn)?
+        .into_iter()
+        .filter(|job| job.enabled && job.next_run_at.is_some_and(|next| next <= now))
+        .collect();
+
+    let mut runs = Vec::new();
+    for mut job in due {
+        runs.push(record_run(conn, &mut job, data_dir, now)?);
+    }
+
+    Ok(runs)
+}
+
+/// Runs a job straight away, whether or not it is enabled or due.
+pub fn run_job_now(conn: &Connection, id: i64, data_dir: &Path, now: DateTime<Utc>) -> Result<(JobRun, JobOutcome), Box<dyn Error>> {
+    let mut job = get_scheduled_job(conn, id)?;
+
+    record_run(conn, &mut job, data_dir, now)
+}
+
+/// Runs the job and stores the run. A failing job gives a Failed run rather
+/// than an error, so one broken job does not hold up the others.
+fn record_run(conn: &Connection, job: &mut ScheduledJob, data_dir: &Path, now: DateTime<Utc>) -> Result<(JobRun, JobOutcome), Box<dyn Error>> {
+    let job_id = job.id.ok_or("Job has not been saved")?;
+
+    let (status, outcome) = match run_job(conn, job, data_dir, now) {
+        Ok(outcome) => (JobRunStatus::Succeeded, outcome),
+        Err(e) => (JobRunStatus::Failed, JobOutcome { summary: e.to_string(), ..Default::default() }),
+    };
+
+    let mut run = JobRun {
+        id: None,
+        job_id,
+        job_name: job.name.clone(),
+        started_at: now,
+        finished_at: Utc::now().max(now),
+        status,
+        summary: outcome.summary.clone(),
+    };
+    save_job_run(conn, &mut run)?;
+
+    job.last_run_at = Some(now);
+    job.next_run_at = next_run(conn, job, now)?;
+    conn.execute(
+        "UPDATE scheduled_jobs SET last_run_at = ?1, next_run_at = ?2 WHERE id = ?3",
+        params![
+            job.last_run_at.map(|dt| dt.to_rfc3339()),
+            job.next_run_at.map(|dt| dt.to_rfc3339()),
+            job_id,
+        ],
+    )?;
+
+    Ok((run, outcome))
+}
+
+pub fn run_job(conn: &Connection, job: &ScheduledJob, data_dir: &Path, now: DateTime<Utc>) -> Result<JobOutcome, Box<dyn Error>> {
+    match &job.kind {
+        JobKind::Backup { keep } => backup_database(conn, &data_dir.join("backups"), *keep, now),
+        JobKind::RerateReminder => {
+            let queue = rating_freshness_service::get_rerating_queue(conn, now)?;
+            let symbols: Vec<String> = queue.iter().map(|f| f.rating.symbol.clone()).collect();
+            let summary = format!("{} ratings need redoing", symbols.len());
+
+            Ok(JobOutcome {
+                notification: (!symbols.is_empty()).then(|| format!("{}: {}", summary, symbols.join(", "))),
+                summary,
+                ..Default::default()
+            })
+        }
+        JobKind::AlertEvaluation => {
+            let triggers = alert_service::evaluate_alerts(conn, None, now)?;
+
+            Ok(JobOutcome {
+                summary: format!("{} alerts triggered", triggers.len()),
+                alert_triggers: triggers,
+                ..Default::default()
+            })
+        }
+        JobKind::MarkToMarket => {
+            let greeks = portfolio_risk_service::get_portfolio_greeks(conn, now, None)?;
+            let summary = format!(
+                "Net delta {:.2}, theta {:.2}, vega {:.2}",
+                greeks.net_delta, greeks.net_theta, greeks.net_vega
+            );
+
+            Ok(JobOutcome {
+                notification: (!greeks.breaches.is_empty())
+                    .then(|| format!("{} portfolio risk limits breached", greeks.breaches.len())),
+                summary,
+                ..Default::default()
+            })
+        }
+        JobKind::StalePlanCleanup { older_than_days } => {
+            let cancelled = trade_service::cancel_stale_plans(conn, now - Duration::days(*older_than_days))?;
+
+            Ok(JobOutcome {
+                summary: format!("{} planned trades cancelled", cancelled),
+                ..Default::default()
+            })
+        }
+        JobKind::Report => write_report(conn, &data_dir.join("reports"), now),
+    }
+}
+
+/// Checks a cron expression, returning its next run after now.
+pub fn validate_cron(conn: &Connection, expression: &str) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+    let zone = time_zone_service::get_time_zone_settings(conn)?.user_timezone;
+
+    Ok(CronExpression::parse(expression)?.next_after(Utc::now(), zone))
+}
+
+/// Next run of an enabled job, with its schedule read in the user's zone.
+fn next_run(conn: &Connection, job: &ScheduledJob, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+    let cron = CronExpression::parse(&job.cron)?;
+    let zone = time_zone_service::get_time_zone_settings(conn)?.user_timezone;
+
+    Ok(if job.enabled { cron.next_after(after, zone) } else { None })
+}
+
+/// Copies the database into `directory` and deletes all but the newest
+/// `keep` copies.
+fn backup_database(conn: &Connection, directory: &Path, keep: usize, now: DateTime<Utc>) -> Result<JobOutcome, Box<dyn Error>> {
+    std::fs::create_dir_all(directory)?;
+
+    let path = directory.join(format!("{}{}.db", BACKUP_PREFIX, now.format("%Y%m%d-%H%M%S")));
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+
+    let mut backups: Vec<_> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(".db")))
+        .collect();
+    backups.sort();
+
+    let pruned = backups.len().saturating_sub(keep.max(1));
+    for old in &backups[..pruned] {
+        std::fs::remove_file(old)?;
+    }
+
+    Ok(JobOutcome {
+        summary: format!("Backed up to {} ({} older copies removed)", path.display(), pruned),
+        ..Default::default()
+    })
+}
+
+/// Each account, then all accounts together when there are several and they
+/// share a currency.
+fn account_summaries(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<AccountSummary>, Box<dyn Error>> {
+    let mut summaries = Vec::new();
+    for account in account_service::get_accounts(conn)? {
+        summaries.push(account_service::get_account_summary(conn, account.id, now)?);
+    }
+    if summaries.len() >= 2 && summaries.windows(2).all(|pair| pair[0].currency == pair[1].currency) {
+        summaries.push(account_service::get_account_summary(conn, None, now)?);
+    }
+
+    Ok(summaries)
+}
+
+fn write_report(conn: &Connection, directory: &Path, now: DateTime<Utc>) -> Result<JobOutcome, Box<dyn Error>> {
+    let greeks = portfolio_risk_service::get_portfolio_greeks(conn, now, None)?;
+
+    let report = DailyReport {
+        generated_at: now,
+        open_trades: trade_service::get_open_trades(conn, None)?.len(),
+        planned_trades: trade_service::count_trades(conn, TradeStatus::Planned, None)?,
+        net_delta: greeks.net_delta,
+        net_theta: greeks.net_theta,
+        net_vega: greeks.net_vega,
+        beta_weighted_delta: greeks.beta_weighted_delta,
+        stale_ratings: rating_freshness_service::get_rerating_queue(conn, now)?
+            .into_iter()
+            .map(|f| f.rating.symbol)
+            .collect(),
+        active_alerts: alert_service::get_alerts(conn, Some(AlertStatus::Active), None)?.len(),
+        accounts: account_summaries(conn, now)?,
+    };
+
+    // Named for the exchange date its daily figures cover
+    let date = time_zone_service::exchange_today(conn, now)?;
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(format!("report-{}.json", date.format("%Y-%m-%d")));
+    std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+
+    Ok(JobOutcome {
+        summary: format!("Report written to {}", path.display()),
+        ..Default::default()
+    })
+}
+
+fn save_job_run(conn: &Connection, run: &mut JobRun) -> Result<i64, Box<dyn Error>> {
+    conn.execute(
+        "INSERT INTO job_runs (job_id, job_name, started_at, finished_at, status, summary)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            run.job_id,
+            run.job_name,
+            run.started_at.to_rfc3339(),
+            run.finished_at.to_rfc3339(),
+            to_string(&run.status)?,
+            run.summary,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    run.id = Some(id);
+
+    Ok(id)
+}
+
+fn parse_optional_time(value: Option<String>, idx: usize) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    value
+        .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn job_from_row(row: &Row) -> rusqlite::Result<ScheduledJob> {
+    let kind_json: String = row.get(2)?;
+    let kind = serde_json::from_str(&kind_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(ScheduledJob {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        kind,
+        cron: row.get(3)?,
+        enabled: row.get(4)?,
+        last_run_at: parse_optional_time(row.get(5)?, 5)?,
+        next_run_at: parse_optional_time(row.get(6)?, 6)?,
+    })
+}
+
+fn run_from_row(row: &Row) -> rusqlite::Result<JobRun> {
+    let started_at_str: String = row.get(3)?;
+    let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let finished_at_str: String = row.get(4)?;
+    let finished_at = chrono::DateTime::parse_from_rfc3339(&finished_at_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let status_json: String = row.get(5)?;
+    let status = serde_json::from_str(&status_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(JobRun {
+        id: Some(row.get(0)?),
+        job_id: row.get(1)?,
+        job_name: row.get(2)?,
+        started_at,
+        finished_at,
+        status,
+        summary: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DetailedAnalysis;
+    use crate::models::trade::Trade;
+    use crate::models::account::{Account, AccountType};
+    use crate::services::{db, detailed_analysis_service};
+
+    #[test]
+    fn stale_plan_cleanup_starts_disabled() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+
+        let jobs = get_scheduled_jobs(&conn).unwrap();
+        let cleanup = jobs.iter().find(|job| matches!(job.kind, JobKind::StalePlanCleanup { .. })).unwrap();
+        assert!(!cleanup.enabled);
+        assert!(cleanup.next_run_at.is_none());
+        assert!(jobs.iter().filter(|job| job.id != cleanup.id).all(|job| job.enabled));
+    }
+
+    #[test]
+    fn cleanup_goes_by_recording_time_and_notes_why() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        let mut backdated = Trade::new("AAPL", analysis_id);
+        backdated.timestamp = Utc::now() - Duration::days(60);
+        backdated.notes = Some("Wait for earnings".to_string());
+        trade_service::insert_trade(&conn, &mut backdated).unwrap();
+
+        assert_eq!(trade_service::cancel_stale_plans(&conn, Utc::now() - Duration::days(30)).unwrap(), 0);
+        assert_eq!(trade_service::cancel_stale_plans(&conn, Utc::now() + Duration::minutes(1)).unwrap(), 1);
+
+        let cancelled = trade_service::get_trade(&conn, backdated.id.unwrap()).unwrap();
+        assert!(matches!(cancelled.status, TradeStatus::Cancelled));
+        let notes = cancelled.notes.unwrap();
+        assert!(notes.starts_with("Wait for earnings\nCancelled as stale"), "{}", notes);
+    }
+
+    #[test]
+    fn report_is_named_for_the_exchange_date() {
+        use chrono::TimeZone;
+
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let directory = std::env::temp_dir().join(format!("reports-{}", std::process::id()));
+        // Monday evening in New York, already Tuesday in UTC
+        let now = Utc.with_ymd_and_hms(2024, 6, 18, 1, 0, 0).unwrap();
+
+        write_report(&conn, &directory, now).unwrap();
+
+        assert!(directory.join("report-2024-06-17.json").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn all_accounts_are_summed_only_when_there_are_several() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let now = Utc::now();
+        account_service::default_account_id(&conn).unwrap();
+        assert_eq!(account_summaries(&conn, now).unwrap().len(), 1);
+
+        let mut ira = Account::new("IRA", AccountType::Ira);
+        account_service::save_account(&conn, &mut ira).unwrap();
+        let summaries = account_summaries(&conn, now).unwrap();
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[2].account_id, None);
+    }
+}
@@ -0,0 +1,260 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::analytics::pattern_stats;
+use crate::models::scoring_model::{ScoreBreakdown, ScoringModel};
+use crate::services::{db, pattern_stats_service, stock_rating_service};
+
+const MODEL_COLUMNS: &str = "version, timestamp, name, weights, active, notes";
+
+/// One rating scored under two model versions.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoreComparison {
+    pub rating_id: i64,
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub stored_score: f64,
+    pub stored_version: Option<i64>,
+    pub baseline: ScoreBreakdown,
+    pub candidate: ScoreBreakdown,
+}
+
+/// Saves `model` as a new version. Existing versions are never changed, so
+/// editing weights always produces a new version.
+pub fn save_scoring_model(conn: &Connection, model: &mut ScoringModel) -> Result<i64, Box<dyn Error>> {
+    model.weights.validate()?;
+    model.timestamp = Utc::now();
+
+    let weights_json = to_string(&model.weights)?;
+
+    conn.execute(
+        "INSERT INTO scoring_models (timestamp, name, weights, active, notes)
+        VALUES (?1, ?2, ?3, 0, ?4)",
+        params![
+            model.timestamp.to_rfc3339(),
+            model.name,
+            weights_json,
+            model.notes,
+        ],
+    )?;
+
+    let version = conn.last_insert_rowid();
+    model.version = Some(version);
+
+    if model.active {
+        set_active_scoring_model(conn, version)?;
+    }
+
+    Ok(version)
+}
+
+pub fn get_scoring_model(conn: &Connection, version: i64) -> Result<ScoringModel, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scoring_models WHERE version = ?1", MODEL_COLUMNS))?;
+
+    let model = stmt.query_row(params![version], model_from_row)?;
+
+    Ok(model)
+}
+
+pub fn get_scoring_models(conn: &Connection) -> Result<Vec<ScoringModel>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scoring_models ORDER BY version", MODEL_COLUMNS))?;
+
+    let models_iter = stmt.query_map([], model_from_row)?;
+
+    let mut models = Vec::new();
+    for model in models_iter {
+        models.push(model?);
+    }
+
+    Ok(models)
+}
+
+/// The model new ratings are scored with. When no model has been activated
+/// yet, the default weights are saved and activated as the first version.
+pub fn get_active_scoring_model(conn: &Connection) -> Result<ScoringModel, Box<dyn Error>> {
+    let model = conn.query_row(
+        &format!("SELECT {} FROM scoring_models WHERE active = 1 LIMIT 1", MODEL_COLUMNS),
+        [],
+        model_from_row,
+    ).optional()?;
+
+    match model {
+        Some(model) => Ok(model),
+        None => {
+            let mut model = ScoringModel::default();
+            save_scoring_model(conn, &mut model)?;
+            Ok(model)
+        }
+    }
+}
+
+pub fn set_active_scoring_model(conn: &Connection, version: i64) -> Result<(), Box<dyn Error>> {
+    get_scoring_model(conn, version)?;
+
+    conn.execute(
+        "UPDATE scoring_models SET active = (version = ?1)",
+        params![version],
+    )?;
+
+    Ok(())
+}
+
+/// Re-scores every stored rating with the given model version and records that
+/// version against each rating. Pattern bonuses are refreshed from the pattern
+/// statistics as they stood when each rating was made, so trades closed later
+/// do not leak into them. Either every rating is re-scored or none are.
+/// Returns the number of ratings re-scored.
+pub fn rescore_ratings(conn: &Connection, version: i64) -> Result<usize, Box<dyn Error>> {
+    let model = get_scoring_model(conn, version)?;
+    let (patterns, outcomes) = pattern_stats_service::get_pattern_history(conn)?;
+    let ratings = stock_rating_service::get_all_stock_ratings(conn)?;
+
+    db::in_transaction(conn, || {
+        for mut rating in ratings.iter().cloned() {
+            let pattern_stats = pattern_stats::pattern_window(&patterns, &outcomes, None, rating.timestamp);
+            rating.pattern_bonus = pattern_stats_service::pattern_bonus_for(&pattern_stats, &rating);
+            rating.update_overall_score(&model);
+            stock_rating_service::update_rating_score(conn, &rating)?;
+        }
+
+        Ok(ratings.len())
+    })
+}
+
+/// Scores the most recent ratings under two model versions side by side,
+/// without changing what is stored.
+pub fn compare_scoring_models(
+    conn: &Connection,
+    baseline_version: i64,
+    candidate_version: i64,
+    limit: i64,
+) -> Result<Vec<ScoreComparison>, Box<dyn Error>> {
+    let baseline = get_scoring_model(conn, baseline_version)?;
+    let candidate = get_scoring_model(conn, candidate_version)?;
+
+    let comparisons = stock_rating_service::get_recent_stock_ratings(conn, limit)?
+        .into_iter()
+        .map(|rating| ScoreComparison {
+            rating_id: rating.id.unwrap_or_default(),
+            symbol: rating.symbol.clone(),
+            timestamp: rating.timestamp,
+            stored_score: rating.overall_score,
+            stored_version: rating.model_version,
+            baseline: baseline.breakdown(&rating),
+            candidate: candidate.breakdown(&rating),
+        })
+        .collect();
+
+    Ok(comparisons)
+}
+
+fn model_from_row(row: &Row) -> rusqlite::Result<ScoringModel> {
+    let timestamp_str: String = row.get(1)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let weights_json: String = row.get(3)?;
+    let weights = serde_json::from_str(&weights_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(ScoringModel {
+        version: Some(row.get(0)?),
+        timestamp,
+        name: row.get(2)?,
+        weights,
+        active: row.get(4)?,
+        notes: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::scoring_model::ScoringWeights;
+    use crate::models::stock_rating::StockRating;
+    use crate::services::db;
+
+    #[test]
+    fn first_use_activates_the_default_model() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+
+        let model = get_active_scoring_model(&conn).unwrap();
+        assert_eq!(model.version, Some(1));
+        assert_eq!(get_active_scoring_model(&conn).unwrap().version, Some(1));
+        assert_eq!(get_scoring_models(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn activating_a_version_deactivates_the_others() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        get_active_scoring_model(&conn).unwrap();
+        let mut candidate = ScoringModel::new("Market heavy", ScoringWeights { market: 3.0, ..ScoringWeights::default() });
+        let version = save_scoring_model(&conn, &mut candidate).unwrap();
+
+        set_active_scoring_model(&conn, version).unwrap();
+
+        let active: Vec<Option<i64>> = get_scoring_models(&conn).unwrap().into_iter()
+            .filter(|model| model.active)
+            .map(|model| model.version)
+            .collect();
+        assert_eq!(active, vec![Some(version)]);
+        assert!(set_active_scoring_model(&conn, 99).is_err());
+    }
+
+    #[test]
+    fn comparing_models_leaves_stored_scores_alone() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut rating = StockRating::new("AAPL", "Information Technology");
+        rating.market_sentiment = 2;
+        stock_rating_service::save_stock_rating(&conn, &mut rating).unwrap();
+        let stored_score = rating.overall_score;
+        let mut candidate = ScoringModel::new("Market heavy", ScoringWeights { market: 3.0, ..ScoringWeights::default() });
+        let version = save_scoring_model(&conn, &mut candidate).unwrap();
+
+        let comparison = &compare_scoring_models(&conn, 1, version, 10).unwrap()[0];
+
+        assert_eq!(comparison.stored_score, stored_score);
+        assert!(comparison.candidate.total > comparison.baseline.total);
+        assert_eq!(stock_rating_service::get_stock_rating(&conn, rating.id.unwrap()).unwrap().overall_score, stored_score);
+    }
+
+    #[test]
+    fn negative_weights_are_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut model = ScoringModel::new("Broken", ScoringWeights { sector: -1.0, ..ScoringWeights::default() });
+
+        assert!(save_scoring_model(&conn, &mut model).is_err());
+        assert!(get_scoring_models(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_failed_rescore_leaves_every_rating_alone() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut first = StockRating::new("AAPL", "Information Technology");
+        first.market_sentiment = 2;
+        stock_rating_service::save_stock_rating(&conn, &mut first).unwrap();
+        let mut second = StockRating::new("MSFT", "Information Technology");
+        second.market_sentiment = 2;
+        stock_rating_service::save_stock_rating(&conn, &mut second).unwrap();
+        let mut candidate = ScoringModel::new("Market heavy", ScoringWeights { market: 3.0, ..ScoringWeights::default() });
+        let version = save_scoring_model(&conn, &mut candidate).unwrap();
+        conn.execute_batch(
+            "CREATE TRIGGER refuse_msft BEFORE UPDATE ON stock_ratings WHEN OLD.symbol = 'MSFT'
+            BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+        ).unwrap();
+
+        assert!(rescore_ratings(&conn, version).is_err());
+        let stored = stock_rating_service::get_stock_rating(&conn, first.id.unwrap()).unwrap();
+        assert_eq!(stored.overall_score, first.overall_score);
+        assert_eq!(stored.model_version, first.model_version);
+    }
+}
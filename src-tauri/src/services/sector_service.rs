@@ -0,0 +1,463 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::models::sector::{
+    Sector, SectorLevel, SectorMatch, SectorReview, SectorReviewStatus, SectorSelection, GICS_ALIASES, GICS_HIERARCHY,
+};
+
+const SECTOR_COLUMNS: &str = "id, code, name, level, parent_id, aliases";
+
+/// Tables holding a free-text sector alongside its sector_id.
+const SECTOR_TABLES: [&str; 2] = ["stock_ratings", "detailed_analyses"];
+
+/// Adds any GICS entries missing from the sectors table.
+pub fn seed_gics_sectors(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    for (code, name) in GICS_HIERARCHY {
+        let parent_id: Option<i64> = match code.len() {
+            2 => None,
+            len => conn.query_row(
+                "SELECT id FROM sectors WHERE code = ?1",
+                params![&code[..len - 2]],
+                |row| row.get(0),
+            ).optional()?,
+        };
+        let aliases: Vec<&str> = GICS_ALIASES.iter()
+            .filter(|(alias_code, _)| alias_code == code)
+            .map(|(_, alias)| *alias)
+            .collect();
+
+        conn.execute(
+            "INSERT OR IGNORE INTO sectors (code, name, level, parent_id, aliases)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                code,
+                name,
+                to_string(&SectorLevel::from_gics_code(code))?,
+                parent_id,
+                to_string(&aliases)?,
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn get_sectors(conn: &Connection) -> Result<Vec<Sector>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM sectors ORDER BY code IS NULL, code, name",
+        SECTOR_COLUMNS
+    ))?;
+
+    let sectors_iter = stmt.query_map([], sector_from_row)?;
+
+    let mut sectors = Vec::new();
+    for sector in sectors_iter {
+        sectors.push(sector?);
+    }
+
+    Ok(sectors)
+}
+
+pub fn get_sector(conn: &Connection, id: i64) -> Result<Sector, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM sectors WHERE id = ?1", SECTOR_COLUMNS))?;
+
+    let sector = stmt.query_row(params![id], sector_from_row)?;
+
+    Ok(sector)
+}
+
+pub fn save_custom_sector(conn: &Connection, sector: &mut Sector) -> Result<i64, Box<dyn Error>> {
+    if sector.name.trim().is_empty() {
+        return Err("Sector name is required".into());
+    }
+    if let SectorMatch::Matched(existing) = match_sector(conn, &sector.name)? {
+        return Err(format!("{} already exists as sector {}", sector.name, existing).into());
+    }
+
+    sector.code = None;
+    sector.level = SectorLevel::Custom;
+
+    conn.execute(
+        "INSERT INTO sectors (code, name, level, parent_id, aliases) VALUES (NULL, ?1, ?2, ?3, ?4)",
+        params![
+            sector.name,
+            to_string(&sector.level)?,
+            sector.parent_id,
+            to_string(&sector.aliases)?,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    sector.id = Some(id);
+
+    Ok(id)
+}
+
+pub fn update_custom_sector(conn: &Connection, sector: &Sector) -> Result<(), Box<dyn Error>> {
+    let id = sector.id.ok_or("Sector has no id")?;
+    if get_sector(conn, id)?.level != SectorLevel::Custom {
+        return Err("GICS sectors cannot be edited".into());
+    }
+    if sector.name.trim().is_empty() {
+        return Err("Sector name is required".into());
+    }
+    let duplicate = get_sectors(conn)?.into_iter()
+        .find(|other| other.id != Some(id) && other.matches_exactly(&sector.name));
+    if let Some(duplicate) = duplicate {
+        return Err(format!("{} already exists as sector {}", sector.name, duplicate.id.unwrap_or_default()).into());
+    }
+
+    conn.execute(
+        "UPDATE sectors SET name = ?1, parent_id = ?2, aliases = ?3 WHERE id = ?4",
+        params![sector.name, sector.parent_id, to_string(&sector.aliases)?, id],
+    )?;
+
+    for table in SECTOR_TABLES {
+        conn.execute(&format!("UPDATE {} SET sector = ?1 WHERE sector_id = ?2", table), params![sector.name, id])?;
+    }
+
+    Ok(())
+}
+
+/// Deletes a custom sector. Fails while ratings, analyses, securities or
+/// other sectors still use it.
+pub fn delete_custom_sector(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    if get_sector(conn, id)?.level != SectorLevel::Custom {
+        return Err("GICS sectors cannot be deleted".into());
+    }
+
+    let references: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM stock_ratings WHERE sector_id = ?1)
+            + (SELECT COUNT(*) FROM detailed_analyses WHERE sector_id = ?1)
+            + (SELECT COUNT(*) FROM securities WHERE sector_id = ?1)
+            + (SELECT COUNT(*) FROM sectors WHERE parent_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if references > 0 {
+        return Err(format!("Sector is still used {} times", references).into());
+    }
+
+    // Mappings to the sector go with it; those values are reviewed again
+    conn.execute("DELETE FROM sector_reviews WHERE sector_id = ?1", params![id])?;
+    conn.execute("DELETE FROM sectors WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+/// Finds the sector a free-text value refers to. Exact name or alias matches
+/// win, preferring the broadest level, so "Energy" is the sector rather than
+/// the industry group of the same name. Otherwise partial matches are
+/// returned as candidates.
+pub fn match_sector(conn: &Connection, value: &str) -> Result<SectorMatch, Box<dyn Error>> {
+    Ok(match_sector_in(&get_sectors(conn)?, value))
+}
+
+/// `match_sector` against sectors already loaded, for matching many values.
+pub fn match_sector_in(sectors: &[Sector], value: &str) -> SectorMatch {
+    let exact: Vec<&Sector> = sectors.iter().filter(|sector| sector.matches_exactly(value)).collect();
+    if let Some(broadest) = exact.iter().map(|sector| sector.level).min() {
+        let ids: Vec<i64> = exact.iter()
+            .filter(|sector| sector.level == broadest)
+            .filter_map(|sector| sector.id)
+            .collect();
+        return match ids.as_slice() {
+            [id] => SectorMatch::Matched(*id),
+            _ => SectorMatch::Ambiguous(ids),
+        };
+    }
+
+    let partial: Vec<i64> = sectors.iter()
+        .filter(|sector| sector.matches_partially(value))
+        .filter_map(|sector| sector.id)
+        .collect();
+
+    if partial.is_empty() { SectorMatch::Unknown } else { SectorMatch::Ambiguous(partial) }
+}
+
+/// Links a rating or analysis to its sector before saving. A given sector_id
+/// sets the sector name; otherwise the name is matched, and names that cannot
+/// be matched are queued for review.
+pub fn assign_sector(conn: &Connection, sector_id: &mut Option<i64>, sector: &mut String) -> Result<(), Box<dyn Error>> {
+    if let Some(id) = sector_id {
+        *sector = get_sector(conn, *id)?.name;
+        return Ok(());
+    }
+    if sector.trim().is_empty() {
+        return Ok(());
+    }
+
+    if let Some(id) = get_resolved_sector_id(conn, sector)? {
+        *sector_id = Some(id);
+        *sector = get_sector(conn, id)?.name;
+        return Ok(());
+    }
+
+    match match_sector(conn, sector)? {
+        SectorMatch::Matched(id) => {
+            *sector_id = Some(id);
+            *sector = get_sector(conn, id)?.name;
+        }
+        SectorMatch::Ambiguous(candidates) => save_sector_review(conn, sector, None, SectorReviewStatus::NeedsReview, &candidates)?,
+        SectorMatch::Unknown => save_sector_review(conn, sector, None, SectorReviewStatus::NeedsReview, &[])?,
+    }
+
+    Ok(())
+}
+
+/// Maps free-text sectors of ratings and analyses without a sector_id. Clear
+/// matches are applied and recorded as auto-mapped; the rest wait for review.
+pub fn map_legacy_sectors(conn: &Connection) -> Result<Vec<SectorReview>, Box<dyn Error>> {
+    let mut values: Vec<String> = Vec::new();
+    for table in SECTOR_TABLES {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT DISTINCT sector FROM {} WHERE sector_id IS NULL AND TRIM(sector) != ''",
+            table
+        ))?;
+        for value in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            let value = value?;
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+    }
+
+    for value in &values {
+        if let Some(id) = get_resolved_sector_id(conn, value)? {
+            apply_sector_mapping(conn, value, id)?;
+            continue;
+        }
+
+        match match_sector(conn, value)? {
+            SectorMatch::Matched(id) => {
+                apply_sector_mapping(conn, value, id)?;
+                save_sector_review(conn, value, Some(id), SectorReviewStatus::AutoMapped, &[])?;
+            }
+            SectorMatch::Ambiguous(candidates) => save_sector_review(conn, value, None, SectorReviewStatus::NeedsReview, &candidates)?,
+            SectorMatch::Unknown => save_sector_review(conn, value, None, SectorReviewStatus::NeedsReview, &[])?,
+        }
+    }
+
+    get_sector_reviews(conn, false)
+}
+
+pub fn get_sector_reviews(conn: &Connection, pending_only: bool) -> Result<Vec<SectorReview>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.raw_value, r.sector_id, r.status, r.candidates,
+            (SELECT COUNT(*) FROM stock_ratings WHERE sector = r.raw_value AND sector_id IS NULL)
+            + (SELECT COUNT(*) FROM detailed_analyses WHERE sector = r.raw_value AND sector_id IS NULL)
+        FROM sector_reviews r
+        WHERE ?1 = 0 OR r.status = ?2
+        ORDER BY r.raw_value"
+    )?;
+
+    let reviews_iter = stmt.query_map(
+        params![pending_only, to_string(&SectorReviewStatus::NeedsReview)?],
+        review_from_row,
+    )?;
+
+    let mut reviews = Vec::new();
+    for review in reviews_iter {
+        reviews.push(review?);
+    }
+
+    Ok(reviews)
+}
+
+/// Maps a reviewed free-text value to `sector_id` everywhere it is used, and
+/// for anything saved with that value from now on.
+pub fn resolve_sector_review(conn: &Connection, raw_value: &str, sector_id: i64) -> Result<(), Box<dyn Error>> {
+    get_sector(conn, sector_id)?;
+
+    apply_sector_mapping(conn, raw_value, sector_id)?;
+    save_sector_review(conn, raw_value, Some(sector_id), SectorReviewStatus::Resolved, &[])?;
+
+    Ok(())
+}
+
+pub fn add_sector_selection(conn: &Connection, selection: &mut SectorSelection) -> Result<i64, Box<dyn Error>> {
+    get_sector(conn, selection.sector_id)?;
+
+    let has_rationale = selection.rationale.as_deref().is_some_and(|r| !r.trim().is_empty());
+    let already_selected: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sector_selections WHERE sector_id = ?1",
+        params![selection.sector_id],
+        |row| row.get(0),
+    )?;
+    if already_selected && !has_rationale {
+        return Err("Selecting a sector more than once needs a rationale".into());
+    }
+
+    selection.timestamp = Utc::now();
+    selection.position = conn.query_row(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM sector_selections",
+        [],
+        |row| row.get(0),
+    )?;
+
+    conn.execute(
+        "INSERT INTO sector_selections (timestamp, sector_id, rationale, position) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            selection.timestamp.to_rfc3339(),
+            selection.sector_id,
+            selection.rationale,
+            selection.position,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    selection.id = Some(id);
+
+    Ok(id)
+}
+
+pub fn get_sector_selections(conn: &Connection) -> Result<Vec<SectorSelection>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, sector_id, rationale, position FROM sector_selections ORDER BY position"
+    )?;
+
+    let selections_iter = stmt.query_map([], |row| {
+        let timestamp_str: String = row.get(1)?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+            .with_timezone(&Utc);
+
+        Ok(SectorSelection {
+            id: Some(row.get(0)?),
+            timestamp,
+            sector_id: row.get(2)?,
+            rationale: row.get(3)?,
+            position: row.get(4)?,
+        })
+    })?;
+
+    let mut selections = Vec::new();
+    for selection in selections_iter {
+        selections.push(selection?);
+    }
+
+    Ok(selections)
+}
+
+pub fn remove_sector_selection(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM sector_selections WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+fn get_resolved_sector_id(conn: &Connection, raw_value: &str) -> Result<Option<i64>, Box<dyn Error>> {
+    let id = conn.query_row(
+        "SELECT sector_id FROM sector_reviews WHERE raw_value = ?1 AND status = ?2",
+        params![raw_value, to_string(&SectorReviewStatus::Resolved)?],
+        |row| row.get(0),
+    ).optional()?;
+
+    Ok(id.flatten())
+}
+
+fn apply_sector_mapping(conn: &Connection, raw_value: &str, sector_id: i64) -> Result<(), Box<dyn Error>> {
+    let name = get_sector(conn, sector_id)?.name;
+
+    for table in SECTOR_TABLES {
+        conn.execute(
+            &format!("UPDATE {} SET sector_id = ?1, sector = ?2 WHERE sector = ?3 AND sector_id IS NULL", table),
+            params![sector_id, name, raw_value],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn save_sector_review(
+    conn: &Connection,
+    raw_value: &str,
+    sector_id: Option<i64>,
+    status: SectorReviewStatus,
+    candidates: &[i64],
+) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "INSERT INTO sector_reviews (raw_value, sector_id, status, candidates) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(raw_value) DO UPDATE SET
+            sector_id = excluded.sector_id, status = excluded.status, candidates = excluded.candidates",
+        params![raw_value, sector_id, to_string(&status)?, to_string(candidates)?],
+    )?;
+
+    Ok(())
+}
+
+fn sector_from_row(row: &Row) -> rusqlite::Result<Sector> {
+    let level_json: String = row.get(3)?;
+    let level = serde_json::from_str(&level_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let aliases_json: String = row.get(5)?;
+    let aliases = serde_json::from_str(&aliases_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(Sector {
+        id: Some(row.get(0)?),
+        code: row.get(1)?,
+        name: row.get(2)?,
+        level,
+        parent_id: row.get(4)?,
+        aliases,
+    })
+}
+
+fn review_from_row(row: &Row) -> rusqlite::Result<SectorReview> {
+    let status_json: String = row.get(2)?;
+    let status = serde_json::from_str(&status_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let candidates_json: String = row.get(3)?;
+    let candidates = serde_json::from_str(&candidates_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(SectorReview {
+        raw_value: row.get(0)?,
+        sector_id: row.get(1)?,
+        status,
+        candidates,
+        occurrences: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::stock_rating::StockRating;
+    use crate::services::{db, stock_rating_service};
+
+    #[test]
+    fn sectors_in_use_cannot_be_deleted() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let id = save_custom_sector(&conn, &mut Sector::custom("Space Economy", None)).unwrap();
+        let mut rating = StockRating::new("RKLB", "Space Economy");
+        stock_rating_service::save_stock_rating(&conn, &mut rating).unwrap();
+        assert_eq!(rating.sector_id, Some(id));
+
+        assert!(delete_custom_sector(&conn, id).is_err());
+
+        conn.execute("DELETE FROM stock_ratings WHERE id = ?1", params![rating.id]).unwrap();
+        delete_custom_sector(&conn, id).unwrap();
+        assert!(get_sector(&conn, id).is_err());
+    }
+
+    #[test]
+    fn renames_need_a_new_unique_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut sector = Sector::custom("Space Economy", None);
+        save_custom_sector(&conn, &mut sector).unwrap();
+
+        sector.name = " ".to_string();
+        assert!(update_custom_sector(&conn, &sector).is_err());
+        sector.name = "Energy".to_string();
+        assert!(update_custom_sector(&conn, &sector).is_err());
+        sector.name = "Space economy".to_string();
+        update_custom_sector(&conn, &sector).unwrap();
+    }
+}
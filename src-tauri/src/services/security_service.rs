@@ -0,0 +1,354 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde_json::to_string;
+use std::error::Error;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+
+use crate::models::sector::{Sector, SectorMatch};
+use crate::models::security::{self, AssetClass, Security, SecurityImportSummary, SecurityRecord};
+use crate::models::stock_rating::StockRating;
+use crate::services::{db, sector_service, time_zone_service};
+
+const SECURITY_COLUMNS: &str =
+    "s.symbol, s.name, s.exchange, s.asset_class, s.sector_id, s.industry_id, sector.name, industry.name,
+    s.currency, s.multiplier, s.active";
+
+const SECURITY_JOINS: &str =
+    "FROM securities s
+    LEFT JOIN sectors sector ON s.sector_id = sector.id
+    LEFT JOIN sectors industry ON s.industry_id = industry.id";
+
+/// Most edits a symbol can be from a known one to be suggested as a typo.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Loads the symbol master from a CSV with a header row. Existing symbols are
+/// updated in place; rows that cannot be read are reported and skipped. A
+/// failure part way through leaves the symbol master as it was.
+pub fn import_securities_csv(conn: &Connection, path: &Path) -> Result<SecurityImportSummary, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+    let sectors = sector_service::get_sectors(conn)?;
+
+    db::in_transaction(conn, || {
+        let mut summary = SecurityImportSummary::default();
+        for (index, result) in reader.deserialize::<SecurityRecord>().enumerate() {
+            // Line 1 is the header
+            let line = index + 2;
+            let record = match result {
+                Ok(record) => record,
+                Err(e) => {
+                    summary.errors.push(format!("line {}: {}", line, e));
+                    continue;
+                }
+            };
+
+            let symbol = security::normalize_symbol(&record.symbol);
+            if symbol.is_empty() {
+                summary.errors.push(format!("line {}: missing symbol", line));
+                continue;
+            }
+
+            let sector_id = match_record_sector(&sectors, &symbol, record.sector.as_deref(), &mut summary);
+            let industry_id = match_record_sector(&sectors, &symbol, record.industry.as_deref(), &mut summary);
+
+            save_security(conn, &Security {
+                symbol,
+                name: record.name,
+                exchange: record.exchange.filter(|exchange| !exchange.is_empty()),
+                asset_class: record.asset_class.as_deref().map_or(AssetClass::Equity, AssetClass::parse),
+                sector_id,
+                industry_id,
+                sector: None,
+                industry: None,
+                currency: record.currency.filter(|currency| !currency.is_empty()).unwrap_or_else(|| "USD".to_string()),
+                multiplier: record.multiplier.unwrap_or(1.0),
+                active: record.active.unwrap_or(true),
+            })?;
+            summary.imported += 1;
+        }
+
+        Ok(summary)
+    })
+}
+
+pub fn save_security(conn: &Connection, security: &Security) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "INSERT INTO securities
+        (symbol, name, exchange, asset_class, sector_id, industry_id, currency, multiplier, active)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        ON CONFLICT(symbol) DO UPDATE SET
+            name = excluded.name, exchange = excluded.exchange, asset_class = excluded.asset_class,
+            sector_id = excluded.sector_id, industry_id = excluded.industry_id, currency = excluded.currency,
+            multiplier = excluded.multiplier, active = excluded.active",
+        params![
+            security::normalize_symbol(&security.symbol),
+            security.name,
+            security.exchange,
+            to_string(&security.asset_class)?,
+            security.sector_id,
+            security.industry_id,
+            security.currency,
+            security.multiplier,
+            security.active,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_security(conn: &Connection, symbol: &str) -> Result<Option<Security>, Box<dyn Error>> {
+    let security = conn.query_row(
+        &format!("SELECT {} {} WHERE s.symbol = ?1", SECURITY_COLUMNS, SECURITY_JOINS),
+        params![security::normalize_symbol(symbol)],
+        security_from_row,
+    ).optional()?;
+
+    Ok(security)
+}
+
+/// Active securities for auto-completion: symbols starting with `query`
+/// first, then names containing it. `%` and `_` in the query match literally.
+pub fn search_securities(conn: &Connection, query: &str, limit: i64) -> Result<Vec<Security>, Box<dyn Error>> {
+    let query = query.trim();
+    let symbol = security::normalize_symbol(query);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} {}
+        WHERE s.active = 1 AND (s.symbol LIKE ?1 || '%' ESCAPE '\\' OR s.name LIKE '%' || ?2 || '%' ESCAPE '\\')
+        ORDER BY s.symbol LIKE ?1 || '%' ESCAPE '\\' DESC, s.symbol = ?3 DESC, LENGTH(s.symbol), s.symbol
+        LIMIT ?4",
+        SECURITY_COLUMNS, SECURITY_JOINS
+    ))?;
+
+    let securities_iter = stmt.query_map(
+        params![escape_like(&symbol), escape_like(query), symbol, limit],
+        security_from_row,
+    )?;
+
+    let mut securities = Vec::new();
+    for security in securities_iter {
+        securities.push(security?);
+    }
+
+    Ok(securities)
+}
+
+/// Looks a symbol up in the symbol master. Unknown symbols are rejected with
+/// the closest known symbols as suggestions. Until a symbol master has been
+/// loaded every symbol is accepted.
+pub fn validate_symbol(conn: &Connection, symbol: &str) -> Result<Option<Security>, Box<dyn Error>> {
+    lookup_symbol(conn, symbol, false)
+}
+
+/// Like `validate_symbol`, for a trade made at `traded_at`. Securities no
+/// longer active are accepted when the trade was made before today, since
+/// they may have been listed then.
+pub fn validate_traded_symbol(conn: &Connection, symbol: &str, traded_at: DateTime<Utc>) -> Result<Option<Security>, Box<dyn Error>> {
+    let time_zones = time_zone_service::get_time_zone_settings(conn)?;
+    let backdated = time_zones.exchange_date(traded_at) < time_zones.exchange_date(Utc::now());
+    lookup_symbol(conn, symbol, backdated)
+}
+
+fn lookup_symbol(conn: &Connection, symbol: &str, allow_inactive: bool) -> Result<Option<Security>, Box<dyn Error>> {
+    let has_master: bool = conn.query_row("SELECT COUNT(*) > 0 FROM securities", [], |row| row.get(0))?;
+    if !has_master {
+        return Ok(None);
+    }
+
+    if let Some(security) = get_security(conn, symbol)? {
+        if !security.active && !allow_inactive {
+            return Err(format!("{} is no longer active", security.symbol).into());
+        }
+        return Ok(Some(security));
+    }
+
+    let symbol = security::normalize_symbol(symbol);
+    let suggestions = suggest_symbols(conn, &symbol)?;
+    if suggestions.is_empty() {
+        Err(format!("Unknown symbol {}", symbol).into())
+    } else {
+        Err(format!("Unknown symbol {}. Did you mean {}?", symbol, suggestions.join(", ")).into())
+    }
+}
+
+/// Validates the symbol of a rating and fills in its security name and
+/// sector from the symbol master where they are blank.
+pub fn apply_security_details(conn: &Connection, rating: &mut StockRating) -> Result<(), Box<dyn Error>> {
+    let security = match validate_symbol(conn, &rating.symbol)? {
+        Some(security) => security,
+        None => return Ok(()),
+    };
+
+    rating.symbol = security.symbol;
+    if rating.security_name.as_deref().unwrap_or_default().trim().is_empty() {
+        rating.security_name = Some(security.name);
+    }
+    if rating.sector_id.is_none() && rating.sector.trim().is_empty() {
+        rating.sector_id = security.sector_id;
+    }
+
+    Ok(())
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn suggest_symbols(conn: &Connection, symbol: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT symbol FROM securities WHERE active = 1")?;
+    let symbols = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+    let mut candidates = Vec::new();
+    for known in symbols {
+        let known = known?;
+        let distance = security::symbol_distance(symbol, &known);
+        if distance <= MAX_SUGGESTION_DISTANCE {
+            candidates.push((distance, known));
+        }
+    }
+    candidates.sort();
+
+    Ok(candidates.into_iter().take(3).map(|(_, known)| known).collect())
+}
+
+fn match_record_sector(
+    sectors: &[Sector],
+    symbol: &str,
+    value: Option<&str>,
+    summary: &mut SecurityImportSummary,
+) -> Option<i64> {
+    let value = match value {
+        Some(value) if !value.is_empty() => value,
+        _ => return None,
+    };
+
+    match sector_service::match_sector_in(sectors, value) {
+        SectorMatch::Matched(id) => Some(id),
+        SectorMatch::Ambiguous(_) | SectorMatch::Unknown => {
+            summary.unmatched_sectors.push(format!("{}: {}", symbol, value));
+            None
+        }
+    }
+}
+
+fn security_from_row(row: &Row) -> rusqlite::Result<Security> {
+    let asset_class_json: String = row.get(3)?;
+    let asset_class = serde_json::from_str(&asset_class_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(Security {
+        symbol: row.get(0)?,
+        name: row.get(1)?,
+        exchange: row.get(2)?,
+        asset_class,
+        sector_id: row.get(4)?,
+        industry_id: row.get(5)?,
+        sector: row.get(6)?,
+        industry: row.get(7)?,
+        currency: row.get(8)?,
+        multiplier: row.get(9)?,
+        active: row.get(10)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::models::DetailedAnalysis;
+    use crate::models::trade::Trade;
+    use crate::services::{detailed_analysis_service, trade_service};
+
+    fn listed(symbol: &str, name: &str, active: bool) -> Security {
+        Security {
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            exchange: None,
+            asset_class: AssetClass::Equity,
+            sector_id: None,
+            industry_id: None,
+            sector: None,
+            industry: None,
+            currency: "USD".to_string(),
+            multiplier: 1.0,
+            active,
+        }
+    }
+
+    #[test]
+    fn search_matches_wildcards_literally() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        save_security(&conn, &listed("BRK_B", "Berkshire Hathaway 100% Class B", true)).unwrap();
+        save_security(&conn, &listed("BRKB", "Berkshire Hathaway", true)).unwrap();
+
+        let symbols = |query: &str| -> Vec<String> {
+            search_securities(&conn, query, 10).unwrap().into_iter().map(|s| s.symbol).collect()
+        };
+        assert_eq!(symbols("BRK_"), vec!["BRK_B"]);
+        assert_eq!(symbols("100%"), vec!["BRK_B"]);
+        assert_eq!(symbols("%"), vec!["BRK_B"]);
+        assert!(symbols("_").is_empty());
+    }
+
+    #[test]
+    fn inactive_symbols_are_accepted_for_backdated_trades_only() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        save_security(&conn, &listed("TWTR", "Twitter", false)).unwrap();
+
+        assert!(validate_symbol(&conn, "TWTR").is_err());
+        assert!(validate_traded_symbol(&conn, "TWTR", Utc::now()).is_err());
+        assert!(validate_traded_symbol(&conn, "twtr", Utc::now() - Duration::days(400)).unwrap().is_some());
+    }
+
+    #[test]
+    fn trades_and_analyses_use_the_symbol_master() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        save_security(&conn, &listed("AAPL", "Apple", true)).unwrap();
+        save_security(&conn, &listed("MSFT", "Microsoft", true)).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("APPL", "Information Technology");
+        assert!(detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).is_err());
+        analysis.security = "aapl".to_string();
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        assert_eq!(analysis.security, "AAPL");
+
+        let mut trade = Trade::new("AAPL", analysis_id);
+        let id = trade_service::save_trade(&conn, &mut trade).unwrap();
+        trade.symbol = "MSFTT".to_string();
+        assert!(trade_service::update_trade(&conn, &mut trade).is_err());
+        trade.symbol = "msft".to_string();
+        trade_service::update_trade(&conn, &mut trade).unwrap();
+        assert_eq!(trade_service::get_trade(&conn, id).unwrap().symbol, "MSFT");
+    }
+
+    #[test]
+    fn delisted_symbols_stay_valid_on_stored_analyses() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        save_security(&conn, &listed("TWTR", "Twitter", true)).unwrap();
+        let mut analysis = DetailedAnalysis::new("TWTR", "Communication Services");
+        detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+
+        save_security(&conn, &listed("TWTR", "Twitter", false)).unwrap();
+        analysis.entry_reason = "Revisited".to_string();
+        detailed_analysis_service::update_detailed_analysis(&conn, &mut analysis).unwrap();
+    }
+
+    #[test]
+    fn imports_match_sectors() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let path = std::env::temp_dir().join(format!("securities-{}.csv", std::process::id()));
+        std::fs::write(&path, "symbol,name,sector\nXOM,Exxon Mobil,Energy\nZZZ,Unknown,Made Up\n").unwrap();
+
+        let summary = import_securities_csv(&conn, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(summary.imported, 2);
+        assert_eq!(summary.unmatched_sectors, vec!["ZZZ: Made Up".to_string()]);
+        assert_eq!(get_security(&conn, "XOM").unwrap().unwrap().sector.as_deref(), Some("Energy"));
+    }
+}
@@ -0,0 +1,42 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::to_string;
+use std::error::Error;
+use chrono::Utc;
+
+pub const ACCOUNT_KEY: &str = "account";
+pub const LEGACY_ALERTS_KEY: &str = "legacy_alerts_parsed";
+pub const PORTFOLIO_RISK_KEY: &str = "portfolio_risk";
+pub const RATING_DECAY_KEY: &str = "rating_decay";
+pub const TREND_SETTINGS_KEY: &str = "market_trend";
+pub const TIME_ZONE_KEY: &str = "time_zones";
+
+pub fn get_setting<T: DeserializeOwned>(conn: &Connection, key: &str) -> Result<Option<T>, Box<dyn Error>> {
+    let value: Option<String> = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    ).optional()?;
+
+    match value {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn get_setting_or_default<T: DeserializeOwned + Default>(conn: &Connection, key: &str) -> Result<T, Box<dyn Error>> {
+    Ok(get_setting(conn, key)?.unwrap_or_default())
+}
+
+pub fn set_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    let value_json = to_string(value)?;
+
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value_json, Utc::now().to_rfc3339()],
+    )?;
+
+    Ok(())
+}
@@ -1,22 +1,41 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, Row, params};
 use serde_json::to_string;
 use std::error::Error;
 use chrono::Utc;
 
 use crate::models::stock_rating::{StockRating, MarketTrend, ChartPattern};
+use crate::models::time_zone;
+use crate::services::{pattern_stats_service, scoring_service, sector_service, security_service, trend_service};
+
+const RATING_COLUMNS: &str =
+    "id, timestamp, symbol, security_name, sector, market_sentiment, sector_sentiment,
+    security_sentiment, bull_bear, confidence, market_trend, chart_pattern,
+    strategy, overall_score, notes, model_version, pattern_bonus, sector_id, computed_market_trend, recorded_at";
 
 pub fn save_stock_rating(conn: &Connection, rating: &mut StockRating) -> Result<i64, Box<dyn Error>> {
-    rating.update_overall_score();
-    rating.timestamp = Utc::now();
+    security_service::apply_security_details(conn, rating)?;
+    sector_service::assign_sector(conn, &mut rating.sector_id, &mut rating.sector)?;
+    if rating.computed_market_trend.is_none() {
+        rating.computed_market_trend = trend_service::computed_market_trend(conn, &rating.symbol)?;
+    }
+    let model = scoring_service::get_active_scoring_model(conn)?;
+    let pattern_stats = pattern_stats_service::get_all_time_pattern_stats(conn, rating.timestamp)?;
+    rating.pattern_bonus = pattern_stats_service::pattern_bonus_for(&pattern_stats, rating);
+    rating.update_overall_score(&model);
+    let recorded_at = Utc::now();
+    time_zone::check_entry_timestamp(rating.timestamp, recorded_at)?;
+    rating.recorded_at = Some(recorded_at);
     
     let market_trend_json = to_string(&rating.market_trend)?;
     let chart_pattern_json = to_string(&rating.chart_pattern)?;
+    let computed_market_trend_json = rating.computed_market_trend.as_ref().map(to_string).transpose()?;
     
     conn.execute(
         "INSERT INTO stock_ratings 
         (timestamp, symbol, security_name, sector, market_sentiment, sector_sentiment, security_sentiment, 
-        bull_bear, confidence, market_trend, chart_pattern, strategy, overall_score, notes) 
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        bull_bear, confidence, market_trend, chart_pattern, strategy, overall_score, notes, model_version, 
+        pattern_bonus, sector_id, computed_market_trend, recorded_at) 
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
         params![
             rating.timestamp.to_rfc3339(),
             rating.symbol,
@@ -32,6 +51,11 @@ pub fn save_stock_rating(conn: &Connection, rating: &mut StockRating) -> Result<
             rating.strategy,
             rating.overall_score,
             rating.notes,
+            rating.model_version,
+            rating.pattern_bonus,
+            rating.sector_id,
+            computed_market_trend_json,
+            rating.recorded_at.map(|dt| dt.to_rfc3339()),
         ],
     )?;
     
@@ -41,95 +65,31 @@ pub fn save_stock_rating(conn: &Connection, rating: &mut StockRating) -> Result<
     Ok(id)
 }
 
-pub fn get_stock_rating(conn: &Connection, id: i64) -> Result<StockRating, Box<dyn Error>> {
-    let mut stmt = conn.prepare(
-        "SELECT 
-            id, timestamp, symbol, security_name, sector, market_sentiment, sector_sentiment, 
-            security_sentiment, bull_bear, confidence, market_trend, chart_pattern, 
-            strategy, overall_score, notes
-        FROM stock_ratings 
-        WHERE id = ?1"
+/// Stores a new score for an existing rating, leaving everything else as rated.
+pub fn update_rating_score(conn: &Connection, rating: &StockRating) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "UPDATE stock_ratings SET overall_score = ?1, model_version = ?2, pattern_bonus = ?3 WHERE id = ?4",
+        params![rating.overall_score, rating.model_version, rating.pattern_bonus, rating.id],
     )?;
     
-    let rating = stmt.query_row(params![id], |row| {
-        let timestamp_str: String = row.get(1)?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
-            .with_timezone(&Utc);
-        
-        let market_trend_json: String = row.get(10)?;
-        let market_trend: MarketTrend = serde_json::from_str(&market_trend_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e)))?;
-            
-        let chart_pattern_json: String = row.get(11)?;
-        let chart_pattern: ChartPattern = serde_json::from_str(&chart_pattern_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(11, rusqlite::types::Type::Text, Box::new(e)))?;
-        
-        Ok(StockRating {
-            id: Some(row.get(0)?),
-            timestamp,
-            symbol: row.get(2)?,
-            security_name: row.get(3)?,
-            sector: row.get(4)?,
-            market_sentiment: row.get(5)?,
-            sector_sentiment: row.get(6)?,
-            security_sentiment: row.get(7)?,
-            bull_bear: row.get(8)?,
-            confidence: row.get(9)?,
-            market_trend,
-            chart_pattern,
-            strategy: row.get(12)?,
-            overall_score: row.get(13)?,
-            notes: row.get(14)?,
-        })
-    })?;
+    Ok(())
+}
+
+pub fn get_stock_rating(conn: &Connection, id: i64) -> Result<StockRating, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM stock_ratings WHERE id = ?1", RATING_COLUMNS))?;
+    
+    let rating = stmt.query_row(params![id], rating_from_row)?;
     
     Ok(rating)
 }
 
 pub fn get_stock_ratings_by_symbol(conn: &Connection, symbol: &str) -> Result<Vec<StockRating>, Box<dyn Error>> {
-    let mut stmt = conn.prepare(
-        "SELECT 
-            id, timestamp, symbol, security_name, sector, market_sentiment, sector_sentiment, 
-            security_sentiment, bull_bear, confidence, market_trend, chart_pattern, 
-            strategy, overall_score, notes
-        FROM stock_ratings 
-        WHERE symbol = ?1
-        ORDER BY timestamp DESC"
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM stock_ratings WHERE symbol = ?1 ORDER BY timestamp DESC",
+        RATING_COLUMNS
+    ))?;
     
-    let ratings_iter = stmt.query_map(params![symbol], |row| {
-        let timestamp_str: String = row.get(1)?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
-            .with_timezone(&Utc);
-        
-        let market_trend_json: String = row.get(10)?;
-        let market_trend: MarketTrend = serde_json::from_str(&market_trend_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e)))?;
-            
-        let chart_pattern_json: String = row.get(11)?;
-        let chart_pattern: ChartPattern = serde_json::from_str(&chart_pattern_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(11, rusqlite::types::Type::Text, Box::new(e)))?;
-        
-        Ok(StockRating {
-            id: Some(row.get(0)?),
-            timestamp,
-            symbol: row.get(2)?,
-            security_name: row.get(3)?,
-            sector: row.get(4)?,
-            market_sentiment: row.get(5)?,
-            sector_sentiment: row.get(6)?,
-            security_sentiment: row.get(7)?,
-            bull_bear: row.get(8)?,
-            confidence: row.get(9)?,
-            market_trend,
-            chart_pattern,
-            strategy: row.get(12)?,
-            overall_score: row.get(13)?,
-            notes: row.get(14)?,
-        })
-    })?;
+    let ratings_iter = stmt.query_map(params![symbol], rating_from_row)?;
     
     let mut ratings = Vec::new();
     for rating in ratings_iter {
@@ -140,48 +100,45 @@ pub fn get_stock_ratings_by_symbol(conn: &Connection, symbol: &str) -> Result<Ve
 }
 
 pub fn get_recent_stock_ratings(conn: &Connection, limit: i64) -> Result<Vec<StockRating>, Box<dyn Error>> {
-    let mut stmt = conn.prepare(
-        "SELECT 
-            id, timestamp, symbol, security_name, sector, market_sentiment, sector_sentiment, 
-            security_sentiment, bull_bear, confidence, market_trend, chart_pattern, 
-            strategy, overall_score, notes
-        FROM stock_ratings 
-        ORDER BY timestamp DESC 
-        LIMIT ?1"
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM stock_ratings ORDER BY timestamp DESC LIMIT ?1",
+        RATING_COLUMNS
+    ))?;
     
-    let ratings_iter = stmt.query_map(params![limit], |row| {
-        let timestamp_str: String = row.get(1)?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
-            .with_timezone(&Utc);
-        
-        let market_trend_json: String = row.get(10)?;
-        let market_trend: MarketTrend = serde_json::from_str(&market_trend_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e)))?;
-            
-        let chart_pattern_json: String = row.get(11)?;
-        let chart_pattern: ChartPattern = serde_json::from_str(&chart_pattern_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(11, rusqlite::types::Type::Text, Box::new(e)))?;
-        
-        Ok(StockRating {
-            id: Some(row.get(0)?),
-            timestamp,
-            symbol: row.get(2)?,
-            security_name: row.get(3)?,
-            sector: row.get(4)?,
-            market_sentiment: row.get(5)?,
-            sector_sentiment: row.get(6)?,
-            security_sentiment: row.get(7)?,
-            bull_bear: row.get(8)?,
-            confidence: row.get(9)?,
-            market_trend,
-            chart_pattern,
-            strategy: row.get(12)?,
-            overall_score: row.get(13)?,
-            notes: row.get(14)?,
-        })
-    })?;
+    let ratings_iter = stmt.query_map(params![limit], rating_from_row)?;
+    
+    let mut ratings = Vec::new();
+    for rating in ratings_iter {
+        ratings.push(rating?);
+    }
+    
+    Ok(ratings)
+}
+
+pub fn get_all_stock_ratings(conn: &Connection) -> Result<Vec<StockRating>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM stock_ratings ORDER BY timestamp", RATING_COLUMNS))?;
+    
+    let ratings_iter = stmt.query_map([], rating_from_row)?;
+    
+    let mut ratings = Vec::new();
+    for rating in ratings_iter {
+        ratings.push(rating?);
+    }
+    
+    Ok(ratings)
+}
+
+/// The most recent rating of every rated symbol.
+pub fn get_latest_stock_ratings(conn: &Connection) -> Result<Vec<StockRating>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM stock_ratings r
+        WHERE r.timestamp = (SELECT MAX(timestamp) FROM stock_ratings WHERE symbol = r.symbol)
+        GROUP BY r.symbol
+        ORDER BY r.symbol",
+        RATING_COLUMNS
+    ))?;
+    
+    let ratings_iter = stmt.query_map([], rating_from_row)?;
     
     let mut ratings = Vec::new();
     for rating in ratings_iter {
@@ -189,4 +146,54 @@ pub fn get_recent_stock_ratings(conn: &Connection, limit: i64) -> Result<Vec<Sto
     }
     
     Ok(ratings)
-} 
\ No newline at end of file
+}
+
+fn rating_from_row(row: &Row) -> rusqlite::Result<StockRating> {
+    let timestamp_str: String = row.get(1)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    
+    let market_trend_json: String = row.get(10)?;
+    let market_trend: MarketTrend = serde_json::from_str(&market_trend_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let computed_market_trend_json: Option<String> = row.get(18)?;
+    let computed_market_trend = computed_market_trend_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(18, rusqlite::types::Type::Text, Box::new(e)))?;
+        
+    let recorded_at_str: Option<String> = row.get(19)?;
+    let recorded_at = recorded_at_str
+        .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(19, rusqlite::types::Type::Text, Box::new(e)))?;
+        
+    let chart_pattern_json: String = row.get(11)?;
+    let chart_pattern: ChartPattern = serde_json::from_str(&chart_pattern_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(11, rusqlite::types::Type::Text, Box::new(e)))?;
+    
+    Ok(StockRating {
+        id: Some(row.get(0)?),
+        timestamp,
+        symbol: row.get(2)?,
+        security_name: row.get(3)?,
+        sector: row.get(4)?,
+        sector_id: row.get(17)?,
+        market_sentiment: row.get(5)?,
+        sector_sentiment: row.get(6)?,
+        security_sentiment: row.get(7)?,
+        bull_bear: row.get(8)?,
+        confidence: row.get(9)?,
+        market_trend,
+        computed_market_trend,
+        chart_pattern,
+        strategy: row.get(12)?,
+        overall_score: row.get(13)?,
+        notes: row.get(14)?,
+        model_version: row.get(15)?,
+        pattern_bonus: row.get(16)?,
+        recorded_at,
+    })
+}
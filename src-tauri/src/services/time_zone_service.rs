@@ -0,0 +1,81 @@
+use rusqlite::Connection;
+use std::error::Error;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::models::time_zone::{LocalTimestamp, TimeZoneSettings};
+use crate::services::{calendar_service, settings_service};
+
+/// The stored zones, with the exchange zone taken from the calendar of the
+/// default exchange so trading dates agree with its sessions.
+pub fn get_time_zone_settings(conn: &Connection) -> Result<TimeZoneSettings, Box<dyn Error>> {
+    let mut settings: TimeZoneSettings = settings_service::get_setting_or_default(conn, settings_service::TIME_ZONE_KEY)?;
+    if let Some(calendar) = calendar_service::get_calendar(conn, calendar_service::DEFAULT_EXCHANGE)? {
+        settings.exchange_timezone = calendar.timezone;
+    }
+
+    Ok(settings)
+}
+
+/// Stores the user zone. The exchange zone follows the default exchange's
+/// calendar, so a different one is refused rather than ignored.
+pub fn save_time_zone_settings(conn: &Connection, settings: &TimeZoneSettings) -> Result<(), Box<dyn Error>> {
+    if let Some(calendar) = calendar_service::get_calendar(conn, calendar_service::DEFAULT_EXCHANGE)? {
+        if settings.exchange_timezone != calendar.timezone {
+            return Err(format!(
+                "The exchange time zone comes from the {} calendar ({}); import a calendar to change it",
+                calendar.exchange, calendar.timezone,
+            ).into());
+        }
+    }
+
+    settings_service::set_setting(conn, settings_service::TIME_ZONE_KEY, settings)
+}
+
+/// The exchange date at `now`, for expiries and other trading dates.
+pub fn exchange_today(conn: &Connection, now: DateTime<Utc>) -> Result<NaiveDate, Box<dyn Error>> {
+    Ok(get_time_zone_settings(conn)?.exchange_date(now))
+}
+
+pub fn localize_timestamps(conn: &Connection, timestamps: &[DateTime<Utc>]) -> Result<Vec<LocalTimestamp>, Box<dyn Error>> {
+    let settings = get_time_zone_settings(conn)?;
+
+    Ok(timestamps.iter().map(|at| settings.localize(*at)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+    use crate::models::calendar::TradingCalendar;
+    use crate::services::db;
+
+    #[test]
+    fn exchange_zone_follows_the_default_calendar() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut calendar: TradingCalendar = calendar_service::built_in_calendar().unwrap();
+        calendar.timezone = Tz::America__Chicago;
+        calendar_service::save_calendar(&conn, &calendar).unwrap();
+
+        let settings = get_time_zone_settings(&conn).unwrap();
+        assert_eq!(settings.exchange_timezone, Tz::America__Chicago);
+        // 23:30 in New York is still 22:30 of the same day in Chicago
+        let now = Utc.with_ymd_and_hms(2024, 6, 18, 3, 30, 0).unwrap();
+        assert_eq!(exchange_today(&conn, now).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 17).unwrap());
+    }
+
+    #[test]
+    fn exchange_zone_other_than_the_calendars_is_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+
+        let mut settings = get_time_zone_settings(&conn).unwrap();
+        settings.user_timezone = Tz::Europe__London;
+        save_time_zone_settings(&conn, &settings).unwrap();
+        assert_eq!(get_time_zone_settings(&conn).unwrap().user_timezone, Tz::Europe__London);
+
+        settings.exchange_timezone = Tz::Asia__Tokyo;
+        assert!(save_time_zone_settings(&conn, &settings).is_err());
+    }
+}
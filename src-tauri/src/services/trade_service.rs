@@ -1,240 +1,599 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, Row, params};
 use serde_json::to_string;
 use std::error::Error;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
 
-use crate::models::trade::{Trade, TradeStatus};
+use crate::models::app_settings::AccountSettings;
+use crate::models::checklist::TradeGate;
+use crate::models::{security, time_zone};
+use crate::models::trade::{PositionSide, Trade, TradeStatus, TradeUpdate};
+use crate::services::{
+    account_service, calendar_service, checklist_service, db, detailed_analysis_service, option_leg_service, portfolio_risk_service,
+    security_service, settings_service,
+};
+
+const TRADE_COLUMNS: &str =
+    "id, analysis_id, timestamp, symbol, status, entry_time, exit_time, entry_price,
+    exit_price, quantity, profit_loss, percent_return, notes, parent_trade_id, trading_day, recorded_at, account_id, side, fees";
 
 pub fn save_trade(conn: &Connection, trade: &mut Trade) -> Result<i64, Box<dyn Error>> {
-    trade.timestamp = Utc::now();
+    if let Some(security) = security_service::validate_traded_symbol(conn, &trade.symbol, trade.entry_time.unwrap_or(trade.timestamp))? {
+        trade.symbol = security.symbol;
+    }
+    apply_trade_gate(conn, trade)?;
+    check_trade_risk(conn, trade)?;
+
+    insert_trade(conn, trade)
+}
+
+/// Refuses the trade when its analysis risks more of the account's equity
+/// than the per-trade risk limit allows.
+fn check_trade_risk(conn: &Connection, trade: &Trade) -> Result<(), Box<dyn Error>> {
+    let risk_max = detailed_analysis_service::get_risk_max(conn, trade.analysis_id)?;
+    let account_id = match trade.account_id {
+        Some(id) => id,
+        None => account_of_analysis(conn, trade.analysis_id)?,
+    };
+    let equity = account_service::get_account_summary(conn, Some(account_id), Utc::now())?.equity;
+    let limits = portfolio_risk_service::get_portfolio_risk_settings(conn)?.limits;
+
+    match limits.trade_risk_breach(risk_max, equity) {
+        Some(percent) => Err(format!(
+            "Trade risks {:.2}% of account equity, over the {:.2}% limit per trade",
+            percent, limits.max_risk_per_trade_percent
+        ).into()),
+        None => Ok(()),
+    }
+}
+
+/// Refuses the trade when the analysis's pre-trade checklists block it, and
+/// notes any flagged failures on it.
+fn apply_trade_gate(conn: &Connection, trade: &mut Trade) -> Result<(), Box<dyn Error>> {
+    match checklist_service::check_trade_gate(conn, trade.analysis_id)? {
+        TradeGate::Blocked(items) => {
+            Err(format!("Trade blocked by pre-trade checklist: {}", items.join(", ")).into())
+        }
+        TradeGate::Flagged(items) => {
+            trade.flag_checklist_failures(&items);
+            Ok(())
+        }
+        TradeGate::Clear => Ok(()),
+    }
+}
+
+/// Stores a trade without consulting the pre-trade checklist, for trades the
+/// user did not choose to open such as stock received through assignment.
+pub fn insert_trade(conn: &Connection, trade: &mut Trade) -> Result<i64, Box<dyn Error>> {
+    db::in_transaction(conn, || {
+        let recorded_at = Utc::now();
+        time_zone::check_entry_timestamp(trade.timestamp, recorded_at)?;
+        trade.recorded_at = Some(recorded_at);
+        trade.trading_day = Some(trading_day_of(conn, trade)?);
+        book_to_analysis_account(conn, trade)?;
+        charge_fees_on_close(conn, trade)?;
     
-    let status_json = to_string(&trade.status)?;
+        let status_json = to_string(&trade.status)?;
+        let side_json = to_string(&trade.side)?;
     
-    conn.execute(
-        "INSERT INTO trades 
-        (analysis_id, timestamp, symbol, status, entry_time, exit_time, entry_price, exit_price, 
-        quantity, profit_loss, percent_return, notes) 
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-        params![
-            trade.analysis_id,
-            trade.timestamp.to_rfc3339(),
-            trade.symbol,
-            status_json,
-            trade.entry_time.map(|dt| dt.to_rfc3339()),
-            trade.exit_time.map(|dt| dt.to_rfc3339()),
-            trade.entry_price,
-            trade.exit_price,
-            trade.quantity,
-            trade.profit_loss,
-            trade.percent_return,
-            trade.notes,
-        ],
-    )?;
+        conn.execute(
+            "INSERT INTO trades 
+            (analysis_id, timestamp, symbol, status, entry_time, exit_time, entry_price, exit_price, 
+            quantity, profit_loss, percent_return, notes, parent_trade_id, trading_day, recorded_at, account_id, side, fees) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                trade.analysis_id,
+                trade.timestamp.to_rfc3339(),
+                trade.symbol,
+                status_json,
+                trade.entry_time.map(|dt| dt.to_rfc3339()),
+                trade.exit_time.map(|dt| dt.to_rfc3339()),
+                trade.entry_price,
+                trade.exit_price,
+                trade.quantity,
+                trade.profit_loss,
+                trade.percent_return,
+                trade.notes,
+                trade.parent_trade_id,
+                trade.trading_day.map(|day| day.to_string()),
+                trade.recorded_at.map(|dt| dt.to_rfc3339()),
+                trade.account_id,
+                side_json,
+                trade.fees,
+            ],
+        )?;
     
-    let id = conn.last_insert_rowid();
-    trade.id = Some(id);
+        let id = conn.last_insert_rowid();
+        trade.id = Some(id);
+
+        option_leg_service::replace_trade_legs(conn, id, trade.analysis_id, &mut trade.legs)?;
     
-    Ok(id)
+        Ok(id)
+    })
 }
 
-pub fn update_trade(conn: &Connection, trade: &Trade) -> Result<(), Box<dyn Error>> {
-    let status_json = to_string(&trade.status)?;
+/// Applies an edit from the UI, keeping the stored legs when it leaves them
+/// out.
+pub fn apply_trade_update(conn: &Connection, update: TradeUpdate) -> Result<Trade, Box<dyn Error>> {
+    let mut trade = update.trade;
+    let id = trade.id.ok_or("Trade has no id")?;
+    trade.legs = match update.legs {
+        Some(legs) => legs,
+        None => option_leg_service::get_legs_by_trade(conn, id)?,
+    };
+    update_trade(conn, &mut trade)?;
+
+    Ok(trade)
+}
+
+/// Updates a stored trade to match `trade`, legs included. The account
+/// always follows the analysis.
+pub fn update_trade(conn: &Connection, trade: &mut Trade) -> Result<(), Box<dyn Error>> {
+    db::in_transaction(conn, || {
+        let id = trade.id.ok_or("Trade has no id")?;
+        let stored = get_trade(conn, id)?;
+        if security::normalize_symbol(&trade.symbol) == security::normalize_symbol(&stored.symbol) {
+            trade.symbol = stored.symbol;
+        } else if let Some(security) = security_service::validate_traded_symbol(conn, &trade.symbol, trade.entry_time.unwrap_or(trade.timestamp))? {
+            trade.symbol = security.symbol;
+        }
+        if matches!(stored.status, TradeStatus::Planned) && matches!(trade.status, TradeStatus::Open | TradeStatus::Closed) {
+            // Opening a planned trade is when the checklist and risk limit matter
+            apply_trade_gate(conn, trade)?;
+            check_trade_risk(conn, trade)?;
+        }
+        trade.fees = trade.fees.or(stored.fees);
+
+        trade.trading_day = Some(trading_day_of(conn, trade)?);
+        book_to_analysis_account(conn, trade)?;
+        charge_fees_on_close(conn, trade)?;
     
-    conn.execute(
-        "UPDATE trades 
-        SET status = ?1, entry_time = ?2, exit_time = ?3, entry_price = ?4, exit_price = ?5,
-        quantity = ?6, profit_loss = ?7, percent_return = ?8, notes = ?9
-        WHERE id = ?10",
-        params![
-            status_json,
-            trade.entry_time.map(|dt| dt.to_rfc3339()),
-            trade.exit_time.map(|dt| dt.to_rfc3339()),
-            trade.entry_price,
-            trade.exit_price,
-            trade.quantity,
-            trade.profit_loss,
-            trade.percent_return,
-            trade.notes,
-            trade.id,
-        ],
-    )?;
+        let status_json = to_string(&trade.status)?;
+        let side_json = to_string(&trade.side)?;
     
-    Ok(())
+        conn.execute(
+            "UPDATE trades 
+            SET status = ?1, entry_time = ?2, exit_time = ?3, entry_price = ?4, exit_price = ?5,
+            quantity = ?6, profit_loss = ?7, percent_return = ?8, notes = ?9, parent_trade_id = ?10,
+            trading_day = ?11, account_id = ?12, side = ?13, fees = ?14, symbol = ?15
+            WHERE id = ?16",
+            params![
+                status_json,
+                trade.entry_time.map(|dt| dt.to_rfc3339()),
+                trade.exit_time.map(|dt| dt.to_rfc3339()),
+                trade.entry_price,
+                trade.exit_price,
+                trade.quantity,
+                trade.profit_loss,
+                trade.percent_return,
+                trade.notes,
+                trade.parent_trade_id,
+                trade.trading_day.map(|day| day.to_string()),
+                trade.account_id,
+                side_json,
+                trade.fees,
+                trade.symbol,
+                id,
+            ],
+        )?;
+    
+        option_leg_service::replace_trade_legs(conn, id, trade.analysis_id, &mut trade.legs)?;
+    
+        Ok(())
+    })
 }
 
 pub fn get_trade(conn: &Connection, id: i64) -> Result<Trade, Box<dyn Error>> {
-    let mut stmt = conn.prepare(
-        "SELECT 
-            id, analysis_id, timestamp, symbol, status, entry_time, exit_time, entry_price, 
-            exit_price, quantity, profit_loss, percent_return, notes
-        FROM trades 
-        WHERE id = ?1"
-    )?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM trades WHERE id = ?1", TRADE_COLUMNS))?;
     
-    let trade = stmt.query_row(params![id], |row| {
-        let timestamp_str: String = row.get(2)?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?
-            .with_timezone(&Utc);
-        
-        let status_json: String = row.get(4)?;
-        let status: TradeStatus = serde_json::from_str(&status_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
-            
-        let entry_time: Option<String> = row.get(5)?;
-        let entry_time = entry_time.map(|s| {
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))
-                .unwrap()
-                .with_timezone(&Utc)
-        });
-        
-        let exit_time: Option<String> = row.get(6)?;
-        let exit_time = exit_time.map(|s| {
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))
-                .unwrap()
-                .with_timezone(&Utc)
-        });
-        
-        Ok(Trade {
-            id: Some(row.get(0)?),
-            analysis_id: row.get(1)?,
-            timestamp,
-            symbol: row.get(3)?,
-            status,
-            entry_time,
-            exit_time,
-            entry_price: row.get(7)?,
-            exit_price: row.get(8)?,
-            quantity: row.get(9)?,
-            profit_loss: row.get(10)?,
-            percent_return: row.get(11)?,
-            notes: row.get(12)?,
-        })
-    })?;
+    let mut trade = stmt.query_row(params![id], trade_from_row)?;
+    
+    trade.legs = option_leg_service::get_legs_by_trade(conn, id)?;
     
     Ok(trade)
 }
 
 pub fn get_trades_by_analysis(conn: &Connection, analysis_id: i64) -> Result<Vec<Trade>, Box<dyn Error>> {
-    let mut stmt = conn.prepare(
-        "SELECT 
-            id, analysis_id, timestamp, symbol, status, entry_time, exit_time, entry_price, 
-            exit_price, quantity, profit_loss, percent_return, notes
-        FROM trades 
-        WHERE analysis_id = ?1
-        ORDER BY timestamp DESC"
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM trades WHERE analysis_id = ?1 ORDER BY timestamp DESC",
+        TRADE_COLUMNS
+    ))?;
     
-    let trades_iter = stmt.query_map(params![analysis_id], |row| {
-        let timestamp_str: String = row.get(2)?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?
-            .with_timezone(&Utc);
-        
-        let status_json: String = row.get(4)?;
-        let status: TradeStatus = serde_json::from_str(&status_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
-            
-        let entry_time: Option<String> = row.get(5)?;
-        let entry_time = entry_time.map(|s| {
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))
-                .unwrap()
-                .with_timezone(&Utc)
-        });
-        
-        let exit_time: Option<String> = row.get(6)?;
-        let exit_time = exit_time.map(|s| {
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))
-                .unwrap()
-                .with_timezone(&Utc)
-        });
-        
-        Ok(Trade {
-            id: Some(row.get(0)?),
-            analysis_id: row.get(1)?,
-            timestamp,
-            symbol: row.get(3)?,
-            status,
-            entry_time,
-            exit_time,
-            entry_price: row.get(7)?,
-            exit_price: row.get(8)?,
-            quantity: row.get(9)?,
-            profit_loss: row.get(10)?,
-            percent_return: row.get(11)?,
-            notes: row.get(12)?,
-        })
-    })?;
+    let trades_iter = stmt.query_map(params![analysis_id], trade_from_row)?;
     
-    let mut trades = Vec::new();
-    for trade in trades_iter {
-        trades.push(trade?);
+    collect_trades(conn, trades_iter)
+}
+
+/// Latest trades of one account, or of all accounts when `account_id` is None.
+pub fn get_recent_trades(conn: &Connection, limit: i64, account_id: Option<i64>) -> Result<Vec<Trade>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM trades WHERE (?2 IS NULL OR account_id = ?2) ORDER BY timestamp DESC LIMIT ?1",
+        TRADE_COLUMNS
+    ))?;
+    
+    let trades_iter = stmt.query_map(params![limit, account_id], trade_from_row)?;
+    
+    collect_trades(conn, trades_iter)
+}
+
+/// Open trades of one account, or of all accounts when `account_id` is None.
+pub fn get_open_trades(conn: &Connection, account_id: Option<i64>) -> Result<Vec<Trade>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM trades WHERE status = ?1 AND (?2 IS NULL OR account_id = ?2) ORDER BY timestamp",
+        TRADE_COLUMNS
+    ))?;
+    
+    let trades_iter = stmt.query_map(params![to_string(&TradeStatus::Open)?, account_id], trade_from_row)?;
+    
+    collect_trades(conn, trades_iter)
+}
+
+/// Number of trades with `status` in one account, or in all accounts when
+/// `account_id` is None.
+pub fn count_trades(conn: &Connection, status: TradeStatus, account_id: Option<i64>) -> Result<usize, Box<dyn Error>> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM trades WHERE status = ?1 AND (?2 IS NULL OR account_id = ?2)",
+        params![to_string(&status)?, account_id],
+        |row| row.get(0),
+    )?;
+
+    Ok(count as usize)
+}
+
+/// Fills in the trading day of trades stored before trades had one.
+pub fn assign_trading_days(conn: &Connection) -> Result<usize, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM trades WHERE trading_day IS NULL", TRADE_COLUMNS))?;
+    let trades: Vec<Trade> = stmt.query_map([], trade_from_row)?.collect::<rusqlite::Result<_>>()?;
+    
+    for trade in &trades {
+        let trading_day = trading_day_of(conn, trade)?;
+        conn.execute("UPDATE trades SET trading_day = ?1 WHERE id = ?2", params![trading_day.to_string(), trade.id])?;
     }
     
-    Ok(trades)
+    Ok(trades.len())
 }
 
-pub fn get_recent_trades(conn: &Connection, limit: i64) -> Result<Vec<Trade>, Box<dyn Error>> {
-    let mut stmt = conn.prepare(
-        "SELECT 
-            id, analysis_id, timestamp, symbol, status, entry_time, exit_time, entry_price, 
-            exit_price, quantity, profit_loss, percent_return, notes
-        FROM trades 
-        ORDER BY timestamp DESC 
-        LIMIT ?1"
+/// Cancels planned trades recorded before `before`, noting why on each.
+/// Goes by when the plan was recorded, since its timestamp may be backdated.
+/// Returns how many.
+pub fn cancel_stale_plans(conn: &Connection, before: DateTime<Utc>) -> Result<usize, Box<dyn Error>> {
+    let note = format!("Cancelled as stale: planned before {}", before.format("%Y-%m-%d"));
+    let cancelled = conn.execute(
+        "UPDATE trades
+        SET status = ?1, notes = CASE WHEN notes IS NULL OR notes = '' THEN ?4 ELSE notes || char(10) || ?4 END
+        WHERE status = ?2 AND COALESCE(recorded_at, timestamp) < ?3",
+        params![
+            to_string(&TradeStatus::Cancelled)?,
+            to_string(&TradeStatus::Planned)?,
+            before.to_rfc3339(),
+            note,
+        ],
     )?;
     
-    let trades_iter = stmt.query_map(params![limit], |row| {
-        let timestamp_str: String = row.get(2)?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?
-            .with_timezone(&Utc);
-        
-        let status_json: String = row.get(4)?;
-        let status: TradeStatus = serde_json::from_str(&status_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
-            
-        let entry_time: Option<String> = row.get(5)?;
-        let entry_time = entry_time.map(|s| {
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))
-                .unwrap()
-                .with_timezone(&Utc)
-        });
-        
-        let exit_time: Option<String> = row.get(6)?;
-        let exit_time = exit_time.map(|s| {
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))
-                .unwrap()
-                .with_timezone(&Utc)
-        });
-        
-        Ok(Trade {
-            id: Some(row.get(0)?),
-            analysis_id: row.get(1)?,
-            timestamp,
-            symbol: row.get(3)?,
-            status,
-            entry_time,
-            exit_time,
-            entry_price: row.get(7)?,
-            exit_price: row.get(8)?,
-            quantity: row.get(9)?,
-            profit_loss: row.get(10)?,
-            percent_return: row.get(11)?,
-            notes: row.get(12)?,
-        })
-    })?;
+    Ok(cancelled)
+}
+
+/// Trades that resulted from another trade, e.g. stock from an assigned put.
+pub fn get_child_trades(conn: &Connection, parent_trade_id: i64) -> Result<Vec<Trade>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM trades WHERE parent_trade_id = ?1 ORDER BY timestamp",
+        TRADE_COLUMNS
+    ))?;
     
+    let trades_iter = stmt.query_map(params![parent_trade_id], trade_from_row)?;
+    
+    collect_trades(conn, trades_iter)
+}
+
+/// Session the trade was entered in, or planned in until it is entered.
+fn trading_day_of(conn: &Connection, trade: &Trade) -> Result<NaiveDate, Box<dyn Error>> {
+    calendar_service::trading_day_for(conn, &trade.symbol, trade.entry_time.unwrap_or(trade.timestamp))
+}
+
+/// Takes the commissions of the opening and closing orders out of the profit
+/// of a closed trade. The profit is worked out again from the prices first,
+/// so fees are taken once however often the trade is edited. Fees already on
+/// the trade are kept; a trade closing without any is charged the round trip
+/// from the fee settings. Trades without both prices keep the profit they
+/// were given.
+fn charge_fees_on_close(conn: &Connection, trade: &mut Trade) -> Result<(), Box<dyn Error>> {
+    if !matches!(trade.status, TradeStatus::Closed) || trade.entry_price.is_none() || trade.exit_price.is_none() {
+        return Ok(());
+    }
+
+    trade.compute_profit_loss();
+    let fees = match trade.fees {
+        Some(fees) => fees,
+        None => {
+            let settings: AccountSettings = settings_service::get_setting_or_default(conn, settings_service::ACCOUNT_KEY)?;
+            2.0 * settings.fees.order_fee(trade)
+        }
+    };
+    trade.charge_fees(fees);
+
+    Ok(())
+}
+
+/// Books a trade to the account of its analysis, refusing any other.
+fn book_to_analysis_account(conn: &Connection, trade: &mut Trade) -> Result<(), Box<dyn Error>> {
+    let account_id = account_of_analysis(conn, trade.analysis_id)?;
+    match trade.account_id {
+        Some(id) if id != account_id => {
+            Err(format!("Trade is booked to account {} but its analysis to account {}", id, account_id).into())
+        }
+        _ => {
+            trade.account_id = Some(account_id);
+            Ok(())
+        }
+    }
+}
+
+/// Account of the analysis behind a trade, or the default account.
+fn account_of_analysis(conn: &Connection, analysis_id: i64) -> Result<i64, Box<dyn Error>> {
+    let account_id: Option<i64> = conn.query_row(
+        "SELECT account_id FROM detailed_analyses WHERE id = ?1",
+        params![analysis_id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    
+    match account_id {
+        Some(id) => Ok(id),
+        None => account_service::default_account_id(conn),
+    }
+}
+
+fn collect_trades(
+    conn: &Connection,
+    trades_iter: impl Iterator<Item = rusqlite::Result<Trade>>,
+) -> Result<Vec<Trade>, Box<dyn Error>> {
     let mut trades = Vec::new();
     for trade in trades_iter {
-        trades.push(trade?);
+        let mut trade = trade?;
+        if let Some(id) = trade.id {
+            trade.legs = option_leg_service::get_legs_by_trade(conn, id)?;
+        }
+        trades.push(trade);
     }
     
     Ok(trades)
-} 
\ No newline at end of file
+}
+
+fn trade_from_row(row: &Row) -> rusqlite::Result<Trade> {
+    let timestamp_str: String = row.get(2)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    
+    let status_json: String = row.get(4)?;
+    let status: TradeStatus = serde_json::from_str(&status_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+        
+    let entry_time: Option<String> = row.get(5)?;
+    let entry_time = entry_time.map(|s| {
+        chrono::DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))
+    }).transpose()?;
+    
+    let exit_time: Option<String> = row.get(6)?;
+    let exit_time = exit_time.map(|s| {
+        chrono::DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))
+    }).transpose()?;
+    
+    let trading_day: Option<String> = row.get(14)?;
+    let trading_day = trading_day.map(|s| {
+        NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(14, rusqlite::types::Type::Text, Box::new(e)))
+    }).transpose()?;
+    
+    let side_json: String = row.get(17)?;
+    let side: PositionSide = serde_json::from_str(&side_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(17, rusqlite::types::Type::Text, Box::new(e)))?;
+    
+    let recorded_at: Option<String> = row.get(15)?;
+    let recorded_at = recorded_at.map(|s| {
+        chrono::DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(15, rusqlite::types::Type::Text, Box::new(e)))
+    }).transpose()?;
+    
+    Ok(Trade {
+        id: Some(row.get(0)?),
+        analysis_id: row.get(1)?,
+        account_id: row.get(16)?,
+        timestamp,
+        symbol: row.get(3)?,
+        status,
+        entry_time,
+        exit_time,
+        entry_price: row.get(7)?,
+        exit_price: row.get(8)?,
+        quantity: row.get(9)?,
+        side,
+        profit_loss: row.get(10)?,
+        percent_return: row.get(11)?,
+        fees: row.get(18)?,
+        notes: row.get(12)?,
+        parent_trade_id: row.get(13)?,
+        trading_day,
+        recorded_at,
+        legs: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::black_scholes::OptionType;
+    use crate::models::DetailedAnalysis;
+    use crate::models::account::{Account, AccountType};
+    use crate::models::option_leg::{LegSide, OptionLeg};
+    use crate::services::detailed_analysis_service;
+
+    #[test]
+    fn trades_stay_in_the_account_of_their_analysis() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let default_id = account_service::default_account_id(&conn).unwrap();
+        let mut account = Account::new("Margin", AccountType::Margin);
+        let account_id = account_service::save_account(&conn, &mut account).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        trade.account_id = Some(account_id);
+        assert!(save_trade(&conn, &mut trade).is_err());
+
+        trade.account_id = None;
+        let id = save_trade(&conn, &mut trade).unwrap();
+        assert_eq!(trade.account_id, Some(default_id));
+
+        analysis.account_id = Some(account_id);
+        detailed_analysis_service::update_detailed_analysis(&conn, &mut analysis).unwrap();
+        assert_eq!(get_trade(&conn, id).unwrap().account_id, Some(account_id));
+    }
+
+    #[test]
+    fn update_keeps_the_stored_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        account_service::default_account_id(&conn).unwrap();
+        let mut account = Account::new("Margin", AccountType::Margin);
+        let account_id = account_service::save_account(&conn, &mut account).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.account_id = Some(account_id);
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        let id = save_trade(&conn, &mut trade).unwrap();
+
+        let mut payload = get_trade(&conn, id).unwrap();
+        payload.account_id = None;
+        payload.notes = Some("Scaled in".to_string());
+        update_trade(&conn, &mut payload).unwrap();
+
+        let stored = get_trade(&conn, id).unwrap();
+        assert_eq!(stored.notes.as_deref(), Some("Scaled in"));
+        assert_eq!(stored.account_id, Some(account_id));
+    }
+
+    #[test]
+    fn updates_leaving_out_the_legs_keep_them() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        trade.legs = vec![OptionLeg::new("AAPL", NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 100.0, OptionType::Call, LegSide::Buy)];
+        let id = save_trade(&conn, &mut trade).unwrap();
+
+        let mut payload = serde_json::to_value(get_trade(&conn, id).unwrap()).unwrap();
+        payload.as_object_mut().unwrap().remove("legs");
+        payload["notes"] = "Rolled later".into();
+        apply_trade_update(&conn, serde_json::from_value(payload.clone()).unwrap()).unwrap();
+        let stored = get_trade(&conn, id).unwrap();
+        assert_eq!(stored.notes.as_deref(), Some("Rolled later"));
+        assert_eq!(stored.legs.len(), 1);
+
+        payload["legs"] = serde_json::json!([]);
+        apply_trade_update(&conn, serde_json::from_value(payload).unwrap()).unwrap();
+        assert!(get_trade(&conn, id).unwrap().legs.is_empty());
+    }
+
+    #[test]
+    fn a_failed_leg_write_leaves_no_trade() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        conn.execute_batch("CREATE TRIGGER no_legs BEFORE INSERT ON option_legs BEGIN SELECT RAISE(ABORT, 'no legs'); END").unwrap();
+
+        let mut trade = Trade::new("AAPL", analysis_id);
+        trade.legs = vec![OptionLeg::new("AAPL", NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(), 100.0, OptionType::Call, LegSide::Buy)];
+        assert!(save_trade(&conn, &mut trade).is_err());
+        let trades: i64 = conn.query_row("SELECT COUNT(*) FROM trades", [], |row| row.get(0)).unwrap();
+        assert_eq!(trades, 0);
+    }
+
+    #[test]
+    fn closing_a_trade_charges_round_trip_fees() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut account = AccountSettings::default();
+        account.fees.per_trade = 1.0;
+        account.fees.per_share = 0.01;
+        settings_service::set_setting(&conn, settings_service::ACCOUNT_KEY, &account).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        trade.enter_trade(Utc::now(), 100.0, 100);
+        save_trade(&conn, &mut trade).unwrap();
+
+        trade.exit_trade(Utc::now(), 110.0);
+        update_trade(&conn, &mut trade).unwrap();
+        assert_eq!(trade.fees, Some(4.0));
+        assert_eq!(trade.profit_loss, Some(996.0));
+
+        // Later edits of the closed trade are not charged again
+        let mut stored = get_trade(&conn, trade.id.unwrap()).unwrap();
+        stored.notes = Some("Took profit".to_string());
+        update_trade(&conn, &mut stored).unwrap();
+        let stored = get_trade(&conn, trade.id.unwrap()).unwrap();
+        assert_eq!(stored.profit_loss, Some(996.0));
+        assert!((stored.percent_return.unwrap() - 9.96).abs() < 1e-9);
+
+        // A corrected exit price is charged the same fees
+        let mut corrected = stored;
+        corrected.exit_price = Some(108.0);
+        update_trade(&conn, &mut corrected).unwrap();
+        assert_eq!(get_trade(&conn, trade.id.unwrap()).unwrap().profit_loss, Some(796.0));
+    }
+
+    #[test]
+    fn fees_given_with_the_trade_are_kept() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut account = AccountSettings::default();
+        account.fees.per_trade = 1.0;
+        settings_service::set_setting(&conn, settings_service::ACCOUNT_KEY, &account).unwrap();
+
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        trade.enter_trade(Utc::now(), 100.0, 100);
+        trade.exit_trade(Utc::now(), 110.0);
+        trade.fees = Some(7.5);
+        let id = save_trade(&conn, &mut trade).unwrap();
+
+        let stored = get_trade(&conn, id).unwrap();
+        assert_eq!(stored.fees, Some(7.5));
+        assert_eq!(stored.profit_loss, Some(992.5));
+    }
+
+    #[test]
+    fn trades_risking_too_much_of_the_account_are_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let mut account = Account::new("Margin", AccountType::Margin);
+        account.starting_balance = 100_000.0;
+        let account_id = account_service::save_account(&conn, &mut account).unwrap();
+
+        // 2,000 of risk on 100,000 of equity, over the 1% default
+        let mut analysis = DetailedAnalysis::new("AAPL", "Information Technology");
+        analysis.account_id = Some(account_id);
+        analysis.bought = true;
+        analysis.entry_price = 100.0;
+        analysis.stop_loss = 90.0;
+        analysis.quantity = 200;
+        let analysis_id = detailed_analysis_service::save_detailed_analysis(&conn, &mut analysis).unwrap();
+        let mut trade = Trade::new("AAPL", analysis_id);
+        assert!(save_trade(&conn, &mut trade).is_err());
+
+        let mut settings = portfolio_risk_service::get_portfolio_risk_settings(&conn).unwrap();
+        settings.limits.max_risk_per_trade_percent = 2.5;
+        portfolio_risk_service::save_portfolio_risk_settings(&conn, &settings).unwrap();
+        assert!(save_trade(&conn, &mut trade).is_ok());
+    }
+}
@@ -0,0 +1,71 @@
+use rusqlite::Connection;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::analytics::trend;
+use crate::models::price_bar::Timeframe;
+use crate::models::stock_rating::MarketTrend;
+use crate::models::trend::{TimeframeTrend, TrendClassification, TrendSettings};
+use crate::services::{price_service, settings_service};
+
+/// Bars read per chart, enough for the long average and ADX to settle.
+const CLASSIFICATION_BARS: i64 = 200;
+
+pub fn get_trend_settings(conn: &Connection) -> Result<TrendSettings, Box<dyn Error>> {
+    settings_service::get_setting_or_default(conn, settings_service::TREND_SETTINGS_KEY)
+}
+
+pub fn save_trend_settings(conn: &Connection, settings: &TrendSettings) -> Result<(), Box<dyn Error>> {
+    if settings.index_weight < 0.0 || settings.timeframes.iter().any(|(_, weight)| *weight < 0.0) {
+        return Err("Trend weights cannot be negative".into());
+    }
+
+    settings_service::set_setting(conn, settings_service::TREND_SETTINGS_KEY, settings)
+}
+
+/// Classifies the trend of `symbol` from its own charts and the daily chart of
+/// the market index. Charts without enough stored bars are left out; with
+/// none at all the trend is Uncertain.
+pub fn classify_market_trend(conn: &Connection, symbol: &str) -> Result<TrendClassification, Box<dyn Error>> {
+    let settings = get_trend_settings(conn)?;
+    let symbol = symbol.to_ascii_uppercase();
+
+    let mut charts: Vec<(String, Timeframe, f64)> = settings.timeframes.iter()
+        .map(|(timeframe, weight)| (symbol.clone(), *timeframe, *weight))
+        .collect();
+    let index_symbol = settings.index_symbol.trim().to_ascii_uppercase();
+    if !index_symbol.is_empty() && index_symbol != symbol {
+        charts.push((index_symbol, Timeframe::Daily, settings.index_weight));
+    }
+
+    let mut classified = Vec::new();
+    for (chart_symbol, timeframe, weight) in charts {
+        let bars = price_service::get_bars(conn, &chart_symbol, timeframe, CLASSIFICATION_BARS)?;
+        if let Some(signal) = trend::classify(&bars) {
+            classified.push(TimeframeTrend { symbol: chart_symbol, timeframe, weight, signal });
+        }
+    }
+
+    let weighted: Vec<_> = classified.iter().map(|chart| (chart.weight, &chart.signal)).collect();
+    let (trend, score) = trend::blend(&weighted);
+
+    Ok(TrendClassification {
+        symbol,
+        computed_at: Utc::now(),
+        trend,
+        score,
+        charts: classified,
+    })
+}
+
+/// The classified trend to offer as the default of a new rating or analysis,
+/// None when no chart has enough bars.
+pub fn computed_market_trend(conn: &Connection, symbol: &str) -> Result<Option<MarketTrend>, Box<dyn Error>> {
+    let classification = classify_market_trend(conn, symbol)?;
+
+    if classification.charts.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(classification.trend))
+    }
+}
@@ -0,0 +1,349 @@
+use rusqlite::{Connection, Row, params};
+use serde_json::to_string;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+
+use crate::models::detailed_analysis::DetailedAnalysis;
+use crate::models::security;
+use crate::models::watchlist::{Watchlist, WatchlistItem, WatchlistItemSource};
+use crate::services::{detailed_analysis_service, security_service, stock_rating_service};
+
+const WATCHLIST_COLUMNS: &str = "id, timestamp, name, description, rule";
+
+const ITEM_COLUMNS: &str =
+    "id, watchlist_id, symbol, added_at, source, notes, target_price, rating_id, analysis_id";
+
+pub fn save_watchlist(conn: &Connection, watchlist: &mut Watchlist) -> Result<i64, Box<dyn Error>> {
+    watchlist.timestamp = Utc::now();
+
+    let rule_json = watchlist.rule.as_ref().map(to_string).transpose()?;
+
+    conn.execute(
+        "INSERT INTO watchlists (timestamp, name, description, rule) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            watchlist.timestamp.to_rfc3339(),
+            watchlist.name,
+            watchlist.description,
+            rule_json,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    watchlist.id = Some(id);
+
+    for item in watchlist.items.iter_mut() {
+        item.watchlist_id = id;
+        add_watchlist_item(conn, item)?;
+    }
+
+    Ok(id)
+}
+
+/// Updates the name, description and rule of a watchlist. Items are managed
+/// one at a time.
+pub fn update_watchlist(conn: &Connection, watchlist: &Watchlist) -> Result<(), Box<dyn Error>> {
+    let rule_json = watchlist.rule.as_ref().map(to_string).transpose()?;
+
+    conn.execute(
+        "UPDATE watchlists SET name = ?1, description = ?2, rule = ?3 WHERE id = ?4",
+        params![watchlist.name, watchlist.description, rule_json, watchlist.id],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_watchlist(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM watchlist_items WHERE watchlist_id = ?1", params![id])?;
+    conn.execute("DELETE FROM watchlists WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+pub fn get_watchlist(conn: &Connection, id: i64) -> Result<Watchlist, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM watchlists WHERE id = ?1", WATCHLIST_COLUMNS))?;
+
+    let mut watchlist = stmt.query_row(params![id], watchlist_from_row)?;
+    watchlist.items = get_watchlist_items(conn, id)?;
+
+    Ok(watchlist)
+}
+
+pub fn get_watchlists(conn: &Connection) -> Result<Vec<Watchlist>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM watchlists ORDER BY name", WATCHLIST_COLUMNS))?;
+
+    let watchlists_iter = stmt.query_map([], watchlist_from_row)?;
+
+    let mut watchlists = Vec::new();
+    for watchlist in watchlists_iter {
+        let mut watchlist = watchlist?;
+        watchlist.items = get_watchlist_items(conn, watchlist.id.unwrap_or_default())?;
+        watchlists.push(watchlist);
+    }
+
+    Ok(watchlists)
+}
+
+pub fn add_watchlist_item(conn: &Connection, item: &mut WatchlistItem) -> Result<i64, Box<dyn Error>> {
+    item.symbol = match security_service::validate_symbol(conn, &item.symbol)? {
+        Some(security) => security.symbol,
+        None => security::normalize_symbol(&item.symbol),
+    };
+    item.added_at = Utc::now();
+
+    conn.execute(
+        "INSERT INTO watchlist_items
+        (watchlist_id, symbol, added_at, source, notes, target_price, rating_id, analysis_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            item.watchlist_id,
+            item.symbol,
+            item.added_at.to_rfc3339(),
+            to_string(&item.source)?,
+            item.notes,
+            item.target_price,
+            item.rating_id,
+            item.analysis_id,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    item.id = Some(id);
+
+    Ok(id)
+}
+
+/// Updates the notes and target price of an item.
+pub fn update_watchlist_item(conn: &Connection, item: &WatchlistItem) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "UPDATE watchlist_items SET notes = ?1, target_price = ?2 WHERE id = ?3",
+        params![item.notes, item.target_price, item.id],
+    )?;
+
+    Ok(())
+}
+
+pub fn remove_watchlist_item(conn: &Connection, id: i64) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM watchlist_items WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+pub fn get_watchlist_item(conn: &Connection, id: i64) -> Result<WatchlistItem, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM watchlist_items WHERE id = ?1", ITEM_COLUMNS))?;
+
+    let item = stmt.query_row(params![id], item_from_row)?;
+
+    Ok(item)
+}
+
+pub fn get_watchlist_items(conn: &Connection, watchlist_id: i64) -> Result<Vec<WatchlistItem>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM watchlist_items WHERE watchlist_id = ?1 ORDER BY added_at, symbol",
+        ITEM_COLUMNS
+    ))?;
+
+    let items_iter = stmt.query_map(params![watchlist_id], item_from_row)?;
+
+    let mut items = Vec::new();
+    for item in items_iter {
+        items.push(item?);
+    }
+
+    Ok(items)
+}
+
+/// Re-applies the rule of a rule-built watchlist to the latest rating of every
+/// symbol. Matching symbols are added; rule-added items that no longer match
+/// are dropped unless they carry notes, a target price or were promoted.
+pub fn refresh_watchlist(conn: &Connection, id: i64, now: DateTime<Utc>) -> Result<Watchlist, Box<dyn Error>> {
+    let watchlist = get_watchlist(conn, id)?;
+    let rule = match &watchlist.rule {
+        Some(rule) => rule,
+        None => return Ok(watchlist),
+    };
+
+    let matching: Vec<_> = stock_rating_service::get_latest_stock_ratings(conn)?
+        .into_iter()
+        .filter(|rating| rule.matches(rating, now))
+        .collect();
+
+    for item in &watchlist.items {
+        let still_matches = matching.iter().any(|rating| rating.symbol == item.symbol);
+        let kept_for_user = item.notes.is_some() || item.target_price.is_some() || item.analysis_id.is_some();
+        if item.source == WatchlistItemSource::Rule && !still_matches && !kept_for_user {
+            remove_watchlist_item(conn, item.id.unwrap_or_default())?;
+        }
+    }
+
+    for rating in matching {
+        match watchlist.items.iter().find(|item| item.symbol == rating.symbol) {
+            Some(item) if item.source == WatchlistItemSource::Rule => {
+                conn.execute(
+                    "UPDATE watchlist_items SET rating_id = ?1 WHERE id = ?2",
+                    params![rating.id, item.id],
+                )?;
+            }
+            Some(_) => {}
+            None => {
+                let mut item = WatchlistItem::new(id, &rating.symbol);
+                item.source = WatchlistItemSource::Rule;
+                item.rating_id = rating.id;
+                insert_rule_item(conn, &mut item)?;
+            }
+        }
+    }
+
+    get_watchlist(conn, id)
+}
+
+/// Opens a detailed analysis for a watchlist item, pre-filled from the latest
+/// rating of the symbol, and links it to the item.
+pub fn promote_watchlist_item(conn: &Connection, item_id: i64) -> Result<DetailedAnalysis, Box<dyn Error>> {
+    let item = get_watchlist_item(conn, item_id)?;
+    if let Some(analysis_id) = item.analysis_id {
+        return Err(format!("{} was already promoted to analysis {}", item.symbol, analysis_id).into());
+    }
+
+    let latest = stock_rating_service::get_stock_ratings_by_symbol(conn, &item.symbol)?
+        .into_iter()
+        .next();
+
+    let mut analysis = match &latest {
+        Some(rating) => DetailedAnalysis::from_rating(rating),
+        None => DetailedAnalysis::new(&item.symbol, ""),
+    };
+    if let Some(target_price) = item.target_price {
+        analysis.target_price = target_price;
+    }
+    if let Some(notes) = &item.notes {
+        analysis.entry_reason = notes.clone();
+    }
+
+    let analysis_id = detailed_analysis_service::save_detailed_analysis(conn, &mut analysis)?;
+    conn.execute(
+        "UPDATE watchlist_items SET analysis_id = ?1 WHERE id = ?2",
+        params![analysis_id, item_id],
+    )?;
+
+    Ok(analysis)
+}
+
+/// Rule items come from stored ratings, so their symbols need no validation.
+fn insert_rule_item(conn: &Connection, item: &mut WatchlistItem) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "INSERT INTO watchlist_items
+        (watchlist_id, symbol, added_at, source, notes, target_price, rating_id, analysis_id)
+        VALUES (?1, ?2, ?3, ?4, NULL, NULL, ?5, NULL)",
+        params![
+            item.watchlist_id,
+            item.symbol,
+            item.added_at.to_rfc3339(),
+            to_string(&item.source)?,
+            item.rating_id,
+        ],
+    )?;
+
+    item.id = Some(conn.last_insert_rowid());
+
+    Ok(())
+}
+
+fn watchlist_from_row(row: &Row) -> rusqlite::Result<Watchlist> {
+    let timestamp_str: String = row.get(1)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let rule_json: Option<String> = row.get(4)?;
+    let rule = rule_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(Watchlist {
+        id: Some(row.get(0)?),
+        timestamp,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        rule,
+        items: Vec::new(),
+    })
+}
+
+fn item_from_row(row: &Row) -> rusqlite::Result<WatchlistItem> {
+    let added_at_str: String = row.get(3)?;
+    let added_at = chrono::DateTime::parse_from_rfc3339(&added_at_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    let source_json: String = row.get(4)?;
+    let source = serde_json::from_str(&source_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    Ok(WatchlistItem {
+        id: Some(row.get(0)?),
+        watchlist_id: row.get(1)?,
+        symbol: row.get(2)?,
+        added_at,
+        source,
+        notes: row.get(5)?,
+        target_price: row.get(6)?,
+        rating_id: row.get(7)?,
+        analysis_id: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::models::stock_rating::StockRating;
+    use crate::models::watchlist::WatchlistRule;
+    use crate::services::db;
+
+    fn rate(conn: &Connection, symbol: &str, bull_bear: i8, timestamp: DateTime<Utc>) {
+        let mut rating = StockRating::new(symbol, "Information Technology");
+        rating.bull_bear = bull_bear;
+        rating.timestamp = timestamp;
+        stock_rating_service::save_stock_rating(conn, &mut rating).unwrap();
+    }
+
+    fn symbols(watchlist: &Watchlist) -> Vec<&str> {
+        watchlist.items.iter().map(|item| item.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn refresh_follows_the_latest_ratings() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let now = Utc::now();
+        rate(&conn, "AAPL", 1, now - Duration::days(2));
+        rate(&conn, "MSFT", -1, now - Duration::days(2));
+        let rule = WatchlistRule { bull_bear: Some(1), ..WatchlistRule::default() };
+        let id = save_watchlist(&conn, &mut Watchlist::new("Bullish", Some(rule))).unwrap();
+
+        assert_eq!(symbols(&refresh_watchlist(&conn, id, now).unwrap()), vec!["AAPL"]);
+
+        rate(&conn, "AAPL", -1, now - Duration::days(1));
+        rate(&conn, "MSFT", 1, now - Duration::days(1));
+        assert_eq!(symbols(&refresh_watchlist(&conn, id, now).unwrap()), vec!["MSFT"]);
+    }
+
+    #[test]
+    fn promoted_items_stay_and_promote_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::initialize_database(&conn).unwrap();
+        let now = Utc::now();
+        rate(&conn, "AAPL", 1, now - Duration::days(2));
+        let rule = WatchlistRule { bull_bear: Some(1), ..WatchlistRule::default() };
+        let id = save_watchlist(&conn, &mut Watchlist::new("Bullish", Some(rule))).unwrap();
+        let item_id = refresh_watchlist(&conn, id, now).unwrap().items[0].id.unwrap();
+
+        let analysis = promote_watchlist_item(&conn, item_id).unwrap();
+        assert_eq!(analysis.security, "AAPL");
+        assert!(promote_watchlist_item(&conn, item_id).is_err());
+
+        rate(&conn, "AAPL", -1, now - Duration::days(1));
+        assert_eq!(symbols(&refresh_watchlist(&conn, id, now).unwrap()), vec!["AAPL"]);
+    }
+}